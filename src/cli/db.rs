@@ -0,0 +1,69 @@
+use std::path::{Path, PathBuf};
+
+use log::info;
+
+use crate::{
+    core::{dasm::arch::Context, error::FdResult, user_config::user_config_dir},
+    prelude::Config,
+};
+
+pub const DB_DIR: &str = "db";
+
+/// The analysis database directory
+/// $XDG_CONFIG_HOME/litedasm/db unless it is configured
+pub fn db_dir(cfg: &Config) -> PathBuf {
+    cfg.db_dir
+        .clone()
+        .unwrap_or_else(|| user_config_dir().join(DB_DIR))
+}
+
+// FNV-1a, the key only has to tell inputs apart
+pub fn hash(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// The ctx file of an input, keyed by the hash and the length of its bytes
+pub fn entry(dir: &Path, data: &[u8]) -> PathBuf {
+    dir.join(format!("{:016x}-{:x}.ron", hash(data), data.len()))
+}
+
+/// The database entry of the bytes of the input file unless the database is disabled
+/// or the input is read from stdin
+pub fn input_entry(cfg: &Config, input: Option<&[u8]>) -> Option<PathBuf> {
+    input
+        .filter(|_| !cfg.no_db)
+        .map(|data| entry(&db_dir(cfg), data))
+}
+
+pub fn save(path: &Path, ctx: &Context) -> FdResult<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    info!("Saving ctx to database entry '{}'", path.display());
+    let data =
+        ron::ser::to_string_pretty(ctx, Default::default()).expect("Unable to convert context");
+    std::fs::write(path, data)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::{entry, hash};
+
+    #[test]
+    fn keys() {
+        assert_eq!(0xcbf29ce484222325, hash(b""));
+        assert_eq!(0xaf63dc4c8601ec8c, hash(b"a"));
+
+        let dir = Path::new("db");
+        assert_eq!(
+            Path::new("db/af63dc4c8601ec8c-1.ron"),
+            entry(dir, b"a").as_path()
+        );
+        assert_ne!(entry(dir, &[1, 2]), entry(dir, &[2, 1]));
+    }
+}
@@ -4,15 +4,25 @@ use std::{
     path::{Path, PathBuf},
 };
 
+use clap::ValueEnum;
 use log::info;
 
 use crate::{
     core::dasm::{
-        arch::{a6502, a65c02, a65c816, Archs, Context, DisasCallback},
-        symbols::Symbol,
-        Address,
+        arch::{
+            a6502, a65c02, a65c816, a6800, a6803, a8051, chip8, msp430, pdp11, sh2, spc700,
+            superfx, Archs, Context, DisasCallback, Variable,
+        },
+        classify::ByteClass,
+        dispatch, duplicates, expr, flow, interrupts, loader,
+        packs::SymbolPack,
+        pointers, similarity, stack, stats, strings,
+        symbols::{Scope, Symbol, SymbolKind},
+        Address, ValueType,
+    },
+    prelude::{
+        auto_radix_usize, auto_radix_value, parse_input_file, Config, Error, FdResult, InputFile,
     },
-    prelude::{auto_radix_address, auto_radix_usize, Config, Error, FdResult},
 };
 
 use super::{CallbackKind, CommandCallback};
@@ -22,6 +32,15 @@ fn arch_map() -> BTreeMap<String, Archs> {
     map.insert("6502".into(), a6502::ARCH.to_owned());
     map.insert("65c02".into(), a65c02::ARCH.to_owned());
     map.insert("65816".into(), a65c816::ARCH.to_owned());
+    map.insert("superfx".into(), superfx::ARCH.to_owned());
+    map.insert("8051".into(), a8051::ARCH.to_owned());
+    map.insert("6800".into(), a6800::ARCH.to_owned());
+    map.insert("6803".into(), a6803::ARCH.to_owned());
+    map.insert("msp430".into(), msp430::ARCH.to_owned());
+    map.insert("chip8".into(), chip8::ARCH.to_owned());
+    map.insert("pdp11".into(), pdp11::ARCH.to_owned());
+    map.insert("sh2".into(), sh2::ARCH.to_owned());
+    map.insert("spc700".into(), spc700::ARCH.to_owned());
     map
 }
 
@@ -38,8 +57,8 @@ pub fn default_actions() -> ActionList {
         Action::new(
             "lf",
             vec![Param::new("path")],
-            optional_file_read_path_parser,
-            "Read a file",
+            file_read_parser,
+            "Read a file, several files (path:org[:name]) are loaded as segments that share their symbols",
         ),
         Action::new(
             "dcl",
@@ -51,13 +70,13 @@ pub fn default_actions() -> ActionList {
             "dcr",
             vec![Param::new("len")],
             disas_read_len_parser,
-            "Set disas read length",
+            "Set disas read length or the range of offsets (start..end excludes end, start..=end includes it)",
         ),
         Action::new(
             "dca",
             vec![Param::new("address")],
             disas_start_address_parser,
-            "Set disas starting point to an address",
+            "Set disas starting point to an address or to an offset into a bank (bank:offset)",
         ),
         Action::new(
             "sc",
@@ -96,6 +115,173 @@ pub fn default_actions() -> ActionList {
                 })
             ),
         ),
+        Action::new(
+            "window",
+            vec![],
+            window_parser,
+            "Show the range of the input that is disassembled",
+        ),
+        Action::new(
+            "calc",
+            vec![Param::new("expression")],
+            calc_parser,
+            "Evaluate an expression of values and symbols (e.g. label+0x20)",
+        ),
+        Action::new(
+            "size",
+            vec![],
+            size_parser,
+            "Report the output size per section and bank",
+        ),
+        Action::new(
+            "dups",
+            vec![Param::with_default("min_len", "16")],
+            duplicates_parser,
+            "List the byte sequences of at least min_len bytes that appear more than once",
+        ),
+        Action::new(
+            "propagate",
+            vec![Param::with_default("min_len", "16")],
+            propagate_parser,
+            "Copy labels and comments between duplicated byte sequences",
+        ),
+        Action::new(
+            "similar",
+            vec![
+                Param::with_default("percent", "80"),
+                Param::with_default("min_len", "8"),
+            ],
+            similar_parser,
+            "List routines of at least min_len instructions whose instructions are similar, ignoring the operands",
+        ),
+        Action::new(
+            "irq",
+            vec![],
+            interrupts_parser,
+            "Find the rti-terminated handlers of the interrupt vectors and label them",
+        ),
+        Action::new(
+            "cfg",
+            vec![Param::new("path")],
+            cfg_parser,
+            "Write the control flow from the start and the vectors as a Graphviz file",
+        ),
+        Action::new(
+            "stack",
+            vec![],
+            stack_parser,
+            "List the push and pull balance of the routines and comment the ones that change their return address",
+        ),
+        Action::new(
+            "dispatch",
+            vec![],
+            dispatch_parser,
+            "Read the address tables of the rts dispatch routines and label their targets",
+        ),
+        Action::new(
+            "stats",
+            vec![],
+            stats_parser,
+            "Show how much of the input is code, data or unknown, the opcode frequencies, the symbols and the largest functions",
+        ),
+        Action::new(
+            "strings",
+            vec![Param::with_default("min_len", "8")],
+            strings_parser,
+            "Find the runs of at least min_len characters in the unclassified bytes, output them as text and label them",
+        ),
+        Action::new(
+            "pointers",
+            vec![
+                Param::with_default("min_len", "4"),
+                Param::with_default("bits", "16"),
+            ],
+            pointers_parser,
+            "Find the tables of at least min_len 16 or 24 bit pointers into the input in the unclassified bytes, output them as words and add their targets as entry points",
+        ),
+        Action::new(
+            "class",
+            vec![Param::new("class"), Param::new("range")],
+            class_parser,
+            "Classify the bytes of a range of offsets as code, data or unknown, data is never decoded",
+        ),
+        Action::new(
+            "classes",
+            vec![],
+            classes_parser,
+            "List the classified ranges of offsets",
+        ),
+        Action::new(
+            "sym",
+            vec![Param::new("name")],
+            show_symbol_parser,
+            "Show the value and documentation of a (qualified) symbol",
+        ),
+        Action::new(
+            "tmpsym",
+            vec![
+                Param::new("name"),
+                Param::new("value"),
+                Param::with_default("kind", "label"),
+            ],
+            temp_symbol_parser,
+            "Define a label or const for this session only, it is not saved with the context",
+        ),
+        Action::new(
+            "syms",
+            vec![Param::new("tag")],
+            symbols_parser,
+            "List the symbols (with the tag), the temporary symbols are marked with ~",
+        ),
+        Action::new(
+            "tag",
+            vec![Param::new("name"), Param::new("tag")],
+            tag_parser,
+            "Add a tag to a (qualified) symbol, e.g. audio or verified",
+        ),
+        Action::new(
+            "untag",
+            vec![Param::new("name"), Param::new("tag")],
+            untag_parser,
+            "Remove a tag from a (qualified) symbol",
+        ),
+        Action::new(
+            "xrefs",
+            vec![Param::new("tag")],
+            xrefs_parser,
+            "List the addresses that reference the symbols with the tag",
+        ),
+        Action::new(
+            "banks",
+            vec![],
+            banks_parser,
+            "List the banks, the active bank of each window is marked with *",
+        ),
+        Action::new(
+            "bank",
+            vec![Param::new("bank")],
+            view_bank_parser,
+            "Disassemble only the bank (number or name) and map it to its window",
+        ),
+        Action::new(
+            "view",
+            vec![Param::new("name")],
+            view_parser,
+            "Restore a view and disassemble it, view save name saves the window, output profile and flags as a view",
+        ),
+        Action::new("views", vec![], views_parser, "List the saved views"),
+        Action::new(
+            "pack",
+            vec![Param::new("name")],
+            pack_parser,
+            "Define the hardware register labels of a system (nes, snes, c64, game-boy, c64-kernal)",
+        ),
+        Action::new(
+            "var",
+            vec![Param::new("name"), Param::new("value")],
+            set_var_parser,
+            "Define a context variable that can be referenced as {var:name}",
+        ),
     ];
 
     actions.sort_by_key(|l| l.name.to_owned());
@@ -215,9 +401,10 @@ pub enum Commands {
     Help(String),
     DisasCode,
     SetStartLabel(String),
-    SetStartAddress(Address),
-    SetReadLen(usize),
+    SetStartAddress(Location),
+    SetReadRange(ReadRange),
     ReadFile(Option<PathBuf>),
+    ReadFiles(Vec<InputFile>),
     ReadContext(Option<PathBuf>),
     ReadArch(Option<PathBuf>),
     UseArch(String),
@@ -225,11 +412,90 @@ pub enum Commands {
     SaveContext(Option<PathBuf>),
     SetOrg(Address),
     NewSymbol(Symbol),
+    TempSymbol(Symbol),
+    ShowSymbol(String),
+    Symbols(Option<String>),
+    Tag(String, String),
+    Untag(String, String),
+    Xrefs(String),
     DefFlag(String, String),
     UndefFlag(String),
+    SetVar(String, Variable),
+    Window,
+    Banks,
+    ApplyPack(SymbolPack),
+    ViewBank(String),
+    SaveView(String),
+    View(String),
+    Views,
+    Size,
+    Calc(String),
+    Duplicates(usize),
+    Propagate(usize),
+    Similar(usize, usize),
+    Interrupts,
+    ControlFlow(Option<PathBuf>),
+    StackEffects,
+    Dispatch,
+    Stats,
+    Strings(usize),
+    Pointers(usize, usize),
+    Classify(ByteClass, usize, usize),
+    Classes,
 }
 
 impl Commands {
+    /// The command changes the context, e.g. its window, symbols or views
+    pub fn modifies_ctx(&self) -> bool {
+        match self {
+            Commands::SetStartLabel(_)
+            | Commands::SetStartAddress(_)
+            | Commands::SetReadRange(_)
+            | Commands::ReadFiles(_)
+            | Commands::SetOrg(_)
+            | Commands::NewSymbol(_)
+            | Commands::Tag(_, _)
+            | Commands::Untag(_, _)
+            | Commands::DefFlag(_, _)
+            | Commands::UndefFlag(_)
+            | Commands::SetVar(_, _)
+            | Commands::ApplyPack(_)
+            | Commands::ViewBank(_)
+            | Commands::SaveView(_)
+            | Commands::View(_)
+            | Commands::Propagate(_)
+            | Commands::Interrupts
+            | Commands::StackEffects
+            | Commands::Dispatch
+            | Commands::Strings(_)
+            | Commands::Pointers(_, _)
+            | Commands::Classify(_, _, _) => true,
+            Commands::Exit
+            | Commands::Help(_)
+            | Commands::DisasCode
+            | Commands::ReadFile(_)
+            | Commands::ReadContext(_)
+            | Commands::ReadArch(_)
+            | Commands::UseArch(_)
+            | Commands::SaveArch(_)
+            | Commands::SaveContext(_)
+            | Commands::TempSymbol(_)
+            | Commands::ShowSymbol(_)
+            | Commands::Symbols(_)
+            | Commands::Xrefs(_)
+            | Commands::Window
+            | Commands::Banks
+            | Commands::Views
+            | Commands::Size
+            | Commands::Calc(_)
+            | Commands::Duplicates(_)
+            | Commands::Similar(_, _)
+            | Commands::ControlFlow(_)
+            | Commands::Stats
+            | Commands::Classes => false,
+        }
+    }
+
     pub fn execute(
         &self,
         mut f: impl CommandCallback,
@@ -240,7 +506,10 @@ impl Commands {
         _cfg: &Config,
     ) -> FdResult<()> {
         match self {
-            Commands::Exit => std::process::exit(0),
+            Commands::Exit => {
+                cmd_ctx.exit = true;
+                Ok(())
+            }
             Commands::Help(cmd) => cmd_ctx.actions.help(&mut f, &cmd),
             Commands::DisasCode => {
                 ctx.restart();
@@ -253,22 +522,37 @@ impl Commands {
                 let mut buffer = Vec::new();
                 f.read_to_end(&mut buffer)?;
                 cmd_ctx.data = buffer;
+                cmd_ctx.path = path.clone();
                 info!("Binary loaded from {:?}", path);
                 Ok(())
             }
+            Commands::ReadFiles(files) => {
+                // the segments of the previous files do not apply to the new data
+                cmd_ctx.data.clear();
+                ctx.segments.clear();
+                cmd_ctx.path = files.first().map(|file| file.path.clone());
+                for file in files {
+                    loader::append_file(&mut cmd_ctx.data, file, ctx)?;
+                    info!("Binary loaded from {:?} at {:x}", file.path, file.org);
+                }
+                Ok(())
+            }
             Commands::SetStartLabel(label) => {
                 ctx.set_start_to_symbol(label)?;
                 info!("New ctx start address: {:x}", ctx.start_read);
 
                 Ok(())
             }
-            Commands::SetReadLen(len) => {
-                ctx.set_len(Some(*len));
+            Commands::SetReadRange(range) => {
+                if let ReadRange::Offsets(start, _) = range {
+                    ctx.set_start(Some(*start));
+                }
+                ctx.set_len(Some(range.len()));
                 info!("New ctx read len: {:?}", ctx.len_read);
                 Ok(())
             }
-            Commands::SetStartAddress(address) => {
-                ctx.set_start(Some(*address as usize));
+            Commands::SetStartAddress(location) => {
+                ctx.set_start(Some(location.offset(ctx)?));
                 info!("New ctx start address: {:x}", ctx.start_read);
 
                 Ok(())
@@ -279,7 +563,10 @@ impl Commands {
                 info!("Reading from context path '{path:?}'");
                 let mut data = String::new();
                 f.read_to_string(&mut data)?;
+                // the temporary symbols live until the session ends
+                let temporary: Vec<Symbol> = ctx.syms.temporary().cloned().collect();
                 *ctx = ron::from_str(&data).map_err(|_| Error::FileDeserError)?;
+                temporary.into_iter().for_each(|sym| ctx.def_symbol(sym));
                 Ok(())
             }
             Commands::ReadArch(path) => {
@@ -325,10 +612,84 @@ impl Commands {
                 ctx.set_org(*address);
                 Ok(())
             }
-            Commands::NewSymbol(sym) => {
+            Commands::NewSymbol(sym) | Commands::TempSymbol(sym) => {
                 ctx.def_symbol(sym.to_owned());
                 Ok(())
             }
+            Commands::ShowSymbol(name) => {
+                let sym = ctx
+                    .syms
+                    .get_first_by_name(name)
+                    .ok_or_else(|| Error::LabelNotFound(name.into()))?;
+                f(
+                    &format!(
+                        "{} = {:#x} ({:?}, {} bytes)\n",
+                        sym.qualified_name(),
+                        sym.value,
+                        sym.kind,
+                        sym.len
+                    ),
+                    CallbackKind::None,
+                )?;
+                if let Some(doc) = &sym.doc {
+                    f(&ctx.output.comment_block(doc, "  "), CallbackKind::None)?;
+                }
+                Ok(())
+            }
+            Commands::Symbols(tag) => {
+                let syms = ctx
+                    .syms
+                    .iter()
+                    .filter(|s| tag.as_ref().is_none_or(|tag| s.tags.contains(tag)));
+                for sym in syms {
+                    f(
+                        &format!(
+                            "{}{} = {:#x} ({:?})\n",
+                            if sym.temporary { "~" } else { " " },
+                            sym.qualified_name(),
+                            sym.value,
+                            sym.kind
+                        ),
+                        CallbackKind::None,
+                    )?;
+                }
+                Ok(())
+            }
+            Commands::Tag(name, tag) => {
+                if !ctx.syms.tag(name, tag) {
+                    return Err(Error::LabelNotFound(name.into()));
+                }
+                Ok(())
+            }
+            Commands::Untag(name, tag) => {
+                if !ctx.syms.untag(name, tag) {
+                    return Err(Error::LabelNotFound(name.into()));
+                }
+                Ok(())
+            }
+            Commands::Xrefs(tag) => {
+                // the references are collected by an analysis pass over a copy of the context
+                // so the analysis does not define auto labels
+                let mut analysis = ctx.clone();
+                analysis.output.xrefs = true;
+                analysis.analyze = true;
+                analysis.restart();
+                arch.disas_ctx(|_, _, _, _, _| Ok(()), &cmd_ctx.data, &mut analysis)?;
+                for sym in ctx.syms.tagged(tag) {
+                    let sites: Vec<String> = analysis
+                        .xrefs
+                        .get(&(sym.value as Address))
+                        .into_iter()
+                        .flatten()
+                        .map(|site| format!("{site:x}"))
+                        .collect();
+                    f(
+                        &format!("{}: {}\n", sym.qualified_name(), sites.join(", ")),
+                        CallbackKind::None,
+                    )?;
+                }
+                Ok(())
+            }
             Commands::DefFlag(key, value) => {
                 ctx.def_flag(key, value);
                 Ok(())
@@ -337,6 +698,195 @@ impl Commands {
                 ctx.undef_flag(key);
                 Ok(())
             }
+            Commands::SetVar(name, value) => {
+                ctx.def_var(name, value.to_owned());
+                Ok(())
+            }
+            Commands::Window => {
+                let window = ctx.window(&cmd_ctx.data);
+                f(&format!("{window}\n"), CallbackKind::None)?;
+                for warning in window.warnings.iter() {
+                    f(&format!("warning: {warning}\n"), CallbackKind::None)?;
+                }
+                Ok(())
+            }
+            Commands::Banks => {
+                for (i, bank) in ctx.banks().iter().enumerate() {
+                    let active = ctx.active_bank(bank.org as ValueType) == Some(&bank.name);
+                    f(
+                        &format!(
+                            "{}{i}: {} {:#x}..{:#x} at {:x}\n",
+                            if active { "*" } else { " " },
+                            bank.name,
+                            bank.start,
+                            bank.end,
+                            bank.org
+                        ),
+                        CallbackKind::None,
+                    )?;
+                }
+                Ok(())
+            }
+            Commands::ApplyPack(pack) => {
+                let count = pack.apply(ctx);
+                info!("Defined {count} labels of {pack:?}");
+                Ok(())
+            }
+            Commands::ViewBank(bank) => {
+                ctx.view_bank(bank)?;
+                info!("Viewing bank {bank} at {:x}", ctx.start_read);
+                Ok(())
+            }
+            Commands::SaveView(name) => {
+                ctx.save_view(name);
+                info!("Saved view {name}");
+                Ok(())
+            }
+            Commands::View(name) => {
+                ctx.restore_view(name)?;
+                ctx.restart();
+                arch.disas_ctx(&mut dcb, &cmd_ctx.data, ctx)?;
+                Ok(())
+            }
+            Commands::Views => {
+                for (name, view) in ctx.views.iter() {
+                    f(&format!("{name}: {view}\n"), CallbackKind::None)?;
+                }
+                Ok(())
+            }
+            Commands::Calc(expr) => {
+                let value = expr::eval(expr, &ctx.syms)?;
+                f(
+                    &format!("{value:#x} {value} {value:#b}\n"),
+                    CallbackKind::None,
+                )?;
+                Ok(())
+            }
+            Commands::Size => {
+                // a silent output pass that only collects the sizes
+                let size_report = ctx.output.size_report;
+                ctx.output.size_report = true;
+                ctx.restart();
+                let res = arch.disas_ctx(|_, _, _, _, _| Ok(()), &cmd_ctx.data, ctx);
+                ctx.output.size_report = size_report;
+                res?;
+                f(&ctx.size_report.to_string(), CallbackKind::None)?;
+                Ok(())
+            }
+            Commands::Duplicates(min_len) => {
+                for dup in duplicates::find(&cmd_ctx.data, *min_len) {
+                    f(&format!("{}\n", dup.describe(ctx)), CallbackKind::None)?;
+                }
+                Ok(())
+            }
+            Commands::Similar(percent, min_len) => {
+                let routines = similarity::routines(arch, &cmd_ctx.data, ctx)?;
+                for similar in similarity::find(&routines, *percent as f32 / 100.0, *min_len) {
+                    f(&format!("{similar}\n"), CallbackKind::None)?;
+                }
+                Ok(())
+            }
+            Commands::Interrupts => {
+                let handlers = interrupts::find(arch, &cmd_ctx.data, ctx)?;
+                let labeled = interrupts::annotate(ctx, &handlers);
+                for handler in &handlers {
+                    f(
+                        &format!("{}: {:x}\n", handler.vector, handler.address),
+                        CallbackKind::None,
+                    )?;
+                }
+                f(
+                    &format!("Found {} handlers, labeled {labeled}\n", handlers.len()),
+                    CallbackKind::None,
+                )?;
+                Ok(())
+            }
+            Commands::ControlFlow(path) => {
+                let dot = flow::control_flow_graph(arch, &cmd_ctx.data, ctx)?;
+                match path {
+                    Some(path) => std::fs::write(path, dot)?,
+                    None => f(&dot, CallbackKind::None)?,
+                }
+                Ok(())
+            }
+            Commands::StackEffects => {
+                let effects = stack::find(arch, &cmd_ctx.data, ctx)?;
+                for effect in &effects {
+                    f(&format!("{effect}\n"), CallbackKind::None)?;
+                }
+                let annotated = stack::annotate(ctx, &effects);
+                f(
+                    &format!("Commented {annotated} routines\n"),
+                    CallbackKind::None,
+                )?;
+                Ok(())
+            }
+            Commands::Dispatch => {
+                let dispatches = dispatch::find(arch, &cmd_ctx.data, ctx)?;
+                for dispatch in &dispatches {
+                    f(&format!("{dispatch}\n"), CallbackKind::None)?;
+                }
+                let labeled = dispatch::annotate(ctx, &dispatches);
+                f(
+                    &format!(
+                        "Found {} dispatch tables, labeled {labeled}\n",
+                        dispatches.len()
+                    ),
+                    CallbackKind::None,
+                )?;
+                Ok(())
+            }
+            Commands::Stats => {
+                let stats = stats::collect(arch, &cmd_ctx.data, ctx)?;
+                f(&stats.to_string(), CallbackKind::None)?;
+                Ok(())
+            }
+            Commands::Strings(min_len) => {
+                let texts = strings::find(&cmd_ctx.data, ctx, *min_len);
+                for text in &texts {
+                    f(&format!("{text}\n"), CallbackKind::None)?;
+                }
+                let labeled = strings::annotate(ctx, &texts);
+                f(
+                    &format!("Found {} strings, labeled {labeled}\n", texts.len()),
+                    CallbackKind::None,
+                )?;
+                Ok(())
+            }
+            Commands::Pointers(min_len, width) => {
+                let tables = pointers::find(arch, &cmd_ctx.data, ctx, *min_len, *width)?;
+                for table in &tables {
+                    f(&format!("{table}\n"), CallbackKind::None)?;
+                }
+                let labeled = pointers::annotate(ctx, &cmd_ctx.data, &tables);
+                f(
+                    &format!("Found {} pointer tables, labeled {labeled}\n", tables.len()),
+                    CallbackKind::None,
+                )?;
+                Ok(())
+            }
+            Commands::Classify(class, start, end) => {
+                ctx.classes.set(*start, *end, *class);
+                Ok(())
+            }
+            Commands::Classes => {
+                for range in ctx.classes.ranges() {
+                    f(&format!("{range}\n"), CallbackKind::None)?;
+                }
+                Ok(())
+            }
+            Commands::Propagate(min_len) => {
+                let dups = duplicates::find(&cmd_ctx.data, *min_len);
+                let copied = duplicates::propagate(ctx, &dups);
+                f(
+                    &format!(
+                        "Copied {copied} labels and comments between {} duplicates\n",
+                        dups.len()
+                    ),
+                    CallbackKind::None,
+                )?;
+                Ok(())
+            }
         }
         // Ok(())
     }
@@ -360,10 +910,83 @@ impl Commands {
     }
 }
 
+/// An offset into the input or into a bank (bank:offset)
+pub enum Location {
+    Offset(usize),
+    Bank(String, usize),
+}
+
+impl Location {
+    fn parse(arg: &str) -> FdResult<Self> {
+        Ok(match arg.split_once(':') {
+            Some((bank, offset)) => Self::Bank(bank.into(), auto_radix_usize(offset)?),
+            None => Self::Offset(auto_radix_usize(arg)?),
+        })
+    }
+
+    /// The file offset, banks are looked up in the context
+    pub fn offset(&self, ctx: &Context) -> FdResult<usize> {
+        match self {
+            Self::Offset(offset) => Ok(*offset),
+            Self::Bank(bank, offset) => ctx.bank_offset(bank, *offset),
+        }
+    }
+}
+
+/// A length or a range of offsets
+/// start..end excludes the end and start..=end includes it
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReadRange {
+    Len(usize),
+    // the end is always exclusive
+    Offsets(usize, usize),
+}
+
+impl ReadRange {
+    pub fn parse(arg: &str) -> FdResult<Self> {
+        let (start, end) = if let Some((start, end)) = arg.split_once("..=") {
+            (
+                auto_radix_usize(start)?,
+                auto_radix_usize(end)?.checked_add(1),
+            )
+        } else if let Some((start, end)) = arg.split_once("..") {
+            (auto_radix_usize(start)?, Some(auto_radix_usize(end)?))
+        } else {
+            return Ok(Self::Len(auto_radix_usize(arg)?));
+        };
+        match end {
+            Some(end) if end >= start => Ok(Self::Offsets(start, end)),
+            _ => Err(Error::InvalidArgument(format!(
+                "The range {arg} ends before it starts"
+            ))),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Len(len) => *len,
+            Self::Offsets(start, end) => end - start,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The prompt of the interactive mode if no template is configured
+pub const DEFAULT_PROMPT: &str = ">> ";
+
 #[derive(Default)]
 pub struct CommandContext {
     pub actions: ActionList,
     pub data: Vec<u8>,
+    // the file the data was read from
+    pub path: Option<PathBuf>,
+    // the context changed since it was last saved or read
+    pub modified: bool,
+    // q was entered, the caller ends the session
+    pub exit: bool,
 }
 
 impl CommandContext {
@@ -373,9 +996,34 @@ impl CommandContext {
         Ok(Self {
             actions,
             data: buffer,
+            ..Default::default()
         })
     }
 
+    /// Replaces the placeholders of a prompt template:
+    /// {file} the name of the input, {arch} the arch key, {org}, {address} of the window's start,
+    /// {bank} the active bank and {modified} a * if the context has unsaved changes
+    pub fn prompt(&self, template: &str, ctx: &Context) -> String {
+        let file = self
+            .path
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let address = ctx.address_of(ctx.start_read as Address);
+        let bank = ctx
+            .active_bank(address as ValueType)
+            .cloned()
+            .unwrap_or_default();
+        template
+            .replace("{file}", &file)
+            .replace("{arch}", &ctx.arch_key)
+            .replace("{org}", &format!("{:x}", ctx.org))
+            .replace("{address}", &format!("{address:x}"))
+            .replace("{bank}", &bank)
+            .replace("{modified}", if self.modified { "*" } else { "" })
+    }
+
     pub fn execute(
         &mut self,
         f: impl CommandCallback,
@@ -399,6 +1047,12 @@ impl CommandContext {
         cfg: &Config,
     ) -> FdResult<()> {
         cmd.execute(f, dcb, arch, ctx, self, cfg)?;
+        match cmd {
+            // the context written to stdout is not saved
+            Commands::SaveContext(Some(_)) | Commands::ReadContext(_) => self.modified = false,
+            cmd if cmd.modifies_ctx() => self.modified = true,
+            _ => {}
+        }
         Ok(())
     }
 }
@@ -410,7 +1064,7 @@ fn try_get_arg(args: &[&str], params: &[Param], index: usize) -> FdResult<String
     let arg = args.get(index);
     let param = params.get(index);
 
-    if let Some(arg) = arg && let Some(_param) = param {
+    if let (Some(arg), Some(_param)) = (arg, param) {
         Ok(arg.to_string())
     } else if let Some(param) = param {
         if let Some(def) = &param.default_value {
@@ -462,8 +1116,8 @@ fn disas_start_label_parser(args: &[&str], params: &[Param]) -> FdResult<Command
 
 fn disas_read_len_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
     has_too_many_args(args, params)?;
-    let to: usize = auto_radix_usize(&try_get_arg(args, params, 0)?)?;
-    Ok(Commands::SetReadLen(to))
+    let range = ReadRange::parse(&try_get_arg(args, params, 0)?)?;
+    Ok(Commands::SetReadRange(range))
 }
 
 fn disas_code_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
@@ -471,14 +1125,16 @@ fn disas_code_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
     Ok(Commands::DisasCode)
 }
 
-fn optional_file_read_path_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
-    has_too_many_args(args, params)?;
-    let path = get_optional_arg(args, params, 0);
-
-    if let Some(path) = path {
-        Ok(Commands::SaveContext(Some(expand_path(&path))))
-    } else {
-        Ok(Commands::ReadFile(None))
+// a single path is read as is, otherwise every file needs its own org
+fn file_read_parser(args: &[&str], _params: &[Param]) -> FdResult<Commands> {
+    match args {
+        [] => Ok(Commands::ReadFile(None)),
+        [path] if !path.contains(':') => Ok(Commands::ReadFile(Some(expand_path(path)))),
+        _ => Ok(Commands::ReadFiles(
+            args.iter()
+                .map(|arg| parse_input_file(arg).map_err(Error::InvalidArgument))
+                .collect::<FdResult<_>>()?,
+        )),
     }
 }
 
@@ -506,9 +1162,9 @@ fn optional_arch_write_path_parser(args: &[&str], params: &[Param]) -> FdResult<
 
 fn disas_start_address_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
     has_too_many_args(args, params)?;
-    let address = auto_radix_address(&try_get_arg(args, params, 0)?)?;
+    let location = Location::parse(&try_get_arg(args, params, 0)?)?;
 
-    Ok(Commands::SetStartAddress(address))
+    Ok(Commands::SetStartAddress(location))
 }
 
 fn optional_ctx_read_path_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
@@ -540,3 +1196,356 @@ fn load_build_in_arch_parser(args: &[&str], params: &[Param]) -> FdResult<Comman
     let name = try_get_arg(args, params, 0)?;
     Ok(Commands::UseArch(name))
 }
+
+fn set_var_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    let name = try_get_arg(args, params, 0)?;
+    let value = try_get_arg(args, params, 1)?;
+    Ok(Commands::SetVar(name, value.as_str().into()))
+}
+
+fn show_symbol_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    let name = try_get_arg(args, params, 0)?;
+    Ok(Commands::ShowSymbol(name))
+}
+
+fn temp_symbol_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    let name = try_get_arg(args, params, 0)?;
+    let value = auto_radix_value(&try_get_arg(args, params, 1)?)?;
+    let kind = match try_get_arg(args, params, 2)?.as_str() {
+        "label" => SymbolKind::Label,
+        "const" => SymbolKind::Const,
+        kind => {
+            return Err(Error::InvalidArgument(format!(
+                "Unknown symbol kind {kind}, expected label or const"
+            )))
+        }
+    };
+    let mut sym = Symbol::new(name, kind, Scope::Global, value, 1);
+    sym.temporary = true;
+    Ok(Commands::TempSymbol(sym))
+}
+
+fn symbols_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    Ok(Commands::Symbols(get_optional_arg(args, params, 0)))
+}
+
+fn tag_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    let name = try_get_arg(args, params, 0)?;
+    let tag = try_get_arg(args, params, 1)?;
+    Ok(Commands::Tag(name, tag))
+}
+
+fn untag_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    let name = try_get_arg(args, params, 0)?;
+    let tag = try_get_arg(args, params, 1)?;
+    Ok(Commands::Untag(name, tag))
+}
+
+fn xrefs_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    let tag = try_get_arg(args, params, 0)?;
+    Ok(Commands::Xrefs(tag))
+}
+
+fn window_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    Ok(Commands::Window)
+}
+
+fn banks_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    Ok(Commands::Banks)
+}
+
+fn view_bank_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    let bank = try_get_arg(args, params, 0)?;
+    Ok(Commands::ViewBank(bank))
+}
+
+// view name restores a view, view save name saves one
+fn view_parser(args: &[&str], _params: &[Param]) -> FdResult<Commands> {
+    match args {
+        ["save", name] => Ok(Commands::SaveView(name.to_string())),
+        [name] => Ok(Commands::View(name.to_string())),
+        [] => Err(Error::InsufficientArguments),
+        _ => Err(Error::TooManyArguments),
+    }
+}
+
+fn views_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    Ok(Commands::Views)
+}
+
+fn pack_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    let name = try_get_arg(args, params, 0)?;
+    let pack = SymbolPack::from_str(&name, true).map_err(Error::InvalidArgument)?;
+    Ok(Commands::ApplyPack(pack))
+}
+
+fn size_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    Ok(Commands::Size)
+}
+
+// the expression may be split into multiple arguments by spaces
+fn calc_parser(args: &[&str], _params: &[Param]) -> FdResult<Commands> {
+    if args.is_empty() {
+        return Err(Error::InsufficientArguments);
+    }
+    Ok(Commands::Calc(args.join(" ")))
+}
+
+fn duplicates_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    let min_len = auto_radix_usize(&try_get_arg(args, params, 0)?)?;
+    Ok(Commands::Duplicates(min_len))
+}
+
+fn propagate_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    let min_len = auto_radix_usize(&try_get_arg(args, params, 0)?)?;
+    Ok(Commands::Propagate(min_len))
+}
+
+fn interrupts_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    Ok(Commands::Interrupts)
+}
+
+fn cfg_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    let path = get_optional_arg(args, params, 0);
+    Ok(Commands::ControlFlow(path.map(|p| expand_path(&p))))
+}
+
+fn stack_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    Ok(Commands::StackEffects)
+}
+
+fn dispatch_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    Ok(Commands::Dispatch)
+}
+
+fn stats_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    Ok(Commands::Stats)
+}
+
+fn strings_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    let min_len = auto_radix_usize(&try_get_arg(args, params, 0)?)?;
+    Ok(Commands::Strings(min_len))
+}
+
+fn pointers_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    let min_len = auto_radix_usize(&try_get_arg(args, params, 0)?)?;
+    let width = match auto_radix_usize(&try_get_arg(args, params, 1)?)? {
+        16 => 2,
+        24 => 3,
+        bits => {
+            return Err(Error::InvalidArgument(format!(
+                "Pointers have 16 or 24 bits, not {bits}"
+            )))
+        }
+    };
+    Ok(Commands::Pointers(min_len, width))
+}
+
+fn class_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    let class = ByteClass::from_str(&try_get_arg(args, params, 0)?, true)
+        .map_err(Error::InvalidArgument)?;
+    match ReadRange::parse(&try_get_arg(args, params, 1)?)? {
+        ReadRange::Offsets(start, end) => Ok(Commands::Classify(class, start, end)),
+        ReadRange::Len(_) => Err(Error::InvalidArgument(
+            "The range needs a start and an end".into(),
+        )),
+    }
+}
+
+fn classes_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    Ok(Commands::Classes)
+}
+
+fn similar_parser(args: &[&str], params: &[Param]) -> FdResult<Commands> {
+    has_too_many_args(args, params)?;
+    let percent = auto_radix_usize(&try_get_arg(args, params, 0)?)?;
+    let min_len = auto_radix_usize(&try_get_arg(args, params, 1)?)?;
+    Ok(Commands::Similar(percent, min_len))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::dasm::{
+            arch::{a6502, Context},
+            symbols::{Scope, Symbol, SymbolKind},
+        },
+        prelude::Config,
+    };
+
+    use super::{default_actions, CommandContext, ReadRange};
+
+    #[test]
+    fn ranges() {
+        assert_eq!(ReadRange::Len(0x10), ReadRange::parse("0x10").unwrap());
+        assert_eq!(
+            ReadRange::Offsets(0x10, 0x20),
+            ReadRange::parse("0x10..0x20").unwrap()
+        );
+        let inclusive = ReadRange::parse("0x10..=0x1f").unwrap();
+        assert_eq!(ReadRange::Offsets(0x10, 0x20), inclusive);
+        assert_eq!(0x10, inclusive.len());
+        assert_eq!(0, ReadRange::parse("4..4").unwrap().len());
+        assert!(ReadRange::parse("0x20..0x10").is_err());
+        assert!(ReadRange::parse("0x10..").is_err());
+    }
+
+    #[test]
+    fn prompt() {
+        let mut cmd_ctx = CommandContext {
+            actions: default_actions(),
+            path: Some("roms/game.nes".into()),
+            ..Default::default()
+        };
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        ctx.set_start(Some(0x10));
+        let template = "{file} {org}:{address}{modified}> ";
+        assert_eq!("game.nes 8000:8010> ", cmd_ctx.prompt(template, &ctx));
+
+        let mut arch = a6502::ARCH.to_owned();
+        let mut execute = |cmd_ctx: &mut CommandContext, ctx: &mut Context, input: &str| {
+            cmd_ctx
+                .execute(
+                    |_s, _kind| Ok(()),
+                    |_n, _kind, _raw, _arch, _ctx| Ok(()),
+                    input,
+                    &mut arch,
+                    ctx,
+                    &Config::default(),
+                )
+                .unwrap()
+        };
+        execute(&mut cmd_ctx, &mut ctx, "window");
+        assert!(!cmd_ctx.modified);
+        execute(&mut cmd_ctx, &mut ctx, "dca 0x20");
+        assert_eq!("game.nes 8000:8020*> ", cmd_ctx.prompt(template, &ctx));
+    }
+
+    #[test]
+    fn temporary_symbols() {
+        let mut cmd_ctx = CommandContext {
+            actions: default_actions(),
+            ..Default::default()
+        };
+        let mut ctx = Context::default();
+        let mut arch = a6502::ARCH.to_owned();
+        let mut output = String::new();
+        for input in ["tmpsym probe 0x8010", "tmpsym size 4 const", "syms"] {
+            cmd_ctx
+                .execute(
+                    |s, _kind| {
+                        output.push_str(s);
+                        Ok(())
+                    },
+                    |_n, _kind, _raw, _arch, _ctx| Ok(()),
+                    input,
+                    &mut arch,
+                    &mut ctx,
+                    &Config::default(),
+                )
+                .unwrap();
+        }
+        assert!(!cmd_ctx.modified);
+        assert_eq!("~probe = 0x8010 (Label)\n~size = 0x4 (Const)\n", output);
+        let saved = ron::to_string(&ctx).unwrap();
+        assert!(!saved.contains("probe"));
+
+        // defining the symbol again keeps it
+        ctx.def_symbol(Symbol::new(
+            "probe".into(),
+            SymbolKind::Label,
+            Scope::Global,
+            0x8010,
+            1,
+        ));
+        assert_eq!(1, ctx.syms.get_symbols(0x8010).len());
+        assert_eq!(1, ctx.syms.temporary().count());
+        assert!(ron::to_string(&ctx).unwrap().contains("probe"));
+    }
+
+    #[test]
+    fn tags() {
+        let mut cmd_ctx = CommandContext {
+            actions: default_actions(),
+            // jsr 8006, jmp 8006, 8006: rts
+            data: vec![0x20, 0x06, 0x80, 0x4C, 0x06, 0x80, 0x60],
+            ..Default::default()
+        };
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        let mut arch = a6502::ARCH.to_owned();
+        let mut output = String::new();
+        for input in [
+            "tmpsym play 0x8006",
+            "tmpsym init 0x8000",
+            "tag play audio",
+            "tag init audio",
+            "tag init verified",
+            "untag init audio",
+            "syms audio",
+            "xrefs audio",
+        ] {
+            cmd_ctx
+                .execute(
+                    |s, _kind| {
+                        output.push_str(s);
+                        Ok(())
+                    },
+                    |_n, _kind, _raw, _arch, _ctx| Ok(()),
+                    input,
+                    &mut arch,
+                    &mut ctx,
+                    &Config::default(),
+                )
+                .unwrap();
+        }
+        assert_eq!("~play = 0x8006 (Label)\nplay: 8000, 8003\n", output);
+        assert!(ctx
+            .syms
+            .get_first_by_name("init")
+            .unwrap()
+            .tags
+            .contains("verified"));
+        // the analysis of the references does not define labels
+        assert_eq!(2, ctx.syms.iter().count());
+        assert!(cmd_ctx
+            .execute(
+                |_s, _kind| Ok(()),
+                |_n, _kind, _raw, _arch, _ctx| Ok(()),
+                "tag missing audio",
+                &mut arch,
+                &mut ctx,
+                &Config::default(),
+            )
+            .is_err());
+    }
+}
@@ -1,6 +1,6 @@
 pub mod command;
 
-use std::io::LineWriter;
+use std::{io::LineWriter, path::PathBuf};
 
 use crate::{
     core::dasm::arch::{Archs, Context},
@@ -8,7 +8,7 @@ use crate::{
 };
 use rustyline::error::ReadlineError;
 
-use self::command::{default_actions, CommandContext};
+use self::command::{default_actions, CommandContext, DEFAULT_PROMPT};
 
 use super::print_callback;
 
@@ -28,17 +28,24 @@ pub fn command_line(
     mut arch: Archs,
     mut ctx: Context,
     data: Vec<u8>,
+    path: Option<PathBuf>,
 ) -> FdResult<()> {
     let mut rl = rustyline::DefaultEditor::new().expect("Unable to init interactive mode");
+    if let Some(history) = &cfg.history {
+        // the history file does not exist on the first run
+        let _ = rl.load_history(history);
+    }
     let actions = default_actions();
     let mut cmd_ctx = CommandContext {
         actions,
         data,
+        path,
         ..Default::default()
     };
+    let template = cfg.prompt.as_deref().unwrap_or(DEFAULT_PROMPT);
     let mut output = LineWriter::new(std::io::stdout().lock());
     loop {
-        let readline = rl.readline(">> ");
+        let readline = rl.readline(&cmd_ctx.prompt(template, &ctx));
         match readline {
             Ok(line) => {
                 if !line.is_empty() {
@@ -55,10 +62,20 @@ pub fn command_line(
                     ) {
                         eprintln!("{:?}", err);
                     }
+                    if cmd_ctx.exit {
+                        break;
+                    }
                 }
             }
-            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => return Ok(()),
+            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => break,
             Err(err) => eprintln!("{:?}", err),
         }
     }
+    // q, ctrl-c and ctrl-d all end the session here
+    if let Some(history) = &cfg.history {
+        if let Err(err) = rl.save_history(history) {
+            eprintln!("Unable to save history: {err}");
+        }
+    }
+    Ok(())
 }
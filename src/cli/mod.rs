@@ -1,21 +1,27 @@
+pub mod db;
 pub mod interactive;
 
 use crate::{
     core::{
-        config::generate_completion,
+        config::{generate_completion, ArchKind},
         dasm::{
-            arch::{Arch, Archs, CallbackKind, Context, Node},
-            Address,
+            arch::{layout::Column, Arch, Archs, CallbackKind, Context, Node},
+            check,
+            loader::{self, hexdump},
+            output::SourceMap,
+            segment::Segment,
+            split, Address,
         },
-        error::FdResult,
+        error::{Error, FdResult},
     },
-    prelude::{Config, DefSym, DisasCommand},
+    prelude::{input_name, Config, DefSym, DisasCommand, SymFileCommand},
 };
-use log::{info, LevelFilter};
+use log::{info, warn, LevelFilter};
 use simple_logger::SimpleLogger;
 use std::{
     io::{prelude::*, LineWriter},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
 };
 
 use self::interactive::{
@@ -26,13 +32,30 @@ use self::interactive::{
 const CTX_DEFAULT_FILE: &str = "./ctx.ron";
 const CTX_DEFAULT_FILE_VAR: &str = "LITEDASM_CTX_PATH";
 
-fn get_ctx_file(cfg: &Config) -> Option<PathBuf> {
+// the file the ctx is written to unless a path is passed
+fn default_ctx_file() -> PathBuf {
+    match std::env::var(CTX_DEFAULT_FILE_VAR) {
+        Ok(val) => val.into(),
+        Err(_) => CTX_DEFAULT_FILE.into(),
+    }
+}
+
+fn get_ctx_file(cfg: &Config, db_entry: Option<&Path>) -> Option<PathBuf> {
     if let Some(path) = &cfg.ctx_file {
+        // commands that write the ctx create the file
+        if !path.exists() {
+            info!("'{}' does not exist yet", path.display());
+            return None;
+        }
         return Some(path.to_owned());
     }
     let path: PathBuf = match std::env::var(CTX_DEFAULT_FILE_VAR) {
         Ok(val) => val.into(),
-        Err(_) => CTX_DEFAULT_FILE.into(),
+        // a previous analysis of the input is preferred over the default file
+        Err(_) => match db_entry {
+            Some(entry) if entry.exists() => entry.to_owned(),
+            _ => CTX_DEFAULT_FILE.into(),
+        },
     };
 
     if !path.exists() {
@@ -42,8 +65,39 @@ fn get_ctx_file(cfg: &Config) -> Option<PathBuf> {
     }
 }
 
-pub fn read_ctx(cfg: &Config) -> FdResult<Context> {
-    let mut ctx = if let Some(path) = get_ctx_file(cfg) {
+// what write_ctx does with the ctx
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum CtxWrite {
+    Write,
+    // asks before the file is created
+    Ask,
+    // prints the ctx instead of creating the file
+    Print,
+}
+
+// only a new default ctx file needs --yes or an answer,
+// without a terminal there is nobody to ask
+fn ctx_write(explicit: bool, exists: bool, yes: bool, attended: bool) -> CtxWrite {
+    if explicit || exists || yes {
+        CtxWrite::Write
+    } else if attended {
+        CtxWrite::Ask
+    } else {
+        CtxWrite::Print
+    }
+}
+
+// asks before a default ctx file is created
+fn confirm_create(path: &Path) -> FdResult<bool> {
+    eprint!("Create the context file '{}'? [y/N] ", path.display());
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Reads the ctx from the path that get_ctx_file resolved, the default ctx if there is none
+pub fn read_ctx(cfg: &Config, path: Option<&Path>) -> FdResult<Context> {
+    let mut ctx = if let Some(path) = path {
         info!(
             "Reading from context path '{}'",
             path.to_str().unwrap_or("")
@@ -58,6 +112,9 @@ pub fn read_ctx(cfg: &Config) -> FdResult<Context> {
     if let Some(org) = cfg.org {
         ctx.org = org;
     }
+    if let Some(memory_map) = cfg.memory_map {
+        ctx.memory_map = memory_map;
+    }
     ctx.set_start(cfg.start_read);
     if let Some(label) = &cfg.start_at_label {
         ctx.set_start_to_symbol(label)?;
@@ -66,6 +123,85 @@ pub fn read_ctx(cfg: &Config) -> FdResult<Context> {
 
     // ctx.set_end(cfg.end_read);
     ctx.set_len(cfg.read_len);
+    if !cfg.terminator.is_empty() {
+        ctx.set_terminator(Some(cfg.terminator.clone()));
+    }
+
+    for (name, value) in &cfg.var {
+        ctx.def_var(name, value.clone());
+    }
+    for (name, value) in &cfg.flag {
+        ctx.def_flag(name, value);
+    }
+    if let Some(data_per_line) = cfg.data_per_line {
+        ctx.output.data_per_line = data_per_line;
+    }
+    if let Some(line_width) = cfg.line_width {
+        ctx.output.line_width = line_width;
+    }
+    ctx.output.ascii_sidebar |= cfg.ascii_sidebar;
+    ctx.output.org_directive |= cfg.org_directive;
+    ctx.output.reassemble |= cfg.reassemble;
+    ctx.output.size_report |= cfg.size_report;
+    ctx.output.crossing_comments |= cfg.crossing_comments;
+    ctx.zp_vars |= cfg.zp_vars;
+    ctx.label_prefixes.extend(cfg.label_prefix.iter().cloned());
+    ctx.descent |= cfg.descent;
+    ctx.entry_points.extend(&cfg.entry);
+    // only the 6502 family has its vectors at the end of the address space
+    ctx.cpu_vectors |= matches!(
+        cfg.arch(),
+        ArchKind::Arch6502 | ArchKind::Arch65c02 | ArchKind::Arch65c816
+    );
+    ctx.native_vectors |= matches!(cfg.arch(), ArchKind::Arch65c816);
+    ctx.output.xrefs |= cfg.xrefs;
+    ctx.output.json |= cfg.json;
+    ctx.output.clean |= cfg.clean;
+    ctx.output.markdown |= cfg.markdown;
+    ctx.output.hexdump |= cfg.hexdump;
+    ctx.output.cycles |= cfg.cycles;
+    ctx.output.uppercase_mnemonics |= cfg.uppercase_mnemonics;
+    ctx.output.uppercase_hex |= cfg.uppercase_hex;
+    if !cfg.line_order.is_empty() {
+        ctx.output.line_order = cfg.line_order.clone();
+    }
+    if let Some(comment_column) = cfg.comment_column {
+        ctx.output.comment_column = Some(comment_column);
+    }
+    if let Some(tag) = &cfg.filter_tag {
+        ctx.output.filter_tag = Some(tag.clone());
+    }
+    if !cfg.columns.is_empty() || cfg.address_width.is_some() || cfg.code_width.is_some() {
+        let layout = ctx.output.layout.get_or_insert_with(Default::default);
+        if !cfg.columns.is_empty() {
+            layout.columns = cfg.columns.clone();
+        }
+        if let Some(address_width) = cfg.address_width {
+            layout.address_width = address_width;
+        }
+        if let Some(code_width) = cfg.code_width {
+            layout.code_width = code_width;
+        }
+        // listing the raw column shows it
+        ctx.allow_raw |= cfg.columns.contains(&Column::Raw);
+    }
+    for pack in &cfg.pack {
+        pack.apply(&mut ctx);
+    }
+    ctx.regions.extend(cfg.region.iter().cloned());
+    if let Some(path) = &cfg.segment_map {
+        let data = std::fs::read_to_string(path)?;
+        let segments: Vec<Segment> = ron::from_str(&data).map_err(|_| Error::FileDeserError)?;
+        ctx.segments.extend(segments);
+    }
+    ctx.segments.extend(cfg.segment.iter().cloned());
+    ctx.gaps.extend(cfg.gap.iter().cloned());
+    ctx.metadata.extend(cfg.metadata.iter().cloned());
+    ctx.endianess.extend(cfg.endianess.iter().cloned());
+    ctx.overlays.extend(cfg.overlay.iter().cloned());
+    for (name, lookup) in &cfg.namespace_lookup {
+        ctx.namespace_lookup.insert(name.clone(), lookup.clone());
+    }
 
     Ok(ctx)
 }
@@ -81,17 +217,40 @@ fn verbose_to_level_filter(v: u8) -> LevelFilter {
     }
 }
 
-pub fn write_ctx(cfg: &Config, ctx: &Context) -> FdResult<()> {
+/// Writes the ctx back to the file it was read from, e.g. its database entry,
+/// otherwise to --ctx-file, LITEDASM_CTX_PATH or ./ctx.ron
+/// A default file that does not exist yet is only created after confirming,
+/// otherwise the ctx is printed
+pub fn write_ctx(cfg: &Config, ctx: &Context, read_from: Option<&Path>) -> FdResult<()> {
     let data =
         ron::ser::to_string_pretty(ctx, Default::default()).expect("Unable to convert context");
 
-    if let Some(path) = get_ctx_file(cfg) {
-        info!("Writing context to '{}'", path.to_str().unwrap_or(""));
-        let mut f = std::fs::File::create(path)?;
-        f.write_all(&data.into_bytes())?;
-    } else {
+    let path = match read_from {
+        Some(path) => path.to_owned(),
+        None => cfg.ctx_file.clone().unwrap_or_else(default_ctx_file),
+    };
+    let create = match ctx_write(
+        read_from.is_some() || cfg.ctx_file.is_some(),
+        path.exists(),
+        cfg.yes,
+        console::user_attended_stderr(),
+    ) {
+        CtxWrite::Write => true,
+        CtxWrite::Ask => confirm_create(&path)?,
+        CtxWrite::Print => false,
+    };
+    if !create {
+        eprintln!(
+            "Not creating '{}', pass --ctx-file or --yes to write the context",
+            path.display()
+        );
         println!("{data}");
+        return Ok(());
     }
+
+    let mut f = std::fs::File::create(&path)?;
+    f.write_all(&data.into_bytes())?;
+    eprintln!("Context written to '{}'", path.display());
     Ok(())
 }
 
@@ -107,8 +266,30 @@ pub fn init(cfg: &Config) -> FdResult<()> {
     }
 
     // first get the arch
-    let mut arch = cfg.arch.to_arch(cfg)?;
-    let mut ctx = read_ctx(cfg)?;
+    let mut arch = cfg.arch().to_arch(cfg)?;
+    for (name, kind) in &cfg.coprocessor {
+        arch.add_coprocessor(name, &kind.to_arch(cfg)?);
+    }
+    if let Some(dialect) = cfg.dialect {
+        dialect.apply(&mut arch);
+    }
+    let input = match &cfg.command {
+        Some(crate::prelude::Commands::Disas(d)) | Some(crate::prelude::Commands::Patch(d)) => {
+            d.input_path()
+        }
+        Some(crate::prelude::Commands::Interactive { input })
+        | Some(crate::prelude::Commands::CheckCtx { input }) => Some(input.as_path()),
+        _ => None,
+    };
+    // the input is read once for its database entry and the command
+    let input_data = input.map(std::fs::read).transpose()?;
+    let db_entry = db::input_entry(cfg, input_data.as_deref());
+    let ctx_path = get_ctx_file(cfg, db_entry.as_deref());
+    let ctx_path = ctx_path.as_deref();
+    let mut ctx = read_ctx(cfg, ctx_path)?;
+    if let Some(layout) = &ctx.output.layout {
+        layout.apply(&mut arch)?;
+    }
 
     // run commands using the parser
     {
@@ -129,39 +310,107 @@ pub fn init(cfg: &Config) -> FdResult<()> {
                 &mut ctx,
                 cfg,
             )?;
+            // q ends the program before the command runs
+            if interactive.exit {
+                std::process::exit(0);
+            }
+        }
+    }
+
+    // the entry is saved before the input is loaded
+    // the loader adds its regions and labels again on every run
+    if cfg.db_save {
+        match &db_entry {
+            Some(entry) => db::save(entry, &ctx)?,
+            None => warn!("The analysis database is only used for input files"),
         }
     }
 
     if let Some(command) = &cfg.command {
         match command {
-            crate::prelude::Commands::Org { address } => org(cfg, *address, &arch, &mut ctx),
-            crate::prelude::Commands::Disas(d) => disas(cfg, d, &arch, &mut ctx),
+            crate::prelude::Commands::Org { address } => {
+                org(cfg, *address, &arch, &mut ctx, ctx_path)
+            }
+            crate::prelude::Commands::Disas(d) => disas(cfg, d, input_data, &arch, &mut ctx),
             crate::prelude::Commands::DumpArch => dump_arch(cfg, &arch),
             crate::prelude::Commands::DumpCtx => dump_ctx(cfg, &ctx),
-            crate::prelude::Commands::DefSym(ds) => defsym(cfg, ds, &arch, &mut ctx),
-            crate::prelude::Commands::Patch(d) => patch(cfg, d, &mut ctx),
+            crate::prelude::Commands::DefSym(ds) => defsym(cfg, ds, &arch, &mut ctx, ctx_path),
+            crate::prelude::Commands::Patch(d) => patch(cfg, d, input_data, &mut ctx),
+            crate::prelude::Commands::Unhex(d) => unhex(cfg, d, &mut ctx, ctx_path),
+            crate::prelude::Commands::ImportSyms(s) => import_syms(cfg, s, &mut ctx, ctx_path),
+            crate::prelude::Commands::ExportSyms(s) => export_syms(cfg, s, &ctx),
+            crate::prelude::Commands::CheckCtx { input } => {
+                check_ctx(cfg, input, input_data.unwrap_or_default(), &arch, &mut ctx)
+            }
             crate::prelude::Commands::Interactive { input } => {
-                let mut f = std::fs::File::open(input)?;
-                let mut buffer = Vec::new();
-                f.read_to_end(&mut buffer)?;
-                interactive::command_line(cfg, arch, ctx, buffer)
+                let buffer = input_data.unwrap_or_default();
+                let buffer = cfg.loader(Some(input)).load(buffer, &mut ctx)?;
+                interactive::command_line(cfg, arch, ctx, buffer, Some(input.clone()))
             }
         }
     } else {
-        interactive::command_line(cfg, arch, ctx, vec![])
+        interactive::command_line(cfg, arch, ctx, vec![], None)
     }
 }
 
-fn patch(_cfg: &Config, disas: &DisasCommand, ctx: &mut Context) -> FdResult<()> {
-    // set up io
-    let mut input = disas.input()?;
-    let mut output = disas.output()?;
-
-    // read all the input data into a buffer
-    // FIXME this may be bad for larger files!
+fn unhex(
+    cfg: &Config,
+    unhex: &DisasCommand,
+    ctx: &mut Context,
+    ctx_path: Option<&Path>,
+) -> FdResult<()> {
+    let mut input = unhex.input()?;
     let mut buffer = Vec::new();
     input.read_to_end(&mut buffer)?;
 
+    let data = hexdump::unhex(&buffer, ctx)?;
+    unhex.output()?.write_all(&data)?;
+
+    // the segments are needed to disassemble the binary at the records' addresses
+    // and the gaps to skip the fill bytes
+    if !ctx.segments.is_empty() || !ctx.gaps.is_empty() {
+        if unhex.is_stdout() && cfg.ctx_file.is_none() {
+            eprintln!("Pass --ctx-file to save the segments and gaps of the input");
+        } else {
+            write_ctx(cfg, ctx, ctx_path)?;
+        }
+    }
+    Ok(())
+}
+
+fn import_syms(
+    cfg: &Config,
+    syms: &SymFileCommand,
+    ctx: &mut Context,
+    ctx_path: Option<&Path>,
+) -> FdResult<()> {
+    let data = match &syms.path {
+        Some(path) => std::fs::read_to_string(path)?,
+        None => std::io::read_to_string(std::io::stdin())?,
+    };
+    let count = syms.format.import(syms.path.as_deref(), &data, ctx)?;
+    info!("Imported {count} symbols");
+    write_ctx(cfg, ctx, ctx_path)
+}
+
+fn export_syms(_cfg: &Config, syms: &SymFileCommand, ctx: &Context) -> FdResult<()> {
+    let data = syms.format.export(ctx)?;
+    match &syms.path {
+        Some(path) => std::fs::write(path, data)?,
+        None => print!("{data}"),
+    }
+    Ok(())
+}
+
+fn patch(
+    _cfg: &Config,
+    disas: &DisasCommand,
+    input: Option<Vec<u8>>,
+    ctx: &mut Context,
+) -> FdResult<()> {
+    let buffer = read_input(disas, input)?;
+    let mut output = disas.output()?;
+
     let res = ctx.patch(&buffer)?;
     output.write_all(&res)?;
 
@@ -184,9 +433,35 @@ fn dump_ctx(_cfg: &Config, ctx: &Context) -> FdResult<()> {
     Ok(())
 }
 
-fn org(cfg: &Config, address: Address, _arch: &Archs, ctx: &mut Context) -> FdResult<()> {
+fn check_ctx(
+    cfg: &Config,
+    input: &Path,
+    buffer: Vec<u8>,
+    arch: &Archs,
+    ctx: &mut Context,
+) -> FdResult<()> {
+    let data = cfg.loader(Some(input)).load(buffer, ctx)?;
+    let findings = check::check(arch, ctx, &data);
+    for finding in &findings {
+        println!("{finding}");
+    }
+    if findings.is_empty() {
+        println!("The context is consistent with the input");
+    } else {
+        println!("{} problems found", findings.len());
+    }
+    Ok(())
+}
+
+fn org(
+    cfg: &Config,
+    address: Address,
+    _arch: &Archs,
+    ctx: &mut Context,
+    ctx_path: Option<&Path>,
+) -> FdResult<()> {
     ctx.org = address;
-    write_ctx(cfg, ctx)
+    write_ctx(cfg, ctx, ctx_path)
 }
 
 fn print_callback<T>(
@@ -213,55 +488,154 @@ where
     if cfg.no_color {
         write!(output, "{}", node.string)?;
     } else {
-        use console::style;
-        let s = &node.string;
-
-        write!(
-            output,
-            "{}",
-            match kind {
-                CallbackKind::Val => style(s).cyan(),
-                CallbackKind::Raw => style(s).red(),
-                CallbackKind::Address => style(s).yellow(),
-                CallbackKind::Label => style(s).green(),
-                CallbackKind::Symbol => style(s).cyan(),
-                CallbackKind::MatcherName => style(s),
-                CallbackKind::Static => style(s),
-                _ => style(s),
-            }
-        )?;
+        write!(output, "{}", cfg.theme.style(&kind).apply_to(&node.string))?;
     }
     Ok(())
 }
 
-fn disas(cfg: &Config, disas: &DisasCommand, arch: &Archs, ctx: &mut Context) -> FdResult<()> {
+// the pager is only used when writing to a terminal
+fn spawn_pager(cfg: &Config, disas: &DisasCommand) -> FdResult<Option<Child>> {
+    match &cfg.pager {
+        Some(pager) if disas.is_stdout() && !cfg.no_pager && console::user_attended() => Ok(Some(
+            Command::new("sh")
+                .arg("-c")
+                .arg(pager)
+                .stdin(Stdio::piped())
+                .spawn()?,
+        )),
+        _ => Ok(None),
+    }
+}
+
+fn disas(
+    cfg: &Config,
+    disas: &DisasCommand,
+    input: Option<Vec<u8>>,
+    arch: &Archs,
+    ctx: &mut Context,
+) -> FdResult<()> {
+    let buffer = read_input(disas, input)?;
     // set up io
-    let mut input = disas.input()?;
-    let mut output = disas.output()?;
+    let mut pager = spawn_pager(cfg, disas)?;
+    let mut output: Box<dyn Write> = match pager.as_mut().and_then(|p| p.stdin.take()) {
+        Some(stdin) => Box::new(LineWriter::new(stdin)),
+        None => disas.output()?,
+    };
 
-    // read all the input data into a buffer
-    // FIXME this may be bad for larger files!
-    let mut buffer = Vec::new();
-    input.read_to_end(&mut buffer)?;
+    let mut buffer = cfg.loader(disas.input_path()).load(buffer, ctx)?;
+    let main = input_name(disas.input_path());
+    loader::append_files(&mut buffer, &main, &disas.files, ctx)?;
 
     // first pass - generate symbols
-    if disas.pre_analyze {
+    if disas.pre_analyze || ctx.zp_vars || ctx.output.xrefs {
+        // the output starts with the flags the analysis started with, e.g. the register widths
+        let flags = ctx.flags.clone();
         ctx.analyze = true;
         arch.disas_ctx(|_node, _kind, _data, _arch, _ctx| Ok(()), &buffer, ctx)?;
         ctx.restart();
         ctx.analyze = false;
+        ctx.flags = flags;
+    }
+
+    if let Some(dir) = &disas.split {
+        if disas.source_map.is_some() {
+            return Err(Error::InvalidArgument(
+                "The source map can not be written for a split output".into(),
+            ));
+        }
+        return split(cfg, dir, arch, &buffer, ctx);
+    }
+    if disas.source_map.is_some() {
+        ctx.source_map = Some(SourceMap::default());
     }
 
     // second pass - the actual output
+    // reassemble mode only writes the output once it is verified
+    let mut verified = Vec::new();
+    let mut out: &mut dyn Write = if ctx.output.byte_exact() {
+        &mut verified
+    } else {
+        &mut output
+    };
     arch.disas_ctx(
-        |node, kind, data, arch, ctx| print_callback(node, kind, data, arch, ctx, &mut output, cfg),
+        |node, kind, data, arch, ctx| print_callback(node, kind, data, arch, ctx, &mut out, cfg),
         &buffer,
         ctx,
     )?;
+    output.write_all(&verified)?;
+    if let (Some(path), Some(map)) = (&disas.source_map, ctx.source_map.take()) {
+        std::fs::write(path, map.to_string())?;
+    }
+    // the report must not end up in the output file
+    if ctx.output.size_report {
+        eprint!("{}", ctx.size_report);
+    }
+
+    // close the pager's input and wait for the user to quit it
+    drop(output);
+    if let Some(mut pager) = pager {
+        pager.wait()?;
+    }
+    Ok(())
+}
+
+// every part is output using its own copy of the context
+fn split(cfg: &Config, dir: &Path, arch: &Archs, data: &[u8], ctx: &Context) -> FdResult<()> {
+    let parts = split::parts(ctx, data)?;
+    std::fs::create_dir_all(dir)?;
+    for part in &parts {
+        let mut part_ctx = ctx.clone();
+        part.select(&mut part_ctx)?;
+        let mut output = LineWriter::new(std::fs::File::create(dir.join(&part.file))?);
+        arch.disas_ctx(
+            |node, kind, data, arch, ctx| {
+                print_callback(node, kind, data, arch, ctx, &mut output, cfg)
+            },
+            data,
+            &mut part_ctx,
+        )?;
+        info!("Wrote {}", part.file);
+    }
+    std::fs::write(
+        dir.join(split::MASTER_FILE),
+        split::master(arch.arch(ctx)?, &parts),
+    )?;
     Ok(())
 }
 
-fn defsym(cfg: &Config, defsym: &DefSym, _arch: &Archs, ctx: &mut Context) -> FdResult<()> {
-    ctx.def_symbol(defsym.clone().into());
-    write_ctx(cfg, ctx)
+fn defsym(
+    cfg: &Config,
+    defsym: &DefSym,
+    _arch: &Archs,
+    ctx: &mut Context,
+    ctx_path: Option<&Path>,
+) -> FdResult<()> {
+    ctx.def_symbol(defsym.clone().symbol(ctx));
+    write_ctx(cfg, ctx, ctx_path)
+}
+
+// the input file that was already read, stdin otherwise
+// FIXME this may be bad for larger files!
+fn read_input(disas: &DisasCommand, input: Option<Vec<u8>>) -> FdResult<Vec<u8>> {
+    if let Some(data) = input {
+        return Ok(data);
+    }
+    let mut buffer = Vec::new();
+    disas.input()?.read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ctx_write, CtxWrite};
+
+    #[test]
+    fn create_ctx() {
+        // a new default file is not created without --yes and a terminal
+        assert_eq!(CtxWrite::Print, ctx_write(false, false, false, false));
+        assert_eq!(CtxWrite::Ask, ctx_write(false, false, false, true));
+        assert_eq!(CtxWrite::Write, ctx_write(false, false, true, false));
+        assert_eq!(CtxWrite::Write, ctx_write(false, true, false, false));
+        assert_eq!(CtxWrite::Write, ctx_write(true, false, false, false));
+    }
 }
@@ -2,14 +2,30 @@ use std::{
     fmt::Display,
     io::{BufReader, LineWriter, Read, Write},
     num::ParseIntError,
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 use super::dasm::{
-    arch::{a6502, a65c02, a65c816, Archs},
-    symbols::{Scope, Symbol, SymbolKind},
+    arch::{
+        a6502, a65c02, a65c816, a6800, a6803, a8051, chip8, dialect::Dialect, layout::Column,
+        msp430, pdp11, sh2, spc700, superfx, Archs, Context, Endianess, EndianessRange, Variable,
+    },
+    loader::{
+        metadata::{Metadata, MetadataLocation},
+        LoaderKind,
+    },
+    memory_map::MemoryMap,
+    output::LineKind,
+    overlay::Overlay,
+    packs::SymbolPack,
+    region::Region,
+    segment::{Gap, Segment},
+    symbols::{split_qualified, ReferenceKind, Scope, Symbol, SymbolKind},
+    symfile::SymFileKind,
     Address, ValueType,
 };
+#[cfg(feature = "cli")]
+use super::user_config::{Theme, UserConfig};
 use crate::prelude::FdResult;
 #[cfg(feature = "cli")]
 use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
@@ -29,6 +45,20 @@ pub struct DisasCommand {
 
     #[cfg_attr(feature = "cli", arg(long, short))]
     pub pre_analyze: bool,
+
+    // additional inputs (path:org[:name]) that are disassembled as segments
+    // after the input and share its symbols
+    #[cfg_attr(feature = "cli", arg(long = "file", value_parser = parse_input_file))]
+    pub files: Vec<InputFile>,
+
+    // writes a file per bank or segment and a master file that includes them
+    // into the directory instead of the output
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub split: Option<PathBuf>,
+
+    // writes the address and the file offset of every output line to the file
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub source_map: Option<PathBuf>,
 }
 
 impl DisasCommand {
@@ -40,17 +70,78 @@ impl DisasCommand {
         })
     }
 
+    pub fn input_path(&self) -> Option<&Path> {
+        self.input.as_deref()
+    }
+
+    pub fn is_stdout(&self) -> bool {
+        self.output.is_none() && self.split.is_none()
+    }
+
     pub fn output(&self) -> FdResult<Box<dyn Write>> {
         Ok(if let Some(path) = &self.output {
-            Box::new(LineWriter::new(
-                std::fs::File::options().write(true).open(path)?,
-            ))
+            Box::new(LineWriter::new(std::fs::File::create(path)?))
         } else {
             Box::new(LineWriter::new(std::io::stdout().lock()))
         })
     }
 }
 
+/// An input that is loaded as a segment at its own org
+#[derive(Clone, Debug, Default)]
+pub struct InputFile {
+    pub path: PathBuf,
+    pub org: Address,
+    // the name of the segment, the file name if not set
+    pub name: Option<String>,
+}
+
+impl InputFile {
+    pub fn name(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| input_name(Some(&self.path)))
+    }
+
+    pub fn read(&self) -> FdResult<Vec<u8>> {
+        Ok(std::fs::read(&self.path)?)
+    }
+}
+
+/// The segment name of an input, stdin is called input
+pub fn input_name(path: Option<&Path>) -> String {
+    path.and_then(|p| p.file_stem())
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "input".into())
+}
+
+/// Parses an input file in the form path:org[:name]
+pub fn parse_input_file(s: &str) -> Result<InputFile, String> {
+    let parts: Vec<&str> = s.split(':').collect();
+    if parts.len() < 2 || parts.len() > 3 {
+        return Err(format!("Expected path:org[:name] but got '{s}'"));
+    }
+    // the shell does not expand the ~ after the = of --file=~/bank.bin
+    #[cfg(feature = "cli")]
+    let path = PathBuf::from(shellexpand::tilde(parts[0]).into_owned());
+    #[cfg(not(feature = "cli"))]
+    let path = PathBuf::from(parts[0]);
+    Ok(InputFile {
+        path,
+        org: auto_radix_address(parts[1]).map_err(|e| e.to_string())?,
+        name: parts.get(2).map(|name| name.to_string()),
+    })
+}
+
+#[cfg_attr(feature = "cli", derive(Args))]
+#[derive(Clone, Debug, Default)]
+pub struct SymFileCommand {
+    #[cfg_attr(feature = "cli", arg(long, short, value_enum, default_value_t))]
+    pub format: SymFileKind,
+    // stdin or stdout if not set
+    pub path: Option<PathBuf>,
+}
+
 #[cfg_attr(feature = "cli", derive(Args))]
 #[derive(Clone, Debug, Default)]
 pub struct DefSym {
@@ -62,16 +153,29 @@ pub struct DefSym {
     len: Option<usize>,
     #[cfg_attr(feature = "cli", clap(long, short))]
     const_value: bool,
+    // the co-processor region the symbol belongs to
+    #[cfg_attr(feature = "cli", clap(long, short))]
+    namespace: Option<String>,
+    // documentation that is output above the label
+    #[cfg_attr(feature = "cli", clap(long))]
+    doc: Option<String>,
+    // tags that group the symbol, e.g. audio
+    #[cfg_attr(feature = "cli", clap(long = "tag"))]
+    tags: Vec<String>,
     name: String,
     #[cfg_attr(feature = "cli", clap(value_parser = auto_radix_value))]
     pub value: ValueType,
 }
 
-#[allow(clippy::from_over_into)]
-impl Into<Symbol> for DefSym {
-    fn into(self) -> Symbol {
+impl DefSym {
+    /// The name may be qualified by a namespace of the context if no namespace is set
+    pub fn symbol(self, ctx: &Context) -> Symbol {
+        let (namespace, name) = match &self.namespace {
+            Some(namespace) => (namespace.as_str(), self.name.as_str()),
+            None => split_qualified(&self.name, |namespace| ctx.is_namespace(namespace)),
+        };
         Symbol {
-            name: self.name.to_owned(),
+            name: name.into(),
             kind: if self.const_value {
                 SymbolKind::Const
             } else {
@@ -88,6 +192,11 @@ impl Into<Symbol> for DefSym {
             value: self.value,
             additional_values: Default::default(),
             len: self.len.unwrap_or(1),
+            namespace: namespace.into(),
+            auto: false,
+            doc: self.doc,
+            tags: self.tags.into_iter().collect(),
+            temporary: false,
         }
     }
 }
@@ -102,8 +211,18 @@ pub enum Commands {
     DefSym(DefSym),
     Disas(DisasCommand),
     Patch(DisasCommand),
+    // converts a hex dump, Intel HEX or S-record file to binary
+    Unhex(DisasCommand),
+    // defines the symbols of a label file in the context
+    ImportSyms(SymFileCommand),
+    // writes the labels of the context to a label file
+    ExportSyms(SymFileCommand),
     DumpArch,
     DumpCtx,
+    // validates the references of the context against the input
+    CheckCtx {
+        input: PathBuf,
+    },
     Interactive {
         input: PathBuf,
     },
@@ -116,12 +235,22 @@ impl Default for Commands {
 }
 
 #[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
 #[derive(Default, Copy, Clone, Debug)]
 pub enum ArchKind {
     #[default]
     Arch6502,
     Arch65c02,
     Arch65c816,
+    ArchSuperFx,
+    Arch8051,
+    Arch6800,
+    Arch6803,
+    ArchMsp430,
+    ArchChip8,
+    ArchPdp11,
+    ArchSh2,
+    ArchSpc700,
     ArchCustom,
 }
 
@@ -131,6 +260,15 @@ impl ArchKind {
             ArchKind::Arch6502 => a6502::ARCH.to_owned(),
             ArchKind::Arch65c02 => a65c02::ARCH.to_owned(),
             ArchKind::Arch65c816 => a65c816::ARCH.to_owned(),
+            ArchKind::ArchSuperFx => superfx::ARCH.to_owned(),
+            ArchKind::Arch8051 => a8051::ARCH.to_owned(),
+            ArchKind::Arch6800 => a6800::ARCH.to_owned(),
+            ArchKind::Arch6803 => a6803::ARCH.to_owned(),
+            ArchKind::ArchMsp430 => msp430::ARCH.to_owned(),
+            ArchKind::ArchChip8 => chip8::ARCH.to_owned(),
+            ArchKind::ArchPdp11 => pdp11::ARCH.to_owned(),
+            ArchKind::ArchSh2 => sh2::ARCH.to_owned(),
+            ArchKind::ArchSpc700 => spc700::ARCH.to_owned(),
             #[cfg(feature = "serde")]
             ArchKind::ArchCustom => ron::from_str(&std::fs::read_to_string(
                 cfg.arch_file.as_ref().expect("No arch file found"),
@@ -149,6 +287,15 @@ impl Display for ArchKind {
             ArchKind::ArchCustom => write!(f, "archCustom"),
             ArchKind::Arch65c02 => write!(f, "arch65c02"),
             ArchKind::Arch65c816 => write!(f, "arch65c816"),
+            ArchKind::ArchSuperFx => write!(f, "archSuperFx"),
+            ArchKind::Arch8051 => write!(f, "arch8051"),
+            ArchKind::Arch6800 => write!(f, "arch6800"),
+            ArchKind::Arch6803 => write!(f, "arch6803"),
+            ArchKind::ArchMsp430 => write!(f, "archMsp430"),
+            ArchKind::ArchChip8 => write!(f, "archChip8"),
+            ArchKind::ArchPdp11 => write!(f, "archPdp11"),
+            ArchKind::ArchSh2 => write!(f, "archSh2"),
+            ArchKind::ArchSpc700 => write!(f, "archSpc700"),
         }
     }
 }
@@ -198,6 +345,136 @@ pub fn auto_radix_usize(s: &str) -> Result<usize, ParseIntError> {
     }
 }
 
+pub fn auto_radix_u8(s: &str) -> Result<u8, String> {
+    let value = auto_radix_usize(s).map_err(|e| e.to_string())?;
+    u8::try_from(value).map_err(|e| e.to_string())
+}
+
+/// Parses a key in the form name=value
+pub fn parse_key_value(s: &str) -> Result<(String, String), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("Expected name=value but got '{s}'"))?;
+    Ok((name.into(), value.into()))
+}
+
+/// Parses a context variable in the form name=value
+pub fn parse_var(s: &str) -> Result<(String, Variable), String> {
+    let (name, value) = parse_key_value(s)?;
+    Ok((name, value.as_str().into()))
+}
+
+/// Parses the lookup order of a namespace in the form name=namespace,namespace
+pub fn parse_namespace_lookup(s: &str) -> Result<(String, Vec<String>), String> {
+    let (name, lookup) = parse_key_value(s)?;
+    Ok((name, lookup.split(',').map(|x| x.to_owned()).collect()))
+}
+
+/// Parses a co-processor in the form name=arch
+#[cfg(feature = "cli")]
+pub fn parse_coprocessor(s: &str) -> Result<(String, ArchKind), String> {
+    let (name, arch) = parse_key_value(s)?;
+    Ok((name, ArchKind::from_str(&arch, true)?))
+}
+
+/// Parses the prefix of the auto labels of a reference kind in the form kind=prefix
+#[cfg(feature = "cli")]
+pub fn parse_label_prefix(s: &str) -> Result<(ReferenceKind, String), String> {
+    let (kind, prefix) = parse_key_value(s)?;
+    Ok((ReferenceKind::from_str(&kind, true)?, prefix))
+}
+
+/// Parses a co-processor region in the form name:start:end[:org]
+/// The region uses the co-processor with the same name
+pub fn parse_region(s: &str) -> Result<Region, String> {
+    let parts: Vec<&str> = s.split(':').collect();
+    if parts.len() < 3 || parts.len() > 4 {
+        return Err(format!("Expected name:start:end[:org] but got '{s}'"));
+    }
+    let usize_at = |i: usize| auto_radix_usize(parts[i]).map_err(|e| e.to_string());
+    let org = match parts.get(3) {
+        Some(org) => auto_radix_address(org).map_err(|e| e.to_string())?,
+        None => 0,
+    };
+    Ok(Region::new(
+        parts[0],
+        parts[0],
+        usize_at(1)?,
+        usize_at(2)?,
+        org,
+    ))
+}
+
+/// Parses a segment in the form name:start:end:org[:arch]
+pub fn parse_segment(s: &str) -> Result<Segment, String> {
+    let parts: Vec<&str> = s.split(':').collect();
+    if parts.len() < 4 || parts.len() > 5 {
+        return Err(format!("Expected name:start:end:org[:arch] but got '{s}'"));
+    }
+    let usize_at = |i: usize| auto_radix_usize(parts[i]).map_err(|e| e.to_string());
+    Ok(Segment::new(
+        parts[0],
+        usize_at(1)?,
+        usize_at(2)?,
+        auto_radix_address(parts[3]).map_err(|e| e.to_string())?,
+        parts.get(4).unwrap_or(&""),
+    ))
+}
+
+/// Parses a gap in the form start:end
+pub fn parse_gap(s: &str) -> Result<Gap, String> {
+    let parts: Vec<&str> = s.split(':').collect();
+    let [start, end] = parts[..] else {
+        return Err(format!("Expected start:end but got '{s}'"));
+    };
+    let offset = |s: &str| auto_radix_usize(s).map_err(|e| e.to_string());
+    Ok(Gap::new(offset(start)?, offset(end)?))
+}
+
+/// Parses an endianess override in the form start:end:little|big
+pub fn parse_endianess(s: &str) -> Result<EndianessRange, String> {
+    let parts: Vec<&str> = s.split(':').collect();
+    let [start, end, endianess] = parts[..] else {
+        return Err(format!("Expected start:end:little|big but got '{s}'"));
+    };
+    let endianess = match endianess {
+        "little" => Endianess::Little,
+        "big" => Endianess::Big,
+        _ => return Err(format!("Expected little or big but got '{endianess}'")),
+    };
+    let address = |s: &str| auto_radix_address(s).map_err(|e| e.to_string());
+    Ok(EndianessRange::new(
+        address(start)?,
+        address(end)?,
+        endianess,
+    ))
+}
+
+/// Parses a bank overlay in the form start:end:flag
+pub fn parse_overlay(s: &str) -> Result<Overlay, String> {
+    let parts: Vec<&str> = s.split(':').collect();
+    let [start, end, flag] = parts[..] else {
+        return Err(format!("Expected start:end:flag but got '{s}'"));
+    };
+    let address = |s: &str| auto_radix_address(s).map_err(|e| e.to_string());
+    Ok(Overlay::new(address(start)?, address(end)?, flag))
+}
+
+/// Parses a metadata block in the form name:start|end:size
+pub fn parse_metadata(s: &str) -> Result<Metadata, String> {
+    let parts: Vec<&str> = s.split(':').collect();
+    let [name, location, size] = parts[..] else {
+        return Err(format!("Expected name:start|end:size but got '{s}'"));
+    };
+    let location = match location {
+        "start" => MetadataLocation::Start,
+        "end" => MetadataLocation::End,
+        _ => return Err(format!("Expected start or end but got '{location}'")),
+    };
+    let size = auto_radix_usize(size).map_err(|e| e.to_string())?;
+    Ok(Metadata::new(name, location, size))
+}
+
 #[derive(Debug, Default)]
 #[cfg_attr(feature = "cli", derive(Parser))]
 #[cfg_attr(feature = "cli", command(author, version, about, long_about = None))]
@@ -208,10 +485,9 @@ pub struct Config {
     // pub input: Option<PatbBuf>,
     // pub output: Option<PathBuf>,
 
-    // built in arch that may be loaded
+    // built in arch that may be loaded (default: arch6502)
     #[cfg_attr(feature = "cli", clap(long, short))]
-    #[cfg_attr(feature = "cli", arg(default_value_t))]
-    pub arch: ArchKind,
+    pub arch: Option<ArchKind>,
 
     // custom arch config file to load
     #[cfg_attr(feature = "cli", clap(long))]
@@ -220,6 +496,34 @@ pub struct Config {
     #[cfg_attr(feature = "cli", clap(long, short))]
     pub ctx_file: Option<PathBuf>,
 
+    // the directory of the analysis database that maps the hashes
+    // of input files to their ctx (default: the user config's db directory)
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub db_dir: Option<PathBuf>,
+
+    // do not look up the input file in the analysis database
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub no_db: bool,
+
+    // store the ctx in the analysis database entry of the input file
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub db_save: bool,
+
+    // create a new default ctx file without asking
+    #[cfg_attr(feature = "cli", arg(long, short))]
+    pub yes: bool,
+
+    // the container format of the input
+    // a loader sets the start, length and org from the header
+    // hex and S-record files are detected by their extension, raw otherwise
+    #[cfg_attr(feature = "cli", clap(long, value_enum))]
+    pub loader: Option<LoaderKind>,
+
+    // headers, trainers and footers (name:start|end:size) that are
+    // stripped from the input
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_metadata))]
+    pub metadata: Vec<Metadata>,
+
     #[cfg_attr(feature = "cli", arg(short, long, action = clap::ArgAction::Count))]
     pub verbose: u8,
 
@@ -234,15 +538,205 @@ pub struct Config {
     #[cfg_attr(feature = "cli", arg(long, short, value_parser = auto_radix_usize))]
     pub read_len: Option<usize>,
 
+    // stop reading after this byte sequence (e.g. 0xFF,0x00)
+    #[cfg_attr(feature = "cli", arg(long, value_delimiter = ',', value_parser = auto_radix_u8))]
+    pub terminator: Vec<u8>,
+
     #[cfg_attr(feature = "cli", arg(long, value_parser = auto_radix_address))]
     pub org: Option<Address>,
 
+    // maps the input offsets to bus addresses
+    #[cfg_attr(feature = "cli", arg(long, value_enum))]
+    pub memory_map: Option<MemoryMap>,
+
     #[cfg_attr(feature = "cli", arg(long))]
     pub no_color: bool,
 
+    // colors the output even if the user config sets no_color
+    #[cfg_attr(feature = "cli", arg(long, conflicts_with = "no_color"))]
+    pub color: bool,
+
+    // pipe the output of disas into this command
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub pager: Option<String>,
+
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub no_pager: bool,
+
+    // the interactive mode's history file
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub history: Option<PathBuf>,
+
+    // the interactive mode's prompt, the placeholders {file}, {arch}, {org}, {address},
+    // {bank} and {modified} are replaced
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub prompt: Option<String>,
+
+    // the output styles from the user config
+    #[cfg_attr(feature = "cli", arg(skip))]
+    #[cfg(feature = "cli")]
+    pub theme: Theme,
+
     #[cfg_attr(feature = "cli", arg(long))]
     pub run: Vec<String>,
 
+    // context variables (name=value) that can be referenced as {var:name}
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_var))]
+    pub var: Vec<(String, Variable)>,
+
+    // context flags (name=value) that are set before disassembling
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_key_value))]
+    pub flag: Vec<(String, String)>,
+
+    // amount of values grouped into a single data line
+    #[cfg_attr(feature = "cli", arg(long, value_parser = auto_radix_usize))]
+    pub data_per_line: Option<usize>,
+
+    // the width that symbol docs are wrapped to
+    #[cfg_attr(feature = "cli", arg(long, value_parser = auto_radix_usize))]
+    pub line_width: Option<usize>,
+
+    // show data lines as ascii in a trailing comment
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub ascii_sidebar: bool,
+
+    // emit the org directive of the loaded file
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub org_directive: bool,
+
+    // output the bytes outside of the window as data and fail
+    // if the output does not cover the input byte for byte
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub reassemble: bool,
+
+    // print the output size per section and bank after disassembling
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub size_report: bool,
+
+    // define var_xx symbols for the zero page addresses the code accesses
+    // runs the analysis pass, named symbols at the addresses take precedence
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub zp_vars: bool,
+
+    // the prefix of the auto labels for a kind of reference (call, branch or data)
+    // in the form kind=prefix, e.g. call=fn_
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_label_prefix))]
+    pub label_prefix: Vec<(ReferenceKind, String)>,
+
+    // only decode the bytes that are reached from the entry points as instructions
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub descent: bool,
+
+    // an entry point of the recursive descent, the vectors are used if there is none
+    #[cfg_attr(feature = "cli", arg(long, value_parser = auto_radix_address))]
+    pub entry: Vec<Address>,
+
+    // output the addresses that reference a label below it, runs the analysis pass
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub xrefs: bool,
+
+    // output a comment after instructions whose bytes cross a bank boundary
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub crossing_comments: bool,
+
+    // output every instruction as a json object on its own line
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub json: bool,
+
+    // output that assembles back to the input without address and raw columns
+    #[cfg_attr(feature = "cli", arg(long, alias = "reasm"))]
+    pub clean: bool,
+
+    // output fenced code blocks with a heading for every label
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub markdown: bool,
+
+    // output a hexdump of the input on the left of the lines
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub hexdump: bool,
+
+    // output the cycles of every instruction and the total since the last label
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub cycles: bool,
+
+    // the order of the lines before an instruction (e.g. comments,labels,statics)
+    #[cfg_attr(feature = "cli", arg(long, value_enum, value_delimiter = ','))]
+    pub line_order: Vec<LineKind>,
+
+    // the assembler syntax of the output (directives, local labels, hex prefixes and org)
+    #[cfg_attr(feature = "cli", arg(long, value_enum))]
+    pub dialect: Option<Dialect>,
+
+    // the order of the columns of a line (e.g. raw,code), columns that are not listed are hidden
+    #[cfg_attr(feature = "cli", arg(long, value_enum, value_delimiter = ','))]
+    pub columns: Vec<Column>,
+
+    // the amount of hex digits of the address column
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub address_width: Option<usize>,
+
+    // the width of the code column if another column follows
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub code_width: Option<usize>,
+
+    // output the single line comments after the instructions at this column
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub comment_column: Option<usize>,
+
+    // only output the sections of the labels that have the tag
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub filter_tag: Option<String>,
+
+    // output the mnemonics in uppercase, directives keep their case
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub uppercase_mnemonics: bool,
+
+    // output the digits of hex values in uppercase
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub uppercase_hex: bool,
+
+    // built in hardware register labels (e.g. nes,c64)
+    #[cfg_attr(feature = "cli", arg(long, value_enum, value_delimiter = ','))]
+    pub pack: Vec<SymbolPack>,
+
+    // built in archs (name=arch) for co-processors that are added
+    // using the name as the arch key
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_coprocessor))]
+    pub coprocessor: Vec<(String, ArchKind)>,
+
+    // file offsets (name:start:end[:org]) that are disassembled using
+    // the co-processor with the same name
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_region))]
+    pub region: Vec<Region>,
+
+    // file offsets (name:start:end:org[:arch]) that are disassembled at their own
+    // org instead of the window
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_segment))]
+    pub segment: Vec<Segment>,
+
+    // a ron file with a list of segments
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub segment_map: Option<PathBuf>,
+
+    // file offsets (start:end) that hold no data, e.g. unreadable parts of a dump
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_gap))]
+    pub gap: Vec<Gap>,
+
+    // addresses (start:end:little|big) whose data directives use a different
+    // endianess than the arch
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_endianess))]
+    pub endianess: Vec<EndianessRange>,
+
+    // address windows (start:end:flag) that are shared by banks, the flag holds
+    // the namespace of the active bank
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_overlay))]
+    pub overlay: Vec<Overlay>,
+
+    // namespaces (name=namespace,namespace) whose symbols may be referenced
+    // from a namespace, the main processor uses an empty name
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_namespace_lookup))]
+    pub namespace_lookup: Vec<(String, Vec<String>)>,
+
     #[cfg_attr(feature = "cli", clap(long, value_name = "SHELL"))]
     #[cfg(feature = "cli")]
     pub completions: Option<Shell>,
@@ -251,7 +745,41 @@ pub struct Config {
 impl Config {
     #[cfg(feature = "cli")]
     pub fn new() -> Self {
-        Self::parse()
+        let mut cfg = Self::parse();
+        if let Some(user) = UserConfig::load() {
+            cfg.merge(user);
+        }
+        cfg
+    }
+
+    /// Uses the user's defaults for everything that was not passed as a flag
+    #[cfg(feature = "cli")]
+    pub fn merge(&mut self, user: UserConfig) {
+        if self.verbose == 0 {
+            self.verbose = user.verbose.unwrap_or_default();
+        }
+        self.no_color = !self.color && (self.no_color || user.no_color.unwrap_or_default());
+        self.arch = self.arch.or(user.arch);
+        self.pager = self.pager.take().or_else(|| user.pager.clone());
+        self.history = self.history.take().or_else(|| user.history());
+        self.prompt = self.prompt.take().or_else(|| user.prompt.clone());
+        self.db_dir = self.db_dir.take().or_else(|| user.db_dir());
+        self.theme = user.theme;
+    }
+
+    /// The loader of the input, record files do not need to pass a loader
+    pub fn loader(&self, input: Option<&Path>) -> LoaderKind {
+        self.loader
+            .or_else(|| input.and_then(LoaderKind::from_path))
+            .unwrap_or_default()
+    }
+
+    pub fn arch(&self) -> ArchKind {
+        self.arch.unwrap_or(match self.loader.unwrap_or_default() {
+            // spc files can only contain spc700 code
+            LoaderKind::Spc => ArchKind::ArchSpc700,
+            _ => Default::default(),
+        })
     }
 
     #[cfg(not(feature = "cli"))]
@@ -3,7 +3,8 @@ use std::collections::BTreeMap;
 use crate::core::dasm::{arch::Archs, DataType, ValueTypeFmt};
 
 use super::{
-    Arch, Matcher, MatcherList, Node, Pattern, PatternAt, Transform, TransformMap, ValOut,
+    Arch, DataOut, Matcher, MatcherList, Node, Pattern, PatternAt, Transform, TransformMap, ValOut,
+    DEFINE_BYTE, DEFINE_LONG, DEFINE_TEXT, DEFINE_WORD,
 };
 use lazy_static::lazy_static;
 
@@ -12,25 +13,25 @@ lazy_static! {
     pub static ref ARCH: Archs = Archs {archs: archs(), ..Default::default()};
 }
 
-pub(super) const IMMEDIATE: &str = "immediate";
-pub(super) const IMMEDIATE16: &str = "immediate16";
-pub(super) const IMMEDIATE_NO_M_FLAG: &str = "immediate_no_m_flag";
-pub(super) const IMMEDIATE_M_FLAG: &str = "immediate_m_flag";
-pub(super) const IMMEDIATE_X_FLAG: &str = "immediate_no_x_flag";
-pub(super) const IMMEDIATE_NO_X_FLAG: &str = "immediate_x_flag";
-pub(super) const ZP: &str = "zp";
-pub(super) const ZP_X: &str = "zp_x";
-pub(super) const ZP_Y: &str = "zp_y";
-pub(super) const ABSOLUTE: &str = "absolute";
-pub(super) const ABSOLUTE_Y: &str = "absolute_y";
-pub(super) const ABSOLUTE_X: &str = "absolute_x";
-pub(super) const INDIRECT_JMP: &str = "indirect_jmp";
-pub(super) const INDIRECT_X: &str = "indirect_x";
-pub(super) const INDIRECT_Y: &str = "indirect_y";
-pub(super) const IMPLIED: &str = "implied";
-pub(super) const ACCUMULATOR: &str = "accumulator";
+pub const IMMEDIATE: &str = "immediate";
+pub const IMMEDIATE16: &str = "immediate16";
+pub const IMMEDIATE_NO_M_FLAG: &str = "immediate_no_m_flag";
+pub const IMMEDIATE_M_FLAG: &str = "immediate_m_flag";
+pub const IMMEDIATE_X_FLAG: &str = "immediate_no_x_flag";
+pub const IMMEDIATE_NO_X_FLAG: &str = "immediate_x_flag";
+pub const ZP: &str = "zp";
+pub const ZP_X: &str = "zp_x";
+pub const ZP_Y: &str = "zp_y";
+pub const ABSOLUTE: &str = "absolute";
+pub const ABSOLUTE_Y: &str = "absolute_y";
+pub const ABSOLUTE_X: &str = "absolute_x";
+pub const INDIRECT_JMP: &str = "indirect_jmp";
+pub const INDIRECT_X: &str = "indirect_x";
+pub const INDIRECT_Y: &str = "indirect_y";
+pub const IMPLIED: &str = "implied";
+pub const ACCUMULATOR: &str = "accumulator";
 // addressing mode for brapubes
-pub(super) const RELATIVE: &str = "relative";
+pub const RELATIVE: &str = "relative";
 
 pub(super) const FLAG_M: &str = "m";
 pub(super) const FLAG_X: &str = "x";
@@ -129,6 +130,7 @@ fn transform_zp(map: &mut TransformMap) {
                 offset: 0,
                 fmt: ValueTypeFmt::LowerHex(2),
                 data_type: DataType::U8,
+                zp_var: true,
                 ..Default::default()
             }),
         ],
@@ -146,6 +148,7 @@ fn transform_zp_x(map: &mut TransformMap) {
                 offset: 0,
                 fmt: ValueTypeFmt::LowerHex(2),
                 data_type: DataType::U8,
+                zp_var: true,
                 ..Default::default()
             }),
             Transform::Static(Node::new(", x".into())),
@@ -164,6 +167,7 @@ fn transform_zp_y(map: &mut TransformMap) {
                 offset: 0,
                 fmt: ValueTypeFmt::LowerHex(2),
                 data_type: DataType::U8,
+                zp_var: true,
                 ..Default::default()
             }),
             Transform::Static(Node::new(", y".into())),
@@ -235,6 +239,7 @@ fn transform_indirect_x(map: &mut TransformMap) {
                 offset: 0,
                 fmt: ValueTypeFmt::LowerHex(2),
                 data_type: DataType::U8,
+                zp_var: true,
                 ..Default::default()
             }),
             Transform::Static(Node::new(", x)".into())),
@@ -253,6 +258,7 @@ fn transform_indirect_y(map: &mut TransformMap) {
                 offset: 0,
                 fmt: ValueTypeFmt::LowerHex(2),
                 data_type: DataType::U8,
+                zp_var: true,
                 ..Default::default()
             }),
             Transform::Static(Node::new("), y".into())),
@@ -288,18 +294,40 @@ fn transforms_default_modes(map: &mut TransformMap) {
     transform_indirect_jmp(map);
 }
 
-pub(super) fn transforms() -> TransformMap {
-    let mut map = BTreeMap::default();
-
+// transforms that are shared by all built-in architectures
+pub(super) fn transforms_common(map: &mut TransformMap) {
     map.insert(
-        "define_byte".into(),
+        DEFINE_BYTE.into(),
         vec![
             Transform::Static(Node::new(".db ".into())),
-            Transform::Val(ValOut {
-                offset: 0,
-                fmt: ValueTypeFmt::LowerHex(2),
-                data_type: crate::core::dasm::DataType::U8,
-                ..Default::default()
+            Transform::Data(DataOut::new(ValueTypeFmt::LowerHex(2), DataType::U8)),
+        ],
+    );
+    map.insert(
+        DEFINE_TEXT.into(),
+        vec![
+            Transform::Static(Node::new(".text ".into())),
+            Transform::Text,
+        ],
+    );
+    // pointer tables are output one pointer per line
+    map.insert(
+        DEFINE_WORD.into(),
+        vec![
+            Transform::Static(Node::new(".word ".into())),
+            Transform::Data(DataOut {
+                per_line: Some(1),
+                ..DataOut::new(ValueTypeFmt::LowerHex(4), DataType::U16)
+            }),
+        ],
+    );
+    map.insert(
+        DEFINE_LONG.into(),
+        vec![
+            Transform::Static(Node::new(".long ".into())),
+            Transform::Data(DataOut {
+                per_line: Some(1),
+                ..DataOut::new(ValueTypeFmt::LowerHex(6), DataType::U24)
             }),
         ],
     );
@@ -307,8 +335,16 @@ pub(super) fn transforms() -> TransformMap {
         "address".into(),
         vec![Transform::Label, Transform::Address(8), Transform::space(1)],
     );
-    map.insert("raw".into(), vec![Transform::Pad(25), Transform::Raw]);
+    map.insert(
+        "raw".into(),
+        vec![Transform::Pad(25), Transform::Raw, Transform::Comment],
+    );
     map.insert("new_line".into(), vec![Transform::new_line()]);
+}
+
+pub(super) fn transforms() -> TransformMap {
+    let mut map = BTreeMap::default();
+    transforms_common(&mut map);
     transforms_default_modes(&mut map);
 
     map
@@ -319,6 +355,7 @@ pub(super) fn matcher1(matchers: &mut MatcherList, op: u8, name: &str, mode: &st
         patterns: vec![PatternAt::new(Pattern::Exact(op), 0)],
         transforms: mode.into(),
         name: Node::new(name.into()),
+        cycles: None,
     })
 }
 
@@ -331,6 +368,7 @@ pub(super) fn matcher_immediate_no_m(matchers: &mut MatcherList, op: u8, name: &
         ],
         transforms: mode.into(),
         name: Node::new(name.into()),
+        cycles: None,
     })
 }
 
@@ -343,6 +381,7 @@ pub(super) fn matcher_immediate_m(matchers: &mut MatcherList, op: u8, name: &str
         ],
         transforms: mode.into(),
         name: Node::new(name.into()),
+        cycles: None,
     })
 }
 
@@ -355,6 +394,7 @@ pub(super) fn matcher_immediate_no_x(matchers: &mut MatcherList, op: u8, name: &
         ],
         transforms: mode.into(),
         name: Node::new(name.into()),
+        cycles: None,
     })
 }
 
@@ -367,6 +407,7 @@ pub(super) fn matcher_immediate_x(matchers: &mut MatcherList, op: u8, name: &str
         ],
         transforms: mode.into(),
         name: Node::new(name.into()),
+        cycles: None,
     })
 }
 
@@ -378,6 +419,7 @@ pub(super) fn matcher2(matchers: &mut MatcherList, op: u8, name: &str, mode: &st
         ],
         transforms: mode.into(),
         name: Node::new(name.into()),
+        cycles: None,
     })
 }
 
@@ -389,6 +431,7 @@ pub(super) fn matcher3(matchers: &mut MatcherList, op: u8, name: &str, mode: &st
         ],
         transforms: mode.into(),
         name: Node::new(name.into()),
+        cycles: None,
     })
 }
 
@@ -588,7 +631,7 @@ fn instruction_map() -> InstructionMap {
             "jmp",
             ModeMap::from([(ABSOLUTE, 0x4C), (INDIRECT_JMP, 0x6C)]),
         ),
-        ("jsr", ModeMap::from([(ABSOLUTE, 0x4C)])),
+        ("jsr", ModeMap::from([(ABSOLUTE, 0x20)])),
         default_instruction_map("lda", 0xA9, 0xA5, 0xB5, 0xAD, 0xBD, 0xB9, 0xA1, 0xB1),
         (
             "ldx",
@@ -738,11 +781,49 @@ pub(super) fn patterns() -> MatcherList {
     list
 }
 
+// the cycles of the documented opcodes by their high and low nibble (0 = not an instruction)
+// page crossings and taken branches add a cycle that is not counted
+#[rustfmt::skip]
+const CYCLES: [[usize; 16]; 16] = [
+    [7, 6, 0, 0, 0, 3, 5, 0, 3, 2, 2, 0, 0, 4, 6, 0],
+    [2, 5, 0, 0, 0, 4, 6, 0, 2, 4, 0, 0, 0, 4, 7, 0],
+    [6, 6, 0, 0, 3, 3, 5, 0, 4, 2, 2, 0, 4, 4, 6, 0],
+    [2, 5, 0, 0, 0, 4, 6, 0, 2, 4, 0, 0, 0, 4, 7, 0],
+    [6, 6, 0, 0, 0, 3, 5, 0, 3, 2, 2, 0, 3, 4, 6, 0],
+    [2, 5, 0, 0, 0, 4, 6, 0, 2, 4, 0, 0, 0, 4, 7, 0],
+    [6, 6, 0, 0, 0, 3, 5, 0, 4, 2, 2, 0, 5, 4, 6, 0],
+    [2, 5, 0, 0, 0, 4, 6, 0, 2, 4, 0, 0, 0, 4, 7, 0],
+    [0, 6, 0, 0, 3, 3, 3, 0, 2, 0, 2, 0, 4, 4, 4, 0],
+    [2, 6, 0, 0, 4, 4, 4, 0, 2, 5, 2, 0, 0, 5, 0, 0],
+    [2, 6, 2, 0, 3, 3, 3, 0, 2, 2, 2, 0, 4, 4, 4, 0],
+    [2, 5, 0, 0, 4, 4, 4, 0, 2, 4, 2, 0, 4, 4, 4, 0],
+    [2, 6, 0, 0, 3, 3, 5, 0, 2, 2, 2, 0, 4, 4, 6, 0],
+    [2, 5, 0, 0, 0, 4, 6, 0, 2, 4, 0, 0, 0, 4, 7, 0],
+    [2, 6, 0, 0, 3, 3, 5, 0, 2, 2, 2, 0, 4, 4, 6, 0],
+    [2, 5, 0, 0, 0, 4, 6, 0, 2, 4, 0, 0, 0, 4, 7, 0],
+];
+
+// the 65c02 and the 65816 take different cycles for some opcodes, so only the 6502 has them
+fn with_cycles(mut list: MatcherList) -> MatcherList {
+    for matcher in list.iter_mut() {
+        if let Some(PatternAt {
+            pattern: Pattern::Exact(op),
+            ..
+        }) = matcher.patterns.first()
+        {
+            let cycles = CYCLES[(op >> 4) as usize][(op & 0xF) as usize];
+            matcher.cycles = (cycles > 0).then_some(cycles);
+        }
+    }
+    list
+}
+
 pub(super) fn add_patterns_default(mut list: MatcherList) -> MatcherList {
     list.push(Matcher {
         patterns: vec![PatternAt::new(Pattern::Any, 0)],
-        transforms: "define_byte".into(),
+        transforms: DEFINE_BYTE.into(),
         name: Node::new(".db".into()),
+        cycles: None,
     });
     list
 }
@@ -752,23 +833,26 @@ fn archs() -> BTreeMap<String, Arch> {
     map.insert(
         "".into(),
         Arch {
-            patterns: add_patterns_default(patterns()),
+            patterns: add_patterns_default(with_cycles(patterns())),
             transforms: transforms(),
             pre_patterns: vec![Matcher {
                 patterns: vec![PatternAt::new(Pattern::Always, 0)],
                 name: "address".into(),
                 transforms: "address".into(),
+                cycles: None,
             }],
             post_patterns: vec![
                 Matcher {
                     patterns: vec![PatternAt::new(Pattern::Always, 0)],
                     name: "raw".into(),
                     transforms: "raw".into(),
+                    cycles: None,
                 },
                 Matcher {
                     patterns: vec![PatternAt::new(Pattern::Always, 0)],
                     name: "new_line".into(),
                     transforms: "new_line".into(),
+                    cycles: None,
                 },
             ],
             node_map: BTreeMap::from([
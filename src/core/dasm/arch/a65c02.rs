@@ -20,6 +20,8 @@ lazy_static! {
 
 const INDIRECT: &str = "indirect";
 const ABS_INDIRECT_X: &str = "abs_indirect_x";
+// zero page address followed by a relative branch target (bbrn, bbsn)
+const ZP_RELATIVE: &str = "zp_relative";
 
 fn transform_indirect(map: &mut TransformMap) {
     map.insert(
@@ -32,6 +34,7 @@ fn transform_indirect(map: &mut TransformMap) {
                 offset: 0,
                 fmt: ValueTypeFmt::LowerHex(2),
                 data_type: DataType::U8,
+                zp_var: true,
                 ..Default::default()
             }),
             Transform::Static(Node::new(")".into())),
@@ -57,10 +60,42 @@ fn transform_absolute_indirect_x(map: &mut TransformMap) {
     );
 }
 
+fn transform_zp_relative(map: &mut TransformMap) {
+    map.insert(
+        ZP_RELATIVE.into(),
+        vec![
+            Transform::MatcherName,
+            Transform::Static(Node::new(" ".into())),
+            Transform::Val(ValOut {
+                offset: 1,
+                fmt: ValueTypeFmt::LowerHex(2),
+                data_type: DataType::U8,
+                peek: true,
+                zp_var: true,
+                ..Default::default()
+            }),
+            Transform::Static(Node::new(", ".into())),
+            // the branch is relative to the end of the 3 byte instruction
+            Transform::OffsetAddress(3),
+            Transform::Val(ValOut {
+                offset: 2,
+                fmt: ValueTypeFmt::LowerHex(2),
+                data_type: DataType::I8,
+                rel: true,
+                peek: true,
+                ..Default::default()
+            }),
+            Transform::OffsetAddress(-3),
+            Transform::Consume(3),
+        ],
+    );
+}
+
 pub(super) fn transforms() -> TransformMap {
     let mut map = super::a6502::transforms();
     transform_indirect(&mut map);
     transform_absolute_indirect_x(&mut map);
+    transform_zp_relative(&mut map);
     map
 }
 
@@ -114,6 +149,30 @@ fn instruction_map() -> InstructionMap {
     ])
 }
 
+// the Rockwell and WDC bit instructions
+// those are not available on the 65816
+fn bit_instruction_map() -> InstructionMap {
+    let mut map = InstructionMap::default();
+    let names = [
+        ("rmb0", "smb0", "bbr0", "bbs0"),
+        ("rmb1", "smb1", "bbr1", "bbs1"),
+        ("rmb2", "smb2", "bbr2", "bbs2"),
+        ("rmb3", "smb3", "bbr3", "bbs3"),
+        ("rmb4", "smb4", "bbr4", "bbs4"),
+        ("rmb5", "smb5", "bbr5", "bbs5"),
+        ("rmb6", "smb6", "bbr6", "bbs6"),
+        ("rmb7", "smb7", "bbr7", "bbs7"),
+    ];
+    for (bit, (rmb, smb, bbr, bbs)) in names.into_iter().enumerate() {
+        let row = (bit as u8) << 4;
+        map.insert(rmb, ModeMap::from([(ZP, row | 0x07)]));
+        map.insert(smb, ModeMap::from([(ZP, row | 0x87)]));
+        map.insert(bbr, ModeMap::from([(ZP_RELATIVE, row | 0x0F)]));
+        map.insert(bbs, ModeMap::from([(ZP_RELATIVE, row | 0x8F)]));
+    }
+    map
+}
+
 fn matcher_indirect(matchers: &mut MatcherList, op: u8, name: &str) {
     matcher2(matchers, op, name, INDIRECT);
 }
@@ -130,6 +189,9 @@ pub(super) fn matchers_from(matchers: &mut MatcherList, instrs: InstructionMap)
         if let Some(op) = modes.get(ABS_INDIRECT_X) {
             matcher_absolute_indirect_x(matchers, *op, k);
         }
+        if let Some(op) = modes.get(ZP_RELATIVE) {
+            matcher3(matchers, *op, k, ZP_RELATIVE);
+        }
     }
     super::a6502::matchers_from(matchers, instrs);
 }
@@ -141,11 +203,14 @@ pub(super) fn patterns() -> MatcherList {
 }
 
 pub(super) fn archs() -> BTreeMap<String, Arch> {
+    let mut patterns = patterns();
+    matchers_from(&mut patterns, bit_instruction_map());
+
     let mut map = BTreeMap::default();
     map.insert(
         "".into(),
         Arch {
-            patterns: super::a6502::add_patterns_default(patterns()),
+            patterns: super::a6502::add_patterns_default(patterns),
             transforms: transforms(),
             // we can unwrap this because the 6502 is guaranteed to have an empty
             // arch key!
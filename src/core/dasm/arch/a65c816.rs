@@ -4,10 +4,10 @@ use crate::core::dasm::{arch::Archs, DataType, ValueTypeFmt};
 
 use super::{
     a6502::{
-        implied_instruction_map, matcher2, matcher3, InstructionMap, ModeMap, ABSOLUTE, IMMEDIATE,
-        IMMEDIATE16,
+        implied_instruction_map, matcher2, matcher3, InstructionMap, ModeMap, ABSOLUTE, FLAG_M,
+        FLAG_X, IMMEDIATE, IMMEDIATE16,
     },
-    Arch, MatcherList, Node, Transform, TransformMap, ValOut,
+    Arch, Matcher, MatcherList, Node, Pattern, PatternAt, Transform, TransformMap, ValOut,
 };
 
 use lazy_static::lazy_static;
@@ -28,6 +28,24 @@ pub(super) const MOVE: &str = "move";
 pub(super) const JUMP_LONG_INDIRECT: &str = "jump_long_indirect";
 pub(super) const JSR_INDIRECT_X: &str = "jsr_indirect_x";
 
+// the emulation flag is set while the cpu is in 6502 emulation mode
+// the carry flag is tracked to know which mode xce switches to
+pub(super) const FLAG_E: &str = "e";
+pub(super) const FLAG_C: &str = "c";
+const CLC: &str = "implied_clc";
+const SEC: &str = "implied_sec";
+// xce transforms for each combination of the carry and emulation flag
+const XCE_NATIVE: &str = "implied_xce_native";
+const XCE_EMULATION: &str = "implied_xce_emulation";
+const XCE_TO_NATIVE: &str = "implied_xce_to_native";
+const XCE_TO_EMULATION: &str = "implied_xce_to_emulation";
+// php saves the width flags for the next plp, nested pushes are not tracked
+const FLAG_SAVED_M: &str = "saved_m";
+const FLAG_SAVED_X: &str = "saved_x";
+// the status bits of rep and sep that select the width of the registers
+const STATUS_M: u8 = 0x20;
+const STATUS_X: u8 = 0x10;
+
 fn transform_stack_s(map: &mut TransformMap) {
     map.insert(
         STACK_S.into(),
@@ -75,6 +93,7 @@ fn transform_direct24(map: &mut TransformMap) {
                 offset: 0,
                 fmt: ValueTypeFmt::LowerHex(2),
                 data_type: DataType::U8,
+                zp_var: true,
                 ..Default::default()
             }),
             Transform::Static(Node::new("]".into())),
@@ -164,6 +183,7 @@ fn transform_indirect_y24(map: &mut TransformMap) {
                 offset: 0,
                 fmt: ValueTypeFmt::LowerHex(2),
                 data_type: DataType::U8,
+                zp_var: true,
                 ..Default::default()
             }),
             Transform::Static(Node::new("], y".into())),
@@ -215,8 +235,110 @@ fn transform_move(map: &mut TransformMap) {
     );
 }
 
+fn implied_with(flags: Vec<Transform>) -> Vec<Transform> {
+    let mut list = vec![Transform::MatcherName, Transform::Consume(1)];
+    list.extend(flags);
+    list
+}
+
+fn immediate_with(flags: Vec<Transform>) -> Vec<Transform> {
+    let mut list = vec![
+        Transform::MatcherName,
+        Transform::Consume(1),
+        Transform::Static(Node::new(" #".into())),
+        Transform::Val(ValOut {
+            offset: 0,
+            fmt: ValueTypeFmt::LowerHex(2),
+            data_type: DataType::U8,
+            ..Default::default()
+        }),
+    ];
+    list.extend(flags);
+    list
+}
+
+// the flags are set while the registers are 16 bit
+fn width_flag(flag: &str, wide: bool) -> Transform {
+    if wide {
+        Transform::SetFlag(flag.into(), "".into())
+    } else {
+        Transform::UnsetFlag(flag.into())
+    }
+}
+
+// the combinations of a 16 bit accumulator and 16 bit index registers
+const WIDTHS: [(bool, bool); 4] = [(false, false), (true, false), (false, true), (true, true)];
+
+fn width_name(prefix: &str, m: bool, x: bool) -> String {
+    let bits = |wide| if wide { 16 } else { 8 };
+    format!("{prefix}_m{}_x{}", bits(m), bits(x))
+}
+
+// rep clears the status bits which makes the registers 16 bit, sep sets them
+// the bits that are not part of the operand keep their width
+fn status_transforms(map: &mut TransformMap) {
+    for (name, wide) in [("rep", true), ("sep", false)] {
+        for (m, x) in WIDTHS.into_iter().filter(|w| *w != (false, false)) {
+            let mut flags = vec![];
+            if m {
+                flags.push(width_flag(FLAG_M, wide));
+            }
+            if x {
+                flags.push(width_flag(FLAG_X, wide));
+            }
+            map.insert(width_name(name, m, x), immediate_with(flags));
+        }
+    }
+    for (m, x) in WIDTHS {
+        map.insert(
+            width_name("implied_php", m, x),
+            implied_with(vec![
+                width_flag(FLAG_SAVED_M, m),
+                width_flag(FLAG_SAVED_X, x),
+            ]),
+        );
+        map.insert(
+            width_name("implied_plp", m, x),
+            implied_with(vec![width_flag(FLAG_M, m), width_flag(FLAG_X, x)]),
+        );
+    }
+}
+
+fn transform_mode_switch(map: &mut TransformMap) {
+    map.insert(
+        CLC.into(),
+        implied_with(vec![Transform::UnsetFlag(FLAG_C.into())]),
+    );
+    map.insert(
+        SEC.into(),
+        implied_with(vec![Transform::SetFlag(FLAG_C.into(), "".into())]),
+    );
+    // xce swaps carry and emulation
+    map.insert(XCE_NATIVE.into(), implied_with(vec![]));
+    map.insert(XCE_EMULATION.into(), implied_with(vec![]));
+    map.insert(
+        XCE_TO_NATIVE.into(),
+        implied_with(vec![
+            Transform::UnsetFlag(FLAG_E.into()),
+            Transform::SetFlag(FLAG_C.into(), "".into()),
+        ]),
+    );
+    // emulation mode forces 8 bit registers
+    map.insert(
+        XCE_TO_EMULATION.into(),
+        implied_with(vec![
+            Transform::SetFlag(FLAG_E.into(), "".into()),
+            Transform::UnsetFlag(FLAG_C.into()),
+            Transform::UnsetFlag(FLAG_M.into()),
+            Transform::UnsetFlag(FLAG_X.into()),
+        ]),
+    );
+    status_transforms(map);
+}
+
 pub(super) fn transforms() -> TransformMap {
     let mut map = super::a65c02::transforms();
+    transform_mode_switch(&mut map);
     transform_stack_s(&mut map);
     transform_direct24(&mut map);
     transform_long(&mut map);
@@ -270,6 +392,84 @@ fn matcher_jsr_indirect_x(matchers: &mut MatcherList, op: u8, name: &str) {
     matcher3(matchers, op, name, JSR_INDIRECT_X);
 }
 
+fn flag_pattern(flag: &str, set: bool) -> PatternAt {
+    PatternAt::new(
+        Pattern::Flag(flag.into(), if set { Some("".into()) } else { None }),
+        0,
+    )
+}
+
+// the mode switching instructions have to be matched before the
+// regular implied instructions
+fn matchers_mode_switch(matchers: &mut MatcherList) {
+    for (op, name, mode) in [(0x18, "clc", CLC), (0x38, "sec", SEC)] {
+        matchers.push(Matcher {
+            patterns: vec![PatternAt::new(Pattern::Exact(op), 0)],
+            transforms: mode.into(),
+            name: Node::new(name.into()),
+            cycles: None,
+        });
+    }
+    for (carry, emulation, mode) in [
+        (false, false, XCE_NATIVE),
+        (true, true, XCE_EMULATION),
+        (false, true, XCE_TO_NATIVE),
+        (true, false, XCE_TO_EMULATION),
+    ] {
+        matchers.push(Matcher {
+            patterns: vec![
+                PatternAt::new(Pattern::Exact(0xFB), 0),
+                flag_pattern(FLAG_C, carry),
+                flag_pattern(FLAG_E, emulation),
+            ],
+            transforms: mode.into(),
+            name: Node::new("xce".into()),
+            cycles: None,
+        });
+    }
+    // the registers stay 8 bit in emulation mode no matter what rep clears
+    for (op, name) in [(0xC2, "rep"), (0xE2, "sep")] {
+        for (m, x) in WIDTHS.into_iter().filter(|w| *w != (false, false)) {
+            let bits = if m { STATUS_M } else { 0 } | if x { STATUS_X } else { 0 };
+            let mut patterns = vec![
+                PatternAt::new(Pattern::Exact(op), 0),
+                PatternAt::new(Pattern::Mask(STATUS_M | STATUS_X, bits), 1),
+            ];
+            if name == "rep" {
+                patterns.push(flag_pattern(FLAG_E, false));
+            }
+            matchers.push(Matcher {
+                patterns,
+                transforms: width_name(name, m, x),
+                name: Node::new(name.into()),
+                cycles: None,
+            });
+        }
+    }
+    for (m, x) in WIDTHS {
+        matchers.push(Matcher {
+            patterns: vec![
+                PatternAt::new(Pattern::Exact(0x08), 0),
+                flag_pattern(FLAG_M, m),
+                flag_pattern(FLAG_X, x),
+            ],
+            transforms: width_name("implied_php", m, x),
+            name: Node::new("php".into()),
+            cycles: None,
+        });
+        matchers.push(Matcher {
+            patterns: vec![
+                PatternAt::new(Pattern::Exact(0x28), 0),
+                flag_pattern(FLAG_SAVED_M, m),
+                flag_pattern(FLAG_SAVED_X, x),
+            ],
+            transforms: width_name("implied_plp", m, x),
+            name: Node::new("plp".into()),
+            cycles: None,
+        });
+    }
+}
+
 pub(super) fn matchers_from(matchers: &mut MatcherList, instrs: InstructionMap) {
     for (k, modes) in instrs.iter() {
         if let Some(op) = modes.get(STACK_S) {
@@ -353,7 +553,6 @@ fn instruction_map() -> InstructionMap {
         implied_instruction_map("txy", 0xBB),
         implied_instruction_map("tyx", 0xBB),
         implied_instruction_map("stp", 0xDB),
-        implied_instruction_map("xce", 0xFB),
         ("cop", ModeMap::from([(IMMEDIATE, 0x02)])),
         ("jsl", ModeMap::from([(LONG, 0x22)])),
         implied_instruction_map("wdm", 0x42),
@@ -374,7 +573,9 @@ fn instruction_map() -> InstructionMap {
 }
 
 pub(super) fn patterns() -> MatcherList {
-    let mut list = super::a65c02::patterns();
+    let mut list = vec![];
+    matchers_mode_switch(&mut list);
+    list.append(&mut super::a65c02::patterns());
     matchers_from(&mut list, instruction_map());
     list
 }
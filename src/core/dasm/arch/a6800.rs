@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+
+use crate::core::dasm::arch::{Archs, Endianess};
+
+use super::{
+    a6502::{
+        implied_instruction_map, relative_instruction_map, InstructionMap, ModeMap, ABSOLUTE,
+        IMMEDIATE, IMMEDIATE16, ZP, ZP_X,
+    },
+    Arch, MatcherList,
+};
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// Built-in architecture for the Motorola 6800
+    pub static ref ARCH: Archs = Archs {archs: archs(), ..Default::default()};
+}
+
+// The 6800 modes map onto the 6502 modes:
+// direct = zp, indexed = zp_x and extended = absolute.
+// All values are big endian.
+
+// accumulator instructions use the same low nibble for all modes
+// the upper nibble selects the mode (imm, direct, indexed, extended)
+fn acc_instruction_map(name: &'static str, imm: Option<u8>, base: u8) -> (&'static str, ModeMap) {
+    let mut modes = ModeMap::from([
+        (ZP, base + 0x10),
+        (ZP_X, base + 0x20),
+        (ABSOLUTE, base + 0x30),
+    ]);
+    if let Some(imm) = imm {
+        modes.insert(IMMEDIATE, imm);
+    }
+    (name, modes)
+}
+
+// 16 bit register instructions
+fn reg16_instruction_map(name: &'static str, imm: Option<u8>, base: u8) -> (&'static str, ModeMap) {
+    let (name, mut modes) = acc_instruction_map(name, None, base);
+    if let Some(imm) = imm {
+        modes.insert(IMMEDIATE16, imm);
+    }
+    (name, modes)
+}
+
+// memory instructions only have indexed and extended modes
+fn memory_instruction_map(name: &'static str, indexed: u8) -> (&'static str, ModeMap) {
+    (
+        name,
+        ModeMap::from([(ZP_X, indexed), (ABSOLUTE, indexed + 0x10)]),
+    )
+}
+
+pub(super) fn instruction_map() -> InstructionMap {
+    let mut map = InstructionMap::from([
+        implied_instruction_map("nop", 0x01),
+        implied_instruction_map("tap", 0x06),
+        implied_instruction_map("tpa", 0x07),
+        implied_instruction_map("inx", 0x08),
+        implied_instruction_map("dex", 0x09),
+        implied_instruction_map("clv", 0x0A),
+        implied_instruction_map("sev", 0x0B),
+        implied_instruction_map("clc", 0x0C),
+        implied_instruction_map("sec", 0x0D),
+        implied_instruction_map("cli", 0x0E),
+        implied_instruction_map("sei", 0x0F),
+        implied_instruction_map("sba", 0x10),
+        implied_instruction_map("cba", 0x11),
+        implied_instruction_map("tab", 0x16),
+        implied_instruction_map("tba", 0x17),
+        implied_instruction_map("daa", 0x19),
+        implied_instruction_map("aba", 0x1B),
+        implied_instruction_map("tsx", 0x30),
+        implied_instruction_map("ins", 0x31),
+        implied_instruction_map("pula", 0x32),
+        implied_instruction_map("pulb", 0x33),
+        implied_instruction_map("des", 0x34),
+        implied_instruction_map("txs", 0x35),
+        implied_instruction_map("psha", 0x36),
+        implied_instruction_map("pshb", 0x37),
+        implied_instruction_map("rts", 0x39),
+        implied_instruction_map("rti", 0x3B),
+        implied_instruction_map("wai", 0x3E),
+        implied_instruction_map("swi", 0x3F),
+        relative_instruction_map("bra", 0x20),
+        relative_instruction_map("bhi", 0x22),
+        relative_instruction_map("bls", 0x23),
+        relative_instruction_map("bcc", 0x24),
+        relative_instruction_map("bcs", 0x25),
+        relative_instruction_map("bne", 0x26),
+        relative_instruction_map("beq", 0x27),
+        relative_instruction_map("bvc", 0x28),
+        relative_instruction_map("bvs", 0x29),
+        relative_instruction_map("bpl", 0x2A),
+        relative_instruction_map("bmi", 0x2B),
+        relative_instruction_map("bge", 0x2C),
+        relative_instruction_map("blt", 0x2D),
+        relative_instruction_map("bgt", 0x2E),
+        relative_instruction_map("ble", 0x2F),
+        relative_instruction_map("bsr", 0x8D),
+        memory_instruction_map("neg", 0x60),
+        memory_instruction_map("com", 0x63),
+        memory_instruction_map("lsr", 0x64),
+        memory_instruction_map("ror", 0x66),
+        memory_instruction_map("asr", 0x67),
+        memory_instruction_map("asl", 0x68),
+        memory_instruction_map("rol", 0x69),
+        memory_instruction_map("dec", 0x6A),
+        memory_instruction_map("inc", 0x6C),
+        memory_instruction_map("tst", 0x6D),
+        memory_instruction_map("jmp", 0x6E),
+        memory_instruction_map("clr", 0x6F),
+        ("jsr", ModeMap::from([(ZP_X, 0xAD), (ABSOLUTE, 0xBD)])),
+        reg16_instruction_map("cpx", Some(0x8C), 0x8C),
+        reg16_instruction_map("lds", Some(0x8E), 0x8E),
+        reg16_instruction_map("sts", None, 0x8F),
+        reg16_instruction_map("ldx", Some(0xCE), 0xCE),
+        reg16_instruction_map("stx", None, 0xCF),
+    ]);
+
+    // the accumulator forms of the memory instructions
+    for (name_a, name_b, op) in [
+        ("nega", "negb", 0x40),
+        ("coma", "comb", 0x43),
+        ("lsra", "lsrb", 0x44),
+        ("rora", "rorb", 0x46),
+        ("asra", "asrb", 0x47),
+        ("asla", "aslb", 0x48),
+        ("rola", "rolb", 0x49),
+        ("deca", "decb", 0x4A),
+        ("inca", "incb", 0x4C),
+        ("tsta", "tstb", 0x4D),
+        ("clra", "clrb", 0x4F),
+    ] {
+        map.extend([
+            implied_instruction_map(name_a, op),
+            implied_instruction_map(name_b, op + 0x10),
+        ]);
+    }
+
+    // accumulator a is located at 0x80 and accumulator b at 0xC0
+    for (name_a, name_b, op, imm) in [
+        ("suba", "subb", 0x00, true),
+        ("cmpa", "cmpb", 0x01, true),
+        ("sbca", "sbcb", 0x02, true),
+        ("anda", "andb", 0x04, true),
+        ("bita", "bitb", 0x05, true),
+        ("ldaa", "ldab", 0x06, true),
+        ("staa", "stab", 0x07, false),
+        ("eora", "eorb", 0x08, true),
+        ("adca", "adcb", 0x09, true),
+        ("oraa", "orab", 0x0A, true),
+        ("adda", "addb", 0x0B, true),
+    ] {
+        let (a, b) = (0x80 + op, 0xC0 + op);
+        map.extend([
+            acc_instruction_map(name_a, imm.then_some(a), a),
+            acc_instruction_map(name_b, imm.then_some(b), b),
+        ]);
+    }
+
+    map
+}
+
+pub(super) fn patterns_from(map: InstructionMap) -> MatcherList {
+    let mut list = vec![];
+    super::a6502::matchers_from(&mut list, map);
+    list
+}
+
+fn archs() -> BTreeMap<String, Arch> {
+    let mut map = BTreeMap::default();
+    map.insert(
+        "".into(),
+        Arch {
+            patterns: super::a6502::add_patterns_default(patterns_from(instruction_map())),
+            endianess: Endianess::Big,
+            // we can unwrap this because the 6502 is guaranteed to have an empty
+            // arch key!
+            ..super::a6502::ARCH.archs.get("").unwrap().to_owned()
+        },
+    );
+    map
+}
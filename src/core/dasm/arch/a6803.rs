@@ -0,0 +1,83 @@
+use std::collections::BTreeMap;
+
+use crate::core::dasm::arch::{Archs, Endianess};
+
+use super::{
+    a6502::{
+        implied_instruction_map, relative_instruction_map, InstructionMap, ModeMap, ABSOLUTE,
+        IMMEDIATE16, ZP, ZP_X,
+    },
+    Arch,
+};
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// Built-in architecture for the Motorola 6801/6803
+    pub static ref ARCH: Archs = Archs {archs: archs(), ..Default::default()};
+}
+
+fn instruction_map() -> InstructionMap {
+    let mut map = super::a6800::instruction_map();
+    map.extend([
+        implied_instruction_map("lsrd", 0x04),
+        implied_instruction_map("asld", 0x05),
+        implied_instruction_map("pulx", 0x38),
+        implied_instruction_map("abx", 0x3A),
+        implied_instruction_map("pshx", 0x3C),
+        implied_instruction_map("mul", 0x3D),
+        relative_instruction_map("brn", 0x21),
+        (
+            "subd",
+            ModeMap::from([
+                (IMMEDIATE16, 0x83),
+                (ZP, 0x93),
+                (ZP_X, 0xA3),
+                (ABSOLUTE, 0xB3),
+            ]),
+        ),
+        (
+            "addd",
+            ModeMap::from([
+                (IMMEDIATE16, 0xC3),
+                (ZP, 0xD3),
+                (ZP_X, 0xE3),
+                (ABSOLUTE, 0xF3),
+            ]),
+        ),
+        (
+            "ldd",
+            ModeMap::from([
+                (IMMEDIATE16, 0xCC),
+                (ZP, 0xDC),
+                (ZP_X, 0xEC),
+                (ABSOLUTE, 0xFC),
+            ]),
+        ),
+        (
+            "std",
+            ModeMap::from([(ZP, 0xDD), (ZP_X, 0xED), (ABSOLUTE, 0xFD)]),
+        ),
+    ]);
+    // jsr gained a direct mode
+    if let Some(modes) = map.get_mut("jsr") {
+        modes.insert(ZP, 0x9D);
+    }
+    map
+}
+
+fn archs() -> BTreeMap<String, Arch> {
+    let mut map = BTreeMap::default();
+    map.insert(
+        "".into(),
+        Arch {
+            patterns: super::a6502::add_patterns_default(super::a6800::patterns_from(
+                instruction_map(),
+            )),
+            endianess: Endianess::Big,
+            // we can unwrap this because the 6502 is guaranteed to have an empty
+            // arch key!
+            ..super::a6502::ARCH.archs.get("").unwrap().to_owned()
+        },
+    );
+    map
+}
@@ -0,0 +1,440 @@
+use std::collections::BTreeMap;
+
+use crate::core::dasm::{arch::Archs, DataType, ValueTypeFmt};
+
+use super::{
+    Arch, Endianess, Matcher, MatcherList, Node, Pattern, PatternAt, Transform, TransformMap,
+    ValOut, ValueOp,
+};
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// Built-in architecture for the Intel 8051 family
+    pub static ref ARCH: Archs = Archs {archs: archs(), ..Default::default()};
+}
+
+/// Operands of an instruction
+/// the usize is the offset of the operand byte in the instruction
+#[derive(Clone, Copy)]
+enum Operand {
+    Static(&'static str),
+    // r0-r7 encoded in the lower 3 bits of the opcode
+    Reg,
+    // @r0 or @r1 encoded in the lowest bit of the opcode
+    AtReg,
+    Direct(usize),
+    Imm(usize),
+    Imm16(usize),
+    Addr16(usize),
+    // 11 bit address, the upper 3 bits are stored in the opcode
+    Addr11,
+    Rel(usize),
+    // bit address, bits 0x00-0x7F are located in ram 0x20-0x2F,
+    // 0x80-0xFF are located in the bit-addressable sfrs
+    Bit(usize),
+    NotBit(usize),
+}
+
+use Operand::*;
+
+impl Operand {
+    fn key(&self, high_bit: bool) -> String {
+        match self {
+            Static(s) => s.replace(['@', '+'], ""),
+            Reg => "rn".into(),
+            AtReg => "ri".into(),
+            Direct(o) => format!("direct{o}"),
+            Imm(o) => format!("imm{o}"),
+            Imm16(o) => format!("imm16_{o}"),
+            Addr16(o) => format!("addr16_{o}"),
+            Addr11 => "addr11".into(),
+            Rel(o) => format!("rel{o}"),
+            Bit(o) if high_bit => format!("bit{o}_sfr"),
+            Bit(o) => format!("bit{o}_ram"),
+            NotBit(o) if high_bit => format!("not_bit{o}_sfr"),
+            NotBit(o) => format!("not_bit{o}_ram"),
+        }
+    }
+
+    // the offset and size of the bytes this operand reads
+    fn bytes(&self) -> Option<(usize, usize)> {
+        match self {
+            Direct(o) | Imm(o) | Rel(o) | Bit(o) | NotBit(o) => Some((*o, 1)),
+            Imm16(o) | Addr16(o) => Some((*o, 2)),
+            Addr11 => Some((0, 2)),
+            _ => None,
+        }
+    }
+
+    fn bit_offset(&self) -> Option<usize> {
+        match self {
+            Bit(o) | NotBit(o) => Some(*o),
+            _ => None,
+        }
+    }
+
+    fn transforms(&self, high_bit: bool, len: usize) -> Vec<Transform> {
+        match self {
+            Static(s) => vec![Transform::Static(Node::new(s.to_string()))],
+            Reg => vec![Transform::Static(Node::new("r".into())), opcode_field(0x07)],
+            AtReg => vec![
+                Transform::Static(Node::new("@r".into())),
+                opcode_field(0x01),
+            ],
+            Direct(o) => vec![value(*o, DataType::U8, 2, vec![])],
+            Imm(o) => vec![
+                Transform::Static(Node::new("#".into())),
+                value(*o, DataType::U8, 2, vec![]),
+            ],
+            Imm16(o) => vec![
+                Transform::Static(Node::new("#".into())),
+                value(*o, DataType::U16, 4, vec![]),
+            ],
+            Addr16(o) => vec![value(*o, DataType::U16, 4, vec![])],
+            Addr11 => vec![
+                Transform::OffsetAddress(len as i64),
+                value(
+                    0,
+                    DataType::U16,
+                    4,
+                    vec![ValueOp::Extract(0xE0FF), ValueOp::AddBank(0xF800)],
+                ),
+                Transform::OffsetAddress(-(len as i64)),
+            ],
+            Rel(o) => vec![
+                Transform::OffsetAddress(len as i64),
+                Transform::Val(ValOut {
+                    offset: *o,
+                    fmt: ValueTypeFmt::LowerHex(2),
+                    data_type: DataType::I8,
+                    rel: true,
+                    peek: true,
+                    ..Default::default()
+                }),
+                Transform::OffsetAddress(-(len as i64)),
+            ],
+            Bit(o) => bit(*o, high_bit),
+            NotBit(o) => {
+                let mut v = vec![Transform::Static(Node::new("/".into()))];
+                v.append(&mut bit(*o, high_bit));
+                v
+            }
+        }
+    }
+}
+
+fn value(offset: usize, data_type: DataType, width: usize, ops: Vec<ValueOp>) -> Transform {
+    Transform::Val(ValOut {
+        offset,
+        fmt: ValueTypeFmt::LowerHex(width),
+        data_type,
+        ops,
+        peek: true,
+        ..Default::default()
+    })
+}
+
+fn opcode_field(mask: i64) -> Transform {
+    Transform::Val(ValOut {
+        offset: 0,
+        fmt: ValueTypeFmt::Decimal(0),
+        data_type: DataType::U8,
+        ops: vec![ValueOp::And(mask)],
+        peek: true,
+        no_sym: true,
+        ..Default::default()
+    })
+}
+
+// outputs a bit address as byte.bit
+// the byte is resolved as a symbol e.g. acc.7
+fn bit(offset: usize, high_bit: bool) -> Vec<Transform> {
+    let byte_ops = if high_bit {
+        vec![ValueOp::And(0xF8)]
+    } else {
+        vec![ValueOp::Shr(3), ValueOp::Add(0x20)]
+    };
+    vec![
+        value(offset, DataType::U8, 2, byte_ops),
+        Transform::Static(Node::new(".".into())),
+        Transform::Val(ValOut {
+            offset,
+            fmt: ValueTypeFmt::Decimal(0),
+            data_type: DataType::U8,
+            ops: vec![ValueOp::And(0x07)],
+            peek: true,
+            no_sym: true,
+            ..Default::default()
+        }),
+    ]
+}
+
+fn instruction_len(operands: &[Operand]) -> usize {
+    operands
+        .iter()
+        .filter_map(|x| x.bytes())
+        .map(|(offset, size)| offset + size)
+        .max()
+        .unwrap_or(1)
+}
+
+fn mode_key(operands: &[Operand], high_bit: bool) -> String {
+    let keys: Vec<String> = operands.iter().map(|x| x.key(high_bit)).collect();
+    format!("i8051_{}", keys.join("_"))
+}
+
+fn add_instruction(
+    matchers: &mut MatcherList,
+    transforms: &mut TransformMap,
+    op: u8,
+    name: &str,
+    operands: &[Operand],
+) {
+    let bit_offset = operands.iter().find_map(|x| x.bit_offset());
+    let variants: &[bool] = if bit_offset.is_some() {
+        &[false, true]
+    } else {
+        &[false]
+    };
+
+    let len = instruction_len(operands);
+    for high_bit in variants {
+        let key = mode_key(operands, *high_bit);
+
+        let mut list = vec![Transform::MatcherName];
+        for (i, operand) in operands.iter().enumerate() {
+            let sep = if i == 0 { " " } else { ", " };
+            list.push(Transform::Static(Node::new(sep.into())));
+            list.append(&mut operand.transforms(*high_bit, len));
+        }
+        list.push(Transform::Consume(len));
+        transforms.insert(key.clone(), list);
+
+        let mut patterns = vec![PatternAt::new(Pattern::Exact(op), 0)];
+        if len > 1 {
+            patterns.push(PatternAt::new(Pattern::Any, len - 1));
+        }
+        if let Some(offset) = bit_offset {
+            let value = if *high_bit { 0x80 } else { 0x00 };
+            patterns.push(PatternAt::new(Pattern::Mask(0x80, value), offset));
+        }
+        matchers.push(Matcher {
+            patterns,
+            transforms: key,
+            name: Node::new(name.into()),
+            cycles: None,
+        });
+    }
+}
+
+// instructions that repeat the same pattern in every row of the opcode table:
+// x4: #data, x5: direct, x6-x7: @ri, x8-xf: rn
+fn add_arith_row(
+    matchers: &mut MatcherList,
+    transforms: &mut TransformMap,
+    row: u8,
+    name: &str,
+    first: Operand,
+    imm: bool,
+) {
+    if imm {
+        add_instruction(matchers, transforms, row | 0x04, name, &[first, Imm(1)]);
+    }
+    add_instruction(matchers, transforms, row | 0x05, name, &[first, Direct(1)]);
+    for op in 0x06..=0x07 {
+        add_instruction(matchers, transforms, row | op, name, &[first, AtReg]);
+    }
+    for op in 0x08..=0x0F {
+        add_instruction(matchers, transforms, row | op, name, &[first, Reg]);
+    }
+}
+
+// instructions that only take a single operand following the same pattern
+fn add_unary_row(matchers: &mut MatcherList, transforms: &mut TransformMap, row: u8, name: &str) {
+    add_instruction(matchers, transforms, row | 0x04, name, &[Static("a")]);
+    add_instruction(matchers, transforms, row | 0x05, name, &[Direct(1)]);
+    for op in 0x06..=0x07 {
+        add_instruction(matchers, transforms, row | op, name, &[AtReg]);
+    }
+    for op in 0x08..=0x0F {
+        add_instruction(matchers, transforms, row | op, name, &[Reg]);
+    }
+}
+
+// (op, name, operands)
+type Instruction = (u8, &'static str, &'static [Operand]);
+
+fn instructions() -> Vec<Instruction> {
+    vec![
+        (0x00, "nop", &[]),
+        (0x02, "ljmp", &[Addr16(1)]),
+        (0x03, "rr", &[Static("a")]),
+        (0x10, "jbc", &[Bit(1), Rel(2)]),
+        (0x12, "lcall", &[Addr16(1)]),
+        (0x13, "rrc", &[Static("a")]),
+        (0x20, "jb", &[Bit(1), Rel(2)]),
+        (0x22, "ret", &[]),
+        (0x23, "rl", &[Static("a")]),
+        (0x30, "jnb", &[Bit(1), Rel(2)]),
+        (0x32, "reti", &[]),
+        (0x33, "rlc", &[Static("a")]),
+        (0x40, "jc", &[Rel(1)]),
+        (0x42, "orl", &[Direct(1), Static("a")]),
+        (0x43, "orl", &[Direct(1), Imm(2)]),
+        (0x50, "jnc", &[Rel(1)]),
+        (0x52, "anl", &[Direct(1), Static("a")]),
+        (0x53, "anl", &[Direct(1), Imm(2)]),
+        (0x60, "jz", &[Rel(1)]),
+        (0x62, "xrl", &[Direct(1), Static("a")]),
+        (0x63, "xrl", &[Direct(1), Imm(2)]),
+        (0x70, "jnz", &[Rel(1)]),
+        (0x72, "orl", &[Static("c"), Bit(1)]),
+        (0x73, "jmp", &[Static("@a+dptr")]),
+        (0x74, "mov", &[Static("a"), Imm(1)]),
+        (0x75, "mov", &[Direct(1), Imm(2)]),
+        (0x76, "mov", &[AtReg, Imm(1)]),
+        (0x77, "mov", &[AtReg, Imm(1)]),
+        (0x80, "sjmp", &[Rel(1)]),
+        (0x82, "anl", &[Static("c"), Bit(1)]),
+        (0x83, "movc", &[Static("a"), Static("@a+pc")]),
+        (0x84, "div", &[Static("ab")]),
+        // the source is encoded before the destination
+        (0x85, "mov", &[Direct(2), Direct(1)]),
+        (0x86, "mov", &[Direct(1), AtReg]),
+        (0x87, "mov", &[Direct(1), AtReg]),
+        (0x90, "mov", &[Static("dptr"), Imm16(1)]),
+        (0x92, "mov", &[Bit(1), Static("c")]),
+        (0x93, "movc", &[Static("a"), Static("@a+dptr")]),
+        (0xA0, "orl", &[Static("c"), NotBit(1)]),
+        (0xA2, "mov", &[Static("c"), Bit(1)]),
+        (0xA3, "inc", &[Static("dptr")]),
+        (0xA4, "mul", &[Static("ab")]),
+        (0xA6, "mov", &[AtReg, Direct(1)]),
+        (0xA7, "mov", &[AtReg, Direct(1)]),
+        (0xB0, "anl", &[Static("c"), NotBit(1)]),
+        (0xB2, "cpl", &[Bit(1)]),
+        (0xB3, "cpl", &[Static("c")]),
+        (0xB4, "cjne", &[Static("a"), Imm(1), Rel(2)]),
+        (0xB5, "cjne", &[Static("a"), Direct(1), Rel(2)]),
+        (0xB6, "cjne", &[AtReg, Imm(1), Rel(2)]),
+        (0xB7, "cjne", &[AtReg, Imm(1), Rel(2)]),
+        (0xC0, "push", &[Direct(1)]),
+        (0xC2, "clr", &[Bit(1)]),
+        (0xC3, "clr", &[Static("c")]),
+        (0xC4, "swap", &[Static("a")]),
+        (0xD0, "pop", &[Direct(1)]),
+        (0xD2, "setb", &[Bit(1)]),
+        (0xD3, "setb", &[Static("c")]),
+        (0xD4, "da", &[Static("a")]),
+        (0xD5, "djnz", &[Direct(1), Rel(2)]),
+        (0xD6, "xchd", &[Static("a"), AtReg]),
+        (0xD7, "xchd", &[Static("a"), AtReg]),
+        (0xE0, "movx", &[Static("a"), Static("@dptr")]),
+        (0xE2, "movx", &[Static("a"), AtReg]),
+        (0xE3, "movx", &[Static("a"), AtReg]),
+        (0xE4, "clr", &[Static("a")]),
+        (0xF0, "movx", &[Static("@dptr"), Static("a")]),
+        (0xF2, "movx", &[AtReg, Static("a")]),
+        (0xF3, "movx", &[AtReg, Static("a")]),
+        (0xF4, "cpl", &[Static("a")]),
+        (0xF5, "mov", &[Direct(1), Static("a")]),
+        (0xF6, "mov", &[AtReg, Static("a")]),
+        (0xF7, "mov", &[AtReg, Static("a")]),
+    ]
+}
+
+fn patterns_and_transforms() -> (MatcherList, TransformMap) {
+    let mut matchers = vec![];
+    let mut transforms = BTreeMap::default();
+    super::a6502::transforms_common(&mut transforms);
+
+    for (op, name, operands) in instructions() {
+        add_instruction(&mut matchers, &mut transforms, op, name, operands);
+    }
+
+    // ajmp and acall are located in every odd column
+    for row in (0x00..=0xE0).step_by(0x20) {
+        add_instruction(
+            &mut matchers,
+            &mut transforms,
+            row | 0x01,
+            "ajmp",
+            &[Addr11],
+        );
+        add_instruction(
+            &mut matchers,
+            &mut transforms,
+            (row + 0x10) | 0x01,
+            "acall",
+            &[Addr11],
+        );
+    }
+
+    add_unary_row(&mut matchers, &mut transforms, 0x00, "inc");
+    add_unary_row(&mut matchers, &mut transforms, 0x10, "dec");
+    let a = Static("a");
+    add_arith_row(&mut matchers, &mut transforms, 0x20, "add", a, true);
+    add_arith_row(&mut matchers, &mut transforms, 0x30, "addc", a, true);
+    add_arith_row(&mut matchers, &mut transforms, 0x40, "orl", a, true);
+    add_arith_row(&mut matchers, &mut transforms, 0x50, "anl", a, true);
+    add_arith_row(&mut matchers, &mut transforms, 0x60, "xrl", a, true);
+    add_arith_row(&mut matchers, &mut transforms, 0x90, "subb", a, true);
+    add_arith_row(&mut matchers, &mut transforms, 0xC0, "xch", a, false);
+    // e4 is clr a, mov a, #data is located at 74
+    add_arith_row(&mut matchers, &mut transforms, 0xE0, "mov", a, false);
+
+    for op in 0x78..=0x7F {
+        add_instruction(&mut matchers, &mut transforms, op, "mov", &[Reg, Imm(1)]);
+    }
+    for op in 0x88..=0x8F {
+        add_instruction(&mut matchers, &mut transforms, op, "mov", &[Direct(1), Reg]);
+    }
+    for op in 0xA8..=0xAF {
+        add_instruction(&mut matchers, &mut transforms, op, "mov", &[Reg, Direct(1)]);
+    }
+    for op in 0xB8..=0xBF {
+        add_instruction(
+            &mut matchers,
+            &mut transforms,
+            op,
+            "cjne",
+            &[Reg, Imm(1), Rel(2)],
+        );
+    }
+    for op in 0xD8..=0xDF {
+        add_instruction(&mut matchers, &mut transforms, op, "djnz", &[Reg, Rel(1)]);
+    }
+    for op in 0xF8..=0xFF {
+        add_instruction(&mut matchers, &mut transforms, op, "mov", &[Reg, a]);
+    }
+
+    (matchers, transforms)
+}
+
+fn archs() -> BTreeMap<String, Arch> {
+    let (patterns, transforms) = patterns_and_transforms();
+    let mut map = BTreeMap::default();
+    map.insert(
+        "".into(),
+        Arch {
+            patterns: super::a6502::add_patterns_default(patterns),
+            transforms,
+            endianess: Endianess::Big,
+            addr_type: DataType::U16,
+            node_map: BTreeMap::from([
+                (
+                    ValueTypeFmt::LowerHex(0).pre().into(),
+                    Node::new("0x".into()),
+                ),
+                (
+                    ValueTypeFmt::UpperHex(0).pre().into(),
+                    Node::new("0x".into()),
+                ),
+            ]),
+            // we can unwrap this because the 6502 is guaranteed to have an empty
+            // arch key!
+            ..super::a6502::ARCH.archs.get("").unwrap().to_owned()
+        },
+    );
+    map
+}
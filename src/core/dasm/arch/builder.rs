@@ -0,0 +1,309 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    core::dasm::{DataType, ValueTypeFmt},
+    prelude::{Error, FdResult},
+};
+
+use super::{
+    a6502, Arch, Archs, Endianess, Matcher, Node, Pattern, PatternAt, Transform, TransformList,
+    ValOut,
+};
+
+/// Builds an arch from its instructions and their addressing modes
+/// e.g. ArchBuilder::new().add_instruction("adc").mode(a6502::IMMEDIATE, 0x69).build()
+/// The builder starts out with the layout of the 6502 (labels, address and raw columns),
+/// its addressing modes and the define_byte transform for unmatched bytes.
+/// Modes are the names of transform lists and can be added with mode or the helpers
+#[derive(Clone)]
+pub struct ArchBuilder {
+    arch: Arch,
+    // the name, the mode, the patterns and the cycles of every instruction in order
+    instructions: Vec<(String, String, Vec<PatternAt>, Option<usize>)>,
+}
+
+impl Default for ArchBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ArchBuilder {
+    pub fn new() -> Self {
+        let arch = Arch {
+            patterns: vec![],
+            // the 6502 is guaranteed to have an empty arch key
+            ..a6502::ARCH.archs.get("").unwrap().to_owned()
+        };
+        Self {
+            arch,
+            instructions: vec![],
+        }
+    }
+
+    pub fn endianess(mut self, endianess: Endianess) -> Self {
+        self.arch.endianess = endianess;
+        self
+    }
+
+    pub fn addr_type(mut self, addr_type: DataType) -> Self {
+        self.arch.addr_type = addr_type;
+        self
+    }
+
+    /// The most bytes a single instruction may consume, None disables the check
+    pub fn max_consume(mut self, limit: Option<usize>) -> Self {
+        self.arch.max_consume = limit;
+        self
+    }
+
+    /// Sets a node of the node map, e.g. fmt_hex_pre or comment_pre
+    pub fn node(mut self, key: &str, value: &str) -> Self {
+        self.arch
+            .node_map
+            .insert(key.into(), Node::new(value.into()));
+        self
+    }
+
+    /// Defines or replaces a mode
+    pub fn mode(mut self, name: &str, transforms: TransformList) -> Self {
+        self.arch.transforms.insert(name.into(), transforms);
+        self
+    }
+
+    /// A mode without operands, the opcode is a single byte
+    pub fn implied_mode(self, name: &str) -> Self {
+        self.mode(name, vec![Transform::MatcherName, Transform::Consume(1)])
+    }
+
+    /// A mode with a single operand after a one byte opcode
+    /// the operand is output as hex between pre and post, e.g. " #" and "" or " (" and "), y"
+    pub fn operand_mode(self, name: &str, pre: &str, data_type: DataType, post: &str) -> Self {
+        let mut transforms = vec![
+            Transform::MatcherName,
+            Transform::Consume(1),
+            Transform::Static(Node::new(pre.into())),
+            Transform::Val(ValOut {
+                fmt: ValueTypeFmt::LowerHex(data_type.data_len() * 2),
+                data_type,
+                ..Default::default()
+            }),
+        ];
+        if !post.is_empty() {
+            transforms.push(Transform::Static(Node::new(post.into())));
+        }
+        self.mode(name, transforms)
+    }
+
+    /// Starts the next instruction, its modes are added to it
+    pub fn add_instruction(self, name: &str) -> InstructionBuilder {
+        InstructionBuilder {
+            builder: self,
+            name: name.into(),
+            modes: 0,
+        }
+    }
+
+    /// Validates the instructions and returns the arch
+    /// Every mode needs a transform list and no two instructions may have the same patterns
+    pub fn build_arch(self) -> FdResult<Arch> {
+        let mut arch = self.arch;
+        for (i, (name, mode, patterns, cycles)) in self.instructions.iter().enumerate() {
+            if arch.get_transform(mode).is_none() {
+                return Err(Error::TransformNotFound(mode.into()));
+            }
+            let same = self.instructions[..i]
+                .iter()
+                .find(|(_, _, other, _)| Self::same_patterns(patterns, other));
+            if let Some((other, other_mode, _, _)) = same {
+                return Err(Error::InvalidArgument(format!(
+                    "{name} ({mode}) has the same opcode as {other} ({other_mode})"
+                )));
+            }
+            arch.patterns.push(Matcher {
+                patterns: patterns.clone(),
+                transforms: mode.clone(),
+                name: Node::new(name.clone()),
+                cycles: *cycles,
+            });
+        }
+        Ok(arch)
+    }
+
+    /// Validates the instructions and returns the arch as the main arch of an arch list
+    pub fn build(self) -> FdResult<Archs> {
+        Ok(Archs {
+            archs: BTreeMap::from([("".into(), self.build_arch()?)]),
+            ..Default::default()
+        })
+    }
+
+    fn same_patterns(a: &[PatternAt], b: &[PatternAt]) -> bool {
+        a.len() == b.len()
+            && a.iter()
+                .zip(b)
+                .all(|(a, b)| a.offset == b.offset && a.pattern == b.pattern)
+    }
+}
+
+/// The modes of an instruction that is added by ArchBuilder::add_instruction
+pub struct InstructionBuilder {
+    builder: ArchBuilder,
+    name: String,
+    // instructions without modes are rejected
+    modes: usize,
+}
+
+impl InstructionBuilder {
+    /// Adds a mode with a single opcode byte
+    pub fn mode(self, mode: &str, opcode: u8) -> Self {
+        self.mode_with(mode, vec![PatternAt::new(Pattern::Exact(opcode), 0)])
+    }
+
+    /// Adds a mode that is matched by the patterns, e.g. opcodes with a prefix byte
+    pub fn mode_with(mut self, mode: &str, patterns: Vec<PatternAt>) -> Self {
+        self.builder
+            .instructions
+            .push((self.name.clone(), mode.into(), patterns, None));
+        self.modes += 1;
+        self
+    }
+
+    /// The cycles of the last mode, they are output with the cycle counts
+    pub fn cycles(mut self, cycles: usize) -> Self {
+        if let Some((.., last)) = self.builder.instructions.last_mut() {
+            *last = Some(cycles);
+        }
+        self
+    }
+
+    /// Finishes the instruction and returns to the arch
+    pub fn done(self) -> FdResult<ArchBuilder> {
+        if self.modes == 0 {
+            return Err(Error::InvalidArgument(format!(
+                "{} has no modes",
+                self.name
+            )));
+        }
+        Ok(self.builder)
+    }
+
+    /// Finishes the instruction and starts the next one
+    pub fn add_instruction(self, name: &str) -> FdResult<InstructionBuilder> {
+        Ok(self.done()?.add_instruction(name))
+    }
+
+    pub fn build(self) -> FdResult<Archs> {
+        self.done()?.build()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::dasm::{
+            arch::{a6502, Context, Transform},
+            DataType,
+        },
+        prelude::Error,
+    };
+
+    use super::ArchBuilder;
+
+    #[test]
+    fn build() {
+        let arch = ArchBuilder::new()
+            .operand_mode("absolute_long", " ", DataType::U24, "")
+            .add_instruction("adc")
+            .mode(a6502::IMMEDIATE, 0x69)
+            .mode(a6502::ZP, 0x65)
+            .add_instruction("jml")
+            .unwrap()
+            .mode("absolute_long", 0x5C)
+            .add_instruction("nop")
+            .unwrap()
+            .mode(a6502::IMPLIED, 0xEA)
+            .cycles(2)
+            .build()
+            .unwrap();
+
+        let mut result = String::new();
+        arch.disas_ctx(
+            |n, _kind, _raw, _arch, _ctx| {
+                result.push_str(&n.string);
+                Ok(())
+            },
+            &[0x69, 0x10, 0x65, 0x20, 0x5C, 0x00, 0x80, 0x7E, 0xEA, 0xFF],
+            &mut Context::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            "00000000 adc #$10\n00000002 adc $20\n00000004 jml $7e8000\n\
+             00000008 nop\n00000009 .db $ff\n",
+            result
+        );
+        let mut ctx = Context::default();
+        ctx.output.cycles = true;
+        let mut result = String::new();
+        arch.disas_ctx(
+            |n, _kind, _raw, _arch, _ctx| {
+                result.push_str(&n.string);
+                Ok(())
+            },
+            &[0xEA, 0x69, 0x10],
+            &mut ctx,
+        )
+        .unwrap();
+        assert_eq!("00000000 nop ; 2 cycles (2)\n00000001 adc #$10\n", result);
+
+        assert!(ArchBuilder::new()
+            .add_instruction("lda")
+            .mode("missing", 0xA9)
+            .build()
+            .is_err());
+        assert!(ArchBuilder::new()
+            .add_instruction("lda")
+            .mode(a6502::IMMEDIATE, 0xA9)
+            .mode(a6502::ZP, 0xA9)
+            .build()
+            .is_err());
+        assert!(ArchBuilder::new().add_instruction("lda").build().is_err());
+    }
+
+    #[test]
+    fn max_consume() {
+        let builder = ArchBuilder::new()
+            .mode(
+                "block",
+                vec![Transform::MatcherName, Transform::Consume(0x800)],
+            )
+            .add_instruction("blk")
+            .mode("block", 0x00);
+        let data = [0; 0x900];
+        let arch = builder.build().unwrap();
+        let err = arch
+            .disas_ctx(
+                |_n, _kind, _raw, _arch, _ctx| Ok(()),
+                &data,
+                &mut Context::default(),
+            )
+            .unwrap_err();
+        assert!(matches!(err, Error::ConsumeLimit(message)
+                if message == "blk at 0 consumed 2048 bytes, the limit of the arch is 1024"));
+
+        let arch = ArchBuilder::new()
+            .max_consume(None)
+            .mode(
+                "block",
+                vec![Transform::MatcherName, Transform::Consume(0x800)],
+            )
+            .add_instruction("blk")
+            .mode("block", 0x00)
+            .build()
+            .unwrap();
+        let mut ctx = Context::default();
+        ctx.set_len(Some(0x800));
+        arch.disas_ctx(|_n, _kind, _raw, _arch, _ctx| Ok(()), &data, &mut ctx)
+            .unwrap();
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::BTreeMap;
+
+use crate::core::dasm::{arch::Archs, DataType, ValueTypeFmt};
+
+use super::{
+    Arch, Endianess, Matcher, MatcherList, Node, Pattern, PatternAt, Transform, TransformMap,
+    ValOut, ValueOp,
+};
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// Built-in architecture for the CHIP-8 virtual machine
+    pub static ref ARCH: Archs = Archs {archs: archs(), ..Default::default()};
+}
+
+// Every instruction is a single big endian word.
+// The nibbles are named as follows:
+// nnn: 12 bit address, kk: 8 bit immediate, n: 4 bit immediate
+// x and y: register v0-vF
+
+const IMPLIED: &str = "chip8_implied";
+const ADDR: &str = "chip8_addr";
+const V0_ADDR: &str = "chip8_v0_addr";
+const I_ADDR: &str = "chip8_i_addr";
+const X: &str = "chip8_x";
+const X_KK: &str = "chip8_x_kk";
+const X_Y: &str = "chip8_x_y";
+const X_Y_N: &str = "chip8_x_y_n";
+
+fn static_node(s: &str) -> Transform {
+    Transform::Static(Node::new(s.into()))
+}
+
+fn field(offset: usize, ops: Vec<ValueOp>) -> Transform {
+    Transform::Val(ValOut {
+        offset,
+        fmt: ValueTypeFmt::UpperHex(1),
+        data_type: DataType::U8,
+        ops,
+        peek: true,
+        no_sym: true,
+        ..Default::default()
+    })
+}
+
+fn reg_x() -> Vec<Transform> {
+    vec![static_node("v"), field(0, vec![ValueOp::And(0x0F)])]
+}
+
+fn reg_y() -> Vec<Transform> {
+    vec![static_node("v"), field(1, vec![ValueOp::Shr(4)])]
+}
+
+fn addr() -> Transform {
+    Transform::Val(ValOut {
+        offset: 0,
+        fmt: ValueTypeFmt::LowerHex(3),
+        data_type: DataType::U16,
+        ops: vec![ValueOp::And(0x0FFF)],
+        peek: true,
+        ..Default::default()
+    })
+}
+
+fn transforms() -> TransformMap {
+    let mut map = BTreeMap::default();
+    super::a6502::transforms_common(&mut map);
+
+    map.insert(
+        IMPLIED.into(),
+        vec![Transform::MatcherName, Transform::Consume(2)],
+    );
+    map.insert(
+        ADDR.into(),
+        vec![
+            Transform::MatcherName,
+            static_node(" "),
+            addr(),
+            Transform::Consume(2),
+        ],
+    );
+    map.insert(
+        V0_ADDR.into(),
+        vec![
+            Transform::MatcherName,
+            static_node(" v0, "),
+            addr(),
+            Transform::Consume(2),
+        ],
+    );
+    map.insert(
+        I_ADDR.into(),
+        vec![
+            Transform::MatcherName,
+            static_node(" i, "),
+            addr(),
+            Transform::Consume(2),
+        ],
+    );
+
+    let mut list = vec![Transform::MatcherName, static_node(" ")];
+    list.append(&mut reg_x());
+    list.push(Transform::Consume(2));
+    map.insert(X.into(), list);
+
+    let mut list = vec![Transform::MatcherName, static_node(" ")];
+    list.append(&mut reg_x());
+    list.append(&mut vec![
+        static_node(", "),
+        Transform::Val(ValOut {
+            offset: 1,
+            fmt: ValueTypeFmt::LowerHex(2),
+            data_type: DataType::U8,
+            peek: true,
+            no_sym: true,
+            ..Default::default()
+        }),
+        Transform::Consume(2),
+    ]);
+    map.insert(X_KK.into(), list);
+
+    let mut list = vec![Transform::MatcherName, static_node(" ")];
+    list.append(&mut reg_x());
+    list.push(static_node(", "));
+    list.append(&mut reg_y());
+    list.push(Transform::Consume(2));
+    map.insert(X_Y.into(), list);
+
+    let mut list = vec![Transform::MatcherName, static_node(" ")];
+    list.append(&mut reg_x());
+    list.push(static_node(", "));
+    list.append(&mut reg_y());
+    list.append(&mut vec![
+        static_node(", "),
+        field(1, vec![ValueOp::And(0x0F)]),
+        Transform::Consume(2),
+    ]);
+    map.insert(X_Y_N.into(), list);
+
+    // the Fx-- instructions all use the same layout with a different
+    // operand before or after the register
+    for (op, _, pre, post) in fx_instructions() {
+        let mut list = vec![Transform::MatcherName, static_node(&format!(" {pre}"))];
+        list.append(&mut reg_x());
+        list.append(&mut vec![static_node(post), Transform::Consume(2)]);
+        map.insert(fx_key(op), list);
+    }
+
+    map
+}
+
+// (second byte, name, text before the register, text after the register)
+fn fx_instructions() -> Vec<(u8, &'static str, &'static str, &'static str)> {
+    vec![
+        (0x07, "ld", "", ", dt"),
+        (0x0A, "ld", "", ", k"),
+        (0x15, "ld", "dt, ", ""),
+        (0x18, "ld", "st, ", ""),
+        (0x1E, "add", "i, ", ""),
+        (0x29, "ld", "f, ", ""),
+        (0x33, "ld", "b, ", ""),
+        (0x55, "ld", "[i], ", ""),
+        (0x65, "ld", "", ", [i]"),
+    ]
+}
+
+fn fx_key(op: u8) -> String {
+    format!("chip8_fx{op:02x}")
+}
+
+// matches the upper nibble of the first byte and optionally
+// a pattern for the second byte
+fn matcher(matchers: &mut MatcherList, nibble: u8, second: Pattern, name: &str, mode: &str) {
+    matchers.push(Matcher {
+        patterns: vec![
+            PatternAt::new(Pattern::Mask(0xF0, nibble << 4), 0),
+            PatternAt::new(second, 1),
+        ],
+        transforms: mode.into(),
+        name: Node::new(name.into()),
+        cycles: None,
+    });
+}
+
+fn patterns() -> MatcherList {
+    let mut matchers = vec![];
+
+    // 00E0 and 00EE have to be checked before sys
+    matchers.push(Matcher {
+        patterns: vec![
+            PatternAt::new(Pattern::Exact(0x00), 0),
+            PatternAt::new(Pattern::Exact(0xE0), 1),
+        ],
+        transforms: IMPLIED.into(),
+        name: Node::new("cls".into()),
+        cycles: None,
+    });
+    matchers.push(Matcher {
+        patterns: vec![
+            PatternAt::new(Pattern::Exact(0x00), 0),
+            PatternAt::new(Pattern::Exact(0xEE), 1),
+        ],
+        transforms: IMPLIED.into(),
+        name: Node::new("ret".into()),
+        cycles: None,
+    });
+    matcher(&mut matchers, 0x0, Pattern::Any, "sys", ADDR);
+    matcher(&mut matchers, 0x1, Pattern::Any, "jp", ADDR);
+    matcher(&mut matchers, 0x2, Pattern::Any, "call", ADDR);
+    matcher(&mut matchers, 0x3, Pattern::Any, "se", X_KK);
+    matcher(&mut matchers, 0x4, Pattern::Any, "sne", X_KK);
+    matcher(&mut matchers, 0x5, Pattern::Mask(0x0F, 0x0), "se", X_Y);
+    matcher(&mut matchers, 0x6, Pattern::Any, "ld", X_KK);
+    matcher(&mut matchers, 0x7, Pattern::Any, "add", X_KK);
+
+    for (n, name) in [
+        (0x0, "ld"),
+        (0x1, "or"),
+        (0x2, "and"),
+        (0x3, "xor"),
+        (0x4, "add"),
+        (0x5, "sub"),
+        (0x6, "shr"),
+        (0x7, "subn"),
+        (0xE, "shl"),
+    ] {
+        matcher(&mut matchers, 0x8, Pattern::Mask(0x0F, n), name, X_Y);
+    }
+
+    matcher(&mut matchers, 0x9, Pattern::Mask(0x0F, 0x0), "sne", X_Y);
+    matcher(&mut matchers, 0xA, Pattern::Any, "ld", I_ADDR);
+    matcher(&mut matchers, 0xB, Pattern::Any, "jp", V0_ADDR);
+    matcher(&mut matchers, 0xC, Pattern::Any, "rnd", X_KK);
+    matcher(&mut matchers, 0xD, Pattern::Any, "drw", X_Y_N);
+    matcher(&mut matchers, 0xE, Pattern::Exact(0x9E), "skp", X);
+    matcher(&mut matchers, 0xE, Pattern::Exact(0xA1), "sknp", X);
+
+    for (op, name, _, _) in fx_instructions() {
+        matcher(&mut matchers, 0xF, Pattern::Exact(op), name, &fx_key(op));
+    }
+
+    matchers
+}
+
+fn archs() -> BTreeMap<String, Arch> {
+    let mut map = BTreeMap::default();
+    map.insert(
+        "".into(),
+        Arch {
+            patterns: super::a6502::add_patterns_default(patterns()),
+            transforms: transforms(),
+            endianess: Endianess::Big,
+            addr_type: DataType::U16,
+            node_map: BTreeMap::from([(
+                ValueTypeFmt::LowerHex(0).pre().into(),
+                Node::new("0x".into()),
+            )]),
+            // we can unwrap this because the 6502 is guaranteed to have an empty
+            // arch key!
+            ..super::a6502::ARCH.archs.get("").unwrap().to_owned()
+        },
+    );
+    map
+}
@@ -0,0 +1,122 @@
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::core::dasm::ValueTypeFmt;
+
+use super::{Arch, Archs, Node, Transform, INCLUDE_PRE, LOCAL_PRE, ORG_PRE};
+
+/// The syntax of an assembler the output is written for
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Dialect {
+    Ca65,
+    Asm6,
+    #[cfg_attr(feature = "cli", value(name = "64tass"))]
+    Tass64,
+    WlaDx,
+    Vasm,
+}
+
+impl Dialect {
+    // the directives for bytes and words
+    fn data_directives(&self) -> (&'static str, &'static str) {
+        match self {
+            Self::Ca65 | Self::Tass64 | Self::Vasm => (".byte ", ".word "),
+            Self::Asm6 | Self::WlaDx => (".db ", ".dw "),
+        }
+    }
+
+    // the directive for strings, the byte directive accepts them as well
+    fn text_directive(&self) -> &'static str {
+        match self {
+            Self::Tass64 => ".text ",
+            _ => self.data_directives().0,
+        }
+    }
+
+    // the directive for 24-bit values, asm6 and vasm have none
+    fn long_directive(&self) -> Option<&'static str> {
+        match self {
+            Self::Ca65 => Some(".faraddr "),
+            Self::Tass64 => Some(".long "),
+            Self::WlaDx => Some(".dl "),
+            Self::Asm6 | Self::Vasm => None,
+        }
+    }
+
+    fn org_pre(&self) -> &'static str {
+        match self {
+            Self::Ca65 => ".org ",
+            Self::Asm6 | Self::Vasm => "org ",
+            Self::Tass64 => "* = ",
+            // .org is relative to the current bank
+            Self::WlaDx => ".orga ",
+        }
+    }
+
+    fn include_pre(&self) -> &'static str {
+        match self {
+            Self::Ca65 | Self::Tass64 | Self::WlaDx => ".include ",
+            Self::Asm6 | Self::Vasm => "include ",
+        }
+    }
+
+    // the prefix of labels that are only in scope inside of a range
+    fn local_pre(&self) -> &'static str {
+        match self {
+            Self::Ca65 | Self::Asm6 => "@",
+            Self::Tass64 | Self::WlaDx => "_",
+            Self::Vasm => ".",
+        }
+    }
+
+    fn hex_pre(&self) -> &'static str {
+        "$"
+    }
+
+    /// Swaps the node map and the data directives of every arch
+    pub fn apply(&self, archs: &mut Archs) {
+        for arch in archs.archs.values_mut() {
+            self.apply_arch(arch);
+        }
+    }
+
+    pub fn apply_arch(&self, arch: &mut Arch) {
+        for (key, value) in [
+            (ValueTypeFmt::LowerHex(0).pre(), self.hex_pre()),
+            (ValueTypeFmt::UpperHex(0).pre(), self.hex_pre()),
+            (ORG_PRE, self.org_pre()),
+            (LOCAL_PRE, self.local_pre()),
+            (INCLUDE_PRE, self.include_pre()),
+        ] {
+            arch.node_map.insert(key.into(), Node::new(value.into()));
+        }
+
+        // a directive is a static starting with a dot right before the data
+        let (byte, word) = self.data_directives();
+        for transforms in arch.transforms.values_mut() {
+            for i in 1..transforms.len() {
+                let directive = match &transforms[i] {
+                    Transform::Data(d) if d.data_type.data_len() == 1 => byte,
+                    Transform::Data(d) if d.data_type.data_len() == 2 => word,
+                    Transform::Data(d) if d.data_type.data_len() == 3 => {
+                        match self.long_directive() {
+                            Some(long) => long,
+                            None => continue,
+                        }
+                    }
+                    Transform::Text => self.text_directive(),
+                    _ => continue,
+                };
+                if let Transform::Static(node) = &mut transforms[i - 1] {
+                    if node.string.starts_with('.') {
+                        node.string = directive.into();
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,184 @@
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::{Error, FdResult};
+
+use super::{Arch, Archs, Transform, TransformList};
+
+// the transforms of the built in archs that are output before and after every matcher
+const PRE_TRANSFORM: &str = "address";
+const POST_TRANSFORM: &str = "raw";
+
+/// A column of an output line
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Column {
+    Address,
+    // the matcher's output, e.g. the instruction and its operands
+    Code,
+    Raw,
+}
+
+/// The order and the widths of the columns of every line
+/// Columns that are not listed are not output, the code column is required.
+/// The raw column is only output if the context allows raw bytes
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Layout {
+    #[cfg_attr(feature = "serde", serde(default = "default_columns"))]
+    pub columns: Vec<Column>,
+    // the amount of hex digits of the address
+    #[cfg_attr(feature = "serde", serde(default = "default_address_width"))]
+    pub address_width: usize,
+    // the columns are padded to their width if another column follows
+    #[cfg_attr(feature = "serde", serde(default = "default_code_width"))]
+    pub code_width: usize,
+    #[cfg_attr(feature = "serde", serde(default = "default_raw_width"))]
+    pub raw_width: usize,
+}
+
+fn default_columns() -> Vec<Column> {
+    vec![Column::Address, Column::Code, Column::Raw]
+}
+
+fn default_address_width() -> usize {
+    8
+}
+
+fn default_code_width() -> usize {
+    16
+}
+
+fn default_raw_width() -> usize {
+    16
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            columns: default_columns(),
+            address_width: default_address_width(),
+            code_width: default_code_width(),
+            raw_width: default_raw_width(),
+        }
+    }
+}
+
+impl Layout {
+    pub fn shows(&self, column: Column) -> bool {
+        self.columns.contains(&column)
+    }
+
+    fn width(&self, column: Column) -> usize {
+        match column {
+            // the address is followed by a space
+            Column::Address => self.address_width + 1,
+            Column::Code => self.code_width,
+            Column::Raw => self.raw_width,
+        }
+    }
+
+    /// The transforms before and after the code column
+    pub fn transforms(&self) -> FdResult<(TransformList, TransformList)> {
+        for (i, column) in self.columns.iter().enumerate() {
+            if self.columns[..i].contains(column) {
+                return Err(Error::InvalidArgument(format!(
+                    "The column {column:?} is listed more than once"
+                )));
+            }
+        }
+        if !self.shows(Column::Code) {
+            return Err(Error::InvalidArgument(
+                "The layout needs a code column".into(),
+            ));
+        }
+
+        // the labels are output on their own lines before the columns
+        let mut pre = vec![Transform::Label];
+        let mut post = vec![];
+        let mut after_code = false;
+        let mut position = 0;
+        for (i, column) in self.columns.iter().enumerate() {
+            let list = if after_code { &mut post } else { &mut pre };
+            if i > 0 {
+                list.push(Transform::Pad(position));
+            }
+            match column {
+                Column::Address => list.push(Transform::Address(self.address_width)),
+                Column::Raw => list.push(Transform::Raw),
+                Column::Code => after_code = true,
+            }
+            position += self.width(*column);
+        }
+        post.push(Transform::Comment);
+        Ok((pre, post))
+    }
+
+    /// Replaces the column transforms of every arch
+    pub fn apply(&self, archs: &mut Archs) -> FdResult<()> {
+        for arch in archs.archs.values_mut() {
+            self.apply_arch(arch)?;
+        }
+        Ok(())
+    }
+
+    pub fn apply_arch(&self, arch: &mut Arch) -> FdResult<()> {
+        let (pre, post) = self.transforms()?;
+        arch.transforms.insert(PRE_TRANSFORM.into(), pre);
+        arch.transforms.insert(POST_TRANSFORM.into(), post);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::arch::{a6502, CallbackKind, Context};
+
+    use super::{Column, Layout};
+
+    #[test]
+    fn layout() {
+        let layout = Layout {
+            columns: vec![Column::Address, Column::Raw, Column::Code],
+            address_width: 4,
+            raw_width: 12,
+            ..Default::default()
+        };
+        let mut arch = a6502::ARCH.to_owned();
+        layout.apply(&mut arch).unwrap();
+        let mut ctx = Context {
+            org: 0x8000,
+            allow_raw: true,
+            ..Default::default()
+        };
+        let mut result = String::new();
+        arch.disas_ctx(
+            |n, kind, _raw, _arch, ctx| {
+                if let CallbackKind::Pad(chars) = kind {
+                    let pad = chars.saturating_sub(ctx.tr_ctx.line_len);
+                    result.extend(std::iter::repeat_n(' ', pad));
+                }
+                result.push_str(&n.string);
+                Ok(())
+            },
+            &[0xA9, 0x10, 0xEA],
+            &mut ctx,
+        )
+        .unwrap();
+        assert_eq!("8000  [$a9 $10]  lda #$10\n8002  [$ea]      nop\n", result);
+
+        let layout = Layout {
+            columns: vec![Column::Address, Column::Address, Column::Code],
+            ..Default::default()
+        };
+        assert!(layout.apply(&mut arch).is_err());
+        let layout = Layout {
+            columns: vec![Column::Raw],
+            ..Default::default()
+        };
+        assert!(layout.apply(&mut arch).is_err());
+    }
+}
@@ -1,22 +1,48 @@
 pub mod a6502;
 pub mod a65c02;
 pub mod a65c816;
+pub mod a6800;
+pub mod a6803;
+pub mod a8051;
+pub mod builder;
+pub mod chip8;
+pub mod dialect;
+pub mod layout;
+pub mod msp430;
+pub mod pdp11;
+pub mod sh2;
+pub mod spc700;
+pub mod superfx;
 
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     fmt::Display,
     sync::{Arc, Mutex},
 };
 
-use log::info;
+use log::{info, warn};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-use crate::prelude::{Error, FdResult};
+use crate::prelude::{auto_radix_value, Error, FdResult};
 
 use super::{
-    patch::Patch,
-    symbols::{Scope, Symbol, SymbolKind, SymbolList},
+    checksum::Checksum,
+    classify::{ByteClass, ClassMap},
+    descent,
+    event::{Event, EventHook, EventResponse},
+    flow, interrupts,
+    loader::metadata::Metadata,
+    memory_map::MemoryMap,
+    output::{
+        substitute, HexdumpWriter, JsonLine, LineKind, MarkdownWriter, OutputProfile, SourceMap,
+    },
+    overlay::Overlay,
+    patch::{self, ExtendPolicy, Patch},
+    region::Region,
+    report::{BankCrossing, SizeMismatch, SizeReport},
+    segment::{Gap, Segment},
+    symbols::{ReferenceKind, Scope, Symbol, SymbolKind, SymbolList},
     try_to_node, Address, DataType, ValueType, ValueTypeFmt,
 };
 
@@ -102,6 +128,8 @@ pub enum Pattern {
     Address(Address, Address),
     // check if a flag has a certain value
     Flag(String, Option<String>),
+    // byte & mask == value
+    Mask(u8, u8),
     #[default]
     Never,
 }
@@ -117,12 +145,22 @@ impl Pattern {
             Self::Always => true,
             Self::Never => false,
             Self::Flag(key, value) => ctx.get_flag(key) == value.as_ref(),
+            Self::Mask(mask, value) => byte & mask == *value,
         }
     }
 
     pub fn always(&self) -> bool {
         *self == Self::Always
     }
+
+    // true if the matched byte is known from the matcher's name, e.g. an opcode
+    fn encodes(&self) -> bool {
+        match self {
+            Self::Exact(_) | Self::And(_) | Self::Mask(..) => true,
+            Self::List(l) => l.iter().any(|p| p.encodes()),
+            _ => false,
+        }
+    }
 }
 
 type PatternList = Vec<Pattern>;
@@ -142,6 +180,64 @@ pub struct DefSym {
     symbol_kind: SymbolKind,
     #[cfg_attr(feature = "serde", serde(default))]
     len: usize,
+    // operations applied to the value before the symbol is defined
+    #[cfg_attr(feature = "serde", serde(default))]
+    ops: Vec<ValueOp>,
+}
+
+/// Arithmetic that can be applied to a value after it was read
+/// and before it is output or looked up as a symbol
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ValueOp {
+    Add(ValueType),
+    Sub(ValueType),
+    And(ValueType),
+    Or(ValueType),
+    Shl(u32),
+    Shr(u32),
+    // adds the current address masked by the given value
+    // e.g. 0xFF0000 adds the current bank
+    AddBank(ValueType),
+    // gathers all bits selected by the mask into the lowest bits
+    // e.g. 0xE0FF turns 0b101xxxxx_yyyyyyyy into 0b101_yyyyyyyy
+    Extract(ValueType),
+    // sign extends a value that is the given number of bits wide
+    // e.g. 10 turns 0x3FF into -1
+    SignExtend(u32),
+}
+
+impl ValueOp {
+    pub fn apply(&self, value: ValueType, ctx: &Context) -> ValueType {
+        match self {
+            ValueOp::Add(v) => value.wrapping_add(*v),
+            ValueOp::Sub(v) => value.wrapping_sub(*v),
+            ValueOp::And(v) => value & v,
+            ValueOp::Or(v) => value | v,
+            ValueOp::Shl(n) => value.wrapping_shl(*n),
+            ValueOp::Shr(n) => value.wrapping_shr(*n),
+            ValueOp::AddBank(mask) => value.wrapping_add(ctx.address() as ValueType & mask),
+            ValueOp::Extract(mask) => {
+                let mut result = 0;
+                let mut bit = 0;
+                for i in 0..ValueType::BITS {
+                    if (mask >> i) & 1 == 1 {
+                        result |= ((value >> i) & 1) << bit;
+                        bit += 1;
+                    }
+                }
+                result
+            }
+            ValueOp::SignExtend(bits) => {
+                let shift = ValueType::BITS.saturating_sub(*bits);
+                value.wrapping_shl(shift).wrapping_shr(shift)
+            }
+        }
+    }
+
+    pub fn apply_all(ops: &[ValueOp], value: ValueType, ctx: &Context) -> ValueType {
+        ops.iter().fold(value, |v, op| op.apply(v, ctx))
+    }
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -161,6 +257,87 @@ pub struct ValOut {
     // value of no symbol does yet exist at the requested value
     #[cfg_attr(feature = "serde", serde(default))]
     auto_def_sym: bool,
+
+    // operations applied to the value in order
+    // e.g. to extract a register field from an opcode
+    #[cfg_attr(feature = "serde", serde(default))]
+    ops: Vec<ValueOp>,
+    // read the value without consuming it
+    #[cfg_attr(feature = "serde", serde(default))]
+    peek: bool,
+    // never replace this value with a symbol (e.g. register numbers)
+    #[cfg_attr(feature = "serde", serde(default))]
+    no_sym: bool,
+    // the value is a zero page address that the analysis defines as var_xx
+    // if zero page vars are enabled and no symbol is located at the address
+    #[cfg_attr(feature = "serde", serde(default))]
+    zp_var: bool,
+}
+
+pub const COMMENT_PRE: &str = "comment_pre";
+pub const ORG_PRE: &str = "org_pre";
+pub const LOCAL_PRE: &str = "local_pre";
+pub const INCLUDE_PRE: &str = "include_pre";
+// the transform that is used for bytes outside of the window in reassemble mode
+// and for bytes that no matcher matches
+pub const DEFINE_BYTE: &str = "define_byte";
+// the name of the matcher that outputs the bytes using define_byte
+const DEFINE_BYTE_NAME: &str = ".db";
+// the transform that is used for the bytes that are classified as text
+pub const DEFINE_TEXT: &str = "define_text";
+const DEFINE_TEXT_NAME: &str = ".text";
+// the transforms that are used for the bytes that are classified as pointers
+pub const DEFINE_WORD: &str = "define_word";
+const DEFINE_WORD_NAME: &str = ".word";
+pub const DEFINE_LONG: &str = "define_long";
+const DEFINE_LONG_NAME: &str = ".long";
+const DEFAULT_COMMENT_PRE: &str = "; ";
+const DEFAULT_ORG_PRE: &str = ".org ";
+const DEFAULT_INCLUDE_PRE: &str = ".include ";
+
+/// Outputs a run of values separated by commas
+/// The run ends early if the next value would be matched by a different matcher.
+/// Bytes that no matcher matches continue a run of the define_byte fallback
+/// or if a label or static op is located at its address
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Default, Clone)]
+pub struct DataOut {
+    #[cfg_attr(feature = "serde", serde(default))]
+    fmt: ValueTypeFmt,
+    #[cfg_attr(feature = "serde", serde(default))]
+    data_type: DataType,
+    // overrides the amount of values per line set in the output profile
+    #[cfg_attr(feature = "serde", serde(default))]
+    per_line: Option<usize>,
+}
+
+impl DataOut {
+    pub fn new(fmt: ValueTypeFmt, data_type: DataType) -> Self {
+        Self {
+            fmt,
+            data_type,
+            per_line: None,
+        }
+    }
+}
+
+/// Outputs the constant a pc relative load reads as a comment.
+/// The displacement is read from the instruction like a value and the constant
+/// is read from ((address + pc_offset) & !(align - 1)) + displacement.
+/// Nothing is output if the constant is not part of the input
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Default, Clone)]
+pub struct LiteralOut {
+    #[cfg_attr(feature = "serde", serde(default))]
+    disp: ValOut,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pc_offset: i64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    align: Address,
+    #[cfg_attr(feature = "serde", serde(default))]
+    fmt: ValueTypeFmt,
+    #[cfg_attr(feature = "serde", serde(default))]
+    data_type: DataType,
 }
 
 /// A formatter takes an input &[u8] and applies a transform to the data
@@ -176,6 +353,12 @@ pub enum Transform {
     /// from the array
     /// AbsXX takes the offset and radix
     Val(ValOut),
+    /// Outputs multiple values of the same type on a single line
+    Data(DataOut),
+    /// Outputs the constant loaded by a pc relative instruction
+    Literal(LiteralOut),
+    /// Outputs all of the bytes as a quoted string using the charset of the output profile
+    Text,
     /// Outputs the data received as a raw byte dump
     Raw,
     // output label at current address
@@ -200,6 +383,8 @@ pub enum Transform {
     ChangeArch(String),
     // Pad to n chars
     Pad(usize),
+    // outputs the comment of the address in the comment column if it is set
+    Comment,
     #[default]
     Skip,
 }
@@ -225,11 +410,28 @@ impl Transform {
         ctx: &mut Context,
         matcher_name: &Node,
     ) -> FdResult<usize> {
+        if let Transform::Data(d) = self {
+            let read = self.output_data(f, data, arch, ctx, matcher_name, d)?;
+            ctx.claim(ctx.tr_ctx.position, read);
+            return Ok(read);
+        }
+        if let Transform::Literal(l) = self {
+            return self.output_literal(f, data, arch, ctx, l);
+        }
+        if let Transform::Text = self {
+            let read = self.output_text(f, data, arch, ctx)?;
+            ctx.claim(ctx.tr_ctx.position, read);
+            return Ok(read);
+        }
+
         // get all data, if no data is available just return with an error
         // since a transform should *never* be out of data
         // assuming the pattern is defined correctly!
         let data = Self::get_data(data, self.offset(), self.read_len(arch.addr_type, data))
             .ok_or(Error::TransformOutOfData(ctx.org))?;
+        if let Transform::Val(ao) = self {
+            ctx.claim(ctx.tr_ctx.position + ao.offset, data.len());
+        }
 
         let dt = self.data_type(arch.addr_type);
 
@@ -252,10 +454,67 @@ impl Transform {
         arch: &Arch,
         ctx: &mut Context,
     ) -> FdResult<()> {
+        // a pad is held back until the next node is output, so the last pad before
+        // a node wins (e.g. the comment column after an empty raw column)
+        // and the pads at the end of a line are dropped
+        if let CallbackKind::Pad(chars) = kind {
+            ctx.tr_ctx.pad = Some(chars);
+            return Ok(());
+        }
+        if node.string.is_empty() {
+            return f(node, kind, data, arch, ctx);
+        }
+        if let Some(chars) = ctx.tr_ctx.pad.take() {
+            if !node.string.starts_with('\n') {
+                f(
+                    &Node::new("".into()),
+                    CallbackKind::Pad(chars),
+                    &[],
+                    arch,
+                    ctx,
+                )?;
+                // the callback pads the line to the column
+                ctx.tr_ctx.line_len = ctx.tr_ctx.line_len.max(chars);
+            }
+        }
+        let cased = Self::case_rule(node, &kind, arch, ctx);
+        let node = cased.as_ref().unwrap_or(node);
         ctx.tr_ctx.line_len += node.string.len();
         f(node, kind, data, arch, ctx)
     }
 
+    // the uppercase options of the arch and the output profile
+    // returns None if the node keeps its case
+    fn case_rule(node: &Node, kind: &CallbackKind, arch: &Arch, ctx: &Context) -> Option<Node> {
+        let string = match kind {
+            CallbackKind::MatcherName
+                if (arch.uppercase_mnemonics || ctx.output.uppercase_mnemonics)
+                    && !node.string.starts_with('.') =>
+            {
+                node.string.to_uppercase()
+            }
+            CallbackKind::Val if arch.uppercase_hex || ctx.output.uppercase_hex => {
+                let fmt = ValueTypeFmt::LowerHex(0);
+                let [pre, post] = [fmt.pre(), fmt.post()].map(|key| {
+                    arch.node_map
+                        .get(key)
+                        .map(|n| n.string.as_str())
+                        .unwrap_or_default()
+                });
+                let digits = node.string.strip_prefix(pre)?.strip_suffix(post)?;
+                if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return None;
+                }
+                format!("{pre}{}{post}", digits.to_uppercase())
+            }
+            _ => return None,
+        };
+        Some(Node {
+            string,
+            kind: node.kind.clone(),
+        })
+    }
+
     fn no_analyze(
         &self,
         f: &mut dyn DisasCallback,
@@ -266,16 +525,29 @@ impl Transform {
         _dt: DataType,
     ) -> FdResult<()> {
         match self {
-            Transform::Val(ao) => self.output_value(f, data, arch, ctx, ao)?,
+            Transform::Val(ao) => self.output_value(f, data, arch, ctx, ao, arch.endianess)?,
             Transform::Raw => self.output_raw(f, data, arch, ctx)?,
             Transform::Label => self.output_label(f, data, arch, ctx)?,
-            Transform::Static(s) => Self::cb(f, s, CallbackKind::Static, data, arch, ctx)?,
+            Transform::Static(s) => match ctx.expand_vars(&s.string) {
+                Some(expanded) => Self::cb(
+                    f,
+                    &Node::new(expanded),
+                    CallbackKind::Static,
+                    data,
+                    arch,
+                    ctx,
+                )?,
+                None => Self::cb(f, s, CallbackKind::Static, data, arch, ctx)?,
+            },
             Transform::MatcherName => {
                 Self::cb(f, matcher_name, CallbackKind::MatcherName, data, arch, ctx)?
             }
+            // the clean output has no address column
+            Transform::Address(_) if ctx.output.clean => {}
+            // the address of the matcher, the column may follow the code
             Transform::Address(width) => Self::cb(
                 f,
-                &Node::new(format!("{:0width$x}", ctx.address())),
+                &Node::new(format!("{:0width$x}", ctx.tr_ctx.address)),
                 CallbackKind::Address,
                 data,
                 arch,
@@ -289,11 +561,49 @@ impl Transform {
                 arch,
                 ctx,
             )?,
+            Transform::Comment => self.output_inline_comment(f, data, arch, ctx)?,
             _ => {}
         }
         Ok(())
     }
 
+    fn output_inline_comment(
+        &self,
+        f: &mut dyn DisasCallback,
+        data: &[u8],
+        arch: &Arch,
+        ctx: &mut Context,
+    ) -> FdResult<()> {
+        let (Some(column), Some(comment)) = (
+            ctx.output.comment_column,
+            ctx.inline_comment(ctx.tr_ctx.address).cloned(),
+        ) else {
+            return Ok(());
+        };
+        // the comment is separated from a line that is longer than the column
+        let separator = if ctx.tr_ctx.line_len >= column {
+            " "
+        } else {
+            ""
+        };
+        Self::cb(
+            f,
+            &Node::new("".into()),
+            CallbackKind::Pad(column),
+            data,
+            arch,
+            ctx,
+        )?;
+        Self::cb(
+            f,
+            &Node::new(format!("{separator}{}{comment}", arch.comment_pre())),
+            CallbackKind::Static,
+            data,
+            arch,
+            ctx,
+        )
+    }
+
     fn analyze_and_no_analyze(
         &self,
         _f: &mut dyn DisasCallback,
@@ -331,23 +641,58 @@ impl Transform {
         data: &[u8],
         arch: &Arch,
         ctx: &mut Context,
-        _matcher_name: &Node,
+        matcher_name: &Node,
     ) -> FdResult<()> {
         match self {
-            Transform::DefSym(ds) => ctx.def_symbol(Symbol::new(
-                ds.name.clone(),
-                ds.symbol_kind,
-                ds.scope,
-                Self::to_value(data, arch)?,
-                ds.len,
-            )),
-            Transform::DefSymAddress(ds) => ctx.def_symbol(Symbol::new(
-                ds.name.clone(),
-                ds.symbol_kind,
-                ds.scope,
-                Self::to_addr(data, arch)?,
-                ds.len,
-            )),
+            Transform::DefSym(ds) => {
+                let value = ValueOp::apply_all(&ds.ops, Self::to_value(data, arch.endianess)?, ctx);
+                ctx.def_auto_symbol(Symbol::new(
+                    ds.name.clone(),
+                    ds.symbol_kind,
+                    ds.scope,
+                    value,
+                    ds.len,
+                ))
+            }
+            Transform::DefSymAddress(ds) => {
+                let value = ValueOp::apply_all(&ds.ops, Self::to_addr(data, arch)?, ctx);
+                ctx.def_auto_symbol(Symbol::new(
+                    ds.name.clone(),
+                    ds.symbol_kind,
+                    ds.scope,
+                    value,
+                    ds.len,
+                ))
+            }
+            Transform::Val(ao) if !ao.no_sym => {
+                let value = ValueOp::apply_all(&ao.ops, Self::to_value(data, arch.endianess)?, ctx);
+                let sym_val = Self::sym_value(ao, value, arch, ctx);
+                if ctx.output.xrefs {
+                    ctx.xrefs
+                        .entry(sym_val as Address)
+                        .or_default()
+                        .insert(ctx.tr_ctx.address);
+                }
+                if ao.zp_var && ctx.zp_vars && ctx.get_first_symbol(sym_val).is_none() {
+                    ctx.def_auto_symbol(Symbol::new(
+                        format!("var_{sym_val:02x}"),
+                        SymbolKind::Label,
+                        Scope::Global,
+                        sym_val,
+                        1,
+                    ))
+                }
+                if ao.auto_def_sym && ctx.get_first_symbol(sym_val).is_none() {
+                    let kind = flow::reference_kind(&matcher_name.string);
+                    ctx.def_auto_symbol(Symbol::new(
+                        ctx.auto_label_name(kind, sym_val),
+                        SymbolKind::Label,
+                        Scope::Global,
+                        sym_val,
+                        1,
+                    ))
+                }
+            }
             _ => {}
         }
         Ok(())
@@ -360,18 +705,63 @@ impl Transform {
         arch: &Arch,
         ctx: &mut Context,
     ) -> FdResult<()> {
-        let labels = ctx.syms.get_symbols(ctx.address() as ValueType);
+        let mut labels = ctx.syms.get_symbols(ctx.address() as ValueType);
+        // the order must not depend on when the labels were defined
+        // user labels come before auto labels and global labels before scoped ones
+        labels.sort_by_cached_key(|l| (l.auto, l.scope != Scope::Global, l.name.clone()));
         let mut result = "".to_owned();
         for label in labels {
-            if label.scope.is_in_scope(ctx.address()) && label.kind == SymbolKind::Label {
-                result.push_str(&format!("{}:\n", &label.name));
+            if label.scope.is_in_scope(ctx.address())
+                && label.kind == SymbolKind::Label
+                && label.namespace == ctx.namespace
+            {
+                // the doc is output right above its label
+                if let Some(doc) = &label.doc {
+                    if !result.is_empty() {
+                        Self::cb(f, &Node::new(result), CallbackKind::Label, data, arch, ctx)?;
+                        result = "".to_owned();
+                    }
+                    let block = ctx.output.comment_block(doc, arch.comment_pre());
+                    Self::cb(f, &Node::new(block), CallbackKind::Static, data, arch, ctx)?;
+                }
+                result.push_str(&format!(
+                    "{}:\n",
+                    arch.local_name(&label, label.name.clone())
+                ));
             }
         }
+        let xrefs = ctx
+            .xrefs
+            .get(&ctx.address())
+            .filter(|_| ctx.output.xrefs && !result.is_empty())
+            .map(|sites| {
+                sites
+                    .iter()
+                    .map(|site| arch.format_value(*site as ValueType))
+                    .collect::<FdResult<Vec<String>>>()
+            })
+            .transpose()?;
+        if !result.is_empty() {
+            ctx.cycle_total = 0;
+        }
         // labels do not count towards the line lenght because they
         // create a new line -> lenght should be 0 now!
-        let res = Self::cb(f, &Node::new(result), CallbackKind::Label, data, arch, ctx);
+        Self::cb(f, &Node::new(result), CallbackKind::Label, data, arch, ctx)?;
+        if let Some(xrefs) = xrefs {
+            let comment = format!("{}xrefs: {}\n", arch.comment_pre(), xrefs.join(", "));
+            Self::cb(
+                f,
+                &Node::new(comment),
+                CallbackKind::Static,
+                data,
+                arch,
+                ctx,
+            )?;
+        }
+        let (_, after) = ctx.output.lines_around_labels();
+        arch.apply_statics_pre(f, data, ctx, &after)?;
         ctx.tr_ctx.line_len = 0;
-        res
+        Ok(())
     }
 
     fn output_raw(
@@ -381,7 +771,8 @@ impl Transform {
         arch: &Arch,
         ctx: &mut Context,
     ) -> FdResult<()> {
-        if !ctx.allow_raw || data.is_empty() {
+        // the json output contains the bytes
+        if !ctx.allow_raw || data.is_empty() || ctx.output.json || ctx.output.clean {
             return Ok(());
         }
         Self::cb(
@@ -421,6 +812,19 @@ impl Transform {
         Ok(())
     }
 
+    // the value that symbols are looked up by, relative values point to an address
+    fn sym_value(ao: &ValOut, value: ValueType, arch: &Arch, ctx: &Context) -> ValueType {
+        if ao.rel {
+            let addr = (ctx.address() as ValueType).wrapping_add(ao.data_type.sign_extend(value));
+            match arch.addr_type.mask() {
+                0 => addr,
+                mask => addr & mask,
+            }
+        } else {
+            value
+        }
+    }
+
     fn output_value(
         &self,
         f: &mut dyn DisasCallback,
@@ -428,55 +832,194 @@ impl Transform {
         arch: &Arch,
         ctx: &mut Context,
         ao: &ValOut,
+        endianess: Endianess,
     ) -> FdResult<()> {
-        let value = Self::to_value(data, arch)?;
+        let value = ValueOp::apply_all(&ao.ops, Self::to_value(data, endianess)?, ctx);
+        let sym_val = Self::sym_value(ao, value, arch, ctx);
 
-        let sym_val = if ao.rel {
-            let addr = (ctx.address() as ValueType).wrapping_add(value);
-            addr as ValueType & ao.data_type.mask()
-        } else {
-            value
-        };
-
-        if let Some(sym) = ctx.get_first_symbol(sym_val) {
+        if let Some(sym) = ctx.get_first_symbol(sym_val).filter(|_| !ao.no_sym) {
             if !ctx.analyze {
+                let name = arch.local_name(sym, sym.name_in(&ctx.namespace));
                 let sym_name = if sym.value == sym_val {
-                    sym.name.to_owned()
+                    name
                 } else {
                     // represent the actual difference between the current
                     // value and the symbol's value here
-                    format!("{}+{}", sym.name, sym_val - sym.value)
+                    format!("{}+{}", name, sym_val - sym.value)
                 };
+                let node = Node {
+                    string: sym_name,
+                    kind: NodeKind::Value(sym_val),
+                };
+                Self::cb(f, &node, CallbackKind::Symbol, data, arch, ctx)?
+            }
+        } else if !ctx.analyze {
+            // relative values carry the address they point to
+            let mut node = try_to_node(value, ao.fmt, arch)?;
+            node.kind = NodeKind::Value(sym_val);
+            Self::cb(f, &node, CallbackKind::Val, data, arch, ctx)?
+        }
+
+        Ok(())
+    }
+
+    fn output_data(
+        &self,
+        f: &mut dyn DisasCallback,
+        data: &[u8],
+        arch: &Arch,
+        ctx: &mut Context,
+        matcher_name: &Node,
+        d: &DataOut,
+    ) -> FdResult<usize> {
+        let size = d.data_type.data_len().max(1);
+        if data.len() < size {
+            return Err(Error::TransformOutOfData(ctx.org));
+        }
+        let count = Self::data_run_count(data, arch, ctx, matcher_name, d);
+        let endianess = ctx.endianess_at(ctx.address()).unwrap_or(arch.endianess);
+
+        let ao = ValOut {
+            fmt: d.fmt,
+            data_type: d.data_type,
+            ..Default::default()
+        };
+        for i in 0..count {
+            if i > 0 && !ctx.analyze {
                 Self::cb(
                     f,
-                    &Node::new(sym_name),
-                    CallbackKind::Symbol,
+                    &Node::new(", ".into()),
+                    CallbackKind::Static,
                     data,
                     arch,
                     ctx,
-                )?
+                )?;
             }
-        } else if !ctx.analyze {
+            self.output_value(
+                f,
+                &data[i * size..(i + 1) * size],
+                arch,
+                ctx,
+                &ao,
+                endianess,
+            )?;
+        }
+
+        if ctx.output.ascii_sidebar && !ctx.analyze {
+            let chars = ctx.output.to_chars(&data[..count * size]);
             Self::cb(
                 f,
-                &try_to_node(value, ao.fmt, arch)?,
-                CallbackKind::Val,
+                &Node::new(format!(" {}{chars}", arch.comment_pre())),
+                CallbackKind::Static,
                 data,
                 arch,
                 ctx,
-            )?
-        } else if ao.auto_def_sym {
-            let name = format!("auto_{}", ctx.address());
-            ctx.def_symbol(Symbol::new(
-                name,
-                SymbolKind::Label,
-                Scope::Global,
-                value,
-                1,
-            ));
+            )?;
         }
+        Ok(count * size)
+    }
 
-        Ok(())
+    fn output_literal(
+        &self,
+        f: &mut dyn DisasCallback,
+        data: &[u8],
+        arch: &Arch,
+        ctx: &mut Context,
+        l: &LiteralOut,
+    ) -> FdResult<usize> {
+        let disp_data = Self::get_data(data, l.disp.offset, l.disp.data_type.data_len())
+            .ok_or(Error::TransformOutOfData(ctx.org))?;
+        let disp = ValueOp::apply_all(&l.disp.ops, Self::to_value(disp_data, arch.endianess)?, ctx);
+
+        let align = l.align.max(1);
+        let target = (ctx.address().wrapping_add(l.pc_offset as Address) & !(align - 1))
+            .wrapping_add(disp as Address);
+        // only literals that follow the instruction are part of the data
+        if target < ctx.address() {
+            return Ok(0);
+        }
+        let offset = (target - ctx.address()) as usize;
+        let Some(value) = Self::get_data(data, offset, l.data_type.data_len()) else {
+            return Ok(0);
+        };
+
+        if !ctx.analyze {
+            Self::cb(
+                f,
+                &Node::new(format!(" {}", arch.comment_pre())),
+                CallbackKind::Static,
+                data,
+                arch,
+                ctx,
+            )?;
+        }
+        let ao = ValOut {
+            fmt: l.fmt,
+            data_type: l.data_type,
+            ..Default::default()
+        };
+        let endianess = ctx.endianess_at(target).unwrap_or(arch.endianess);
+        self.output_value(f, value, arch, ctx, &ao, endianess)?;
+        Ok(0)
+    }
+
+    fn output_text(
+        &self,
+        f: &mut dyn DisasCallback,
+        data: &[u8],
+        arch: &Arch,
+        ctx: &mut Context,
+    ) -> FdResult<usize> {
+        if !ctx.analyze {
+            let node = Node::new(format!("\"{}\"", ctx.output.to_chars(data)));
+            Self::cb(f, &node, CallbackKind::Static, data, arch, ctx)?;
+        }
+        Ok(data.len())
+    }
+
+    // counts how many values of the data type can be grouped into a single line
+    fn data_run_count(
+        data: &[u8],
+        arch: &Arch,
+        ctx: &mut Context,
+        matcher_name: &Node,
+        d: &DataOut,
+    ) -> usize {
+        let size = d.data_type.data_len().max(1);
+        let per_line = d.per_line.unwrap_or(ctx.output.data_per_line).max(1);
+        let offset = ctx.offset;
+        let endianess = ctx.endianess_at(ctx.address());
+
+        let mut count = 1;
+        while count < per_line && (count + 1) * size <= data.len() {
+            ctx.offset = offset + (count * size) as Address;
+            let address = ctx.address();
+            let has_label = ctx.syms.get_symbols(address as ValueType).iter().any(|s| {
+                s.kind == SymbolKind::Label
+                    && s.scope.is_in_scope(address)
+                    && s.namespace == ctx.namespace
+            });
+            let has_static_op = ctx.has_static_op_at(address);
+            // the recursive descent or the classes already decided that the bytes are data
+            let position = ctx.offset.wrapping_add(ctx.static_offset) as usize;
+            let descended = (ctx.reached.is_some() && ctx.namespace.is_empty())
+                || ctx.classes.get(position).is_data();
+            let same_matcher = (descended && matcher_name.string == DEFINE_BYTE_NAME)
+                || arch
+                    .patterns
+                    .iter()
+                    .find(|m| m.is_match(arch, ctx, &data[count * size..]))
+                    .map(|m| m.name.string == matcher_name.string)
+                    .unwrap_or(matcher_name.string == DEFINE_BYTE_NAME);
+            let same_endianess = ctx.endianess_at(address) == endianess;
+            if has_label || has_static_op || !same_matcher || !same_endianess {
+                break;
+            }
+            count += 1;
+        }
+
+        ctx.offset = offset;
+        count
     }
 
     fn to_addr(data: &[u8], arch: &Arch) -> FdResult<ValueType> {
@@ -485,9 +1028,9 @@ impl Transform {
             .ok_or(Error::TransformOutOfData(0))
     }
 
-    fn to_value(data: &[u8], arch: &Arch) -> FdResult<ValueType> {
-        let data = arch.endianess.pad(data, std::mem::size_of::<ValueType>());
-        arch.endianess
+    fn to_value(data: &[u8], endianess: Endianess) -> FdResult<ValueType> {
+        let data = endianess.pad(data, std::mem::size_of::<ValueType>());
+        endianess
             .transform(&data)
             .ok_or(Error::TransformOutOfData(0))
     }
@@ -504,7 +1047,7 @@ impl Transform {
     // returns amount of bytes that should be read, but *not* consumed
     fn read_len(&self, addr_type: DataType, data: &[u8]) -> usize {
         match self {
-            Transform::Val(_) => self.data_len(),
+            Transform::Val(ao) => ao.data_type.data_len(),
             Transform::Raw => data.len(),
             _ => self.data_type(addr_type).data_len(),
         }
@@ -522,6 +1065,7 @@ impl Transform {
     // returns amount of bytes that should be consumed
     fn data_len(&self) -> usize {
         match self {
+            Transform::Val(dt) if dt.peek => 0,
             Transform::Val(dt) => dt.data_type.data_len(),
             Transform::Consume(skip) => *skip,
             _ => 0,
@@ -533,7 +1077,7 @@ impl Transform {
         if len == 0 {
             Some(&[])
         } else {
-            data.get(offset..offset + len)
+            data.get(offset..offset.checked_add(len)?)
         }
     }
 }
@@ -567,9 +1111,27 @@ pub struct Matcher {
     transforms: String,
     // the name of this matcher
     name: Node,
+    // the cycles the instruction takes, output with the cycle counts
+    #[cfg_attr(feature = "serde", serde(default))]
+    cycles: Option<usize>,
 }
 
 impl Matcher {
+    /// The number of bytes the patterns of this matcher cover
+    pub fn encoded_len(&self) -> usize {
+        self.patterns
+            .iter()
+            .filter(|pa| {
+                !matches!(
+                    pa.pattern,
+                    Pattern::Always | Pattern::Never | Pattern::Address(..) | Pattern::Flag(..)
+                )
+            })
+            .map(|pa| pa.offset + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
     /// check if this matcher matches the pattern specified
     pub fn is_match(&self, arch: &Arch, ctx: &mut Context, data: &[u8]) -> bool {
         Self::is_match_with(arch, ctx, data, &self.patterns)
@@ -623,7 +1185,10 @@ impl Matcher {
         tl: &TransformList,
     ) -> FdResult<usize> {
         let mut total = total;
+        // the transforms may move the address, but not the position in the file
+        let position = ctx.offset.wrapping_add(ctx.static_offset) as usize;
         for t in tl.iter() {
+            ctx.tr_ctx.position = position + total;
             let read = t.apply(f, &data[total..], arch, ctx, &self.name)?;
             total += read;
             ctx.offset += read as Address;
@@ -634,8 +1199,12 @@ impl Matcher {
 
 type MatcherList = Vec<Matcher>;
 
+// outputs a matcher and returns the amount of bytes that were read
+type ApplyMatcher =
+    fn(&Arch, &mut dyn DisasCallback, &[u8], &mut Context, &Matcher) -> FdResult<usize>;
+
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Default, PartialEq, Eq, Copy, Clone)]
+#[derive(Default, PartialEq, Eq, Copy, Clone, Debug)]
 pub enum Endianess {
     // controversial default
     #[default]
@@ -683,6 +1252,30 @@ impl Endianess {
     }
 }
 
+/// Overrides the arch's endianess of data directives and
+/// literal constants in the addresses start..end
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Default, PartialEq, Eq, Copy, Clone, Debug)]
+pub struct EndianessRange {
+    pub start: Address,
+    pub end: Address,
+    pub endianess: Endianess,
+}
+
+impl EndianessRange {
+    pub fn new(start: Address, end: Address, endianess: Endianess) -> Self {
+        Self {
+            start,
+            end,
+            endianess,
+        }
+    }
+
+    pub fn contains(&self, address: Address) -> bool {
+        address >= self.start && address < self.end
+    }
+}
+
 // context for the current transform that is being applied.
 // This will be overwritten every time a new transform starts
 #[derive(Default, Clone)]
@@ -692,6 +1285,12 @@ pub struct TransformContext {
     // this simply counts the total lenght of all nodes passed to the
     // callback
     pub line_len: usize,
+    // the address the matcher started at
+    pub address: Address,
+    // the pad that is output before the next node
+    pub pad: Option<usize>,
+    // the file position of the data of the current transform
+    pub position: usize,
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -701,28 +1300,57 @@ pub enum StaticOp {
     StringLn(String),
     SetFlag(String, String),
     UnsetFlag(String),
+    SetVar(String, Variable),
     // Apply an operation if the data matches the input
-    Match(Vec<PatternAt>, Vec<StaticOp>), // TODO allow checking if flags are set
-                                          // to filter inserts
-                                          // if_flags(...)
+    Match(Vec<PatternAt>, Vec<StaticOp>),
+    // Apply the first list if the flag has the value (None = unset)
+    // and the second list otherwise
+    IfFlag(String, Option<String>, Vec<StaticOp>, Vec<StaticOp>),
+    // Apply the ops of a script that is defined in the context
+    Script(String),
+    // The org directive of the arch at the current address,
+    // clean output already emits it before every block
+    Org,
 }
 
 impl StaticOp {
+    /// true if this op is bound to the given address
+    pub fn is_at(&self, address: Address) -> bool {
+        matches!(self, StaticOp::Address(a, _) if *a == address)
+    }
+
     pub fn apply(
         &self,
         f: &mut dyn DisasCallback,
         data: &[u8],
         arch: &Arch,
         ctx: &mut Context,
+    ) -> FdResult<()> {
+        self.apply_in(f, data, arch, ctx, &mut vec![])
+    }
+
+    // scripts are the names of the scripts that are running,
+    // a script that is called again while it runs is an error
+    fn apply_in(
+        &self,
+        f: &mut dyn DisasCallback,
+        data: &[u8],
+        arch: &Arch,
+        ctx: &mut Context,
+        scripts: &mut Vec<String>,
     ) -> FdResult<()> {
         match self {
             StaticOp::Address(address, ops) => {
                 if ctx.address() == *address {
-                    ops.iter().try_for_each(|x| x.apply(f, data, arch, ctx))?;
+                    ops.iter()
+                        .try_for_each(|x| x.apply_in(f, data, arch, ctx, scripts))?;
                 }
             }
             StaticOp::StringLn(string) => f(
-                &Node::new(format!("{}\n", string)),
+                &Node::new(format!(
+                    "{}\n",
+                    ctx.expand_vars(string).as_ref().unwrap_or(string)
+                )),
                 CallbackKind::Static,
                 &[],
                 arch,
@@ -730,9 +1358,41 @@ impl StaticOp {
             )?,
             StaticOp::SetFlag(flag, value) => ctx.def_flag(&flag, &value),
             StaticOp::UnsetFlag(flag) => ctx.undef_flag(&flag),
+            StaticOp::SetVar(name, value) => ctx.def_var(name, value.clone()),
             StaticOp::Match(patterns, ops) => {
                 if Matcher::is_match_with(arch, ctx, data, &patterns) {
-                    ops.iter().try_for_each(|x| x.apply(f, data, arch, ctx))?;
+                    ops.iter()
+                        .try_for_each(|x| x.apply_in(f, data, arch, ctx, scripts))?;
+                }
+            }
+            StaticOp::IfFlag(flag, value, then_ops, else_ops) => {
+                let ops = if ctx.get_flag(flag) == value.as_ref() {
+                    then_ops
+                } else {
+                    else_ops
+                };
+                ops.iter()
+                    .try_for_each(|x| x.apply_in(f, data, arch, ctx, scripts))?;
+            }
+            StaticOp::Script(name) => {
+                let ops = ctx
+                    .scripts
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| Error::ScriptNotFound(name.clone()))?;
+                if scripts.contains(name) {
+                    scripts.push(name.clone());
+                    return Err(Error::ScriptCycle(scripts.join(" -> ")));
+                }
+                scripts.push(name.clone());
+                ops.iter()
+                    .try_for_each(|x| x.apply_in(f, data, arch, ctx, scripts))?;
+                scripts.pop();
+            }
+            StaticOp::Org => {
+                if !ctx.output.clean {
+                    let org = arch.org_line(ctx.address())?;
+                    f(&Node::new(org), CallbackKind::Static, &[], arch, ctx)?;
                 }
             }
         }
@@ -740,48 +1400,267 @@ impl StaticOp {
     }
 }
 
-/// The context describes the runtime information of a single parser operation
-/// it contains the current address as well as a list of known symbols
+/// A user-defined variable that can be referenced in templates
+/// using {var:name}
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Default, Clone)]
-pub struct Context {
-    // which architecture to use
-    #[cfg_attr(feature = "serde", serde(default))]
-    pub arch_key: String,
-    // a list of flags that can be set or unset using transforms
-    #[cfg_attr(feature = "serde", serde(default))]
-    pub flags: BTreeMap<String, String>,
-    #[cfg_attr(feature = "serde", serde(default))]
-    pub org: Address,
-    #[cfg_attr(feature = "serde", serde(default))]
-    pub offset: Address,
-    #[cfg_attr(feature = "serde", serde(default))]
-    pub static_offset: Address,
-    #[cfg_attr(feature = "serde", serde(default))]
-    pub start_read: usize,
-    #[cfg_attr(feature = "serde", serde(default))]
-    pub len_read: Option<usize>,
-    #[cfg_attr(feature = "serde", serde(default))]
-    pub syms: SymbolList,
-    #[cfg_attr(feature = "serde", serde(default))]
-    pub allow_raw: bool,
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Variable {
+    String(String),
+    Number(ValueType),
+}
 
-    // a file can optionally be patched from data and
-    // from a patch file
-    #[cfg_attr(feature = "serde", serde(default))]
-    pub patches: Vec<Patch>,
+impl Display for Variable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Variable::String(s) => write!(f, "{s}"),
+            Variable::Number(n) => write!(f, "{n}"),
+        }
+    }
+}
+
+impl From<&str> for Variable {
+    // numbers are parsed using the same radix rules as the cli
+    fn from(value: &str) -> Self {
+        match auto_radix_value(value) {
+            Ok(n) => Variable::Number(n),
+            Err(_) => Variable::String(value.into()),
+        }
+    }
+}
+
+const VAR_TEMPLATE_START: &str = "{var:";
+
+/// The range of the input that is disassembled
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct Window {
+    pub start: usize,
+    pub end: usize,
+    pub input_len: usize,
+    // the addresses of the first and the last byte
+    pub first_address: Address,
+    pub last_address: Address,
+    // describes why the window is smaller than requested
+    pub warnings: Vec<String>,
+}
+
+impl Window {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Display for Window {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "offsets {:#x}..{:#x} ({} of {} bytes)",
+            self.start,
+            self.end,
+            self.len(),
+            self.input_len
+        )?;
+        if !self.is_empty() {
+            write!(
+                f,
+                " at addresses {:x}..={:x}",
+                self.first_address, self.last_address
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A named preset of the window, the output profile and the flags
+/// that can be restored instead of setting them up again
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct View {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub start_read: usize,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub len_read: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub terminator: Option<Vec<u8>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub output: OutputProfile,
+    // the flags select the active banks and the transforms
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub flags: BTreeMap<String, String>,
+}
+
+impl Display for View {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "start {:#x}", self.start_read)?;
+        match self.len_read {
+            Some(len) => write!(f, " len {len:#x}")?,
+            None => write!(f, " to the end")?,
+        }
+        for (key, value) in self.flags.iter() {
+            write!(f, " {key}={value}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The context describes the runtime information of a single parser operation
+/// it contains the current address as well as a list of known symbols
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Default, Clone)]
+pub struct Context {
+    // which architecture to use
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub arch_key: String,
+    // a list of flags that can be set or unset using transforms
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub flags: BTreeMap<String, String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub org: Address,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub offset: Address,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub static_offset: Address,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub start_read: usize,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub len_read: Option<usize>,
+    // stops reading after the first occurence of the byte sequence
+    // the terminator itself is still disassembled
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub terminator: Option<Vec<u8>>,
+    // the saved presets of the window and the output by name
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub views: BTreeMap<String, View>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub syms: SymbolList,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub allow_raw: bool,
+    // user-defined variables that can be referenced in templates
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub vars: BTreeMap<String, Variable>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub output: OutputProfile,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub checksums: Vec<Checksum>,
+    // results of the checksum verification by address
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub checksum_comments: BTreeMap<Address, String>,
+    // headers, trainers and footers that are stripped by the loader
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub metadata: Vec<Metadata>,
+    // maps the rom offsets to addresses
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub memory_map: MemoryMap,
+    // address ranges whose data uses a different endianess than the arch
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub endianess: Vec<EndianessRange>,
+    // comments that are output before the line at the address
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub comments: BTreeMap<Address, String>,
+    // co-processor regions that are disassembled using their own arch
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub regions: Vec<Region>,
+    // the parts of the input that are disassembled instead of the window
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub segments: Vec<Segment>,
+    // address ranges that are shared by banks, the active bank is stored in a flag
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub overlays: Vec<Overlay>,
+    // file ranges that hold no data and are skipped
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub gaps: Vec<Gap>,
+    // the label namespace of the current region
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub namespace: String,
+    // additional namespaces that are searched in order when no symbol
+    // is found in a namespace (the main processor's namespace is empty)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub namespace_lookup: BTreeMap<String, Vec<String>>,
+
+    // a file can optionally be patched from data and
+    // from a patch file
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub patches: Vec<Patch>,
+    // patches may write to the same bytes, the later patch wins
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub allow_overlap: bool,
+    // what happens when a patch writes past the end of the data
+    // unless the patch has its own policy
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub extend_policy: ExtendPolicy,
 
     // allows statically inserting values for certain address offsets
     #[cfg_attr(feature = "serde", serde(default))]
     pub static_ops_pre: Arc<Mutex<Vec<StaticOp>>>,
     #[cfg_attr(feature = "serde", serde(default))]
     pub static_ops_post: Arc<Mutex<Vec<StaticOp>>>,
+    // named lists of static ops that are applied by StaticOp::Script
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub scripts: BTreeMap<String, Vec<StaticOp>>,
+    // the analysis defines a var_xx symbol for every zero page address that is accessed
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub zp_vars: bool,
+    // only the bytes that are reached from the entry points are decoded as instructions,
+    // the rest is output as data
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub descent: bool,
+    // the entry points of the recursive descent, the vectors are used if there are none
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub entry_points: Vec<Address>,
+    // the vectors of the 6502 family at fffa-ffff are read, other archs have none there
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub cpu_vectors: bool,
+    // the 65816 native mode vectors at ffe4-ffef are read as well
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub native_vectors: bool,
+    // the bytes that are known to be code or data by their file offset
+    // the bytes that are data are never decoded as instructions
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub classes: ClassMap,
+    // the prefixes of the auto labels by how they are referenced, e.g. call=fn_
+    // the kinds without a prefix use sub_, loc_ and dat_
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub label_prefixes: BTreeMap<ReferenceKind, String>,
 
     // ignored fields
     #[cfg_attr(feature = "serde", serde(skip))]
     pub analyze: bool,
     #[cfg_attr(feature = "serde", serde(skip))]
     pub tr_ctx: TransformContext,
+    // called for auto-defined symbols, regions and warnings
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub event_hook: Option<EventHook>,
+    // the bytes of the last output pass if the size report is enabled
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub size_report: SizeReport,
+    // the address after the last output, the clean output sets the address
+    // if the next output does not start there
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub next_address: Option<Address>,
+    // the addresses of the instructions that reference a value
+    // filled by the analysis pass if xrefs are output
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub xrefs: BTreeMap<Address, BTreeSet<Address>>,
+    // the file range that is reassembled if the output is split, the whole input if not set
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub split_range: Option<(usize, usize)>,
+    // the cycles of the instructions since the last label
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub cycle_total: usize,
+    // the output lines by their address if a source map is written
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub source_map: Option<SourceMap>,
+    // the file offsets of the instructions that the recursive descent reached
+    // and the flags they are decoded with
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub reached: Option<descent::Reached>,
+    // the file ranges that the text of the reassembled output stands for,
+    // the opcodes of the matchers and the bytes of the values
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub claimed: Vec<(usize, usize)>,
 }
 
 impl Context {
@@ -793,37 +1672,353 @@ impl Context {
             syms,
             offset: 0,
             analyze: false,
+            zp_vars: false,
+            descent: false,
+            entry_points: vec![],
+            cpu_vectors: false,
+            native_vectors: false,
+            classes: Default::default(),
+            label_prefixes: Default::default(),
             start_read: 0,
             len_read: None,
+            terminator: None,
+            views: Default::default(),
             patches: Default::default(),
+            allow_overlap: false,
+            extend_policy: Default::default(),
             allow_raw: false,
             tr_ctx: Default::default(),
             static_ops_pre: Default::default(),
             static_ops_post: Default::default(),
+            scripts: Default::default(),
             static_offset: 0,
+            vars: Default::default(),
+            output: Default::default(),
+            checksums: Default::default(),
+            checksum_comments: Default::default(),
+            comments: Default::default(),
+            memory_map: Default::default(),
+            endianess: Default::default(),
+            metadata: Default::default(),
+            regions: Default::default(),
+            segments: Default::default(),
+            overlays: Default::default(),
+            gaps: Default::default(),
+            namespace: Default::default(),
+            namespace_lookup: Default::default(),
+            event_hook: None,
+            size_report: Default::default(),
+            next_address: None,
+            xrefs: Default::default(),
+            split_range: None,
+            cycle_total: 0,
+            source_map: None,
+            reached: None,
+            claimed: vec![],
+        }
+    }
+
+    // restores the org, arch key and memory map from before a region was entered
+    fn leave_region(&mut self, outer: &mut Option<(Address, String, MemoryMap)>) {
+        if let Some((org, arch_key, memory_map)) = outer.take() {
+            self.org = org;
+            self.arch_key = arch_key;
+            self.memory_map = memory_map;
+            self.namespace.clear();
         }
     }
 
     pub fn patch(&self, data: &[u8]) -> FdResult<Vec<u8>> {
         let mut data = data.to_vec();
-        self.patches.iter().try_for_each(|x| x.apply(&mut data))?;
+        patch::apply_all(&mut data, self)?;
+        self.checksums
+            .iter()
+            .filter(|x| x.fix)
+            .try_for_each(|x| x.fix(&mut data))?;
         Ok(data)
     }
 
+    /// Verifies all checksums against the full input data
+    /// The result is output as a comment at the checksum's location
+    pub fn verify_checksums(&mut self, data: &[u8]) -> FdResult<()> {
+        self.checksum_comments.clear();
+        for checksum in self.checksums.iter() {
+            let (ok, comment) = checksum.verify(data)?;
+            if !ok && !self.analyze {
+                self.warn(&comment);
+            }
+            self.checksum_comments
+                .insert(self.address_of(checksum.location as Address), comment);
+        }
+        Ok(())
+    }
+
     pub fn restart(&mut self) {
         self.offset = 0;
     }
 
     pub fn address(&self) -> Address {
-        self.org + self.offset + self.static_offset
+        self.address_of(self.offset.wrapping_add(self.static_offset))
+    }
+
+    /// The current address and file offset
+    pub fn location(&self) -> (Address, usize) {
+        (
+            self.address(),
+            self.offset.wrapping_add(self.static_offset) as usize,
+        )
+    }
+
+    // maps the output lines that start in the text if a source map is written
+    fn map_output(&mut self, text: &str, location: Option<(Address, usize)>) {
+        if self.analyze {
+            return;
+        }
+        if let Some(map) = self.source_map.as_mut() {
+            map.text(text, location);
+        }
+    }
+
+    /// The address of an offset into the input
+    pub fn address_of(&self, offset: Address) -> Address {
+        // the org of a region may be below the current offset
+        self.memory_map.to_address(self.org.wrapping_add(offset))
     }
 
-    pub fn def_symbol(&mut self, sym: Symbol) {
-        self.syms.def_symbol(sym);
+    /// The endianess of the data at the address if it overrides the arch
+    /// The last declared range wins if they overlap
+    pub fn endianess_at(&self, address: Address) -> Option<Endianess> {
+        self.endianess
+            .iter()
+            .rev()
+            .find(|r| r.contains(address))
+            .map(|r| r.endianess)
     }
 
+    /// The address and the label namespace of a file offset
+    /// Offsets inside of a region or segment use its org
+    pub fn locate(&self, offset: usize) -> (Address, String) {
+        if let Some(region) = self.regions.iter().find(|r| r.contains(offset)) {
+            let address = region.org.wrapping_add((offset - region.start) as Address);
+            return (address, region.name.clone());
+        }
+        let address = match self
+            .segments
+            .iter()
+            .find(|s| offset >= s.start && offset < s.end)
+        {
+            Some(segment) => segment
+                .org
+                .wrapping_add((offset - segment.start) as Address),
+            None => self.address_of(offset as Address),
+        };
+        (address, String::new())
+    }
+
+    /// The file offset of an address in a namespace, the inverse of locate
+    /// Addresses outside of the window's rom are not in the input
+    pub fn offset_of(&self, address: Address, namespace: &str) -> Option<usize> {
+        let inside = |org: Address, start: usize, end: usize| {
+            let offset = address.wrapping_sub(org) as usize;
+            (address >= org && offset < end - start).then_some(start + offset)
+        };
+        if let Some(offset) = self
+            .regions
+            .iter()
+            .filter(|r| r.name == namespace)
+            .find_map(|r| inside(r.org, r.start, r.end))
+        {
+            return Some(offset);
+        }
+        if !namespace.is_empty() {
+            return None;
+        }
+        if let Some(offset) = self
+            .segments
+            .iter()
+            .find_map(|s| inside(s.org, s.start, s.end))
+        {
+            return Some(offset);
+        }
+        if self.memory_map != MemoryMap::Linear || address < self.org {
+            return None;
+        }
+        let offset = (address - self.org) as usize;
+        let in_window = offset >= self.start_read
+            && self
+                .len_read
+                .is_none_or(|len| offset < self.start_read + len);
+        in_window.then_some(offset)
+    }
+
+    // marks the bytes at the file position as part of the output text
+    fn claim(&mut self, position: usize, len: usize) {
+        if self.output.byte_exact() && !self.analyze && len > 0 {
+            self.claimed.push((position, position + len));
+        }
+    }
+
+    /// True if a region or a symbol uses the namespace
+    pub fn is_namespace(&self, namespace: &str) -> bool {
+        self.regions.iter().any(|r| r.name == namespace) || self.syms.has_namespace(namespace)
+    }
+
+    /// Symbols without a namespace that are defined while inside
+    /// a region are added to the region's namespace
+    /// Duplicates are merged, see SymbolList::merge_symbol
+    pub fn def_symbol(&mut self, mut sym: Symbol) {
+        if sym.namespace.is_empty() {
+            sym.namespace = self.namespace.clone();
+        }
+        self.syms.merge_symbol(sym);
+    }
+
+    /// The bank that is mapped to the overlay window that contains the value
+    pub fn active_bank(&self, value: ValueType) -> Option<&String> {
+        self.overlays
+            .iter()
+            .find(|o| o.contains(value))
+            .and_then(|o| self.flags.get(&o.flag))
+    }
+
+    /// Maps the bank to every overlay window that contains the address
+    pub fn map_bank(&mut self, address: Address, bank: &str) {
+        for overlay in self.overlays.clone() {
+            if overlay.contains(address as ValueType) {
+                self.def_flag(&overlay.flag, bank);
+            }
+        }
+    }
+
+    /// The regions that are mapped to an overlay window, numbered in the order of the regions
+    pub fn banks(&self) -> Vec<&Region> {
+        self.regions
+            .iter()
+            .filter(|r| self.overlays.iter().any(|o| o.contains(r.org as ValueType)))
+            .collect()
+    }
+
+    /// Finds a bank by its number or its name
+    pub fn bank(&self, bank: &str) -> FdResult<&Region> {
+        let banks = self.banks();
+        banks
+            .iter()
+            .find(|r| r.name == bank)
+            .or_else(|| bank.parse::<usize>().ok().and_then(|i| banks.get(i)))
+            .copied()
+            .ok_or_else(|| Error::BankNotFound(bank.into()))
+    }
+
+    /// The file offset of an offset into a bank (bank:offset)
+    pub fn bank_offset(&self, bank: &str, offset: usize) -> FdResult<usize> {
+        let region = self.bank(bank)?;
+        if offset >= region.end - region.start {
+            return Err(Error::InvalidArgument(format!(
+                "Offset {offset:#x} is outside of bank {}",
+                region.name
+            )));
+        }
+        Ok(region.start + offset)
+    }
+
+    /// Limits the window to the bank and maps it to its overlay window
+    pub fn view_bank(&mut self, bank: &str) -> FdResult<()> {
+        let region = self.bank(bank)?.clone();
+        self.set_start(Some(region.start));
+        self.set_len(Some(region.end - region.start));
+        self.map_bank(region.org, &region.name);
+        Ok(())
+    }
+
+    /// Saves the window, the output profile and the flags as a view
+    /// an existing view of the same name is replaced
+    pub fn save_view(&mut self, name: &str) {
+        let view = View {
+            start_read: self.start_read,
+            len_read: self.len_read,
+            terminator: self.terminator.clone(),
+            output: self.output.clone(),
+            flags: self.flags.clone(),
+        };
+        self.views.insert(name.into(), view);
+    }
+
+    /// Restores the window, the output profile and the flags of a view
+    pub fn restore_view(&mut self, name: &str) -> FdResult<()> {
+        let view = self
+            .views
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::ViewNotFound(name.into()))?;
+        self.set_start(Some(view.start_read));
+        self.set_len(view.len_read);
+        self.set_terminator(view.terminator);
+        self.output = view.output;
+        self.flags = view.flags;
+        Ok(())
+    }
+
+    /// Removes redundant auto symbols after the analysis
+    pub fn cleanup_symbols(&mut self) {
+        let removed = self.syms.cleanup();
+        if removed > 0 {
+            info!("Removed {removed} redundant auto symbols");
+        }
+    }
+
+    pub fn set_event_hook(&mut self, hook: impl FnMut(&Event) -> EventResponse + Send + 'static) {
+        self.event_hook = Some(Arc::new(Mutex::new(hook)));
+    }
+
+    pub fn emit(&self, event: &Event) -> EventResponse {
+        match &self.event_hook {
+            Some(hook) => (hook.lock().unwrap())(event),
+            None => EventResponse::Accept,
+        }
+    }
+
+    pub fn warn(&self, message: &str) {
+        warn!("{message}");
+        self.emit(&Event::Warning(message.into()));
+    }
+
+    /// The name of an auto label for a value that is referenced by a kind of instruction
+    pub fn auto_label_name(&self, kind: ReferenceKind, value: ValueType) -> String {
+        let prefix = self
+            .label_prefixes
+            .get(&kind)
+            .map_or(kind.default_prefix(), |p| p.as_str());
+        format!("{prefix}{value:04x}")
+    }
+
+    /// Defines a symbol that was created by the analysis
+    /// The event hook may veto or rename the symbol
+    pub fn def_auto_symbol(&mut self, mut sym: Symbol) {
+        sym.auto = true;
+        // labels inside of an overlay window belong to the active bank
+        if sym.namespace.is_empty() && sym.kind == SymbolKind::Label {
+            if let Some(bank) = self.active_bank(sym.value) {
+                sym.namespace = bank.clone();
+            }
+        }
+        match self.emit(&Event::SymbolDefined(sym.clone())) {
+            EventResponse::Accept => self.def_symbol(sym),
+            EventResponse::Veto => {}
+            EventResponse::Rename(name) => {
+                sym.name = name;
+                self.def_symbol(sym);
+            }
+        }
+    }
+
+    /// Values inside of an overlay window are looked up in the active bank first
     pub fn get_first_symbol(&self, value: ValueType) -> Option<&Symbol> {
-        self.syms.get_first_symbol(value, self.address())
+        let lookup = self.namespace_lookup.get(&self.namespace);
+        self.active_bank(value)
+            .into_iter()
+            .chain(std::iter::once(&self.namespace))
+            .chain(lookup.into_iter().flatten())
+            .find_map(|ns| self.syms.get_first_symbol(value, self.address(), ns))
     }
 
     pub fn def_flag(&mut self, flag: &str, value: &str) {
@@ -838,6 +2033,60 @@ impl Context {
         self.flags.get(flag)
     }
 
+    /// The comment of the address if it is output in the comment column
+    /// Comments with more than one line are output before the instruction
+    pub fn inline_comment(&self, address: Address) -> Option<&String> {
+        self.output.comment_column?;
+        self.comments.get(&address).filter(|c| !c.contains('\n'))
+    }
+
+    pub fn has_static_op_at(&self, address: Address) -> bool {
+        self.checksum_comments.contains_key(&address)
+            || self.comments.contains_key(&address)
+            || [&self.static_ops_pre, &self.static_ops_post]
+                .iter()
+                .any(|ops| ops.lock().unwrap().iter().any(|op| op.is_at(address)))
+    }
+
+    pub fn def_var(&mut self, name: &str, value: Variable) {
+        self.vars.insert(name.into(), value);
+    }
+
+    pub fn get_var(&self, name: &str) -> Option<&Variable> {
+        self.vars.get(name)
+    }
+
+    /// Replaces all {var:name} templates in the input
+    /// Returns None if the input does not contain any templates
+    /// Unknown variables are left as is
+    pub fn expand_vars(&self, input: &str) -> Option<String> {
+        if !input.contains(VAR_TEMPLATE_START) {
+            return None;
+        }
+
+        let mut result = String::new();
+        let mut rest = input;
+        while let Some(start) = rest.find(VAR_TEMPLATE_START) {
+            result.push_str(&rest[..start]);
+            let template = &rest[start..];
+            if let Some(end) = template.find('}') {
+                let name = &template[VAR_TEMPLATE_START.len()..end];
+                if let Some(value) = self.get_var(name) {
+                    result.push_str(&value.to_string());
+                } else {
+                    self.warn(&format!("Variable '{name}' is not defined"));
+                    result.push_str(&template[..=end]);
+                }
+                rest = &template[end + 1..];
+            } else {
+                result.push_str(template);
+                rest = "";
+            }
+        }
+        result.push_str(rest);
+        Some(result)
+    }
+
     pub fn set_start(&mut self, addr: Option<usize>) {
         self.start_read = addr.unwrap_or(0);
         self.static_offset = self.start_read as Address;
@@ -849,7 +2098,11 @@ impl Context {
             .get_first_by_name(label)
             .ok_or(Error::LabelNotFound(label.to_owned()))?;
 
-        self.start_read = s.value as usize - self.org as usize;
+        let offset = self
+            .memory_map
+            .to_offset(s.value as Address)
+            .ok_or(Error::LabelNotFound(label.to_owned()))?;
+        self.start_read = offset.wrapping_sub(self.org) as usize;
 
         self.static_offset = self.start_read as Address;
 
@@ -863,12 +2116,181 @@ impl Context {
     pub fn set_len(&mut self, len: Option<usize>) {
         self.len_read = len;
     }
+
+    /// Computes the range of the input that is disassembled
+    /// from the start, length and terminator
+    pub fn window(&self, data: &[u8]) -> Window {
+        let mut warnings = vec![];
+        let input_len = data.len();
+        if self.start_read > input_len {
+            warnings.push(format!(
+                "Start {:#x} is past the end of the input ({input_len:#x} bytes)",
+                self.start_read
+            ));
+        }
+        let start = self.start_read.min(input_len);
+
+        let mut end = input_len;
+        if let Some(len) = self.len_read {
+            if start.saturating_add(len) > input_len {
+                warnings.push(format!(
+                    "Length {len:#x} from {start:#x} exceeds the input ({input_len:#x} bytes), \
+                    reading {:#x} bytes",
+                    input_len - start
+                ));
+            } else {
+                end = start + len;
+            }
+        }
+        let end = start + self.terminated_len(&data[start..end]);
+        if start == end {
+            warnings.push("The disassembly window is empty".into());
+        }
+
+        Window {
+            start,
+            end,
+            input_len,
+            first_address: self.address_of(start as Address),
+            last_address: self.address_of((end.max(start + 1) - 1) as Address),
+            warnings,
+        }
+    }
+
+    fn report_size(&mut self, address: Address, matcher: &Matcher, consumed: usize) {
+        self.size_report.add(&self.namespace, address, consumed);
+        let encoded = matcher.encoded_len();
+        if encoded > consumed {
+            let mismatch = SizeMismatch {
+                address,
+                matcher: matcher.name.string.clone(),
+                encoded,
+                consumed,
+            };
+            self.warn(&format!("Size mismatch at {mismatch}"));
+            self.size_report.mismatches.push(mismatch);
+        }
+    }
+
+    /// Checks if the bytes that were just consumed are mapped to more than one bank
+    /// or are not contiguous in the memory map
+    fn check_bank_crossing(
+        &mut self,
+        address: Address,
+        matcher: &Matcher,
+        consumed: usize,
+    ) -> Option<BankCrossing> {
+        if consumed < 2 {
+            return None;
+        }
+        let last = self.address_of(self.offset.wrapping_add(self.static_offset).wrapping_sub(1));
+        let end = address.wrapping_add(consumed as Address - 1);
+        // only banked memory maps and overlay windows have boundaries
+        let crosses_bank =
+            self.memory_map != MemoryMap::Linear && (last >> 16 != address >> 16 || last != end);
+        let crosses_window = self
+            .overlays
+            .iter()
+            .any(|o| o.contains(address as ValueType) != o.contains(end as ValueType));
+        if !crosses_bank && !crosses_window {
+            return None;
+        }
+        let crossing = BankCrossing {
+            address,
+            last,
+            matcher: matcher.name.string.clone(),
+        };
+        self.warn(&format!("Bank crossing at {crossing}"));
+        if self.output.size_report {
+            self.size_report.crossings.push(crossing.clone());
+        }
+        Some(crossing)
+    }
+
+    /// The gaps inside of start..end ordered by their start
+    /// Overlapping gaps are merged
+    pub fn gaps_in(&self, start: usize, end: usize) -> Vec<Gap> {
+        let mut gaps: Vec<Gap> = self
+            .gaps
+            .iter()
+            .map(|g| Gap::new(g.start.max(start), g.end.min(end)))
+            .filter(|g| !g.is_empty())
+            .collect();
+        gaps.sort_by_key(|g| g.start);
+
+        let mut merged: Vec<Gap> = vec![];
+        for gap in gaps {
+            match merged.last_mut() {
+                Some(last) if gap.start <= last.end => last.end = last.end.max(gap.end),
+                _ => merged.push(gap),
+            }
+        }
+        merged
+    }
+
+    /// The segments ordered by their start and limited to the input
+    /// Segments that are empty or overlap an earlier segment are skipped
+    pub fn segment_ranges(&self, data: &[u8]) -> Vec<Segment> {
+        let mut segments = self.segments.clone();
+        segments.sort_by_key(|s| s.start);
+
+        let mut ranges: Vec<Segment> = vec![];
+        for mut segment in segments {
+            let end = segment.end.min(data.len());
+            let previous_end = ranges.last().map(|s| s.end).unwrap_or(0);
+            let warning = if segment.start >= end {
+                Some("is empty or outside of the input")
+            } else if segment.start < previous_end {
+                Some("overlaps the previous segment")
+            } else {
+                None
+            };
+            if let Some(warning) = warning {
+                if !self.analyze {
+                    self.warn(&format!("Skipping {segment}: the segment {warning}"));
+                }
+                continue;
+            }
+            if end < segment.end && !self.analyze {
+                self.warn(&format!(
+                    "The end of {segment} exceeds the input ({:#x} bytes)",
+                    data.len()
+                ));
+            }
+            segment.end = end;
+            ranges.push(segment);
+        }
+        ranges
+    }
+
+    pub fn set_terminator(&mut self, terminator: Option<Vec<u8>>) {
+        self.terminator = terminator.filter(|t| !t.is_empty());
+    }
+
+    /// The end of the data that is read after applying the terminator
+    pub fn terminated_len(&self, data: &[u8]) -> usize {
+        match &self.terminator {
+            Some(terminator) if !terminator.is_empty() => data
+                .windows(terminator.len())
+                .position(|w| w == terminator.as_slice())
+                .map(|pos| pos + terminator.len())
+                .unwrap_or(data.len()),
+            _ => data.len(),
+        }
+    }
 }
 
 pub type TransformMap = BTreeMap<String, TransformList>;
 
+/// The default of the bytes a single matcher may consume
+pub const DEFAULT_MAX_CONSUME: usize = 0x400;
+
+fn default_max_consume() -> Option<usize> {
+    Some(DEFAULT_MAX_CONSUME)
+}
+
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Default, Clone)]
+#[derive(Clone)]
 pub struct Arch {
     // a list of all possible patterns this architecture may match against
     #[cfg_attr(feature = "serde", serde(default))]
@@ -900,14 +2322,48 @@ pub struct Arch {
     ///     fmt_hex_pre, fmt_hex_post, fmt_HEX_pre, fmt_HEX_post,
     ///     fmt_dec_pre, fmt_dec_post, fmt_oct_pre, fmt_oct_post,
     ///     fmt_bin_pre, fmt_bin_post
+    /// Comments (e.g. the ascii sidebar) are prefixed with comment_pre
+    /// and the org directives of the clean output with org_pre.
+    /// Labels that are only in scope inside of a range are prefixed with local_pre
     #[cfg_attr(feature = "serde", serde(default))]
     pub node_map: BTreeMap<String, Node>,
+
+    // the most bytes a matcher and its pre and post patterns may consume
+    // catches a consume or data type that reads far more than an instruction
+    // no limit if None
+    #[cfg_attr(feature = "serde", serde(default = "default_max_consume"))]
+    pub max_consume: Option<usize>,
+
+    // the case of the output, the output profile can enable these as well
+    // directives (matcher names starting with a dot) keep their case
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub uppercase_mnemonics: bool,
+    // the digits of hex values, the pre and post of the value keep their case
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub uppercase_hex: bool,
 }
 
-impl Arch {
-    /// Match a pattern
-    /// All transforms that match a pattern are applied
-    /// until the first transform is hit that consumes actual data
+impl Default for Arch {
+    fn default() -> Self {
+        Self {
+            patterns: Default::default(),
+            transforms: Default::default(),
+            pre_patterns: Default::default(),
+            post_patterns: Default::default(),
+            endianess: Default::default(),
+            addr_type: Default::default(),
+            node_map: Default::default(),
+            max_consume: default_max_consume(),
+            uppercase_mnemonics: false,
+            uppercase_hex: false,
+        }
+    }
+}
+
+impl Arch {
+    /// Match a pattern
+    /// All transforms that match a pattern are applied
+    /// until the first transform is hit that consumes actual data
     /// This means that transforms that do not require data can be used as
     /// a conditional prefix e.g. to insert assembler directives
     /// at a certain address
@@ -921,33 +2377,262 @@ impl Arch {
     ) -> FdResult<usize> {
         for pattern in self.patterns.iter() {
             if pattern.is_match(self, ctx, data) {
-                ctx.tr_ctx = Default::default();
+                if ctx.output.json && !ctx.analyze {
+                    return self.output_json(f, data, ctx, pattern, Self::apply_matcher);
+                }
+                return self.apply_matcher(f, data, ctx, pattern);
+            }
+        }
+        // bytes that no matcher matches are output as data if the arch can define bytes
+        if self.get_transform(DEFINE_BYTE).is_none() {
+            return Err(Error::NoMatch);
+        }
+        let matcher = Self::define_byte_matcher();
+        if ctx.output.json && !ctx.analyze {
+            return self.output_json(f, data, ctx, &matcher, Self::apply_matcher);
+        }
+        self.apply_matcher(f, data, ctx, &matcher)
+    }
+
+    fn define_class_matcher(transforms: &str, name: &str) -> Matcher {
+        Matcher {
+            patterns: vec![PatternAt::new(Pattern::Any, 0)],
+            transforms: transforms.into(),
+            name: Node::new(name.into()),
+            cycles: None,
+        }
+    }
+
+    fn define_byte_matcher() -> Matcher {
+        Matcher {
+            patterns: vec![PatternAt::new(Pattern::Any, 0)],
+            transforms: DEFINE_BYTE.into(),
+            name: Node::new(DEFINE_BYTE_NAME.into()),
+            cycles: None,
+        }
+    }
+
+    fn apply_matcher(
+        &self,
+        f: &mut dyn DisasCallback,
+        data: &[u8],
+        ctx: &mut Context,
+        pattern: &Matcher,
+    ) -> FdResult<usize> {
+        self.output_org(f, ctx)?;
+        let address = ctx.address();
+        ctx.tr_ctx = TransformContext {
+            address,
+            ..Default::default()
+        };
+        let position = ctx.offset.wrapping_add(ctx.static_offset) as usize;
+        for pa in pattern.patterns.iter().filter(|pa| pa.pattern.encodes()) {
+            ctx.claim(position + pa.offset, 1);
+        }
+
+        // the lines after the labels are output by the label transform
+        let (before, after) = ctx.output.lines_around_labels();
+        if self.outputs_labels() {
+            self.apply_statics_pre(f, data, ctx, &before)?;
+        } else {
+            self.apply_statics_pre(f, data, ctx, &[before, after].concat())?;
+        }
+
+        let res = self.apply_columns(f, data, ctx, pattern)?;
+        self.check_consumed(address, pattern, res)?;
+
+        self.apply_statics_post(f, data, ctx)?;
+
+        if ctx.output.size_report && !ctx.analyze {
+            ctx.report_size(address, pattern, res);
+        }
+        if !ctx.analyze {
+            self.report_bank_crossing(f, address, pattern, res, ctx)?;
+        }
+        ctx.next_address = Some(ctx.address());
+
+        Ok(res)
+    }
+
+    // collects the output of a matcher and outputs it as a single json line
+    fn output_json(
+        &self,
+        f: &mut dyn DisasCallback,
+        data: &[u8],
+        ctx: &mut Context,
+        matcher: &Matcher,
+        apply: ApplyMatcher,
+    ) -> FdResult<usize> {
+        let mut line = JsonLine {
+            address: ctx.address(),
+            matcher: matcher.name.string.clone(),
+            ..Default::default()
+        };
+        let res = apply(
+            self,
+            &mut |node: &Node,
+                  kind: CallbackKind,
+                  _raw: &[u8],
+                  _arch: &Arch,
+                  _ctx: &mut Context| {
+                line.push(node, &kind);
+                Ok(())
+            },
+            data,
+            ctx,
+            matcher,
+        )?;
+        let bytes = &data[..res.min(data.len())];
+        line.bytes = bytes.to_vec();
+        let node = Node::new(line.to_json(self.comment_pre()));
+        f(&node, CallbackKind::Static, bytes, self, ctx)?;
+        Ok(res)
+    }
+
+    /// Outputs the data using the define_byte transform
+    /// without trying to match any instructions
+    fn match_data(
+        &self,
+        f: &mut dyn DisasCallback,
+        data: &[u8],
+        ctx: &mut Context,
+    ) -> FdResult<usize> {
+        let matcher = Self::define_byte_matcher();
+        if ctx.output.json {
+            return self.output_json(f, data, ctx, &matcher, Self::apply_data);
+        }
+        self.apply_data(f, data, ctx, &matcher)
+    }
+
+    // outputs the bytes as a string or as pointers depending on their class,
+    // as data if the arch can not define the class or too few bytes are left
+    fn match_class(
+        &self,
+        f: &mut dyn DisasCallback,
+        data: &[u8],
+        ctx: &mut Context,
+        class: ByteClass,
+    ) -> FdResult<usize> {
+        let (transforms, name) = match class {
+            ByteClass::Text => (DEFINE_TEXT, DEFINE_TEXT_NAME),
+            ByteClass::Pointers => (DEFINE_WORD, DEFINE_WORD_NAME),
+            ByteClass::LongPointers => (DEFINE_LONG, DEFINE_LONG_NAME),
+            _ => return self.match_data(f, data, ctx),
+        };
+        if self.get_transform(transforms).is_none() || data.len() < class.value_len() {
+            return self.match_data(f, data, ctx);
+        }
+        let matcher = Self::define_class_matcher(transforms, name);
+        if ctx.output.json {
+            return self.output_json(f, data, ctx, &matcher, Self::apply_data);
+        }
+        self.apply_data(f, data, ctx, &matcher)
+    }
 
-                self.apply_statics_pre(f, data, ctx)?;
+    fn apply_data(
+        &self,
+        f: &mut dyn DisasCallback,
+        data: &[u8],
+        ctx: &mut Context,
+        matcher: &Matcher,
+    ) -> FdResult<usize> {
+        self.output_org(f, ctx)?;
+        let address = ctx.address();
+        ctx.tr_ctx = TransformContext {
+            address,
+            ..Default::default()
+        };
 
-                let mut res = self.match_additional_patterns(f, data, ctx, &self.pre_patterns)?;
-                res += pattern.transform(&mut *f, &data[res..], self, ctx)?;
-                res += self.match_additional_patterns(f, &data[..res], ctx, &self.post_patterns)?;
+        let res = self.apply_columns(f, data, ctx, matcher)?;
+        self.check_consumed(address, matcher, res)?;
 
-                self.apply_statics_post(f, data, ctx)?;
+        if ctx.output.size_report {
+            ctx.report_size(address, matcher, res);
+        }
+        self.report_bank_crossing(f, address, matcher, res, ctx)?;
+        ctx.next_address = Some(ctx.address());
+        Ok(res)
+    }
 
-                return Ok(res);
+    fn report_bank_crossing(
+        &self,
+        f: &mut dyn DisasCallback,
+        address: Address,
+        matcher: &Matcher,
+        consumed: usize,
+        ctx: &mut Context,
+    ) -> FdResult<()> {
+        if let Some(crossing) = ctx.check_bank_crossing(address, matcher, consumed) {
+            if ctx.output.crossing_comments {
+                f(
+                    &Node::new(format!("{}{crossing}\n", self.comment_pre())),
+                    CallbackKind::Static,
+                    &[],
+                    self,
+                    ctx,
+                )?;
             }
         }
-        Err(Error::NoMatch)
+        Ok(())
+    }
+
+    // true if the pre patterns output the labels of an address
+    fn outputs_labels(&self) -> bool {
+        self.pre_patterns.iter().any(|m| {
+            self.get_transform(&m.transforms)
+                .is_some_and(|tl| tl.iter().any(|t| matches!(t, Transform::Label)))
+        })
+    }
+
+    // true if the post patterns output the comment column
+    fn outputs_inline_comments(&self) -> bool {
+        self.post_patterns.iter().any(|m| {
+            self.get_transform(&m.transforms)
+                .is_some_and(|tl| tl.iter().any(|t| matches!(t, Transform::Comment)))
+        })
     }
 
+    // applies the static ops and outputs the comments in the given order
     fn apply_statics_pre(
         &self,
         f: &mut dyn DisasCallback,
         data: &[u8],
         ctx: &mut Context,
+        order: &[LineKind],
     ) -> FdResult<()> {
-        let st = ctx.static_ops_pre.clone();
-        for op in st.lock().unwrap().iter() {
-            op.apply(f, data, self, ctx)?;
+        for kind in order {
+            match kind {
+                LineKind::Statics => {
+                    let st = ctx.static_ops_pre.clone();
+                    for op in st.lock().unwrap().iter() {
+                        op.apply(f, data, self, ctx)?;
+                    }
+                }
+                LineKind::Comments if !ctx.analyze => self.output_comments(f, ctx)?,
+                _ => {}
+            }
         }
+        Ok(())
+    }
 
+    fn output_comments(&self, f: &mut dyn DisasCallback, ctx: &mut Context) -> FdResult<()> {
+        let address = ctx.address();
+        let inline = self.outputs_inline_comments() && ctx.inline_comment(address).is_some();
+        let comments = [
+            ctx.comments.get(&address).cloned().filter(|_| !inline),
+            ctx.checksum_comments.get(&address).cloned(),
+        ];
+        for comment in comments.into_iter().flatten() {
+            for line in comment.lines() {
+                f(
+                    &Node::new(format!("{}{line}\n", self.comment_pre())),
+                    CallbackKind::Static,
+                    &[],
+                    self,
+                    ctx,
+                )?;
+            }
+        }
         Ok(())
     }
 
@@ -965,6 +2650,91 @@ impl Arch {
         Ok(())
     }
 
+    // outputs the pre patterns, the matcher and the post patterns
+    fn apply_columns(
+        &self,
+        f: &mut dyn DisasCallback,
+        data: &[u8],
+        ctx: &mut Context,
+        matcher: &Matcher,
+    ) -> FdResult<usize> {
+        if !self.raw_before_code(ctx) {
+            let mut res = self.match_additional_patterns(f, data, ctx, &self.pre_patterns)?;
+            res += matcher.transform(&mut *f, &data[res..], self, ctx)?;
+            self.output_cycles(f, data, ctx, matcher)?;
+            res += self.match_additional_patterns(f, &data[..res], ctx, &self.post_patterns)?;
+            return Ok(res);
+        }
+
+        // the raw bytes are only known once the matcher consumed them,
+        // so its output is held back until the pre patterns are done
+        let mut nodes: Vec<(Node, CallbackKind, Vec<u8>)> = vec![];
+        let (offset, line_len) = (ctx.offset, ctx.tr_ctx.line_len);
+        let mut res = matcher.transform(
+            |node: &Node, kind: CallbackKind, raw: &[u8], _arch: &Arch, _ctx: &mut Context| {
+                nodes.push((node.clone(), kind, raw.to_vec()));
+                Ok(())
+            },
+            data,
+            self,
+            ctx,
+        )?;
+        let end = std::mem::replace(&mut ctx.offset, offset);
+        ctx.tr_ctx.line_len = line_len;
+        self.match_additional_patterns(f, &data[..res], ctx, &self.pre_patterns)?;
+        ctx.offset = end;
+        for (node, kind, raw) in nodes {
+            Transform::cb(f, &node, kind, &raw, self, ctx)?;
+        }
+        self.output_cycles(f, data, ctx, matcher)?;
+        res += self.match_additional_patterns(f, &data[..res], ctx, &self.post_patterns)?;
+        Ok(res)
+    }
+
+    // the cycles of the instruction and the total since the last label after the code
+    fn output_cycles(
+        &self,
+        f: &mut dyn DisasCallback,
+        data: &[u8],
+        ctx: &mut Context,
+        matcher: &Matcher,
+    ) -> FdResult<()> {
+        let Some(cycles) = matcher.cycles else {
+            return Ok(());
+        };
+        ctx.cycle_total += cycles;
+        if !ctx.output.cycles || ctx.output.json || ctx.analyze {
+            return Ok(());
+        }
+        let node = Node::new(format!(
+            " {}{cycles} cycles ({})",
+            self.comment_pre(),
+            ctx.cycle_total
+        ));
+        Transform::cb(f, &node, CallbackKind::Static, data, self, ctx)
+    }
+
+    fn check_consumed(&self, address: Address, matcher: &Matcher, consumed: usize) -> FdResult<()> {
+        match self.max_consume {
+            Some(limit) if consumed > limit => Err(Error::ConsumeLimit(format!(
+                "{} at {address:x} consumed {consumed} bytes, the limit of the arch is {limit}",
+                matcher.name.string
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    // a raw column before the code, e.g. from a layout
+    fn raw_before_code(&self, ctx: &Context) -> bool {
+        ctx.allow_raw
+            && !ctx.output.json
+            && !ctx.output.clean
+            && self.pre_patterns.iter().any(|p| {
+                self.get_transform(&p.transforms)
+                    .is_some_and(|tl| tl.iter().any(|t| matches!(t, Transform::Raw)))
+            })
+    }
+
     fn match_additional_patterns(
         &self,
         f: &mut dyn DisasCallback,
@@ -981,9 +2751,103 @@ impl Arch {
         Ok(res)
     }
 
+    pub fn comment_pre(&self) -> &str {
+        self.node_map
+            .get(COMMENT_PRE)
+            .map(|n| n.string.as_str())
+            .unwrap_or(DEFAULT_COMMENT_PRE)
+    }
+
+    pub fn org_pre(&self) -> &str {
+        self.node_map
+            .get(ORG_PRE)
+            .map(|n| n.string.as_str())
+            .unwrap_or(DEFAULT_ORG_PRE)
+    }
+
+    /// The directive that includes another source file
+    pub fn include(&self, file: &str) -> String {
+        let pre = self
+            .node_map
+            .get(INCLUDE_PRE)
+            .map(|n| n.string.as_str())
+            .unwrap_or(DEFAULT_INCLUDE_PRE);
+        format!("{pre}\"{file}\"")
+    }
+
+    /// The keys of the flags that the patterns test and the transforms set
+    pub fn flag_keys(&self) -> BTreeSet<String> {
+        let mut keys = BTreeSet::new();
+        let mut patterns: Vec<&Pattern> = self
+            .patterns
+            .iter()
+            .chain(&self.pre_patterns)
+            .chain(&self.post_patterns)
+            .flat_map(|m| m.patterns.iter().map(|pa| &pa.pattern))
+            .collect();
+        while let Some(pattern) = patterns.pop() {
+            match pattern {
+                Pattern::Flag(key, _) => {
+                    keys.insert(key.clone());
+                }
+                Pattern::List(list) => patterns.extend(list),
+                _ => {}
+            }
+        }
+        for transform in self.transforms.values().flatten() {
+            if let Transform::SetFlag(key, _) | Transform::UnsetFlag(key) = transform {
+                keys.insert(key.clone());
+            }
+        }
+        keys
+    }
+
+    /// The name of a label, labels with a range scope are prefixed with local_pre
+    pub fn local_name(&self, sym: &Symbol, name: String) -> String {
+        match self.node_map.get(LOCAL_PRE) {
+            Some(pre) if sym.kind == SymbolKind::Label && sym.scope != Scope::Global => {
+                format!("{}{name}", pre.string)
+            }
+            _ => name,
+        }
+    }
+
+    /// A value as it is written in the arch's syntax
+    /// negative values are written in decimal
+    pub fn format_value(&self, value: ValueType) -> FdResult<String> {
+        if value < 0 {
+            return Ok(value.to_string());
+        }
+        let width = if value > 0xFFFF { 6 } else { 4 };
+        Ok(try_to_node(value, ValueTypeFmt::LowerHex(width), self)?.string)
+    }
+
+    // the clean output sets the address whenever it does not follow the previous output
+    fn output_org(&self, f: &mut dyn DisasCallback, ctx: &mut Context) -> FdResult<()> {
+        let address = ctx.address();
+        if !ctx.output.clean || ctx.analyze || ctx.next_address == Some(address) {
+            return Ok(());
+        }
+        let org = self.org_line(address)?;
+        f(&Node::new(org), CallbackKind::Static, &[], self, ctx)
+    }
+
+    /// The org directive line for the address
+    pub fn org_line(&self, address: Address) -> FdResult<String> {
+        Ok(format!(
+            "{}{}\n",
+            self.org_pre(),
+            self.format_value(address as ValueType)?
+        ))
+    }
+
     pub fn get_transform(&self, name: &str) -> Option<&TransformList> {
         self.transforms.get(name)
     }
+
+    pub fn endianess(&self) -> Endianess {
+        self.endianess
+    }
 }
 
 // a collection of many architectures
@@ -1002,6 +2866,338 @@ pub struct Archs {
 }
 
 impl Archs {
+    /// Adds the archs of a co-processor using the name as the arch key.
+    /// Keys other than the default key are added as name:key
+    pub fn add_coprocessor(&mut self, name: &str, other: &Archs) {
+        for (key, arch) in other.archs.iter() {
+            let key = if key.is_empty() {
+                name.to_owned()
+            } else {
+                format!("{name}:{key}")
+            };
+            self.archs.insert(key, arch.clone());
+        }
+    }
+
+    /// The arch that the context selects
+    pub fn arch(&self, ctx: &Context) -> FdResult<&Arch> {
+        self.archs
+            .get(&ctx.arch_key)
+            .ok_or_else(|| Error::ArchNotFound(ctx.arch_key.clone()))
+    }
+
+    /// Decodes the instruction at the file offset as if it was output at the address
+    /// The callback receives its nodes, returns the amount of bytes it consumed.
+    /// The position and the reports of the context are kept
+    pub fn decode_at(
+        &self,
+        f: &mut dyn DisasCallback,
+        data: &[u8],
+        offset: usize,
+        address: Address,
+        ctx: &mut Context,
+    ) -> FdResult<usize> {
+        let saved = (ctx.offset, ctx.org, ctx.next_address, ctx.cycle_total);
+        let (json, size_report) = (ctx.output.json, ctx.output.size_report);
+        ctx.output.json = false;
+        ctx.output.size_report = false;
+        ctx.offset = (offset as Address).wrapping_sub(ctx.static_offset);
+        // the segments have their own org
+        if ctx.address() != address {
+            ctx.org = address.wrapping_sub(offset as Address);
+        }
+        let res = self
+            .arch(ctx)
+            .and_then(|arch| arch.match_patterns(f, &data[offset..], ctx));
+        (ctx.offset, ctx.org, ctx.next_address, ctx.cycle_total) = saved;
+        (ctx.output.json, ctx.output.size_report) = (json, size_report);
+        res
+    }
+
+    /// The flag keys of every arch
+    pub fn flag_keys(&self) -> BTreeSet<String> {
+        self.archs.values().flat_map(|a| a.flag_keys()).collect()
+    }
+
+    // the segment is disassembled at its own org using its arch
+    fn enter_segment(
+        &self,
+        f: &mut dyn DisasCallback,
+        segment: &Segment,
+        ctx: &mut Context,
+    ) -> FdResult<()> {
+        info!("Entering segment {} at {}", segment.name, segment.start);
+        ctx.memory_map = MemoryMap::Linear;
+        ctx.org = segment
+            .org
+            .wrapping_sub(ctx.offset)
+            .wrapping_sub(ctx.static_offset);
+        if !segment.arch_key.is_empty() {
+            ctx.arch_key = segment.arch_key.clone();
+        }
+
+        let arch = self.arch(ctx)?;
+        if !ctx.analyze && !ctx.output.json {
+            f(
+                &Node::new(format!("{}{segment}\n", arch.comment_pre())),
+                CallbackKind::Static,
+                &[],
+                arch,
+                ctx,
+            )?;
+        }
+        Ok(())
+    }
+
+    // disassembles the parts of input[start..end] that are not gaps
+    // returns the amount of bytes that were read
+    fn disas_defined(
+        &self,
+        f: &mut dyn DisasCallback,
+        input: &[u8],
+        start: usize,
+        end: usize,
+        ctx: &mut Context,
+        emitted: &mut Vec<(usize, usize)>,
+    ) -> FdResult<usize> {
+        let mut pos = start;
+        let mut total = 0;
+        for gap in ctx.gaps_in(start, end) {
+            if gap.start > pos {
+                total += self.disas_range(f, input, pos, gap.start, ctx, emitted)?;
+            }
+            self.skip_gap(f, input, &gap, ctx, emitted)?;
+            pos = gap.end;
+        }
+        if pos < end {
+            total += self.disas_range(f, input, pos, end, ctx, emitted)?;
+        }
+        Ok(total)
+    }
+
+    // a gap is replaced by a notice, the reassembled output keeps its fill bytes
+    fn skip_gap(
+        &self,
+        f: &mut dyn DisasCallback,
+        input: &[u8],
+        gap: &Gap,
+        ctx: &mut Context,
+        emitted: &mut Vec<(usize, usize)>,
+    ) -> FdResult<()> {
+        info!("Skipping gap from {} to {}", gap.start, gap.end);
+        if ctx.output.byte_exact() && !ctx.analyze {
+            self.output_unparsed(f, input, gap.start, gap.end, ctx)?;
+            emitted.push((gap.start, gap.end));
+        } else if !ctx.analyze && !ctx.output.json {
+            let arch = self
+                .archs
+                .get(&ctx.arch_key)
+                .ok_or_else(|| Error::ArchNotFound(ctx.arch_key.clone()))?;
+            let first = ctx.address();
+            let last = ctx.address_of(
+                ctx.offset
+                    .wrapping_add(ctx.static_offset)
+                    .wrapping_add(gap.len() as Address - 1),
+            );
+            f(
+                &Node::new(format!(
+                    "{}undefined {first:x}..={last:x} ({:#x} bytes)\n",
+                    arch.comment_pre(),
+                    gap.len()
+                )),
+                CallbackKind::Static,
+                &[],
+                arch,
+                ctx,
+            )?;
+        }
+        // the bytes after the gap keep their address
+        ctx.offset = ctx.offset.wrapping_add(gap.len() as Address);
+        Ok(())
+    }
+
+    // disassembles input[start_read..end_read] and switches into the regions inside of it
+    // returns the amount of bytes that were read
+    fn disas_range(
+        &self,
+        f: &mut dyn DisasCallback,
+        input: &[u8],
+        start_read: usize,
+        end_read: usize,
+        ctx: &mut Context,
+        emitted: &mut Vec<(usize, usize)>,
+    ) -> FdResult<usize> {
+        let reassemble = ctx.output.byte_exact() && !ctx.analyze;
+        let data = &input[start_read..end_read];
+        let mut total = 0;
+        // the region that is currently active and the org and arch key
+        // that were in use before entering it
+        let mut active: Option<usize> = None;
+        let mut outer: Option<(Address, String, MemoryMap)> = None;
+        // loop until total data processed is out of range
+        // or an error occured
+        while total < data.len() {
+            let pos = start_read + total;
+            let current = ctx.regions.iter().position(|r| r.contains(pos));
+            if current != active {
+                ctx.leave_region(&mut outer);
+                if let Some(i) = current {
+                    let region = ctx.regions[i].clone();
+                    info!("Entering region {} at {pos}", region.name);
+                    ctx.emit(&Event::RegionEntered(region.clone()));
+                    outer = Some((ctx.org, ctx.arch_key.clone(), ctx.memory_map));
+                    ctx.memory_map = MemoryMap::Linear;
+                    ctx.org = region
+                        .org
+                        .wrapping_sub(ctx.offset)
+                        .wrapping_sub(ctx.static_offset);
+                    ctx.arch_key = region.arch_key;
+                    ctx.map_bank(region.org, &region.name);
+                    ctx.namespace = region.name;
+                }
+                active = current;
+            }
+
+            // never read past the end of the current region
+            // or into the next region
+            let end = match current {
+                Some(i) => ctx.regions[i].end,
+                None => ctx
+                    .regions
+                    .iter()
+                    .filter(|r| r.start > pos)
+                    .map(|r| r.start)
+                    .min()
+                    .unwrap_or(end_read),
+            }
+            .clamp(pos + 1, end_read)
+                - start_read;
+
+            let arch = self
+                .archs
+                .get(&ctx.arch_key)
+                .ok_or_else(|| Error::ArchNotFound(ctx.arch_key.clone()))?;
+            // the instructions are decoded with the flags of the path that reached them
+            if let Some(flags) = ctx
+                .reached
+                .as_ref()
+                .filter(|_| current.is_none())
+                .and_then(|reached| reached.get(&pos))
+            {
+                ctx.flags = flags.clone();
+            }
+            // the bytes up to the next instruction that the recursive descent reached are data
+            let unreached = ctx
+                .reached
+                .as_ref()
+                .filter(|reached| current.is_none() && !reached.contains_key(&pos))
+                .map(|reached| {
+                    reached
+                        .range(pos..)
+                        .next()
+                        .map_or(end, |(next, _)| (next - start_read).min(end))
+                });
+            // and so are the bytes that are classified as data,
+            // strings are output as text and pointer tables as words
+            let classified = ctx
+                .classes
+                .range_at(pos)
+                .filter(|r| r.class.is_data())
+                .map(|r| (r.class, (r.end - start_read).min(end)));
+            let read = match (classified, unreached) {
+                (Some((class, next)), _) => {
+                    arch.match_class(f, &data[total..next], ctx, class)?.max(1)
+                }
+                (None, Some(next)) => arch.match_data(f, &data[total..next], ctx)?.max(1),
+                (None, None) => arch.match_patterns(f, &data[total..end], ctx)?,
+            };
+            if reassemble {
+                emitted.push((pos, pos + read));
+            }
+            total += read;
+        }
+        ctx.leave_region(&mut outer);
+        Ok(total)
+    }
+
+    // outputs bytes that are not disassembled as data at their file offset
+    fn output_unparsed(
+        &self,
+        f: &mut dyn DisasCallback,
+        input: &[u8],
+        start: usize,
+        end: usize,
+        ctx: &mut Context,
+    ) -> FdResult<()> {
+        let arch = self
+            .archs
+            .get(&ctx.arch_key)
+            .ok_or_else(|| Error::ArchNotFound(ctx.arch_key.clone()))?;
+        let (offset, static_offset) = (ctx.offset, ctx.static_offset);
+        ctx.offset = 0;
+        ctx.static_offset = start as Address;
+
+        let data = &input[start..end];
+        let mut total = 0;
+        while total < data.len() {
+            total += arch.match_data(f, &data[total..], ctx)?.max(1);
+        }
+
+        ctx.offset = offset;
+        ctx.static_offset = static_offset;
+        Ok(())
+    }
+
+    /// Every byte of the input has to be output exactly once
+    /// and has to be part of the text of its line, as an opcode or a value,
+    /// for the output to assemble to the input
+    fn verify_round_trip(
+        emitted: &mut [(usize, usize)],
+        claimed: &mut [(usize, usize)],
+        input_start: usize,
+        input_len: usize,
+    ) -> FdResult<()> {
+        emitted.sort();
+        let mut expected = input_start;
+        for (start, end) in emitted.iter() {
+            if *start > expected {
+                return Err(Error::RoundTrip(format!(
+                    "bytes {expected:#x}..{start:#x} were not output"
+                )));
+            }
+            if *start < expected {
+                return Err(Error::RoundTrip(format!(
+                    "bytes {start:#x}..{:#x} were output more than once",
+                    expected.min(*end)
+                )));
+            }
+            expected = *end;
+        }
+        if expected != input_len {
+            return Err(Error::RoundTrip(format!(
+                "bytes {expected:#x}..{input_len:#x} were not output"
+            )));
+        }
+
+        claimed.sort();
+        let mut covered = input_start;
+        for (start, end) in claimed.iter() {
+            if *start > covered && covered < input_len {
+                return Err(Error::RoundTrip(format!(
+                    "bytes {covered:#x}..{:#x} were consumed without being output",
+                    start.min(&input_len)
+                )));
+            }
+            covered = covered.max(*end);
+        }
+        if covered < input_len {
+            return Err(Error::RoundTrip(format!(
+                "bytes {covered:#x}..{input_len:#x} were consumed without being output"
+            )));
+        }
+        Ok(())
+    }
+
     /// start disasssembly
     /// This will write all result strings to the f callback,
     /// and it will modify the current context
@@ -1013,32 +3209,948 @@ impl Archs {
     }
 
     /// Call the disas function with an existing context
+    /// If the context declares segments only the segments are disassembled,
+    /// otherwise the window is disassembled
     pub fn disas_ctx(
         &self,
         mut f: impl DisasCallback,
         data: &[u8],
         ctx: &mut Context,
     ) -> FdResult<()> {
-        let start_read = ctx.start_read.min(data.len());
-        let end_read = start_read + ctx.len_read.unwrap_or(data.len()).min(data.len());
-        let data = &data[start_read..end_read];
-        info!(
-            "Starting from {start_read} to {end_read} at org {}",
-            ctx.org
-        );
+        let substitutions = if ctx.analyze || ctx.output.json {
+            vec![]
+        } else {
+            ctx.output.substitutions()?
+        };
+        let clean = ctx.output.clean && !ctx.analyze;
+        let markdown = ctx.output.markdown && !ctx.output.json && !ctx.analyze;
+        let filter_tag = ctx.output.filter_tag.clone().filter(|_| !ctx.analyze);
+        let hexdump = ctx.output.hexdump && !ctx.output.json && !ctx.analyze;
+        if substitutions.is_empty() && !clean && !markdown && filter_tag.is_none() && !hexdump {
+            return self.disas_all(
+                &mut |node: &Node, kind, raw: &[u8], arch: &Arch, ctx: &mut Context| {
+                    ctx.map_output(&node.string, Some(ctx.location()));
+                    f(node, kind, raw, arch, ctx)
+                },
+                data,
+                ctx,
+            );
+        }
+
+        // the substitutions need the whole line, which is passed on as a single static node
+        // the clean output is held back until the equates are known
+        let mut line = String::new();
+        let mut lines = vec![];
+        let mut labels = BTreeSet::new();
+        let mut referenced = BTreeSet::new();
+        let mut writer = MarkdownWriter::default();
+        let mut hexdump_writer = HexdumpWriter::default();
+        // the address and the file offset of the line that is being output
+        let mut location = (0, 0);
+        // the lines before the first label are not part of a section
+        let mut visible = filter_tag.is_none();
+        let mut section_address = None;
+        self.disas_all(
+            &mut |node: &Node, kind: CallbackKind, raw: &[u8], arch: &Arch, ctx: &mut Context| {
+                // a section starts at the first line of a labeled address,
+                // so the comments and docs above the label belong to it
+                let address = ctx.address();
+                if line.is_empty() {
+                    location = ctx.location();
+                }
+                if line.is_empty() && section_address != Some(address) {
+                    section_address = Some(address);
+                    writer.address();
+                    if let Some(tag) = filter_tag.as_ref() {
+                        let labels: Vec<Symbol> = ctx
+                            .syms
+                            .get_symbols(address as ValueType)
+                            .into_iter()
+                            .filter(|s| {
+                                s.kind == SymbolKind::Label
+                                    && s.namespace == ctx.namespace
+                                    && s.scope.is_in_scope(address)
+                            })
+                            .collect();
+                        if !labels.is_empty() {
+                            visible = labels.iter().any(|s| s.tags.contains(tag));
+                        }
+                    }
+                }
+                if markdown && visible && matches!(kind, CallbackKind::Symbol) {
+                    writer.reference(&node.string);
+                }
+                match kind {
+                    // the label lines are replaced by the heading
+                    CallbackKind::Label if markdown && visible => {
+                        writer.label(&node.string);
+                        if clean {
+                            labels.extend(
+                                node.string
+                                    .lines()
+                                    .map(|l| l.trim().trim_end_matches(':').to_owned()),
+                            );
+                        }
+                        return Ok(());
+                    }
+                    CallbackKind::Pad(n) => line.extend(std::iter::repeat_n(
+                        ' ',
+                        n.saturating_sub(ctx.tr_ctx.line_len),
+                    )),
+                    CallbackKind::Label if clean => labels.extend(
+                        node.string
+                            .lines()
+                            .map(|l| l.trim().trim_end_matches(':').to_owned()),
+                    ),
+                    // the offset of name+1 is not part of the name
+                    CallbackKind::Symbol if clean => {
+                        let name = node.string.split('+').next().unwrap_or_default();
+                        referenced.insert(name.to_owned());
+                    }
+                    _ => {}
+                }
+                line.push_str(&node.string);
+                while let Some(end) = line.find('\n') {
+                    let rest = line.split_off(end + 1);
+                    let done = std::mem::replace(&mut line, rest);
+                    let done = substitute(&substitutions, &done[..end]);
+                    // the padding of the comment column is not needed
+                    let done = if clean || markdown {
+                        format!("{}\n", done.trim_end())
+                    } else {
+                        format!("{done}\n")
+                    };
+                    if !visible {
+                        continue;
+                    }
+                    let done = if hexdump {
+                        hexdump_writer.line(data, location.1, &done)
+                    } else {
+                        done
+                    };
+                    if markdown {
+                        writer.line(&done, arch.comment_pre());
+                    } else if clean {
+                        lines.push((done, Some(location)));
+                    } else {
+                        ctx.map_output(&done, Some(location));
+                        f(&Node::new(done), CallbackKind::Static, raw, arch, ctx)?;
+                    }
+                }
+                Ok(())
+            },
+            data,
+            ctx,
+        )?;
+        let arch = self
+            .archs
+            .get(&ctx.arch_key)
+            .ok_or_else(|| Error::ArchNotFound(ctx.arch_key.clone()))?;
+        if !line.is_empty() && visible {
+            let line = substitute(&substitutions, &line);
+            let line = if hexdump {
+                hexdump_writer.line(data, location.1, &line)
+            } else {
+                line
+            };
+            if markdown {
+                // the closing fence needs a line of its own
+                writer.line(&format!("{line}\n"), arch.comment_pre());
+            } else {
+                lines.push((line, Some(location)));
+            }
+        }
+        if markdown {
+            // the sections are not mapped, their lines are rearranged
+            lines.push((writer.finish(), None));
+        }
+        if clean {
+            let mut equates = substitute(
+                &substitutions,
+                &Self::equates(arch, ctx, &labels, &referenced)?,
+            );
+            if markdown && !equates.is_empty() {
+                equates = format!("```asm\n{equates}```\n\n");
+            }
+            lines.insert(0, (equates, None));
+        }
+        for (line, location) in lines.iter().filter(|(l, _)| !l.is_empty()) {
+            ctx.map_output(line, *location);
+            f(
+                &Node::new(line.clone()),
+                CallbackKind::Static,
+                &[],
+                arch,
+                ctx,
+            )?;
+        }
+        Ok(())
+    }
+
+    // the referenced symbols that are not defined as labels of the output
+    // are defined as equates before the clean output
+    fn equates(
+        arch: &Arch,
+        ctx: &Context,
+        labels: &BTreeSet<String>,
+        referenced: &BTreeSet<String>,
+    ) -> FdResult<String> {
+        let mut result = String::new();
+        for name in referenced.difference(labels) {
+            let sym = ctx
+                .syms
+                .iter()
+                .find(|s| s.qualified_name() == *name)
+                .or_else(|| {
+                    ctx.syms
+                        .iter()
+                        .find(|s| arch.local_name(s, s.name.clone()) == *name)
+                });
+            if let Some(sym) = sym {
+                result.push_str(&format!("{name} = {}\n", arch.format_value(sym.value)?));
+            }
+        }
+        if !result.is_empty() {
+            result.push('\n');
+        }
+        Ok(result)
+    }
 
+    fn disas_all(
+        &self,
+        mut f: &mut dyn DisasCallback,
+        data: &[u8],
+        ctx: &mut Context,
+    ) -> FdResult<()> {
+        ctx.verify_checksums(data)?;
+        // the analysis and the recursive descent start at the vectors
+        if ctx.analyze || ctx.descent {
+            interrupts::label_vectors(ctx, data);
+        }
+        // the recursive descent decides which bytes are instructions before the output
+        ctx.reached = None;
+        if ctx.descent {
+            ctx.reached = Some(descent::trace(self, data, ctx)?);
+        }
+        ctx.next_address = None;
+        ctx.cycle_total = 0;
+
+        let ranges = if ctx.segments.is_empty() {
+            let window = ctx.window(data);
+            // the analysis pass would repeat the warnings
+            if !ctx.analyze {
+                for warning in window.warnings.iter() {
+                    ctx.warn(warning);
+                }
+            }
+            vec![(window.start, window.end, None)]
+        } else {
+            ctx.segment_ranges(data)
+                .into_iter()
+                .map(|s| (s.start, s.end, Some(s)))
+                .collect()
+        };
+
+        if ctx.output.size_report && !ctx.analyze {
+            ctx.size_report = Default::default();
+        }
+
+        // the file ranges that were output in reassemble mode
+        let reassemble = ctx.output.byte_exact() && !ctx.analyze;
+        ctx.claimed.clear();
+        let (first, input_end) = ctx.split_range.unwrap_or((0, data.len()));
+        let mut emitted = vec![];
+        let mut last = first;
         let mut total = 0;
-        // loop until total data processed is out of range
-        // or an error occured
-        while total < data.len() {
-            let arch = self
-                .archs
-                .get(&ctx.arch_key)
-                .ok_or_else(|| Error::ArchNotFound(ctx.arch_key.clone()))?;
-            total += arch.match_patterns(&mut f, &data[total..], ctx)?;
+        for (start, end, segment) in ranges {
+            if reassemble && start > last {
+                self.output_unparsed(&mut f, data, last, start, ctx)?;
+                emitted.push((last, start));
+            }
+            last = last.max(end);
+
+            let outer = (ctx.org, ctx.arch_key.clone(), ctx.memory_map);
+            if let Some(segment) = &segment {
+                // the reassembled output follows the file, the segment keeps its org
+                if reassemble {
+                    ctx.offset = (start as Address).wrapping_sub(ctx.static_offset);
+                }
+                self.enter_segment(&mut f, segment, ctx)?;
+            }
+            info!("Starting from {start} to {end} at org {}", ctx.org);
+            total += self.disas_defined(&mut f, data, start, end, ctx, &mut emitted)?;
+            if segment.is_some() {
+                (ctx.org, ctx.arch_key, ctx.memory_map) = outer;
+            }
+        }
+
+        if reassemble {
+            self.output_unparsed(&mut f, data, last, input_end, ctx)?;
+            emitted.push((last, input_end));
+            // the output ends at the end of the input
+            ctx.offset = (input_end as Address).wrapping_sub(ctx.static_offset);
+            Self::verify_round_trip(&mut emitted, &mut ctx.claimed, first, input_end)?;
+        }
+        if ctx.analyze {
+            ctx.cleanup_symbols();
         }
 
         info!("Finished. Read {total} bytes.");
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::BTreeMap,
+        sync::{Arc, Mutex},
+    };
+
+    use super::{
+        Arch, Archs, Context, DataOut, DefSym, Event, EventResponse, LiteralOut, Matcher, Node,
+        Pattern, PatternAt, StaticOp, Transform, ValOut, ValueOp, Variable, View, DEFINE_BYTE,
+    };
+    use crate::{
+        core::dasm::{
+            output::OutputProfile,
+            symbols::{ReferenceKind, Scope, Symbol, SymbolKind},
+            DataType, ValueTypeFmt,
+        },
+        prelude::Error,
+    };
+
+    #[test]
+    fn def_sym_ops() {
+        let arch = Arch {
+            patterns: vec![Matcher {
+                patterns: vec![PatternAt::new(Pattern::Any, 0)],
+                transforms: "def".into(),
+                name: Node::new("def".into()),
+                cycles: None,
+            }],
+            transforms: BTreeMap::from([(
+                "def".into(),
+                vec![
+                    Transform::DefSym(DefSym {
+                        name: "target".into(),
+                        data_type: DataType::U8,
+                        symbol_kind: SymbolKind::Label,
+                        len: 1,
+                        ops: vec![ValueOp::Shl(1), ValueOp::AddBank(0xFF0000)],
+                        ..Default::default()
+                    }),
+                    Transform::Consume(1),
+                ],
+            )]),
+            ..Default::default()
+        };
+        let archs = Archs {
+            archs: BTreeMap::from([("".into(), arch)]),
+            ..Default::default()
+        };
+
+        let mut ctx = Context {
+            org: 0x7E8000,
+            analyze: true,
+            ..Default::default()
+        };
+        archs
+            .disas_ctx(|_n, _kind, _raw, _arch, _ctx| Ok(()), &[0x12], &mut ctx)
+            .unwrap();
+
+        let sym = ctx.syms.get_first_by_name("target").unwrap();
+        assert_eq!(0x7E0024, sym.value);
+    }
+
+    #[test]
+    fn auto_label_names() {
+        let matcher = |byte, name: &str| Matcher {
+            patterns: vec![PatternAt::new(Pattern::Exact(byte), 0)],
+            transforms: "val".into(),
+            name: Node::new(name.into()),
+            cycles: None,
+        };
+        let arch = Arch {
+            patterns: vec![
+                matcher(0x20, "jsr"),
+                matcher(0xD0, "bne"),
+                matcher(0xAD, "lda"),
+            ],
+            transforms: BTreeMap::from([(
+                "val".into(),
+                vec![
+                    Transform::MatcherName,
+                    Transform::Consume(1),
+                    Transform::Val(ValOut {
+                        data_type: DataType::U8,
+                        auto_def_sym: true,
+                        ..Default::default()
+                    }),
+                ],
+            )]),
+            ..Default::default()
+        };
+        let archs = Archs {
+            archs: BTreeMap::from([("".into(), arch)]),
+            ..Default::default()
+        };
+
+        let mut ctx = Context {
+            analyze: true,
+            ..Default::default()
+        };
+        ctx.label_prefixes
+            .insert(ReferenceKind::Data, "table_".into());
+        // the value that is labeled first keeps its name
+        let data = [0x20, 0x10, 0xD0, 0x20, 0xAD, 0x30, 0xAD, 0x10];
+        archs
+            .disas_ctx(|_n, _kind, _raw, _arch, _ctx| Ok(()), &data, &mut ctx)
+            .unwrap();
+
+        let names: Vec<String> = [0x10, 0x20, 0x30]
+            .iter()
+            .map(|v| ctx.syms.get_symbols(*v)[0].name.clone())
+            .collect();
+        assert_eq!(vec!["sub_0010", "loc_0020", "table_0030"], names);
+    }
+
+    #[test]
+    fn event_hook() {
+        let def = |name: &str, add| {
+            Transform::DefSym(DefSym {
+                name: name.into(),
+                data_type: DataType::U8,
+                len: 1,
+                ops: vec![ValueOp::Add(add)],
+                ..Default::default()
+            })
+        };
+        let arch = Arch {
+            patterns: vec![Matcher {
+                patterns: vec![PatternAt::new(Pattern::Any, 0)],
+                transforms: "def".into(),
+                name: Node::new("def".into()),
+                cycles: None,
+            }],
+            transforms: BTreeMap::from([(
+                "def".into(),
+                vec![
+                    def("vetoed", 0),
+                    def("renamed", 1),
+                    def("kept", 2),
+                    Transform::Static(Node::new("{var:missing}".into())),
+                    Transform::Consume(1),
+                ],
+            )]),
+            ..Default::default()
+        };
+        let archs = Archs {
+            archs: BTreeMap::from([("".into(), arch)]),
+            ..Default::default()
+        };
+
+        let events = Arc::new(Mutex::new(vec![]));
+        let mut ctx = Context {
+            analyze: true,
+            ..Default::default()
+        };
+        let log = events.clone();
+        ctx.set_event_hook(move |event| {
+            log.lock().unwrap().push(event.clone());
+            match event {
+                Event::SymbolDefined(sym) if sym.name == "vetoed" => EventResponse::Veto,
+                Event::SymbolDefined(sym) if sym.name == "renamed" => {
+                    EventResponse::Rename("new_name".into())
+                }
+                _ => EventResponse::Accept,
+            }
+        });
+        archs
+            .disas_ctx(|_n, _kind, _raw, _arch, _ctx| Ok(()), &[0x12], &mut ctx)
+            .unwrap();
+
+        assert!(ctx.syms.get_first_by_name("vetoed").is_none());
+        assert!(ctx.syms.get_first_by_name("renamed").is_none());
+        assert_eq!(0x13, ctx.syms.get_first_by_name("new_name").unwrap().value);
+        assert!(ctx.syms.get_first_by_name("kept").is_some());
+
+        // the static node is not expanded when analyzing
+        assert_eq!(3, events.lock().unwrap().len());
+
+        ctx.expand_vars("{var:missing}");
+        let last = events.lock().unwrap().last().cloned();
+        assert!(matches!(
+            last,
+            Some(Event::Warning(msg)) if msg == "Variable 'missing' is not defined"
+        ));
+    }
+
+    #[test]
+    fn auto_symbol_merge() {
+        let sym = |name: &str, value, len| {
+            Symbol::new(name.into(), SymbolKind::Label, Scope::Global, value, len)
+        };
+        let mut ctx = Context::default();
+
+        // the same value is only defined once
+        ctx.def_auto_symbol(sym("auto_16", 0x10, 1));
+        ctx.def_auto_symbol(sym("auto_16_again", 0x10, 1));
+        assert_eq!(1, ctx.syms.get_symbols(0x10).len());
+
+        // user symbols replace auto symbols
+        ctx.def_symbol(sym("user", 0x10, 1));
+        ctx.def_symbol(sym("user", 0x10, 1));
+        let syms = ctx.syms.get_symbols(0x10);
+        assert_eq!(1, syms.len());
+        assert_eq!("user", syms[0].name);
+        ctx.def_auto_symbol(sym("auto_16", 0x10, 1));
+        assert_eq!(1, ctx.syms.get_symbols(0x10).len());
+
+        // auto symbols inside of a user symbol are removed by the cleanup
+        ctx.def_auto_symbol(sym("auto_33", 0x21, 1));
+        ctx.def_symbol(sym("table", 0x20, 4));
+        assert_eq!(2, ctx.syms.get_symbols(0x21).len());
+        ctx.cleanup_symbols();
+        let syms = ctx.syms.get_symbols(0x21);
+        assert_eq!(1, syms.len());
+        assert_eq!("table", syms[0].name);
+    }
+
+    #[test]
+    fn size_report() {
+        // the instruction is encoded in 3 bytes but only consumes 2
+        let arch = Arch {
+            patterns: vec![
+                Matcher {
+                    patterns: vec![
+                        PatternAt::new(Pattern::Exact(0x20), 0),
+                        PatternAt::new(Pattern::Any, 2),
+                    ],
+                    transforms: "short".into(),
+                    name: Node::new("jsr".into()),
+                    cycles: None,
+                },
+                Matcher {
+                    patterns: vec![PatternAt::new(Pattern::Any, 0)],
+                    transforms: "byte".into(),
+                    name: Node::new(".db".into()),
+                    cycles: None,
+                },
+            ],
+            transforms: BTreeMap::from([
+                ("short".into(), vec![Transform::Consume(2)]),
+                ("byte".into(), vec![Transform::Consume(1)]),
+            ]),
+            ..Default::default()
+        };
+        let archs = Archs {
+            archs: BTreeMap::from([("".into(), arch)]),
+            ..Default::default()
+        };
+
+        let mut ctx = Context {
+            org: 0x18000,
+            ..Default::default()
+        };
+        ctx.output.size_report = true;
+        archs
+            .disas_ctx(
+                |_n, _kind, _raw, _arch, _ctx| Ok(()),
+                &[0x20, 0x00, 0x80, 0xEA],
+                &mut ctx,
+            )
+            .unwrap();
+
+        assert_eq!(4, ctx.size_report.total());
+        assert_eq!(Some(&4), ctx.size_report.sections.get(&("".into(), 0x1)));
+        assert_eq!(1, ctx.size_report.mismatches.len());
+        assert_eq!(
+            "18000: jsr is encoded in 3 bytes but consumed 2",
+            ctx.size_report.mismatches[0].to_string()
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut claimed = vec![(0, 6)];
+        let mut emitted = vec![(4, 6), (0, 2)];
+        assert!(Archs::verify_round_trip(&mut emitted, &mut claimed, 0, 6).is_err());
+        let mut emitted = vec![(0, 3), (2, 4)];
+        assert!(Archs::verify_round_trip(&mut emitted, &mut claimed, 0, 4).is_err());
+        let mut emitted = vec![(0, 2)];
+        assert!(Archs::verify_round_trip(&mut emitted, &mut claimed, 0, 4).is_err());
+        let mut emitted = vec![(2, 4), (0, 2)];
+        assert!(Archs::verify_round_trip(&mut emitted, &mut claimed, 0, 4).is_ok());
+        // every byte has to be part of the text
+        let mut claimed = vec![(3, 4), (0, 1), (1, 2)];
+        assert!(Archs::verify_round_trip(&mut emitted, &mut claimed, 0, 4).is_err());
+        let mut claimed = vec![(0, 3)];
+        assert!(Archs::verify_round_trip(&mut emitted, &mut claimed, 0, 4).is_err());
+        let mut claimed = vec![(2, 4), (0, 1), (1, 3)];
+        assert!(Archs::verify_round_trip(&mut emitted, &mut claimed, 0, 4).is_ok());
+
+        // a transform that consumes the high byte without outputting it
+        let reassemble = |value: Vec<Transform>| {
+            let arch = Arch {
+                patterns: vec![Matcher {
+                    patterns: vec![PatternAt::new(Pattern::Exact(0xAD), 0)],
+                    transforms: "abs".into(),
+                    name: Node::new("lda".into()),
+                    cycles: None,
+                }],
+                transforms: BTreeMap::from([(
+                    "abs".into(),
+                    [
+                        vec![
+                            Transform::MatcherName,
+                            Transform::Consume(1),
+                            Transform::Static(Node::new(" ".into())),
+                        ],
+                        value,
+                    ]
+                    .concat(),
+                )]),
+                ..Default::default()
+            };
+            let archs = Archs {
+                archs: BTreeMap::from([("".into(), arch)]),
+                ..Default::default()
+            };
+            let mut ctx = Context {
+                output: OutputProfile {
+                    reassemble: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            archs.disas_ctx(
+                |_n, _kind, _raw, _arch, _ctx| Ok(()),
+                &[0xAD, 0x00, 0x20],
+                &mut ctx,
+            )
+        };
+        let value = |data_type| ValOut {
+            fmt: ValueTypeFmt::LowerHex(2),
+            data_type,
+            ..Default::default()
+        };
+        assert!(reassemble(vec![Transform::Val(value(DataType::U16))]).is_ok());
+        assert!(matches!(
+            reassemble(vec![
+                Transform::Val(value(DataType::U8)),
+                Transform::Consume(1)
+            ]),
+            Err(Error::RoundTrip(_))
+        ));
+    }
+
+    #[test]
+    fn literal() {
+        let disas = |pc_offset: i64, disp: u8| {
+            let arch = Arch {
+                patterns: vec![Matcher {
+                    patterns: vec![PatternAt::new(Pattern::Any, 0)],
+                    transforms: "lit".into(),
+                    name: Node::new("mov".into()),
+                    cycles: None,
+                }],
+                transforms: BTreeMap::from([(
+                    "lit".into(),
+                    vec![
+                        Transform::MatcherName,
+                        Transform::Literal(LiteralOut {
+                            disp: ValOut {
+                                data_type: DataType::U8,
+                                ..Default::default()
+                            },
+                            pc_offset,
+                            align: 1,
+                            fmt: ValueTypeFmt::LowerHex(4),
+                            data_type: DataType::U16,
+                        }),
+                        Transform::Consume(1),
+                    ],
+                )]),
+                ..Default::default()
+            };
+            let archs = Archs {
+                archs: BTreeMap::from([("".into(), arch)]),
+                ..Default::default()
+            };
+            let mut ctx = Context {
+                org: 0x10,
+                ..Default::default()
+            };
+            let mut out = String::new();
+            archs
+                .disas_ctx(
+                    |n, _kind, _raw, _arch, _ctx| {
+                        out.push_str(&n.string);
+                        Ok(())
+                    },
+                    &[disp, 0x34, 0x12],
+                    &mut ctx,
+                )
+                .map(|_| out)
+        };
+        assert!(disas(0, 1).unwrap().starts_with("mov ; 1234"));
+        // a target before the instruction is not in the data
+        assert!(!disas(-1, 0).unwrap().contains(';'));
+        assert!(disas(-0x10, 0).is_ok());
+    }
+
+    #[test]
+    fn window() {
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        ctx.set_start(Some(2));
+        ctx.set_len(Some(2));
+        let window = ctx.window(&[0; 8]);
+        assert_eq!((2, 4), (window.start, window.end));
+        assert!(window.warnings.is_empty());
+        assert_eq!(
+            "offsets 0x2..0x4 (2 of 8 bytes) at addresses 8002..=8003",
+            window.to_string()
+        );
+
+        ctx.set_len(Some(0x10));
+        let window = ctx.window(&[0; 8]);
+        assert_eq!(8, window.end);
+        assert_eq!(1, window.warnings.len());
+
+        ctx.set_start(Some(0x10));
+        let window = ctx.window(&[0; 8]);
+        assert!(window.is_empty());
+        assert_eq!(
+            vec![
+                "Start 0x10 is past the end of the input (0x8 bytes)",
+                "Length 0x10 from 0x8 exceeds the input (0x8 bytes), reading 0x0 bytes",
+                "The disassembly window is empty"
+            ],
+            window.warnings
+        );
+
+        // lengths that do not fit the address space are clamped as well
+        ctx.set_start(Some(2));
+        ctx.set_len(Some(usize::MAX));
+        let window = ctx.window(&[0; 8]);
+        assert_eq!((2, 8), (window.start, window.end));
+        assert_eq!(1, window.warnings.len());
+    }
+
+    #[test]
+    fn define_byte_fallback() {
+        let arch = Arch {
+            patterns: vec![Matcher {
+                patterns: vec![PatternAt::new(Pattern::Exact(0xEA), 0)],
+                transforms: "implied".into(),
+                name: Node::new("nop".into()),
+                cycles: None,
+            }],
+            transforms: BTreeMap::from([
+                (
+                    "implied".into(),
+                    vec![
+                        Transform::MatcherName,
+                        Transform::Consume(1),
+                        Transform::new_line(),
+                    ],
+                ),
+                (
+                    DEFINE_BYTE.into(),
+                    vec![
+                        Transform::Static(Node::new(".db ".into())),
+                        Transform::Data(DataOut::new(ValueTypeFmt::LowerHex(2), DataType::U8)),
+                        Transform::new_line(),
+                    ],
+                ),
+            ]),
+            ..Default::default()
+        };
+        let mut archs = Archs {
+            archs: BTreeMap::from([("".into(), arch)]),
+            ..Default::default()
+        };
+        let mut ctx = Context::default();
+        ctx.output.data_per_line = 2;
+        let mut result = String::new();
+        archs
+            .disas_ctx(
+                |n, _kind, _raw, _arch, _ctx| {
+                    result.push_str(&n.string);
+                    Ok(())
+                },
+                &[0xEA, 0x01, 0x02, 0x03, 0xEA, 0x04],
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!("nop\n.db 01, 02\n.db 03\nnop\n.db 04\n", result);
+
+        // without define_byte the bytes can not be output
+        archs
+            .archs
+            .get_mut("")
+            .unwrap()
+            .transforms
+            .remove(DEFINE_BYTE);
+        ctx.restart();
+        assert!(archs
+            .disas_ctx(|_n, _kind, _raw, _arch, _ctx| Ok(()), &[0x01], &mut ctx)
+            .is_err());
+    }
+
+    #[test]
+    fn views() {
+        let mut ctx = Context::default();
+        ctx.set_start(Some(0x10));
+        ctx.set_len(Some(0x20));
+        ctx.output.ascii_sidebar = true;
+        ctx.def_flag("bank", "sound");
+        ctx.save_view("sounddriver");
+
+        ctx.set_start(Some(0x100));
+        ctx.set_len(None);
+        ctx.output.ascii_sidebar = false;
+        ctx.undef_flag("bank");
+        ctx.restore_view("sounddriver").unwrap();
+        assert_eq!((0x10, Some(0x20)), (ctx.start_read, ctx.len_read));
+        assert!(ctx.output.ascii_sidebar);
+        assert_eq!(Some(&"sound".to_string()), ctx.flags.get("bank"));
+        assert_eq!(
+            "start 0x10 len 0x20 bank=sound",
+            ctx.views["sounddriver"].to_string()
+        );
+        assert_eq!("start 0x0 to the end", View::default().to_string());
+        assert!(ctx.restore_view("soundcode").is_err());
+    }
+
+    #[test]
+    fn vars() {
+        let mut ctx = Context::default();
+        ctx.def_var("title", "GAME".into());
+        ctx.def_var("base", "0x10".into());
+        assert_eq!(Some(&Variable::Number(0x10)), ctx.get_var("base"));
+
+        assert_eq!(None, ctx.expand_vars("no template"));
+        assert_eq!(
+            Some("; GAME at 16 {var:unknown}".into()),
+            ctx.expand_vars("; {var:title} at {var:base} {var:unknown}")
+        );
+
+        // static ops can define variables before they are used
+        let arch = Arch::default();
+        let mut result = String::new();
+        let ops = vec![
+            StaticOp::SetVar("rev".into(), Variable::String("1.1".into())),
+            StaticOp::StringLn("; {var:title} rev {var:rev}".into()),
+        ];
+        for op in ops {
+            op.apply(
+                &mut |n, _kind, _raw, _arch, _ctx| {
+                    result.push_str(&n.string);
+                    Ok(())
+                },
+                &[],
+                &arch,
+                &mut ctx,
+            )
+            .unwrap();
+        }
+        assert_eq!("; GAME rev 1.1\n", result);
+    }
+
+    #[test]
+    fn if_flag() {
+        let op = StaticOp::IfFlag(
+            "rev".into(),
+            Some("1".into()),
+            vec![StaticOp::StringLn("; rev 1".into())],
+            vec![StaticOp::StringLn("; rev 0".into())],
+        );
+        let arch = Arch::default();
+        let output = |ctx: &mut Context| {
+            let mut result = String::new();
+            op.apply(
+                &mut |n, _kind, _raw, _arch, _ctx| {
+                    result.push_str(&n.string);
+                    Ok(())
+                },
+                &[],
+                &arch,
+                ctx,
+            )
+            .unwrap();
+            result
+        };
+
+        let mut ctx = Context::default();
+        assert_eq!("; rev 0\n", output(&mut ctx));
+        ctx.def_flag("rev", "1");
+        assert_eq!("; rev 1\n", output(&mut ctx));
+    }
+
+    #[test]
+    fn script() {
+        let mut ctx = Context::default();
+        ctx.scripts.insert(
+            "bank_header".into(),
+            vec![
+                StaticOp::SetFlag("bank".into(), "1".into()),
+                StaticOp::StringLn("; bank {var:bank}".into()),
+            ],
+        );
+        ctx.def_var("bank", "1".into());
+        let arch = Arch::default();
+        let mut result = String::new();
+        let mut apply = |op: StaticOp, ctx: &mut Context| {
+            op.apply(
+                &mut |n, _kind, _raw, _arch, _ctx| {
+                    result.push_str(&n.string);
+                    Ok(())
+                },
+                &[],
+                &arch,
+                ctx,
+            )
+        };
+
+        // the script only runs at its address
+        apply(
+            StaticOp::Address(0x10, vec![StaticOp::Script("bank_header".into())]),
+            &mut ctx,
+        )
+        .unwrap();
+        assert_eq!(None, ctx.get_flag("bank"));
+        ctx.set_org(0x10);
+        apply(
+            StaticOp::Address(0x10, vec![StaticOp::Script("bank_header".into())]),
+            &mut ctx,
+        )
+        .unwrap();
+        assert_eq!(Some(&"1".to_string()), ctx.get_flag("bank"));
+        assert!(apply(StaticOp::Script("missing".into()), &mut ctx).is_err());
+
+        // scripts that call each other are an error instead of recursing forever
+        ctx.scripts
+            .insert("a".into(), vec![StaticOp::Script("b".into())]);
+        ctx.scripts
+            .insert("b".into(), vec![StaticOp::Script("a".into())]);
+        assert!(matches!(
+            apply(StaticOp::Script("a".into()), &mut ctx),
+            Err(Error::ScriptCycle(cycle)) if cycle == "a -> b -> a"
+        ));
+        // a script can run more than once if it does not call itself
+        ctx.scripts.insert(
+            "twice".into(),
+            vec![
+                StaticOp::Script("bank_header".into()),
+                StaticOp::Script("bank_header".into()),
+            ],
+        );
+        assert!(apply(StaticOp::Script("twice".into()), &mut ctx).is_ok());
+        assert_eq!("; bank 1\n; bank 1\n; bank 1\n", result);
+    }
+}
@@ -0,0 +1,410 @@
+use std::collections::BTreeMap;
+
+use crate::core::dasm::{arch::Archs, DataType, ValueTypeFmt};
+
+use super::{
+    Arch, Matcher, MatcherList, Node, Pattern, PatternAt, Transform, TransformMap, ValOut, ValueOp,
+};
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// Built-in architecture for the TI MSP430
+    pub static ref ARCH: Archs = Archs {archs: archs(), ..Default::default()};
+}
+
+// All instructions are 16 bit little endian words:
+// double operand: oooo ssss dbaa rrrr (s = source, r = destination)
+// single operand: 0001 00oo obaa rrrr
+// jump:           001c ccxx xxxx xxxx
+// where a is the source mode, d is the destination mode and b selects byte mode.
+// The low byte is at offset 0 and the high byte at offset 1.
+
+const IMPLIED: &str = "msp430_implied";
+const JUMP: &str = "msp430_jump";
+const POP: &str = "msp430_pop";
+
+/// Addressing modes of an operand
+#[derive(Clone, Copy)]
+enum Mode {
+    // rn
+    Reg,
+    // x(rn)
+    Indexed,
+    // x(pc), output relative to the extension word
+    Symbolic,
+    // &x (x(sr))
+    Absolute,
+    // @rn
+    Indirect,
+    // @rn+
+    AutoInc,
+    // #x (@pc+)
+    Imm,
+    // values produced by the constant generators r2 and r3
+    Const(&'static str),
+}
+
+use Mode::*;
+
+// (mode bits, register, mode)
+// the entries with a fixed register have to be matched first
+type ModeEntry = (u8, Option<u8>, Mode);
+
+const SRC_MODES: [ModeEntry; 13] = [
+    (0, Some(3), Const("0")),
+    (0, None, Reg),
+    (1, Some(0), Symbolic),
+    (1, Some(2), Absolute),
+    (1, Some(3), Const("1")),
+    (1, None, Indexed),
+    (2, Some(2), Const("4")),
+    (2, Some(3), Const("2")),
+    (2, None, Indirect),
+    (3, Some(0), Imm),
+    (3, Some(2), Const("8")),
+    (3, Some(3), Const("-1")),
+    (3, None, AutoInc),
+];
+
+const DST_MODES: [ModeEntry; 4] = [
+    (0, None, Reg),
+    (1, Some(0), Symbolic),
+    (1, Some(2), Absolute),
+    (1, None, Indexed),
+];
+
+impl Mode {
+    fn key(&self) -> String {
+        match self {
+            Reg => "reg".into(),
+            Indexed => "indexed".into(),
+            Symbolic => "symbolic".into(),
+            Absolute => "absolute".into(),
+            Indirect => "indirect".into(),
+            AutoInc => "autoinc".into(),
+            Imm => "imm".into(),
+            Const(c) => format!("const{}", c.replace('-', "m")),
+        }
+    }
+
+    // true if the mode reads an extension word
+    fn has_ext(&self) -> bool {
+        matches!(self, Indexed | Symbolic | Absolute | Imm)
+    }
+
+    // reg_at is the offset of the byte containing the register number
+    // ext_at is the offset of the extension word
+    fn transforms(&self, reg_at: usize, ext_at: usize) -> Vec<Transform> {
+        match self {
+            Reg => vec![Transform::Static(Node::new("r".into())), reg(reg_at)],
+            Indexed => vec![
+                word(ext_at),
+                Transform::Static(Node::new("(r".into())),
+                reg(reg_at),
+                Transform::Static(Node::new(")".into())),
+            ],
+            Symbolic => vec![
+                Transform::OffsetAddress(ext_at as i64),
+                Transform::Val(ValOut {
+                    offset: ext_at,
+                    fmt: ValueTypeFmt::LowerHex(4),
+                    data_type: DataType::U16,
+                    rel: true,
+                    peek: true,
+                    ..Default::default()
+                }),
+                Transform::OffsetAddress(-(ext_at as i64)),
+            ],
+            Absolute => vec![Transform::Static(Node::new("&".into())), word(ext_at)],
+            Indirect => vec![Transform::Static(Node::new("@r".into())), reg(reg_at)],
+            AutoInc => vec![
+                Transform::Static(Node::new("@r".into())),
+                reg(reg_at),
+                Transform::Static(Node::new("+".into())),
+            ],
+            Imm => vec![Transform::Static(Node::new("#".into())), word(ext_at)],
+            Const(c) => vec![Transform::Static(Node::new(format!("#{c}")))],
+        }
+    }
+}
+
+// the register number is stored in the lower nibble
+fn reg(offset: usize) -> Transform {
+    Transform::Val(ValOut {
+        offset,
+        fmt: ValueTypeFmt::Decimal(0),
+        data_type: DataType::U8,
+        ops: vec![ValueOp::And(0x0F)],
+        peek: true,
+        no_sym: true,
+        ..Default::default()
+    })
+}
+
+fn word(offset: usize) -> Transform {
+    Transform::Val(ValOut {
+        offset,
+        fmt: ValueTypeFmt::LowerHex(4),
+        data_type: DataType::U16,
+        peek: true,
+        ..Default::default()
+    })
+}
+
+fn mask(mask: u8, value: u8, offset: usize) -> PatternAt {
+    PatternAt::new(Pattern::Mask(mask, value), offset)
+}
+
+fn push_matcher(
+    matchers: &mut MatcherList,
+    mut patterns: Vec<PatternAt>,
+    len: usize,
+    name: &str,
+    key: &str,
+) {
+    patterns.push(PatternAt::new(Pattern::Any, len - 1));
+    matchers.push(Matcher {
+        patterns,
+        transforms: key.into(),
+        name: Node::new(name.into()),
+        cycles: None,
+    });
+}
+
+fn name_with_size(name: &str, byte: bool) -> String {
+    if byte {
+        format!("{name}.b")
+    } else {
+        name.into()
+    }
+}
+
+fn add_double_operand(
+    matchers: &mut MatcherList,
+    transforms: &mut TransformMap,
+    op: u8,
+    name: &str,
+) {
+    for (src_bits, src_reg, src) in SRC_MODES {
+        for (dst_bits, dst_reg, dst) in DST_MODES {
+            let src_ext = if src.has_ext() { 2 } else { 0 };
+            let dst_ext = if dst.has_ext() { 2 } else { 0 };
+            let len = 2 + src_ext + dst_ext;
+
+            let key = format!("msp430_{}_{}", src.key(), dst.key());
+            if !transforms.contains_key(&key) {
+                let mut list = vec![
+                    Transform::MatcherName,
+                    Transform::Static(Node::new(" ".into())),
+                ];
+                list.append(&mut src.transforms(1, 2));
+                list.push(Transform::Static(Node::new(", ".into())));
+                list.append(&mut dst.transforms(0, 2 + src_ext));
+                list.push(Transform::Consume(len));
+                transforms.insert(key.clone(), list);
+            }
+
+            for byte in [false, true] {
+                let mut patterns = vec![
+                    mask(0xF0, op << 4, 1),
+                    mask(0x80, dst_bits << 7, 0),
+                    mask(0x40, (byte as u8) << 6, 0),
+                    mask(0x30, src_bits << 4, 0),
+                ];
+                if let Some(r) = src_reg {
+                    patterns.push(mask(0x0F, r, 1));
+                }
+                if let Some(r) = dst_reg {
+                    patterns.push(mask(0x0F, r, 0));
+                }
+                push_matcher(matchers, patterns, len, &name_with_size(name, byte), &key);
+            }
+        }
+    }
+}
+
+fn add_single_operand(
+    matchers: &mut MatcherList,
+    transforms: &mut TransformMap,
+    op: u8,
+    name: &str,
+    allow_byte: bool,
+) {
+    let sizes: &[bool] = if allow_byte { &[false, true] } else { &[false] };
+    for (bits, src_reg, src) in SRC_MODES {
+        let len = if src.has_ext() { 4 } else { 2 };
+
+        let key = format!("msp430_{}", src.key());
+        if !transforms.contains_key(&key) {
+            let mut list = vec![
+                Transform::MatcherName,
+                Transform::Static(Node::new(" ".into())),
+            ];
+            list.append(&mut src.transforms(0, 2));
+            list.push(Transform::Consume(len));
+            transforms.insert(key.clone(), list);
+        }
+
+        for byte in sizes {
+            let mut patterns = vec![
+                PatternAt::new(Pattern::Exact(0x10 | (op >> 1)), 1),
+                mask(0x80, (op & 1) << 7, 0),
+                mask(0x40, (*byte as u8) << 6, 0),
+                mask(0x30, bits << 4, 0),
+            ];
+            if let Some(r) = src_reg {
+                patterns.push(mask(0x0F, r, 0));
+            }
+            push_matcher(matchers, patterns, len, &name_with_size(name, *byte), &key);
+        }
+    }
+}
+
+fn add_jump(matchers: &mut MatcherList, cond: u8, name: &str) {
+    push_matcher(
+        matchers,
+        vec![mask(0xFC, 0x20 | (cond << 2), 1)],
+        2,
+        name,
+        JUMP,
+    );
+}
+
+fn add_implied(matchers: &mut MatcherList, op: u16, name: &str) {
+    let [lo, hi] = op.to_le_bytes();
+    push_matcher(
+        matchers,
+        vec![
+            PatternAt::new(Pattern::Exact(lo), 0),
+            PatternAt::new(Pattern::Exact(hi), 1),
+        ],
+        2,
+        name,
+        IMPLIED,
+    );
+}
+
+fn patterns_and_transforms() -> (MatcherList, TransformMap) {
+    let mut matchers = vec![];
+    let mut transforms = BTreeMap::default();
+    super::a6502::transforms_common(&mut transforms);
+
+    transforms.insert(
+        IMPLIED.into(),
+        vec![Transform::MatcherName, Transform::Consume(2)],
+    );
+    transforms.insert(
+        POP.into(),
+        vec![
+            Transform::MatcherName,
+            Transform::Static(Node::new(" ".into())),
+            Transform::Static(Node::new("r".into())),
+            reg(0),
+            Transform::Consume(2),
+        ],
+    );
+    // the offset is a signed word count relative to the next instruction
+    transforms.insert(
+        JUMP.into(),
+        vec![
+            Transform::MatcherName,
+            Transform::Static(Node::new(" ".into())),
+            Transform::OffsetAddress(2),
+            Transform::Val(ValOut {
+                offset: 0,
+                fmt: ValueTypeFmt::LowerHex(4),
+                data_type: DataType::U16,
+                ops: vec![
+                    ValueOp::And(0x3FF),
+                    ValueOp::SignExtend(10),
+                    ValueOp::Shl(1),
+                    ValueOp::And(0xFFFF),
+                ],
+                rel: true,
+                peek: true,
+                ..Default::default()
+            }),
+            Transform::OffsetAddress(-2),
+            Transform::Consume(2),
+        ],
+    );
+
+    // commonly used emulated instructions
+    // ret = mov @sp+, pc
+    add_implied(&mut matchers, 0x4130, "ret");
+    // nop = mov #0, r3
+    add_implied(&mut matchers, 0x4303, "nop");
+    // pop rn = mov @sp+, rn
+    push_matcher(
+        &mut matchers,
+        vec![PatternAt::new(Pattern::Exact(0x41), 1), mask(0xF0, 0x30, 0)],
+        2,
+        "pop",
+        POP,
+    );
+
+    add_implied(&mut matchers, 0x1300, "reti");
+    for (op, name, allow_byte) in [
+        (0, "rrc", true),
+        (1, "swpb", false),
+        (2, "rra", true),
+        (3, "sxt", false),
+        (4, "push", true),
+        (5, "call", false),
+    ] {
+        add_single_operand(&mut matchers, &mut transforms, op, name, allow_byte);
+    }
+
+    for (cond, name) in ["jne", "jeq", "jnc", "jc", "jn", "jge", "jl", "jmp"]
+        .iter()
+        .enumerate()
+    {
+        add_jump(&mut matchers, cond as u8, name);
+    }
+
+    for (op, name) in [
+        (0x4, "mov"),
+        (0x5, "add"),
+        (0x6, "addc"),
+        (0x7, "subc"),
+        (0x8, "sub"),
+        (0x9, "cmp"),
+        (0xA, "dadd"),
+        (0xB, "bit"),
+        (0xC, "bic"),
+        (0xD, "bis"),
+        (0xE, "xor"),
+        (0xF, "and"),
+    ] {
+        add_double_operand(&mut matchers, &mut transforms, op, name);
+    }
+
+    (matchers, transforms)
+}
+
+fn archs() -> BTreeMap<String, Arch> {
+    let (patterns, transforms) = patterns_and_transforms();
+    let mut map = BTreeMap::default();
+    map.insert(
+        "".into(),
+        Arch {
+            patterns: super::a6502::add_patterns_default(patterns),
+            transforms,
+            addr_type: DataType::U16,
+            node_map: BTreeMap::from([
+                (
+                    ValueTypeFmt::LowerHex(0).pre().into(),
+                    Node::new("0x".into()),
+                ),
+                (
+                    ValueTypeFmt::UpperHex(0).pre().into(),
+                    Node::new("0x".into()),
+                ),
+            ]),
+            // we can unwrap this because the 6502 is guaranteed to have an empty
+            // arch key!
+            ..super::a6502::ARCH.archs.get("").unwrap().to_owned()
+        },
+    );
+    map
+}
@@ -0,0 +1,524 @@
+use std::collections::BTreeMap;
+
+use crate::core::dasm::{arch::Archs, DataType, ValueTypeFmt};
+
+use super::{
+    Arch, DataOut, Matcher, MatcherList, Node, Pattern, PatternAt, Transform, TransformMap, ValOut,
+    ValueOp,
+};
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// Built-in architecture for the PDP-11
+    pub static ref ARCH: Archs = Archs {archs: archs(), ..Default::default()};
+}
+
+// Instructions are 16 bit little endian words that are best read in octal.
+// An operand is a 6 bit field: 3 bits mode followed by 3 bits register.
+// double operand: booo ssss ssdd dddd (b = byte instruction)
+// single operand: oooo oooo oodd dddd
+// branch:         oooo oooo xxxx xxxx
+// The source field starts at bit 6 and the destination field at bit 0.
+
+const IMPLIED: &str = "pdp11_implied";
+const BRANCH: &str = "pdp11_branch";
+const TRAP: &str = "pdp11_trap";
+const RTS: &str = "pdp11_rts";
+const WORD: &str = "pdp11_word";
+
+const SRC_SHIFT: u32 = 6;
+const DST_SHIFT: u32 = 0;
+
+/// Addressing modes of an operand
+#[derive(Clone, Copy)]
+enum Mode {
+    // rn
+    Reg,
+    // (rn)
+    RegDeferred,
+    // (rn)+
+    AutoInc,
+    // @(rn)+
+    AutoIncDeferred,
+    // -(rn)
+    AutoDec,
+    // @-(rn)
+    AutoDecDeferred,
+    // x(rn)
+    Index,
+    // @x(rn)
+    IndexDeferred,
+    // #n (pc)+
+    Imm,
+    // @#a @(pc)+
+    Absolute,
+    // a x(pc)
+    Relative,
+    // @a @x(pc)
+    RelativeDeferred,
+}
+
+use Mode::*;
+
+// (mode bits, pc only, mode)
+// the pc modes have to be matched before the general modes
+const MODES: [(u8, bool, Mode); 12] = [
+    (0, false, Reg),
+    (1, false, RegDeferred),
+    (2, true, Imm),
+    (2, false, AutoInc),
+    (3, true, Absolute),
+    (3, false, AutoIncDeferred),
+    (4, false, AutoDec),
+    (5, false, AutoDecDeferred),
+    (6, true, Relative),
+    (6, false, Index),
+    (7, true, RelativeDeferred),
+    (7, false, IndexDeferred),
+];
+
+impl Mode {
+    fn key(&self) -> &'static str {
+        match self {
+            Reg => "reg",
+            RegDeferred => "reg_deferred",
+            AutoInc => "autoinc",
+            AutoIncDeferred => "autoinc_deferred",
+            AutoDec => "autodec",
+            AutoDecDeferred => "autodec_deferred",
+            Index => "index",
+            IndexDeferred => "index_deferred",
+            Imm => "imm",
+            Absolute => "absolute",
+            Relative => "relative",
+            RelativeDeferred => "relative_deferred",
+        }
+    }
+
+    // true if the mode reads an extension word
+    fn has_ext(&self) -> bool {
+        matches!(
+            self,
+            Index | IndexDeferred | Imm | Absolute | Relative | RelativeDeferred
+        )
+    }
+
+    // shift is the position of the operand field in the opcode
+    // ext_at is the offset of the extension word
+    fn transforms(&self, shift: u32, ext_at: usize) -> Vec<Transform> {
+        let reg = || vec![Transform::Static(Node::new("r".into())), field(shift, 0x07)];
+        let with = |pre: &str, mut middle: Vec<Transform>, post: &str| {
+            let mut list = vec![Transform::Static(Node::new(pre.into()))];
+            list.append(&mut middle);
+            list.push(Transform::Static(Node::new(post.into())));
+            list
+        };
+        let index = |pre: &str| {
+            let mut list = vec![Transform::Static(Node::new(pre.into())), word(ext_at)];
+            list.append(&mut with("(", reg(), ")"));
+            list
+        };
+        // the pc points after the extension word
+        let relative = |pre: &str| {
+            vec![
+                Transform::Static(Node::new(pre.into())),
+                Transform::OffsetAddress(ext_at as i64 + 2),
+                Transform::Val(ValOut {
+                    offset: ext_at,
+                    fmt: ValueTypeFmt::Octal(6),
+                    data_type: DataType::U16,
+                    rel: true,
+                    peek: true,
+                    ..Default::default()
+                }),
+                Transform::OffsetAddress(-(ext_at as i64 + 2)),
+            ]
+        };
+        match self {
+            Reg => reg(),
+            RegDeferred => with("(", reg(), ")"),
+            AutoInc => with("(", reg(), ")+"),
+            AutoIncDeferred => with("@(", reg(), ")+"),
+            AutoDec => with("-(", reg(), ")"),
+            AutoDecDeferred => with("@-(", reg(), ")"),
+            Index => index(""),
+            IndexDeferred => index("@"),
+            Imm => vec![Transform::Static(Node::new("#".into())), word(ext_at)],
+            Absolute => vec![Transform::Static(Node::new("@#".into())), word(ext_at)],
+            Relative => relative(""),
+            RelativeDeferred => relative("@"),
+        }
+    }
+}
+
+// reads a field of the opcode word
+fn field(shift: u32, mask: i64) -> Transform {
+    Transform::Val(ValOut {
+        offset: 0,
+        fmt: ValueTypeFmt::Octal(0),
+        data_type: DataType::U16,
+        ops: vec![ValueOp::Shr(shift), ValueOp::And(mask)],
+        peek: true,
+        no_sym: true,
+        ..Default::default()
+    })
+}
+
+fn word(offset: usize) -> Transform {
+    Transform::Val(ValOut {
+        offset,
+        fmt: ValueTypeFmt::Octal(6),
+        data_type: DataType::U16,
+        peek: true,
+        ..Default::default()
+    })
+}
+
+// matches the bits of the opcode word selected by mask
+fn word_patterns(mask: u16, value: u16) -> Vec<PatternAt> {
+    let [mask_lo, mask_hi] = mask.to_le_bytes();
+    let [value_lo, value_hi] = value.to_le_bytes();
+    vec![
+        PatternAt::new(Pattern::Mask(mask_lo, value_lo), 0),
+        PatternAt::new(Pattern::Mask(mask_hi, value_hi), 1),
+    ]
+}
+
+// matches the mode of an operand field
+fn mode_patterns(shift: u32, mode: u8, pc: bool) -> Vec<PatternAt> {
+    let (mask, value) = if pc {
+        (0o77, (mode << 3 | 0o7) as u16)
+    } else {
+        (0o70, (mode << 3) as u16)
+    };
+    word_patterns(mask << shift, value << shift)
+}
+
+fn push_matcher(
+    matchers: &mut MatcherList,
+    mut patterns: Vec<PatternAt>,
+    len: usize,
+    name: &str,
+    key: &str,
+) {
+    patterns.push(PatternAt::new(Pattern::Any, len - 1));
+    matchers.push(Matcher {
+        patterns,
+        transforms: key.into(),
+        name: Node::new(name.into()),
+        cycles: None,
+    });
+}
+
+fn transform_list(operands: Vec<Vec<Transform>>, len: usize) -> Vec<Transform> {
+    let mut list = vec![Transform::MatcherName];
+    for (i, mut operand) in operands.into_iter().enumerate() {
+        let sep = if i == 0 { " " } else { ", " };
+        list.push(Transform::Static(Node::new(sep.into())));
+        list.append(&mut operand);
+    }
+    list.push(Transform::Consume(len));
+    list
+}
+
+fn add_double_operand(
+    matchers: &mut MatcherList,
+    transforms: &mut TransformMap,
+    op: u16,
+    name: &str,
+) {
+    for (src_bits, src_pc, src) in MODES {
+        for (dst_bits, dst_pc, dst) in MODES {
+            let src_ext = if src.has_ext() { 2 } else { 0 };
+            let dst_ext = if dst.has_ext() { 2 } else { 0 };
+            let len = 2 + src_ext + dst_ext;
+
+            let key = format!("pdp11_{}_{}", src.key(), dst.key());
+            if !transforms.contains_key(&key) {
+                let list = transform_list(
+                    vec![
+                        src.transforms(SRC_SHIFT, 2),
+                        dst.transforms(DST_SHIFT, 2 + src_ext),
+                    ],
+                    len,
+                );
+                transforms.insert(key.clone(), list);
+            }
+
+            let mut patterns = word_patterns(0o170000, op);
+            patterns.append(&mut mode_patterns(SRC_SHIFT, src_bits, src_pc));
+            patterns.append(&mut mode_patterns(DST_SHIFT, dst_bits, dst_pc));
+            push_matcher(matchers, patterns, len, name, &key);
+        }
+    }
+}
+
+// an operand in the lower 6 bits and optionally a register
+// in bits 6-8 before (reg_first) or after the operand
+fn add_single_operand(
+    matchers: &mut MatcherList,
+    transforms: &mut TransformMap,
+    op: u16,
+    mask: u16,
+    name: &str,
+    reg: Option<bool>,
+) {
+    for (bits, pc, dst) in MODES {
+        let len = if dst.has_ext() { 4 } else { 2 };
+
+        let reg_key = match reg {
+            Some(true) => "reg_",
+            Some(false) => "_reg",
+            None => "",
+        };
+        let key = format!("pdp11_single{reg_key}{}", dst.key());
+        if !transforms.contains_key(&key) {
+            let reg_operand = vec![
+                Transform::Static(Node::new("r".into())),
+                field(SRC_SHIFT, 0x07),
+            ];
+            let operands = match reg {
+                Some(true) => vec![reg_operand, dst.transforms(DST_SHIFT, 2)],
+                Some(false) => vec![dst.transforms(DST_SHIFT, 2), reg_operand],
+                None => vec![dst.transforms(DST_SHIFT, 2)],
+            };
+            transforms.insert(key.clone(), transform_list(operands, len));
+        }
+
+        let mut patterns = word_patterns(mask, op);
+        patterns.append(&mut mode_patterns(DST_SHIFT, bits, pc));
+        push_matcher(matchers, patterns, len, name, &key);
+    }
+}
+
+fn patterns_and_transforms() -> (MatcherList, TransformMap) {
+    let mut matchers = vec![];
+    let mut transforms = BTreeMap::default();
+    super::a6502::transforms_common(&mut transforms);
+
+    transforms.insert(
+        IMPLIED.into(),
+        vec![Transform::MatcherName, Transform::Consume(2)],
+    );
+    // the offset is a signed word count relative to the next instruction
+    transforms.insert(
+        BRANCH.into(),
+        vec![
+            Transform::MatcherName,
+            Transform::Static(Node::new(" ".into())),
+            Transform::OffsetAddress(2),
+            Transform::Val(ValOut {
+                offset: 0,
+                fmt: ValueTypeFmt::Octal(6),
+                data_type: DataType::U8,
+                ops: vec![
+                    ValueOp::SignExtend(8),
+                    ValueOp::Shl(1),
+                    ValueOp::And(0xFFFF),
+                ],
+                rel: true,
+                peek: true,
+                ..Default::default()
+            }),
+            Transform::OffsetAddress(-2),
+            Transform::Consume(2),
+        ],
+    );
+    transforms.insert(
+        TRAP.into(),
+        vec![
+            Transform::MatcherName,
+            Transform::Static(Node::new(" ".into())),
+            Transform::Val(ValOut {
+                offset: 0,
+                fmt: ValueTypeFmt::Octal(0),
+                data_type: DataType::U8,
+                peek: true,
+                no_sym: true,
+                ..Default::default()
+            }),
+            Transform::Consume(2),
+        ],
+    );
+    transforms.insert(
+        RTS.into(),
+        transform_list(
+            vec![vec![
+                Transform::Static(Node::new("r".into())),
+                field(DST_SHIFT, 0x07),
+            ]],
+            2,
+        ),
+    );
+    transforms.insert(
+        WORD.into(),
+        vec![
+            Transform::MatcherName,
+            Transform::Static(Node::new(" ".into())),
+            Transform::Data(DataOut::new(ValueTypeFmt::Octal(6), DataType::U16)),
+        ],
+    );
+
+    for (op, name) in [
+        (0o000000, "halt"),
+        (0o000001, "wait"),
+        (0o000002, "rti"),
+        (0o000003, "bpt"),
+        (0o000004, "iot"),
+        (0o000005, "reset"),
+        (0o000006, "rtt"),
+        (0o000240, "nop"),
+        (0o000241, "clc"),
+        (0o000242, "clv"),
+        (0o000244, "clz"),
+        (0o000250, "cln"),
+        (0o000257, "ccc"),
+        (0o000261, "sec"),
+        (0o000262, "sev"),
+        (0o000264, "sez"),
+        (0o000270, "sen"),
+        (0o000277, "scc"),
+    ] {
+        push_matcher(&mut matchers, word_patterns(0xFFFF, op), 2, name, IMPLIED);
+    }
+    push_matcher(
+        &mut matchers,
+        word_patterns(0o177770, 0o000200),
+        2,
+        "rts",
+        RTS,
+    );
+
+    for (op, name) in [
+        (0o000400, "br"),
+        (0o001000, "bne"),
+        (0o001400, "beq"),
+        (0o002000, "bge"),
+        (0o002400, "blt"),
+        (0o003000, "bgt"),
+        (0o003400, "ble"),
+        (0o100000, "bpl"),
+        (0o100400, "bmi"),
+        (0o101000, "bhi"),
+        (0o101400, "blos"),
+        (0o102000, "bvc"),
+        (0o102400, "bvs"),
+        (0o103000, "bcc"),
+        (0o103400, "bcs"),
+    ] {
+        push_matcher(&mut matchers, word_patterns(0xFF00, op), 2, name, BRANCH);
+    }
+    push_matcher(
+        &mut matchers,
+        word_patterns(0xFF00, 0o104000),
+        2,
+        "emt",
+        TRAP,
+    );
+    push_matcher(
+        &mut matchers,
+        word_patterns(0xFF00, 0o104400),
+        2,
+        "trap",
+        TRAP,
+    );
+
+    for (op, name) in [
+        (0o000100, "jmp"),
+        (0o000300, "swab"),
+        (0o005000, "clr"),
+        (0o005100, "com"),
+        (0o005200, "inc"),
+        (0o005300, "dec"),
+        (0o005400, "neg"),
+        (0o005500, "adc"),
+        (0o005600, "sbc"),
+        (0o005700, "tst"),
+        (0o006000, "ror"),
+        (0o006100, "rol"),
+        (0o006200, "asr"),
+        (0o006300, "asl"),
+        (0o006700, "sxt"),
+        (0o105000, "clrb"),
+        (0o105100, "comb"),
+        (0o105200, "incb"),
+        (0o105300, "decb"),
+        (0o105400, "negb"),
+        (0o105500, "adcb"),
+        (0o105600, "sbcb"),
+        (0o105700, "tstb"),
+        (0o106000, "rorb"),
+        (0o106100, "rolb"),
+        (0o106200, "asrb"),
+        (0o106300, "aslb"),
+    ] {
+        add_single_operand(&mut matchers, &mut transforms, op, 0o177700, name, None);
+    }
+
+    // register followed by an operand
+    for (op, name) in [(0o004000, "jsr"), (0o074000, "xor")] {
+        add_single_operand(
+            &mut matchers,
+            &mut transforms,
+            op,
+            0o177000,
+            name,
+            Some(true),
+        );
+    }
+    // operand followed by a register
+    for (op, name) in [
+        (0o070000, "mul"),
+        (0o071000, "div"),
+        (0o072000, "ash"),
+        (0o073000, "ashc"),
+    ] {
+        add_single_operand(
+            &mut matchers,
+            &mut transforms,
+            op,
+            0o177000,
+            name,
+            Some(false),
+        );
+    }
+
+    for (op, name) in [
+        (0o010000, "mov"),
+        (0o020000, "cmp"),
+        (0o030000, "bit"),
+        (0o040000, "bic"),
+        (0o050000, "bis"),
+        (0o060000, "add"),
+        (0o110000, "movb"),
+        (0o120000, "cmpb"),
+        (0o130000, "bitb"),
+        (0o140000, "bicb"),
+        (0o150000, "bisb"),
+        (0o160000, "sub"),
+    ] {
+        add_double_operand(&mut matchers, &mut transforms, op, name);
+    }
+
+    // unknown words are output as data to stay word aligned
+    push_matcher(&mut matchers, vec![], 2, ".word", WORD);
+
+    (matchers, transforms)
+}
+
+fn archs() -> BTreeMap<String, Arch> {
+    let (patterns, transforms) = patterns_and_transforms();
+    let mut map = BTreeMap::default();
+    map.insert(
+        "".into(),
+        Arch {
+            patterns: super::a6502::add_patterns_default(patterns),
+            transforms,
+            addr_type: DataType::U16,
+            // we can unwrap this because the 6502 is guaranteed to have an empty
+            // arch key!
+            ..super::a6502::ARCH.archs.get("").unwrap().to_owned()
+        },
+    );
+    map
+}
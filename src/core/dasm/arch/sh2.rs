@@ -0,0 +1,461 @@
+use std::collections::BTreeMap;
+
+use crate::core::dasm::{arch::Archs, DataType, ValueTypeFmt};
+
+use super::{
+    Arch, DataOut, Endianess, LiteralOut, Matcher, MatcherList, Node, Pattern, PatternAt,
+    Transform, TransformMap, ValOut, ValueOp,
+};
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// Built-in architecture for the Hitachi SH-2
+    pub static ref ARCH: Archs = Archs {archs: archs(), ..Default::default()};
+}
+
+// Every instruction is a single big endian word.
+// The nibbles are named as follows:
+// n and m: register r0-r15, i: immediate, d: displacement
+// The pc is 4 bytes ahead of the current instruction.
+
+const IMPLIED: &str = "sh2_implied";
+const N: &str = "sh2_n";
+const AT_N: &str = "sh2_at_n";
+const M_N: &str = "sh2_m_n";
+const IMM_N: &str = "sh2_imm_n";
+const IMM_R0: &str = "sh2_imm_r0";
+const IMM: &str = "sh2_imm";
+const M_AT_N: &str = "sh2_m_at_n";
+const AT_M_N: &str = "sh2_at_m_n";
+const M_AT_DEC_N: &str = "sh2_m_at_dec_n";
+const AT_M_INC_N: &str = "sh2_at_m_inc_n";
+const M_AT_DISP_N: &str = "sh2_m_at_disp_n";
+const AT_DISP_M_N: &str = "sh2_at_disp_m_n";
+const MOVW_PC: &str = "sh2_movw_pc";
+const MOVL_PC: &str = "sh2_movl_pc";
+const MOVA: &str = "sh2_mova";
+const BRANCH8: &str = "sh2_branch8";
+const BRANCH12: &str = "sh2_branch12";
+const WORD: &str = "sh2_word";
+
+fn static_node(s: &str) -> Transform {
+    Transform::Static(Node::new(s.into()))
+}
+
+fn field(offset: usize, ops: Vec<ValueOp>) -> Transform {
+    Transform::Val(ValOut {
+        offset,
+        fmt: ValueTypeFmt::Decimal(0),
+        data_type: DataType::U8,
+        ops,
+        peek: true,
+        no_sym: true,
+        ..Default::default()
+    })
+}
+
+fn reg_n() -> Vec<Transform> {
+    vec![static_node("r"), field(0, vec![ValueOp::And(0x0F)])]
+}
+
+fn reg_m() -> Vec<Transform> {
+    vec![static_node("r"), field(1, vec![ValueOp::Shr(4)])]
+}
+
+fn signed_imm() -> Vec<Transform> {
+    vec![static_node("#"), field(1, vec![ValueOp::SignExtend(8)])]
+}
+
+fn unsigned_imm() -> Vec<Transform> {
+    vec![
+        static_node("#"),
+        Transform::Val(ValOut {
+            offset: 1,
+            fmt: ValueTypeFmt::LowerHex(2),
+            data_type: DataType::U8,
+            peek: true,
+            no_sym: true,
+            ..Default::default()
+        }),
+    ]
+}
+
+// the displacement in the lower bits scaled by the access size
+fn disp(mask: i64, scale: u32) -> Transform {
+    Transform::Val(ValOut {
+        offset: 1,
+        fmt: ValueTypeFmt::LowerHex(2),
+        data_type: DataType::U8,
+        ops: vec![ValueOp::And(mask), ValueOp::Shl(scale)],
+        peek: true,
+        no_sym: true,
+        ..Default::default()
+    })
+}
+
+// pc relative load, the r0 variant is used by mova
+fn pc_relative(
+    scale: u32,
+    align: u64,
+    literal: Option<DataType>,
+    reg: Vec<Transform>,
+) -> Vec<Transform> {
+    let mut list = vec![
+        Transform::MatcherName,
+        static_node(" @("),
+        disp(0xFF, scale),
+        static_node(", pc), "),
+    ];
+    list.extend(reg);
+    if let Some(data_type) = literal {
+        list.push(Transform::Literal(LiteralOut {
+            disp: ValOut {
+                offset: 1,
+                data_type: DataType::U8,
+                ops: vec![ValueOp::Shl(scale)],
+                ..Default::default()
+            },
+            pc_offset: 4,
+            align,
+            fmt: ValueTypeFmt::LowerHex(data_type.data_len() * 2),
+            data_type,
+        }));
+    }
+    list.push(Transform::Consume(2));
+    list
+}
+
+fn branch(offset: usize, data_type: DataType, ops: Vec<ValueOp>) -> Vec<Transform> {
+    vec![
+        Transform::MatcherName,
+        static_node(" "),
+        Transform::OffsetAddress(4),
+        Transform::Val(ValOut {
+            offset,
+            fmt: ValueTypeFmt::LowerHex(8),
+            data_type,
+            ops,
+            rel: true,
+            peek: true,
+            ..Default::default()
+        }),
+        Transform::OffsetAddress(-4),
+        Transform::Consume(2),
+    ]
+}
+
+// joins operands into a transform list
+fn operands(list: Vec<Vec<Transform>>) -> Vec<Transform> {
+    let mut result = vec![Transform::MatcherName];
+    for (i, mut operand) in list.into_iter().enumerate() {
+        result.push(static_node(if i == 0 { " " } else { ", " }));
+        result.append(&mut operand);
+    }
+    result.push(Transform::Consume(2));
+    result
+}
+
+fn with(pre: &str, mut middle: Vec<Transform>, post: &str) -> Vec<Transform> {
+    let mut list = vec![static_node(pre)];
+    list.append(&mut middle);
+    list.push(static_node(post));
+    list
+}
+
+fn transforms() -> TransformMap {
+    let mut map = BTreeMap::default();
+    super::a6502::transforms_common(&mut map);
+
+    map.insert(IMPLIED.into(), operands(vec![]));
+    map.insert(N.into(), operands(vec![reg_n()]));
+    map.insert(AT_N.into(), operands(vec![with("@", reg_n(), "")]));
+    map.insert(M_N.into(), operands(vec![reg_m(), reg_n()]));
+    map.insert(IMM_N.into(), operands(vec![signed_imm(), reg_n()]));
+    map.insert(
+        IMM_R0.into(),
+        operands(vec![unsigned_imm(), vec![static_node("r0")]]),
+    );
+    map.insert(IMM.into(), operands(vec![unsigned_imm()]));
+    map.insert(
+        M_AT_N.into(),
+        operands(vec![reg_m(), with("@", reg_n(), "")]),
+    );
+    map.insert(
+        AT_M_N.into(),
+        operands(vec![with("@", reg_m(), ""), reg_n()]),
+    );
+    map.insert(
+        M_AT_DEC_N.into(),
+        operands(vec![reg_m(), with("@-", reg_n(), "")]),
+    );
+    map.insert(
+        AT_M_INC_N.into(),
+        operands(vec![with("@", reg_m(), "+"), reg_n()]),
+    );
+
+    let mut at_disp_n = vec![static_node("@("), disp(0x0F, 2), static_node(", ")];
+    at_disp_n.append(&mut with("", reg_n(), ")"));
+    map.insert(M_AT_DISP_N.into(), operands(vec![reg_m(), at_disp_n]));
+
+    // the mov.l @(disp, rm), rn form shares the nibble layout
+    let mut at_disp_m = vec![static_node("@("), disp(0x0F, 2), static_node(", ")];
+    at_disp_m.append(&mut with("", reg_m(), ")"));
+    map.insert(AT_DISP_M_N.into(), operands(vec![at_disp_m, reg_n()]));
+
+    map.insert(
+        MOVW_PC.into(),
+        pc_relative(1, 1, Some(DataType::U16), reg_n()),
+    );
+    map.insert(
+        MOVL_PC.into(),
+        pc_relative(2, 4, Some(DataType::U32), reg_n()),
+    );
+    map.insert(
+        MOVA.into(),
+        pc_relative(2, 4, None, vec![static_node("r0")]),
+    );
+
+    map.insert(
+        BRANCH8.into(),
+        branch(
+            1,
+            DataType::U8,
+            vec![
+                ValueOp::SignExtend(8),
+                ValueOp::Shl(1),
+                ValueOp::And(0xFFFFFFFF),
+            ],
+        ),
+    );
+    map.insert(
+        BRANCH12.into(),
+        branch(
+            0,
+            DataType::U16,
+            vec![
+                ValueOp::And(0x0FFF),
+                ValueOp::SignExtend(12),
+                ValueOp::Shl(1),
+                ValueOp::And(0xFFFFFFFF),
+            ],
+        ),
+    );
+
+    // control and system registers are fixed operands
+    for (i, (_, _, _, pre, post)) in system_instructions().into_iter().enumerate() {
+        map.insert(system_key(i), operands(vec![with(pre, reg_n(), post)]));
+    }
+
+    map.insert(
+        WORD.into(),
+        vec![
+            Transform::MatcherName,
+            static_node(" "),
+            Transform::Data(DataOut::new(ValueTypeFmt::LowerHex(4), DataType::U16)),
+        ],
+    );
+
+    map
+}
+
+// (second byte, upper nibble of the first byte, name, text before the register, text after the register)
+fn system_instructions() -> Vec<(u8, u8, &'static str, &'static str, &'static str)> {
+    vec![
+        (0x02, 0x0, "stc", "sr, ", ""),
+        (0x12, 0x0, "stc", "gbr, ", ""),
+        (0x22, 0x0, "stc", "vbr, ", ""),
+        (0x0A, 0x0, "sts", "mach, ", ""),
+        (0x1A, 0x0, "sts", "macl, ", ""),
+        (0x2A, 0x0, "sts", "pr, ", ""),
+        (0x0E, 0x4, "ldc", "", ", sr"),
+        (0x1E, 0x4, "ldc", "", ", gbr"),
+        (0x2E, 0x4, "ldc", "", ", vbr"),
+        (0x0A, 0x4, "lds", "", ", mach"),
+        (0x1A, 0x4, "lds", "", ", macl"),
+        (0x2A, 0x4, "lds", "", ", pr"),
+        (0x22, 0x4, "sts.l", "pr, @-", ""),
+        (0x26, 0x4, "lds.l", "@", "+, pr"),
+    ]
+}
+
+fn system_key(i: usize) -> String {
+    format!("sh2_system_{i}")
+}
+
+// matches the bits of the opcode word selected by mask
+fn matcher(matchers: &mut MatcherList, mask: u16, value: u16, name: &str, mode: &str) {
+    let [mask_hi, mask_lo] = mask.to_be_bytes();
+    let [value_hi, value_lo] = value.to_be_bytes();
+    matchers.push(Matcher {
+        patterns: vec![
+            PatternAt::new(Pattern::Mask(mask_hi, value_hi), 0),
+            PatternAt::new(Pattern::Mask(mask_lo, value_lo), 1),
+        ],
+        transforms: mode.into(),
+        name: Node::new(name.into()),
+        cycles: None,
+    });
+}
+
+fn patterns() -> MatcherList {
+    let mut matchers = vec![];
+
+    for (op, name) in [
+        (0x0008, "clrt"),
+        (0x0009, "nop"),
+        (0x000B, "rts"),
+        (0x0018, "sett"),
+        (0x0019, "div0u"),
+        (0x001B, "sleep"),
+        (0x0028, "clrmac"),
+        (0x002B, "rte"),
+    ] {
+        matcher(&mut matchers, 0xFFFF, op, name, IMPLIED);
+    }
+
+    for (i, (second, nibble, name, _, _)) in system_instructions().into_iter().enumerate() {
+        matcher(
+            &mut matchers,
+            0xF0FF,
+            (nibble as u16) << 12 | second as u16,
+            name,
+            &system_key(i),
+        );
+    }
+
+    // 0000nnnn----xxxx
+    for (op, name, mode) in [
+        (0x0003, "bsrf", N),
+        (0x0023, "braf", N),
+        (0x0029, "movt", N),
+        (0x400B, "jsr", AT_N),
+        (0x402B, "jmp", AT_N),
+        (0x4000, "shll", N),
+        (0x4001, "shlr", N),
+        (0x4004, "rotl", N),
+        (0x4005, "rotr", N),
+        (0x4008, "shll2", N),
+        (0x4009, "shlr2", N),
+        (0x4010, "dt", N),
+        (0x4011, "cmp/pz", N),
+        (0x4015, "cmp/pl", N),
+        (0x4018, "shll8", N),
+        (0x4019, "shlr8", N),
+        (0x4020, "shal", N),
+        (0x4021, "shar", N),
+        (0x4024, "rotcl", N),
+        (0x4025, "rotcr", N),
+        (0x4028, "shll16", N),
+        (0x4029, "shlr16", N),
+    ] {
+        matcher(&mut matchers, 0xF0FF, op, name, mode);
+    }
+
+    // nnnnmmmm in the middle of the word
+    for (op, name, mode) in [
+        (0x0007, "mul.l", M_N),
+        (0x2000, "mov.b", M_AT_N),
+        (0x2001, "mov.w", M_AT_N),
+        (0x2002, "mov.l", M_AT_N),
+        (0x2004, "mov.b", M_AT_DEC_N),
+        (0x2005, "mov.w", M_AT_DEC_N),
+        (0x2006, "mov.l", M_AT_DEC_N),
+        (0x2007, "div0s", M_N),
+        (0x2008, "tst", M_N),
+        (0x2009, "and", M_N),
+        (0x200A, "xor", M_N),
+        (0x200B, "or", M_N),
+        (0x200C, "cmp/str", M_N),
+        (0x200D, "xtrct", M_N),
+        (0x200E, "mulu.w", M_N),
+        (0x200F, "muls.w", M_N),
+        (0x3000, "cmp/eq", M_N),
+        (0x3002, "cmp/hs", M_N),
+        (0x3003, "cmp/ge", M_N),
+        (0x3004, "div1", M_N),
+        (0x3005, "dmulu.l", M_N),
+        (0x3006, "cmp/hi", M_N),
+        (0x3007, "cmp/gt", M_N),
+        (0x3008, "sub", M_N),
+        (0x300A, "subc", M_N),
+        (0x300B, "subv", M_N),
+        (0x300C, "add", M_N),
+        (0x300D, "dmuls.l", M_N),
+        (0x300E, "addc", M_N),
+        (0x300F, "addv", M_N),
+        (0x6000, "mov.b", AT_M_N),
+        (0x6001, "mov.w", AT_M_N),
+        (0x6002, "mov.l", AT_M_N),
+        (0x6003, "mov", M_N),
+        (0x6004, "mov.b", AT_M_INC_N),
+        (0x6005, "mov.w", AT_M_INC_N),
+        (0x6006, "mov.l", AT_M_INC_N),
+        (0x6007, "not", M_N),
+        (0x6008, "swap.b", M_N),
+        (0x6009, "swap.w", M_N),
+        (0x600A, "negc", M_N),
+        (0x600B, "neg", M_N),
+        (0x600C, "extu.b", M_N),
+        (0x600D, "extu.w", M_N),
+        (0x600E, "exts.b", M_N),
+        (0x600F, "exts.w", M_N),
+    ] {
+        matcher(&mut matchers, 0xF00F, op, name, mode);
+    }
+
+    // the lower byte is an immediate, a displacement or a register pair
+    for (mask, op, name, mode) in [
+        (0xF000, 0x1000, "mov.l", M_AT_DISP_N),
+        (0xF000, 0x5000, "mov.l", AT_DISP_M_N),
+        (0xF000, 0x7000, "add", IMM_N),
+        (0xFF00, 0x8800, "cmp/eq", IMM_R0),
+        (0xFF00, 0x8900, "bt", BRANCH8),
+        (0xFF00, 0x8B00, "bf", BRANCH8),
+        (0xFF00, 0x8D00, "bt/s", BRANCH8),
+        (0xFF00, 0x8F00, "bf/s", BRANCH8),
+        (0xF000, 0x9000, "mov.w", MOVW_PC),
+        (0xF000, 0xA000, "bra", BRANCH12),
+        (0xF000, 0xB000, "bsr", BRANCH12),
+        (0xFF00, 0xC300, "trapa", IMM),
+        (0xFF00, 0xC700, "mova", MOVA),
+        (0xFF00, 0xC800, "tst", IMM_R0),
+        (0xFF00, 0xC900, "and", IMM_R0),
+        (0xFF00, 0xCA00, "xor", IMM_R0),
+        (0xFF00, 0xCB00, "or", IMM_R0),
+        (0xF000, 0xD000, "mov.l", MOVL_PC),
+        (0xF000, 0xE000, "mov", IMM_N),
+    ] {
+        matcher(&mut matchers, mask, op, name, mode);
+    }
+
+    // unknown words are output as data to stay word aligned
+    matchers.push(Matcher {
+        patterns: vec![PatternAt::new(Pattern::Any, 1)],
+        transforms: WORD.into(),
+        name: Node::new(".word".into()),
+        cycles: None,
+    });
+
+    matchers
+}
+
+fn archs() -> BTreeMap<String, Arch> {
+    let mut map = BTreeMap::default();
+    map.insert(
+        "".into(),
+        Arch {
+            patterns: super::a6502::add_patterns_default(patterns()),
+            transforms: transforms(),
+            endianess: Endianess::Big,
+            addr_type: DataType::U32,
+            node_map: BTreeMap::from([(
+                ValueTypeFmt::LowerHex(0).pre().into(),
+                Node::new("0x".into()),
+            )]),
+            // we can unwrap this because the 6502 is guaranteed to have an empty
+            // arch key!
+            ..super::a6502::ARCH.archs.get("").unwrap().to_owned()
+        },
+    );
+    map
+}
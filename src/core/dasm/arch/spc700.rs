@@ -0,0 +1,475 @@
+use std::collections::BTreeMap;
+
+use crate::core::dasm::{arch::Archs, DataType, ValueTypeFmt};
+
+use super::{
+    Arch, Endianess, Matcher, MatcherList, Node, Pattern, PatternAt, Transform, TransformMap,
+    ValOut, ValueOp,
+};
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// Built-in architecture for the Sony SPC700 (the SNES sound cpu)
+    pub static ref ARCH: Archs = Archs {archs: archs(), ..Default::default()};
+}
+
+/// Arch key that outputs every byte as data, e.g. for the dsp registers
+pub const DATA: &str = "data";
+
+/// Operands of an instruction
+/// the usize is the offset of the operand byte in the instruction
+#[derive(Clone, Copy)]
+enum Operand {
+    Static(&'static str),
+    Imm(usize),
+    Dp(usize),
+    DpX(usize),
+    DpY(usize),
+    Abs(usize),
+    AbsX(usize),
+    AbsY(usize),
+    // [dp+x]
+    IndX(usize),
+    // [dp]+y
+    IndY(usize),
+    // [abs+x], only used by jmp
+    AbsIndX(usize),
+    Rel(usize),
+    // dp.bit, the bit is stored in the upper 3 bits of the opcode
+    DpBit(usize),
+    // abs.bit, a 13 bit address and the bit in the upper 3 bits
+    MemBit(usize),
+    NotMemBit(usize),
+    // tcall n, n is stored in the upper nibble of the opcode
+    Table,
+    // pcall, calls into the upper page at ff00
+    UPage(usize),
+}
+
+use Operand::*;
+
+impl Operand {
+    fn key(&self) -> String {
+        match self {
+            Static(s) => s.replace('(', "at_").replace(')', "").replace('+', "_inc"),
+            Imm(o) => format!("imm{o}"),
+            Dp(o) => format!("dp{o}"),
+            DpX(o) => format!("dp_x{o}"),
+            DpY(o) => format!("dp_y{o}"),
+            Abs(o) => format!("abs{o}"),
+            AbsX(o) => format!("abs_x{o}"),
+            AbsY(o) => format!("abs_y{o}"),
+            IndX(o) => format!("ind_x{o}"),
+            IndY(o) => format!("ind_y{o}"),
+            AbsIndX(o) => format!("abs_ind_x{o}"),
+            Rel(o) => format!("rel{o}"),
+            DpBit(o) => format!("dp_bit{o}"),
+            MemBit(o) => format!("mem_bit{o}"),
+            NotMemBit(o) => format!("not_mem_bit{o}"),
+            Table => "table".into(),
+            UPage(o) => format!("upage{o}"),
+        }
+    }
+
+    // the offset and size of the bytes this operand reads
+    fn bytes(&self) -> Option<(usize, usize)> {
+        match self {
+            Imm(o) | Dp(o) | DpX(o) | DpY(o) | IndX(o) | IndY(o) | Rel(o) | DpBit(o) | UPage(o) => {
+                Some((*o, 1))
+            }
+            Abs(o) | AbsX(o) | AbsY(o) | AbsIndX(o) | MemBit(o) | NotMemBit(o) => Some((*o, 2)),
+            _ => None,
+        }
+    }
+
+    fn transforms(&self, len: usize) -> Vec<Transform> {
+        let stat = |s: &str| Transform::Static(Node::new(s.into()));
+        match self {
+            Static(s) => vec![stat(s)],
+            Imm(o) => vec![stat("#"), value(*o, DataType::U8, 2, vec![])],
+            Dp(o) => vec![value(*o, DataType::U8, 2, vec![])],
+            DpX(o) => vec![value(*o, DataType::U8, 2, vec![]), stat("+x")],
+            DpY(o) => vec![value(*o, DataType::U8, 2, vec![]), stat("+y")],
+            Abs(o) => vec![value(*o, DataType::U16, 4, vec![])],
+            AbsX(o) => vec![value(*o, DataType::U16, 4, vec![]), stat("+x")],
+            AbsY(o) => vec![value(*o, DataType::U16, 4, vec![]), stat("+y")],
+            IndX(o) => vec![stat("["), value(*o, DataType::U8, 2, vec![]), stat("+x]")],
+            IndY(o) => vec![stat("["), value(*o, DataType::U8, 2, vec![]), stat("]+y")],
+            AbsIndX(o) => vec![stat("["), value(*o, DataType::U16, 4, vec![]), stat("+x]")],
+            Rel(o) => vec![
+                Transform::OffsetAddress(len as i64),
+                Transform::Val(ValOut {
+                    offset: *o,
+                    fmt: ValueTypeFmt::LowerHex(2),
+                    data_type: DataType::I8,
+                    rel: true,
+                    peek: true,
+                    ..Default::default()
+                }),
+                Transform::OffsetAddress(-(len as i64)),
+            ],
+            DpBit(o) => vec![
+                value(*o, DataType::U8, 2, vec![]),
+                stat("."),
+                field(0, DataType::U8, vec![ValueOp::Shr(5)]),
+            ],
+            MemBit(o) => mem_bit(*o),
+            NotMemBit(o) => {
+                let mut v = vec![stat("/")];
+                v.append(&mut mem_bit(*o));
+                v
+            }
+            Table => vec![field(0, DataType::U8, vec![ValueOp::Shr(4)])],
+            UPage(o) => vec![value(*o, DataType::U8, 4, vec![ValueOp::Or(0xFF00)])],
+        }
+    }
+}
+
+fn value(offset: usize, data_type: DataType, width: usize, ops: Vec<ValueOp>) -> Transform {
+    Transform::Val(ValOut {
+        offset,
+        fmt: ValueTypeFmt::LowerHex(width),
+        data_type,
+        ops,
+        peek: true,
+        ..Default::default()
+    })
+}
+
+// a decimal number that is never resolved as a symbol
+fn field(offset: usize, data_type: DataType, ops: Vec<ValueOp>) -> Transform {
+    Transform::Val(ValOut {
+        offset,
+        fmt: ValueTypeFmt::Decimal(0),
+        data_type,
+        ops,
+        peek: true,
+        no_sym: true,
+        ..Default::default()
+    })
+}
+
+fn mem_bit(offset: usize) -> Vec<Transform> {
+    vec![
+        value(offset, DataType::U16, 4, vec![ValueOp::And(0x1FFF)]),
+        Transform::Static(Node::new(".".into())),
+        field(offset, DataType::U16, vec![ValueOp::Shr(13)]),
+    ]
+}
+
+fn instruction_len(operands: &[Operand]) -> usize {
+    operands
+        .iter()
+        .filter_map(|x| x.bytes())
+        .map(|(offset, size)| offset + size)
+        .max()
+        .unwrap_or(1)
+}
+
+fn add_instruction(
+    matchers: &mut MatcherList,
+    transforms: &mut TransformMap,
+    op: u8,
+    name: &str,
+    operands: &[Operand],
+) {
+    let len = instruction_len(operands);
+    let keys: Vec<String> = operands.iter().map(|x| x.key()).collect();
+    let key = format!("spc700_{}", keys.join("_"));
+
+    let mut list = vec![Transform::MatcherName];
+    for (i, operand) in operands.iter().enumerate() {
+        let sep = if i == 0 { " " } else { ", " };
+        list.push(Transform::Static(Node::new(sep.into())));
+        list.append(&mut operand.transforms(len));
+    }
+    list.push(Transform::Consume(len));
+    transforms.insert(key.clone(), list);
+
+    let mut patterns = vec![PatternAt::new(Pattern::Exact(op), 0)];
+    if len > 1 {
+        patterns.push(PatternAt::new(Pattern::Any, len - 1));
+    }
+    matchers.push(Matcher {
+        patterns,
+        transforms: key,
+        name: Node::new(name.into()),
+        cycles: None,
+    });
+}
+
+// the alu instructions share the same columns in the rows 0-b:
+// x4: dp, x5: abs, x6: (x), x7: [dp+x], x8: #imm, x9: dp, dp
+// and one row below: x4: dp+x, x5: abs+x, x6: abs+y, x7: [dp]+y, x8: dp, #imm, x9: (x), (y)
+fn add_alu_rows(matchers: &mut MatcherList, transforms: &mut TransformMap, row: u8, name: &str) {
+    let mut add = |op: u8, operands: &[Operand]| {
+        add_instruction(matchers, transforms, row | op, name, operands)
+    };
+    add(0x04, &[A, Dp(1)]);
+    add(0x05, &[A, Abs(1)]);
+    add(0x06, &[A, Static("(x)")]);
+    add(0x07, &[A, IndX(1)]);
+    add(0x08, &[A, Imm(1)]);
+    // the source is encoded before the destination
+    add(0x09, &[Dp(2), Dp(1)]);
+    add(0x14, &[A, DpX(1)]);
+    add(0x15, &[A, AbsX(1)]);
+    add(0x16, &[A, AbsY(1)]);
+    add(0x17, &[A, IndY(1)]);
+    add(0x18, &[Dp(2), Imm(1)]);
+    add(0x19, &[Static("(x)"), Static("(y)")]);
+}
+
+const A: Operand = Static("a");
+const X: Operand = Static("x");
+const Y: Operand = Static("y");
+const YA: Operand = Static("ya");
+const C: Operand = Static("c");
+const SP: Operand = Static("sp");
+const PSW: Operand = Static("psw");
+
+// (op, name, operands)
+type Instruction = (u8, &'static str, &'static [Operand]);
+
+fn instructions() -> Vec<Instruction> {
+    vec![
+        (0x00, "nop", &[]),
+        (0x0A, "or1", &[C, MemBit(1)]),
+        (0x0B, "asl", &[Dp(1)]),
+        (0x0C, "asl", &[Abs(1)]),
+        (0x0D, "push", &[PSW]),
+        (0x0E, "tset1", &[Abs(1)]),
+        (0x0F, "brk", &[]),
+        (0x10, "bpl", &[Rel(1)]),
+        (0x1A, "decw", &[Dp(1)]),
+        (0x1B, "asl", &[DpX(1)]),
+        (0x1C, "asl", &[A]),
+        (0x1D, "dec", &[X]),
+        (0x1E, "cmp", &[X, Abs(1)]),
+        (0x1F, "jmp", &[AbsIndX(1)]),
+        (0x20, "clrp", &[]),
+        (0x2A, "or1", &[C, NotMemBit(1)]),
+        (0x2B, "rol", &[Dp(1)]),
+        (0x2C, "rol", &[Abs(1)]),
+        (0x2D, "push", &[A]),
+        (0x2E, "cbne", &[Dp(1), Rel(2)]),
+        (0x2F, "bra", &[Rel(1)]),
+        (0x30, "bmi", &[Rel(1)]),
+        (0x3A, "incw", &[Dp(1)]),
+        (0x3B, "rol", &[DpX(1)]),
+        (0x3C, "rol", &[A]),
+        (0x3D, "inc", &[X]),
+        (0x3E, "cmp", &[X, Dp(1)]),
+        (0x3F, "call", &[Abs(1)]),
+        (0x40, "setp", &[]),
+        (0x4A, "and1", &[C, MemBit(1)]),
+        (0x4B, "lsr", &[Dp(1)]),
+        (0x4C, "lsr", &[Abs(1)]),
+        (0x4D, "push", &[X]),
+        (0x4E, "tclr1", &[Abs(1)]),
+        (0x4F, "pcall", &[UPage(1)]),
+        (0x50, "bvc", &[Rel(1)]),
+        (0x5A, "cmpw", &[YA, Dp(1)]),
+        (0x5B, "lsr", &[DpX(1)]),
+        (0x5C, "lsr", &[A]),
+        (0x5D, "mov", &[X, A]),
+        (0x5E, "cmp", &[Y, Abs(1)]),
+        (0x5F, "jmp", &[Abs(1)]),
+        (0x60, "clrc", &[]),
+        (0x6A, "and1", &[C, NotMemBit(1)]),
+        (0x6B, "ror", &[Dp(1)]),
+        (0x6C, "ror", &[Abs(1)]),
+        (0x6D, "push", &[Y]),
+        (0x6E, "dbnz", &[Dp(1), Rel(2)]),
+        (0x6F, "ret", &[]),
+        (0x70, "bvs", &[Rel(1)]),
+        (0x7A, "addw", &[YA, Dp(1)]),
+        (0x7B, "ror", &[DpX(1)]),
+        (0x7C, "ror", &[A]),
+        (0x7D, "mov", &[A, X]),
+        (0x7E, "cmp", &[Y, Dp(1)]),
+        (0x7F, "reti", &[]),
+        (0x80, "setc", &[]),
+        (0x8A, "eor1", &[C, MemBit(1)]),
+        (0x8B, "dec", &[Dp(1)]),
+        (0x8C, "dec", &[Abs(1)]),
+        (0x8D, "mov", &[Y, Imm(1)]),
+        (0x8E, "pop", &[PSW]),
+        (0x8F, "mov", &[Dp(2), Imm(1)]),
+        (0x90, "bcc", &[Rel(1)]),
+        (0x9A, "subw", &[YA, Dp(1)]),
+        (0x9B, "dec", &[DpX(1)]),
+        (0x9C, "dec", &[A]),
+        (0x9D, "mov", &[X, SP]),
+        (0x9E, "div", &[YA, X]),
+        (0x9F, "xcn", &[A]),
+        (0xA0, "ei", &[]),
+        (0xAA, "mov1", &[C, MemBit(1)]),
+        (0xAB, "inc", &[Dp(1)]),
+        (0xAC, "inc", &[Abs(1)]),
+        (0xAD, "cmp", &[Y, Imm(1)]),
+        (0xAE, "pop", &[A]),
+        (0xAF, "mov", &[Static("(x)+"), A]),
+        (0xB0, "bcs", &[Rel(1)]),
+        (0xBA, "movw", &[YA, Dp(1)]),
+        (0xBB, "inc", &[DpX(1)]),
+        (0xBC, "inc", &[A]),
+        (0xBD, "mov", &[SP, X]),
+        (0xBE, "das", &[A]),
+        (0xBF, "mov", &[A, Static("(x)+")]),
+        (0xC0, "di", &[]),
+        (0xC4, "mov", &[Dp(1), A]),
+        (0xC5, "mov", &[Abs(1), A]),
+        (0xC6, "mov", &[Static("(x)"), A]),
+        (0xC7, "mov", &[IndX(1), A]),
+        (0xC8, "cmp", &[X, Imm(1)]),
+        (0xC9, "mov", &[Abs(1), X]),
+        (0xCA, "mov1", &[MemBit(1), C]),
+        (0xCB, "mov", &[Dp(1), Y]),
+        (0xCC, "mov", &[Abs(1), Y]),
+        (0xCD, "mov", &[X, Imm(1)]),
+        (0xCE, "pop", &[X]),
+        (0xCF, "mul", &[YA]),
+        (0xD0, "bne", &[Rel(1)]),
+        (0xD4, "mov", &[DpX(1), A]),
+        (0xD5, "mov", &[AbsX(1), A]),
+        (0xD6, "mov", &[AbsY(1), A]),
+        (0xD7, "mov", &[IndY(1), A]),
+        (0xD8, "mov", &[Dp(1), X]),
+        (0xD9, "mov", &[DpY(1), X]),
+        (0xDA, "movw", &[Dp(1), YA]),
+        (0xDB, "mov", &[DpX(1), Y]),
+        (0xDC, "dec", &[Y]),
+        (0xDD, "mov", &[A, Y]),
+        (0xDE, "cbne", &[DpX(1), Rel(2)]),
+        (0xDF, "daa", &[A]),
+        (0xE0, "clrv", &[]),
+        (0xE4, "mov", &[A, Dp(1)]),
+        (0xE5, "mov", &[A, Abs(1)]),
+        (0xE6, "mov", &[A, Static("(x)")]),
+        (0xE7, "mov", &[A, IndX(1)]),
+        (0xE8, "mov", &[A, Imm(1)]),
+        (0xE9, "mov", &[X, Abs(1)]),
+        (0xEA, "not1", &[MemBit(1)]),
+        (0xEB, "mov", &[Y, Dp(1)]),
+        (0xEC, "mov", &[Y, Abs(1)]),
+        (0xED, "notc", &[]),
+        (0xEE, "pop", &[Y]),
+        (0xEF, "sleep", &[]),
+        (0xF0, "beq", &[Rel(1)]),
+        (0xF4, "mov", &[A, DpX(1)]),
+        (0xF5, "mov", &[A, AbsX(1)]),
+        (0xF6, "mov", &[A, AbsY(1)]),
+        (0xF7, "mov", &[A, IndY(1)]),
+        (0xF8, "mov", &[X, Dp(1)]),
+        (0xF9, "mov", &[X, DpY(1)]),
+        // the source is encoded before the destination
+        (0xFA, "mov", &[Dp(2), Dp(1)]),
+        (0xFB, "mov", &[Y, DpX(1)]),
+        (0xFC, "inc", &[Y]),
+        (0xFD, "mov", &[Y, A]),
+        (0xFE, "dbnz", &[Y, Rel(1)]),
+        (0xFF, "stop", &[]),
+    ]
+}
+
+fn patterns_and_transforms() -> (MatcherList, TransformMap) {
+    let mut matchers = vec![];
+    let mut transforms = BTreeMap::default();
+    super::a6502::transforms_common(&mut transforms);
+
+    for (op, name, operands) in instructions() {
+        add_instruction(&mut matchers, &mut transforms, op, name, operands);
+    }
+
+    for row in (0x00..=0xF0).step_by(0x10) {
+        add_instruction(
+            &mut matchers,
+            &mut transforms,
+            row | 0x01,
+            "tcall",
+            &[Table],
+        );
+    }
+    // set1 and clr1 alternate in column 2, bbs and bbc in column 3
+    for row in (0x00..=0xE0).step_by(0x20) {
+        add_instruction(
+            &mut matchers,
+            &mut transforms,
+            row | 0x02,
+            "set1",
+            &[DpBit(1)],
+        );
+        add_instruction(
+            &mut matchers,
+            &mut transforms,
+            row | 0x12,
+            "clr1",
+            &[DpBit(1)],
+        );
+        add_instruction(
+            &mut matchers,
+            &mut transforms,
+            row | 0x03,
+            "bbs",
+            &[DpBit(1), Rel(2)],
+        );
+        add_instruction(
+            &mut matchers,
+            &mut transforms,
+            row | 0x13,
+            "bbc",
+            &[DpBit(1), Rel(2)],
+        );
+    }
+
+    add_alu_rows(&mut matchers, &mut transforms, 0x00, "or");
+    add_alu_rows(&mut matchers, &mut transforms, 0x20, "and");
+    add_alu_rows(&mut matchers, &mut transforms, 0x40, "eor");
+    add_alu_rows(&mut matchers, &mut transforms, 0x60, "cmp");
+    add_alu_rows(&mut matchers, &mut transforms, 0x80, "adc");
+    add_alu_rows(&mut matchers, &mut transforms, 0xA0, "sbc");
+
+    (matchers, transforms)
+}
+
+fn archs() -> BTreeMap<String, Arch> {
+    let (patterns, transforms) = patterns_and_transforms();
+    let mut map = BTreeMap::default();
+    map.insert(
+        "".into(),
+        Arch {
+            patterns: super::a6502::add_patterns_default(patterns),
+            transforms,
+            endianess: Endianess::Little,
+            addr_type: DataType::U16,
+            // we can unwrap this because the 6502 is guaranteed to have an empty
+            // arch key!
+            ..super::a6502::ARCH.archs.get("").unwrap().to_owned()
+        },
+    );
+    // register values are never resolved as symbols
+    let mut data = map.get("").unwrap().to_owned();
+    data.patterns = vec![Matcher {
+        patterns: vec![PatternAt::new(Pattern::Any, 0)],
+        transforms: DATA.into(),
+        name: Node::new(".db".into()),
+        cycles: None,
+    }];
+    data.transforms.insert(
+        DATA.into(),
+        vec![
+            Transform::MatcherName,
+            Transform::Static(Node::new(" ".into())),
+            Transform::Val(ValOut {
+                offset: 0,
+                fmt: ValueTypeFmt::LowerHex(2),
+                data_type: DataType::U8,
+                no_sym: true,
+                ..Default::default()
+            }),
+        ],
+    );
+    map.insert(DATA.into(), data);
+    map
+}
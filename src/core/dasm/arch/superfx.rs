@@ -0,0 +1,480 @@
+use std::collections::BTreeMap;
+
+use crate::core::dasm::{arch::Archs, DataType, ValueTypeFmt};
+
+use super::{
+    Arch, Matcher, MatcherList, Node, Pattern, PatternAt, Transform, TransformMap, ValOut, ValueOp,
+};
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// Built-in architecture for the SuperFX (GSU) co-processor
+    pub static ref ARCH: Archs = Archs {archs: archs(), ..Default::default()};
+}
+
+// the ALT1/ALT2/ALT3 prefixes are mapped onto this ctx flag
+// the value is "1", "2" or "3" depending on the prefix
+pub(super) const FLAG_ALT: &str = "alt";
+const ALT1: &str = "1";
+const ALT2: &str = "2";
+const ALT3: &str = "3";
+
+const IMPLIED: &str = "gsu_implied";
+const RELATIVE: &str = "gsu_relative";
+// to, with and from select registers for the next instruction
+// and do not reset the alt flag
+const REG_PREFIX: &str = "gsu_reg_prefix";
+const REG: &str = "gsu_reg";
+const REG_IND: &str = "gsu_reg_ind";
+const IMM4: &str = "gsu_imm4";
+const IBT: &str = "gsu_ibt";
+const IWT: &str = "gsu_iwt";
+const LMS: &str = "gsu_lms";
+const SMS: &str = "gsu_sms";
+const LM: &str = "gsu_lm";
+const SM: &str = "gsu_sm";
+const ALT1_PREFIX: &str = "gsu_alt1";
+const ALT2_PREFIX: &str = "gsu_alt2";
+const ALT3_PREFIX: &str = "gsu_alt3";
+
+// the register number is encoded in the lower nibble of the opcode
+fn reg_val() -> Transform {
+    Transform::Val(ValOut {
+        offset: 0,
+        fmt: ValueTypeFmt::Decimal(0),
+        data_type: DataType::U8,
+        ops: vec![ValueOp::And(0x0F)],
+        peek: true,
+        no_sym: true,
+        ..Default::default()
+    })
+}
+
+fn insert_transform(map: &mut TransformMap, mode: &str, mut transforms: Vec<Transform>) {
+    // every regular instruction clears the alt prefix
+    transforms.push(Transform::UnsetFlag(FLAG_ALT.into()));
+    map.insert(mode.into(), transforms);
+}
+
+fn transform_alt_prefix(map: &mut TransformMap, mode: &str, value: &str) {
+    map.insert(
+        mode.into(),
+        vec![
+            Transform::MatcherName,
+            Transform::Consume(1),
+            Transform::SetFlag(FLAG_ALT.into(), value.into()),
+        ],
+    );
+}
+
+fn transforms() -> TransformMap {
+    let mut map = BTreeMap::default();
+    super::a6502::transforms_common(&mut map);
+
+    insert_transform(
+        &mut map,
+        IMPLIED,
+        vec![Transform::MatcherName, Transform::Consume(1)],
+    );
+    insert_transform(
+        &mut map,
+        RELATIVE,
+        vec![
+            Transform::MatcherName,
+            Transform::Static(Node::new(" ".into())),
+            Transform::OffsetAddress(2),
+            Transform::Val(ValOut {
+                offset: 1,
+                fmt: ValueTypeFmt::LowerHex(2),
+                data_type: DataType::I8,
+                rel: true,
+                ..Default::default()
+            }),
+            Transform::OffsetAddress(-2),
+            Transform::Consume(1),
+        ],
+    );
+    map.insert(
+        REG_PREFIX.into(),
+        vec![
+            Transform::MatcherName,
+            Transform::Static(Node::new(" r".into())),
+            reg_val(),
+            Transform::Consume(1),
+        ],
+    );
+    insert_transform(
+        &mut map,
+        REG,
+        vec![
+            Transform::MatcherName,
+            Transform::Static(Node::new(" r".into())),
+            reg_val(),
+            Transform::Consume(1),
+        ],
+    );
+    insert_transform(
+        &mut map,
+        REG_IND,
+        vec![
+            Transform::MatcherName,
+            Transform::Static(Node::new(" (r".into())),
+            reg_val(),
+            Transform::Static(Node::new(")".into())),
+            Transform::Consume(1),
+        ],
+    );
+    insert_transform(
+        &mut map,
+        IMM4,
+        vec![
+            Transform::MatcherName,
+            Transform::Static(Node::new(" #".into())),
+            reg_val(),
+            Transform::Consume(1),
+        ],
+    );
+    insert_transform(
+        &mut map,
+        IBT,
+        vec![
+            Transform::MatcherName,
+            Transform::Static(Node::new(" r".into())),
+            reg_val(),
+            Transform::Consume(1),
+            Transform::Static(Node::new(", #".into())),
+            Transform::Val(ValOut {
+                offset: 0,
+                fmt: ValueTypeFmt::LowerHex(2),
+                data_type: DataType::U8,
+                ..Default::default()
+            }),
+        ],
+    );
+    insert_transform(
+        &mut map,
+        IWT,
+        vec![
+            Transform::MatcherName,
+            Transform::Static(Node::new(" r".into())),
+            reg_val(),
+            Transform::Consume(1),
+            Transform::Static(Node::new(", #".into())),
+            Transform::Val(ValOut {
+                offset: 0,
+                fmt: ValueTypeFmt::LowerHex(4),
+                data_type: DataType::U16,
+                ..Default::default()
+            }),
+        ],
+    );
+    // lms and sms store the word address divided by 2
+    insert_transform(
+        &mut map,
+        LMS,
+        vec![
+            Transform::MatcherName,
+            Transform::Static(Node::new(" r".into())),
+            reg_val(),
+            Transform::Consume(1),
+            Transform::Static(Node::new(", (".into())),
+            Transform::Val(ValOut {
+                offset: 0,
+                fmt: ValueTypeFmt::LowerHex(4),
+                data_type: DataType::U8,
+                ops: vec![ValueOp::Shl(1)],
+                ..Default::default()
+            }),
+            Transform::Static(Node::new(")".into())),
+        ],
+    );
+    insert_transform(
+        &mut map,
+        SMS,
+        vec![
+            Transform::MatcherName,
+            Transform::Static(Node::new(" (".into())),
+            Transform::Val(ValOut {
+                offset: 1,
+                fmt: ValueTypeFmt::LowerHex(4),
+                data_type: DataType::U8,
+                ops: vec![ValueOp::Shl(1)],
+                peek: true,
+                ..Default::default()
+            }),
+            Transform::Static(Node::new("), r".into())),
+            reg_val(),
+            Transform::Consume(2),
+        ],
+    );
+    insert_transform(
+        &mut map,
+        LM,
+        vec![
+            Transform::MatcherName,
+            Transform::Static(Node::new(" r".into())),
+            reg_val(),
+            Transform::Consume(1),
+            Transform::Static(Node::new(", (".into())),
+            Transform::Val(ValOut {
+                offset: 0,
+                fmt: ValueTypeFmt::LowerHex(4),
+                data_type: DataType::U16,
+                ..Default::default()
+            }),
+            Transform::Static(Node::new(")".into())),
+        ],
+    );
+    insert_transform(
+        &mut map,
+        SM,
+        vec![
+            Transform::MatcherName,
+            Transform::Static(Node::new(" (".into())),
+            Transform::Val(ValOut {
+                offset: 1,
+                fmt: ValueTypeFmt::LowerHex(4),
+                data_type: DataType::U16,
+                peek: true,
+                ..Default::default()
+            }),
+            Transform::Static(Node::new("), r".into())),
+            reg_val(),
+            Transform::Consume(3),
+        ],
+    );
+    transform_alt_prefix(&mut map, ALT1_PREFIX, ALT1);
+    transform_alt_prefix(&mut map, ALT2_PREFIX, ALT2);
+    transform_alt_prefix(&mut map, ALT3_PREFIX, ALT3);
+
+    map
+}
+
+fn data_len(mode: &str) -> usize {
+    match mode {
+        RELATIVE | IBT | LMS | SMS => 2,
+        IWT | LM | SM => 3,
+        _ => 1,
+    }
+}
+
+// creates a matcher that only applies if the alt flag has the requested value
+// matchers without an alt value always apply, which mirrors the hardware
+// falling back to the base instruction
+fn matcher_alt(matchers: &mut MatcherList, op: u8, name: &str, mode: &str, alt: Option<&str>) {
+    let mut patterns = vec![PatternAt::new(Pattern::Exact(op), 0)];
+    let len = data_len(mode);
+    if len > 1 {
+        patterns.push(PatternAt::new(Pattern::Any, len - 1));
+    }
+    if let Some(alt) = alt {
+        patterns.push(PatternAt::new(
+            Pattern::Flag(FLAG_ALT.into(), Some(alt.into())),
+            0,
+        ));
+    }
+    matchers.push(Matcher {
+        patterns,
+        transforms: mode.into(),
+        name: Node::new(name.into()),
+        cycles: None,
+    })
+}
+
+// (base op, first register, last register, [alt0, alt1, alt2, alt3] (name, mode))
+type RegGroup = (u8, u8, u8, [Option<(&'static str, &'static str)>; 4]);
+
+fn reg_groups() -> Vec<RegGroup> {
+    vec![
+        (0x10, 0, 15, [Some(("to", REG_PREFIX)), None, None, None]),
+        (0x20, 0, 15, [Some(("with", REG_PREFIX)), None, None, None]),
+        (0xB0, 0, 15, [Some(("from", REG_PREFIX)), None, None, None]),
+        (
+            0x30,
+            0,
+            11,
+            [Some(("stw", REG_IND)), Some(("stb", REG_IND)), None, None],
+        ),
+        (
+            0x40,
+            0,
+            11,
+            [Some(("ldw", REG_IND)), Some(("ldb", REG_IND)), None, None],
+        ),
+        (
+            0x50,
+            0,
+            15,
+            [
+                Some(("add", REG)),
+                Some(("adc", REG)),
+                Some(("add", IMM4)),
+                Some(("adc", IMM4)),
+            ],
+        ),
+        (
+            0x60,
+            0,
+            15,
+            [
+                Some(("sub", REG)),
+                Some(("sbc", REG)),
+                Some(("sub", IMM4)),
+                Some(("cmp", REG)),
+            ],
+        ),
+        (
+            0x70,
+            1,
+            15,
+            [
+                Some(("and", REG)),
+                Some(("bic", REG)),
+                Some(("and", IMM4)),
+                Some(("bic", IMM4)),
+            ],
+        ),
+        (
+            0x80,
+            0,
+            15,
+            [
+                Some(("mult", REG)),
+                Some(("umult", REG)),
+                Some(("mult", IMM4)),
+                Some(("umult", IMM4)),
+            ],
+        ),
+        (0x90, 1, 4, [Some(("link", IMM4)), None, None, None]),
+        (
+            0x90,
+            8,
+            13,
+            [Some(("jmp", REG)), Some(("ljmp", REG)), None, None],
+        ),
+        (
+            0xA0,
+            0,
+            15,
+            [
+                Some(("ibt", IBT)),
+                Some(("lms", LMS)),
+                Some(("sms", SMS)),
+                None,
+            ],
+        ),
+        (
+            0xC0,
+            1,
+            15,
+            [
+                Some(("or", REG)),
+                Some(("xor", REG)),
+                Some(("or", IMM4)),
+                Some(("xor", IMM4)),
+            ],
+        ),
+        (0xD0, 0, 14, [Some(("inc", REG)), None, None, None]),
+        (0xE0, 0, 14, [Some(("dec", REG)), None, None, None]),
+        (
+            0xF0,
+            0,
+            15,
+            [Some(("iwt", IWT)), Some(("lm", LM)), Some(("sm", SM)), None],
+        ),
+    ]
+}
+
+// (op, [alt0, alt1, alt2, alt3] name)
+type ImpliedOp = (u8, [Option<&'static str>; 4]);
+
+fn implied_ops() -> Vec<ImpliedOp> {
+    vec![
+        (0x00, [Some("stop"), None, None, None]),
+        (0x01, [Some("nop"), None, None, None]),
+        (0x02, [Some("cache"), None, None, None]),
+        (0x03, [Some("lsr"), None, None, None]),
+        (0x04, [Some("rol"), None, None, None]),
+        (0x3C, [Some("loop"), None, None, None]),
+        (0x4C, [Some("plot"), Some("rpix"), None, None]),
+        (0x4D, [Some("swap"), None, None, None]),
+        (0x4E, [Some("color"), Some("cmode"), None, None]),
+        (0x4F, [Some("not"), None, None, None]),
+        (0x70, [Some("merge"), None, None, None]),
+        (0x90, [Some("sbk"), None, None, None]),
+        (0x95, [Some("sex"), None, None, None]),
+        (0x96, [Some("asr"), Some("div2"), None, None]),
+        (0x97, [Some("ror"), None, None, None]),
+        (0x9E, [Some("lob"), None, None, None]),
+        (0x9F, [Some("fmult"), Some("lmult"), None, None]),
+        (0xC0, [Some("hib"), None, None, None]),
+        (0xDF, [Some("getc"), None, Some("ramb"), Some("romb")]),
+        (
+            0xEF,
+            [Some("getb"), Some("getbh"), Some("getbl"), Some("getbs")],
+        ),
+    ]
+}
+
+fn relative_ops() -> Vec<(u8, &'static str)> {
+    vec![
+        (0x05, "bra"),
+        (0x06, "bge"),
+        (0x07, "blt"),
+        (0x08, "bne"),
+        (0x09, "beq"),
+        (0x0A, "bpl"),
+        (0x0B, "bmi"),
+        (0x0C, "bcc"),
+        (0x0D, "bcs"),
+        (0x0E, "bvc"),
+        (0x0F, "bvs"),
+    ]
+}
+
+const ALTS: [Option<&str>; 4] = [None, Some(ALT1), Some(ALT2), Some(ALT3)];
+
+fn patterns() -> MatcherList {
+    let mut list = vec![];
+
+    // alt variants need to be matched before the base instructions
+    for alt in (0..ALTS.len()).rev() {
+        for (op, names) in implied_ops() {
+            if let Some(name) = names[alt] {
+                matcher_alt(&mut list, op, name, IMPLIED, ALTS[alt]);
+            }
+        }
+        for (base, first, last, modes) in reg_groups() {
+            if let Some((name, mode)) = modes[alt] {
+                for reg in first..=last {
+                    matcher_alt(&mut list, base | reg, name, mode, ALTS[alt]);
+                }
+            }
+        }
+    }
+
+    for (op, name) in relative_ops() {
+        matcher_alt(&mut list, op, name, RELATIVE, None);
+    }
+    matcher_alt(&mut list, 0x3D, "alt1", ALT1_PREFIX, None);
+    matcher_alt(&mut list, 0x3E, "alt2", ALT2_PREFIX, None);
+    matcher_alt(&mut list, 0x3F, "alt3", ALT3_PREFIX, None);
+
+    list
+}
+
+fn archs() -> BTreeMap<String, Arch> {
+    let mut map = BTreeMap::default();
+    map.insert(
+        "".into(),
+        Arch {
+            patterns: super::a6502::add_patterns_default(patterns()),
+            transforms: transforms(),
+            addr_type: DataType::U16,
+            // we can unwrap this because the 6502 is guaranteed to have an empty
+            // arch key!
+            ..super::a6502::ARCH.archs.get("").unwrap().to_owned()
+        },
+    );
+    map
+}
@@ -0,0 +1,269 @@
+use std::{collections::BTreeSet, fmt::Display};
+
+use super::{
+    arch::{Archs, Context, StaticOp},
+    symbols::SymbolKind,
+    Address,
+};
+
+/// A reference of the context that does not fit the input and how to fix it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub address: Option<Address>,
+    pub message: String,
+    pub fix: String,
+}
+
+impl Finding {
+    fn new(address: Option<Address>, message: String, fix: &str) -> Self {
+        Self {
+            address,
+            message,
+            fix: fix.into(),
+        }
+    }
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(address) = self.address {
+            write!(f, "{address:x}: ")?;
+        }
+        write!(f, "{}, {}", self.message, self.fix)
+    }
+}
+
+// the address is part of the input in the namespace, the window of the context is ignored
+// because the labels and comments outside of it are still valid
+fn mapped(ctx: &Context, address: Address, namespace: &str, len: usize) -> bool {
+    let inside = |org: Address, start: usize, end: usize| {
+        address >= org && ((address - org) as usize) < end.min(len).saturating_sub(start)
+    };
+    let regions = ctx.regions.iter().filter(|r| r.name == namespace);
+    // the labels of the main processor can be inside of a bank
+    let banks = ctx.banks().into_iter().filter(|_| namespace.is_empty());
+    if regions.chain(banks).any(|r| inside(r.org, r.start, r.end)) {
+        return true;
+    }
+    if !namespace.is_empty() {
+        return false;
+    }
+    if ctx.segments.iter().any(|s| inside(s.org, s.start, s.end)) {
+        return true;
+    }
+    ctx.memory_map
+        .to_offset(address)
+        .and_then(|offset| offset.checked_sub(ctx.org))
+        .is_some_and(|offset| (offset as usize) < len)
+}
+
+// the flags that are tested or set by the ops
+fn static_flag_keys(ops: &[StaticOp], keys: &mut BTreeSet<String>) {
+    for op in ops {
+        match op {
+            StaticOp::SetFlag(key, _) | StaticOp::UnsetFlag(key) => {
+                keys.insert(key.clone());
+            }
+            StaticOp::IfFlag(key, _, then_ops, else_ops) => {
+                keys.insert(key.clone());
+                static_flag_keys(then_ops, keys);
+                static_flag_keys(else_ops, keys);
+            }
+            StaticOp::Address(_, ops) | StaticOp::Match(_, ops) => static_flag_keys(ops, keys),
+            _ => {}
+        }
+    }
+}
+
+// the file ranges of the regions, segments, gaps and classes that do not fit the input
+fn check_range(kind: &str, name: &str, start: usize, end: usize, len: usize) -> Option<Finding> {
+    let name = if name.is_empty() {
+        kind.to_owned()
+    } else {
+        format!("{kind} {name}")
+    };
+    if start >= end {
+        Some(Finding::new(
+            None,
+            format!("the {name} at {start:#x}..{end:#x} is empty"),
+            "remove it or set its end after its start",
+        ))
+    } else if start >= len {
+        Some(Finding::new(
+            None,
+            format!("the {name} starts at {start:#x} past the end of the input ({len:#x})"),
+            "remove it or load the input it was defined for",
+        ))
+    } else if end > len {
+        Some(Finding::new(
+            None,
+            format!("the {name} ends at {end:#x} past the end of the input ({len:#x})"),
+            &format!("set its end to {len:#x}"),
+        ))
+    } else {
+        None
+    }
+}
+
+/// Validates the references of the context against the input
+/// the labels and comments have to be at mapped addresses, the regions, segments, gaps,
+/// classes and patches have to fit the input and the flags have to be used by the arch
+pub fn check(arch: &Archs, ctx: &Context, data: &[u8]) -> Vec<Finding> {
+    let len = data.len();
+    let mut findings = vec![];
+
+    // the analysis defines its labels for addresses it has seen, e.g. ram variables
+    for sym in ctx
+        .syms
+        .iter()
+        .filter(|s| s.kind == SymbolKind::Label && !s.auto)
+    {
+        let address = sym.value as Address;
+        if !mapped(ctx, address, &sym.namespace, len) {
+            findings.push(Finding::new(
+                Some(address),
+                format!(
+                    "the label {} is outside of the mapped address space",
+                    sym.qualified_name()
+                ),
+                "define it as a constant or change the org, regions or segments",
+            ));
+        }
+    }
+
+    for region in &ctx.regions {
+        findings.extend(check_range(
+            "region",
+            &region.name,
+            region.start,
+            region.end,
+            len,
+        ));
+    }
+    for segment in &ctx.segments {
+        findings.extend(check_range(
+            "segment",
+            &segment.name,
+            segment.start,
+            segment.end,
+            len,
+        ));
+    }
+    for gap in &ctx.gaps {
+        findings.extend(check_range("gap", "", gap.start, gap.end, len));
+    }
+    for range in ctx.classes.ranges() {
+        findings.extend(check_range("class range", "", range.start, range.end, len));
+    }
+
+    for (i, patch) in ctx.patches.iter().enumerate() {
+        let (offset, bytes) = match patch.bytes() {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                findings.push(Finding::new(
+                    None,
+                    format!("the patch {i} can not be read ({err})"),
+                    "check the path of its file",
+                ));
+                continue;
+            }
+        };
+        let end = offset + bytes.len();
+        if end > len {
+            let policy = patch.extend().unwrap_or(ctx.extend_policy);
+            findings.push(Finding::new(
+                None,
+                format!(
+                    "the patch {i} writes {offset:#x}..{end:#x} past the end of the input ({len:#x}) using the extend policy {policy:?}"
+                ),
+                "move it or set the extend policy that the input needs",
+            ));
+        }
+    }
+
+    let namespaces: BTreeSet<&str> = std::iter::once("")
+        .chain(ctx.regions.iter().map(|r| r.name.as_str()))
+        .collect();
+    for address in ctx.comments.keys() {
+        if !namespaces.iter().any(|ns| mapped(ctx, *address, ns, len)) {
+            findings.push(Finding::new(
+                Some(*address),
+                "the comment is at an unmapped address".into(),
+                "move it to an address of the input",
+            ));
+        }
+    }
+
+    let mut used = arch.flag_keys();
+    used.extend(ctx.overlays.iter().map(|o| o.flag.clone()));
+    for ops in [&ctx.static_ops_pre, &ctx.static_ops_post] {
+        static_flag_keys(&ops.lock().unwrap(), &mut used);
+    }
+    for ops in ctx.scripts.values() {
+        static_flag_keys(ops, &mut used);
+    }
+    for key in ctx.flags.keys().filter(|key| !used.contains(*key)) {
+        findings.push(Finding::new(
+            None,
+            format!("the flag {key} is never used by the arch"),
+            "remove it or check its name",
+        ));
+    }
+    findings
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{
+        arch::{a6502, Context},
+        patch::Patch,
+        region::Region,
+        symbols::{Scope, Symbol, SymbolKind},
+    };
+
+    use super::check;
+
+    #[test]
+    fn check_ctx() {
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        assert!(check(&a6502::ARCH, &ctx, &[0xEA; 4]).is_empty());
+
+        for (name, value) in [("start", 0x8000), ("far", 0x9000)] {
+            ctx.def_symbol(Symbol::new(
+                name.into(),
+                SymbolKind::Label,
+                Scope::Global,
+                value,
+                1,
+            ));
+        }
+        ctx.regions.push(Region::new("sound", "", 2, 8, 0));
+        ctx.patches.push(Patch::Data {
+            offset: 3,
+            data: vec![0, 0],
+            extend: None,
+        });
+        ctx.comments.insert(0x8003, "last".into());
+        ctx.comments.insert(0x7FFF, "before".into());
+        ctx.flags.insert("bnak".into(), "1".into());
+        let findings: Vec<String> = check(&a6502::ARCH, &ctx, &[0xEA; 4])
+            .iter()
+            .map(|f| f.to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                "9000: the label far is outside of the mapped address space, \
+                 define it as a constant or change the org, regions or segments",
+                "the region sound ends at 0x8 past the end of the input (0x4), set its end to 0x4",
+                "the patch 0 writes 0x3..0x5 past the end of the input (0x4) using the extend policy Grow(0), \
+                 move it or set the extend policy that the input needs",
+                "7fff: the comment is at an unmapped address, move it to an address of the input",
+                "the flag bnak is never used by the arch, remove it or check its name",
+            ],
+            findings
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use std::fmt::Display;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::{Error, FdResult};
+
+use super::{arch::Endianess, ValueType};
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum ChecksumKind {
+    // sum of all bytes truncated to 8 bits
+    #[default]
+    Sum8,
+    // sum of all bytes truncated to 16 bits
+    Sum16,
+    Xor8,
+    // CRC-16/CCITT-FALSE
+    Crc16,
+    // CRC-32 (IEEE)
+    Crc32,
+}
+
+impl ChecksumKind {
+    /// size of the stored value in bytes
+    pub fn size(&self) -> usize {
+        match self {
+            ChecksumKind::Sum8 | ChecksumKind::Xor8 => 1,
+            ChecksumKind::Sum16 | ChecksumKind::Crc16 => 2,
+            ChecksumKind::Crc32 => 4,
+        }
+    }
+
+    pub fn compute<'a>(&self, data: impl Iterator<Item = &'a u8>) -> ValueType {
+        match self {
+            ChecksumKind::Sum8 => data.fold(0u8, |c, b| c.wrapping_add(*b)) as ValueType,
+            ChecksumKind::Sum16 => data.fold(0u16, |c, b| c.wrapping_add(*b as u16)) as ValueType,
+            ChecksumKind::Xor8 => data.fold(0u8, |c, b| c ^ b) as ValueType,
+            ChecksumKind::Crc16 => data.fold(0xFFFFu16, |c, b| {
+                (0..8).fold(c ^ ((*b as u16) << 8), |c, _| {
+                    if c & 0x8000 != 0 {
+                        (c << 1) ^ 0x1021
+                    } else {
+                        c << 1
+                    }
+                })
+            }) as ValueType,
+            ChecksumKind::Crc32 => {
+                let crc = data.fold(0xFFFFFFFFu32, |c, b| {
+                    (0..8).fold(c ^ *b as u32, |c, _| {
+                        if c & 1 != 0 {
+                            (c >> 1) ^ 0xEDB88320
+                        } else {
+                            c >> 1
+                        }
+                    })
+                });
+                !crc as ValueType
+            }
+        }
+    }
+}
+
+impl Display for ChecksumKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChecksumKind::Sum8 => write!(f, "sum8"),
+            ChecksumKind::Sum16 => write!(f, "sum16"),
+            ChecksumKind::Xor8 => write!(f, "xor8"),
+            ChecksumKind::Crc16 => write!(f, "crc16"),
+            ChecksumKind::Crc32 => write!(f, "crc32"),
+        }
+    }
+}
+
+/// A checksum over the file offsets start..end
+/// that is stored at the file offset location.
+/// The stored value itself is skipped when the checksum is computed
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Default)]
+pub struct Checksum {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub name: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub kind: ChecksumKind,
+    pub start: usize,
+    pub end: usize,
+    pub location: usize,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub endianess: Endianess,
+    // update the stored value after patching
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub fix: bool,
+}
+
+impl Checksum {
+    fn stored_range(&self) -> std::ops::Range<usize> {
+        self.location..self.location + self.kind.size()
+    }
+
+    pub fn compute(&self, data: &[u8]) -> FdResult<ValueType> {
+        let region = data
+            .get(self.start..self.end)
+            .ok_or(Error::ChecksumOutOfRange(self.end))?;
+        let stored = self.stored_range();
+        Ok(self.kind.compute(
+            region
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !stored.contains(&(i + self.start)))
+                .map(|(_, b)| b),
+        ))
+    }
+
+    pub fn stored(&self, data: &[u8]) -> FdResult<ValueType> {
+        let bytes = data
+            .get(self.stored_range())
+            .ok_or(Error::ChecksumOutOfRange(self.location))?;
+        let bytes = self.endianess.pad(bytes, std::mem::size_of::<ValueType>());
+        self.endianess
+            .transform(&bytes)
+            .ok_or(Error::ChecksumOutOfRange(self.location))
+    }
+
+    /// Returns a comment describing the computed and stored value
+    pub fn verify(&self, data: &[u8]) -> FdResult<(bool, String)> {
+        let computed = self.compute(data)?;
+        let stored = self.stored(data)?;
+        let width = self.kind.size() * 2;
+        Ok((
+            computed == stored,
+            format!(
+                "checksum {} ({}): computed {:0width$x}, stored {:0width$x}{}",
+                self.name,
+                self.kind,
+                computed,
+                stored,
+                if computed == stored {
+                    ""
+                } else {
+                    " (mismatch)"
+                }
+            ),
+        ))
+    }
+
+    /// Writes the computed value to the stored location
+    pub fn fix(&self, data: &mut [u8]) -> FdResult<()> {
+        let computed = self.compute(data)?;
+        let len = self.kind.size();
+        let bytes = match self.endianess {
+            Endianess::Little => computed.to_le_bytes()[..len].to_vec(),
+            Endianess::Big => computed.to_be_bytes()[8 - len..].to_vec(),
+        };
+        data.get_mut(self.stored_range())
+            .ok_or(Error::ChecksumOutOfRange(self.location))?
+            .copy_from_slice(&bytes);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{
+        arch::{a6502, Context, Endianess},
+        patch::Patch,
+    };
+
+    use super::{Checksum, ChecksumKind};
+
+    #[test]
+    fn algorithms() {
+        let data = b"123456789";
+        assert_eq!(0xDD, ChecksumKind::Sum8.compute(data.iter()));
+        assert_eq!(0x01DD, ChecksumKind::Sum16.compute(data.iter()));
+        assert_eq!(0x31, ChecksumKind::Xor8.compute(data.iter()));
+        assert_eq!(0x29B1, ChecksumKind::Crc16.compute(data.iter()));
+        assert_eq!(0xCBF43926, ChecksumKind::Crc32.compute(data.iter()));
+    }
+
+    #[test]
+    fn verify_and_fix() {
+        let checksum = Checksum {
+            name: "header".into(),
+            kind: ChecksumKind::Sum16,
+            start: 0,
+            end: 6,
+            location: 4,
+            endianess: Endianess::Big,
+            fix: true,
+        };
+
+        // the stored value is not part of the sum
+        let data = vec![1, 2, 3, 4, 0x00, 0x0A];
+        let (ok, comment) = checksum.verify(&data).unwrap();
+        assert!(ok);
+        assert_eq!(
+            "checksum header (sum16): computed 000a, stored 000a",
+            comment
+        );
+
+        let mut ctx = Context::default();
+        ctx.patches.push(Patch::Data {
+            offset: 0,
+            data: vec![0xFF],
+            extend: None,
+        });
+        ctx.checksums.push(checksum.clone());
+        let res = ctx.patch(&data).unwrap();
+        assert_eq!(vec![0xFF, 2, 3, 4, 0x01, 0x08], res);
+
+        let (ok, comment) = checksum.verify(&[1, 2, 3, 4, 0, 0]).unwrap();
+        assert!(!ok);
+        assert_eq!(
+            "checksum header (sum16): computed 000a, stored 0000 (mismatch)",
+            comment
+        );
+    }
+
+    #[test]
+    fn comment() {
+        let mut ctx = Context::default();
+        ctx.checksums.push(Checksum {
+            name: "rom".into(),
+            start: 0,
+            end: 2,
+            location: 1,
+            ..Default::default()
+        });
+
+        let mut result = String::new();
+        a6502::ARCH
+            .disas_ctx(
+                |n, _kind, _raw, _arch, _ctx| {
+                    result.push_str(&n.string);
+                    Ok(())
+                },
+                &[0xFF, 0xFF],
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(
+            "00000000 .db $ff\n; checksum rom (sum8): computed ff, stored ff\n00000001 .db $ff\n",
+            result
+        );
+    }
+}
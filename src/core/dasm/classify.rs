@@ -0,0 +1,217 @@
+use std::fmt::Display;
+
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// What the bytes of the input are known to be
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ByteClass {
+    Code,
+    // never decoded as instructions
+    Data,
+    // data that is output as a string
+    Text,
+    // data that is output as 16-bit pointers
+    Pointers,
+    // data that is output as 24-bit pointers
+    LongPointers,
+    #[default]
+    Unknown,
+}
+
+impl ByteClass {
+    /// The bytes are never decoded as instructions
+    pub fn is_data(&self) -> bool {
+        matches!(
+            self,
+            Self::Data | Self::Text | Self::Pointers | Self::LongPointers
+        )
+    }
+
+    /// The size of the values the bytes are output as
+    pub fn value_len(&self) -> usize {
+        match self {
+            Self::Pointers => 2,
+            Self::LongPointers => 3,
+            _ => 1,
+        }
+    }
+}
+
+/// A file range of the input whose bytes have the same class
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClassRange {
+    pub start: usize,
+    pub end: usize,
+    pub class: ByteClass,
+}
+
+impl Display for ClassRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#x}..{:#x} {:?}", self.start, self.end, self.class)
+    }
+}
+
+/// The class of every byte of the input by its file offset
+/// The bytes that are not part of a range are unknown. The ranges are ordered,
+/// do not overlap and neighbouring ranges have different classes
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClassMap {
+    ranges: Vec<ClassRange>,
+}
+
+impl ClassMap {
+    pub fn ranges(&self) -> &[ClassRange] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn range_at(&self, offset: usize) -> Option<&ClassRange> {
+        let i = self.ranges.partition_point(|r| r.end <= offset);
+        self.ranges.get(i).filter(|r| r.start <= offset)
+    }
+
+    pub fn get(&self, offset: usize) -> ByteClass {
+        self.range_at(offset).map(|r| r.class).unwrap_or_default()
+    }
+
+    /// Classifies the bytes of start..end, unknown removes their class
+    pub fn set(&mut self, start: usize, end: usize, class: ByteClass) {
+        if start >= end {
+            return;
+        }
+        let mut ranges = Vec::with_capacity(self.ranges.len() + 2);
+        for range in self.ranges.drain(..) {
+            if range.end <= start || range.start >= end {
+                ranges.push(range);
+                continue;
+            }
+            // the parts outside of start..end keep their class
+            if range.start < start {
+                ranges.push(ClassRange {
+                    end: start,
+                    ..range.clone()
+                });
+            }
+            if range.end > end {
+                ranges.push(ClassRange {
+                    start: end,
+                    ..range
+                });
+            }
+        }
+        if class != ByteClass::Unknown {
+            ranges.push(ClassRange { start, end, class });
+        }
+        ranges.sort_by_key(|r| r.start);
+        for range in ranges {
+            match self.ranges.last_mut() {
+                Some(last) if last.end == range.start && last.class == range.class => {
+                    last.end = range.end
+                }
+                _ => self.ranges.push(range),
+            }
+        }
+    }
+
+    /// Classifies the bytes of start..end that are still unknown
+    /// so the analysis does not override what the user decided
+    pub fn set_unknown(&mut self, start: usize, end: usize, class: ByteClass) {
+        let mut unknown = vec![];
+        let mut pos = start;
+        for range in self
+            .ranges
+            .iter()
+            .filter(|r| r.end > start && r.start < end)
+        {
+            if range.start > pos {
+                unknown.push((pos, range.start));
+            }
+            pos = pos.max(range.end);
+        }
+        if pos < end {
+            unknown.push((pos, end));
+        }
+        for (start, end) in unknown {
+            self.set(start, end, class);
+        }
+    }
+
+    /// The amount of bytes of the class, the unknown bytes are not counted
+    pub fn count(&self, class: ByteClass) -> usize {
+        self.ranges
+            .iter()
+            .filter(|r| r.class == class)
+            .map(|r| r.end - r.start)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::arch::{a6502, Context};
+
+    use super::{ByteClass, ClassMap};
+
+    #[test]
+    fn class_map() {
+        let mut map = ClassMap::default();
+        map.set(0x10, 0x20, ByteClass::Data);
+        map.set(0x20, 0x28, ByteClass::Data);
+        map.set(0x18, 0x1C, ByteClass::Code);
+        let ranges: Vec<String> = map.ranges().iter().map(|r| r.to_string()).collect();
+        assert_eq!(
+            vec!["0x10..0x18 Data", "0x18..0x1c Code", "0x1c..0x28 Data"],
+            ranges
+        );
+        assert_eq!(ByteClass::Code, map.get(0x1B));
+        assert_eq!(ByteClass::Unknown, map.get(0x28));
+
+        // the analysis only classifies the unknown bytes
+        map.set_unknown(0x00, 0x30, ByteClass::Code);
+        assert_eq!(ByteClass::Data, map.get(0x10));
+        assert_eq!(
+            (0x14, 0x1C),
+            (map.count(ByteClass::Data), map.count(ByteClass::Code))
+        );
+
+        map.set(0x00, 0x30, ByteClass::Unknown);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn data_is_not_decoded() {
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        // nop, then a table that decodes as lda, then rts
+        ctx.classes.set(1, 4, ByteClass::Data);
+        let data = [0xEA, 0xAD, 0x34, 0xD0, 0x60];
+        let mut result = String::new();
+        a6502::ARCH
+            .disas_ctx(
+                |n, _kind, _raw, _arch, _ctx| {
+                    result.push_str(&n.string);
+                    Ok(())
+                },
+                &data,
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(
+            "00008000 nop\n00008001 .db $ad, $34, $d0\n00008004 rts\n",
+            result
+        );
+    }
+}
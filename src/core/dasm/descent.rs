@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+
+use crate::prelude::{Error, FdResult};
+
+use super::{
+    arch::{Archs, Context},
+    classify::ByteClass,
+    flow::{Collector, Flow},
+    interrupts, Address,
+};
+
+/// The entry points of the context, the interrupt vectors if there are none
+/// and the start of the output if there are no vectors either
+pub fn entries(ctx: &Context, data: &[u8]) -> Vec<Address> {
+    if !ctx.entry_points.is_empty() {
+        return ctx.entry_points.clone();
+    }
+    let vectors: Vec<Address> = interrupts::vectors(ctx, data)
+        .into_iter()
+        .map(|(_, address)| address)
+        .collect();
+    if !vectors.is_empty() {
+        return vectors;
+    }
+    let start = match ctx.segments.first() {
+        Some(segment) => segment.org,
+        None => ctx.address_of(ctx.window(data).start as Address),
+    };
+    vec![start]
+}
+
+/// The file offsets of the instructions that are reached and the flags they are decoded with
+pub type Reached = BTreeMap<usize, BTreeMap<String, String>>;
+
+/// Follows the branches, jumps and calls from the entry points and decodes only the bytes
+/// that are reached. Returns the file offsets of the instructions.
+/// The flags that an instruction changes (e.g. the register widths of rep and sep)
+/// follow its paths, an instruction that is reached twice keeps the flags it was reached with first.
+/// Returns, computed jumps and bytes that are not an instruction end a path.
+/// Regions, gaps and the bytes that are classified as data are not followed into.
+/// The instructions are classified as code unless their bytes are classified already
+pub fn trace(arch: &Archs, data: &[u8], ctx: &mut Context) -> FdResult<Reached> {
+    let ranges: Vec<(usize, usize)> = if ctx.segments.is_empty() {
+        let window = ctx.window(data);
+        vec![(window.start, window.end)]
+    } else {
+        ctx.segment_ranges(data)
+            .iter()
+            .map(|s| (s.start, s.end))
+            .collect()
+    };
+    let mut reached = Reached::new();
+    let mut code = vec![];
+    let flags = ctx.flags.clone();
+    let mut pending: Vec<(Address, BTreeMap<String, String>)> = entries(ctx, data)
+        .into_iter()
+        .map(|address| (address, flags.clone()))
+        .collect();
+    while let Some((address, before)) = pending.pop() {
+        let Some(offset) = ctx.offset_of(address, "") else {
+            continue;
+        };
+        let Some((_, end)) = ranges
+            .iter()
+            .find(|(start, end)| (*start..*end).contains(&offset))
+        else {
+            continue;
+        };
+        if reached.contains_key(&offset)
+            || ctx.regions.iter().any(|r| r.contains(offset))
+            || ctx.gaps.iter().any(|g| (g.start..g.end).contains(&offset))
+            || ctx.classes.get(offset).is_data()
+        {
+            continue;
+        }
+
+        let mut collector = Collector::default();
+        ctx.flags = before.clone();
+        let read = arch.decode_at(
+            &mut |node, kind, _raw, _arch, ctx| {
+                collector.push(node, &kind, ctx.address());
+                Ok(())
+            },
+            &data[..*end],
+            offset,
+            address,
+            ctx,
+        );
+        let len = match read {
+            Ok(len) => len,
+            Err(Error::NoMatch | Error::TransformOutOfData(_)) => continue,
+            Err(err) => return Err(err),
+        };
+        // the bytes that no matcher matches are output as data directives
+        let Some(instruction) = collector
+            .instructions
+            .first()
+            .filter(|i| !i.name.starts_with('.'))
+        else {
+            continue;
+        };
+        let after = ctx.flags.clone();
+        reached.insert(offset, before);
+        code.push((offset, offset + len));
+        let next = address.wrapping_add(len.max(1) as Address);
+        match instruction.flow() {
+            Flow::Next => pending.push((next, after)),
+            Flow::Branch(target) | Flow::Call(target) => {
+                pending.extend([(next, after.clone()), (target, after)])
+            }
+            Flow::Jump(target) => pending.push((target, after)),
+            Flow::Return | Flow::InterruptReturn | Flow::Stop => {}
+        }
+    }
+    ctx.flags = flags;
+
+    // the instructions are classified in runs
+    code.sort();
+    let mut runs: Vec<(usize, usize)> = vec![];
+    for (start, end) in code {
+        match runs.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => runs.push((start, end)),
+        }
+    }
+    for (start, end) in runs {
+        ctx.classes.set_unknown(start, end, ByteClass::Code);
+    }
+    Ok(reached)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::arch::{a6502, a65c816, Archs, Context};
+
+    fn disas(data: &[u8], ctx: &mut Context) -> String {
+        disas_arch(&a6502::ARCH, data, ctx)
+    }
+
+    fn disas_arch(arch: &Archs, data: &[u8], ctx: &mut Context) -> String {
+        let mut result = String::new();
+        ctx.restart();
+        arch.disas_ctx(
+            |n, _kind, _raw, _arch, _ctx| {
+                result.push_str(&n.string);
+                Ok(())
+            },
+            data,
+            ctx,
+        )
+        .unwrap();
+        result
+    }
+
+    #[test]
+    fn descent() {
+        let mut ctx = Context {
+            org: 0x8000,
+            descent: true,
+            ..Default::default()
+        };
+        // jsr 8008, jmp 8003, then a table that a linear sweep decodes as lda
+        // 8008: bne 800b, nop, rts
+        let data = [
+            0x20, 0x08, 0x80, 0x4C, 0x03, 0x80, 0xAD, 0x34, 0xD0, 0x01, 0xEA, 0x60,
+        ];
+        assert_eq!(
+            "00008000 jsr $8008\n00008003 jmp $8003\n00008006 .db $ad, $34\n\
+             00008008 bne $01\n0000800a nop\n0000800b rts\n",
+            disas(&data, &mut ctx)
+        );
+        assert_eq!(
+            vec!["0x0..0x6 Code", "0x8..0xc Code"],
+            ctx.classes
+                .ranges()
+                .iter()
+                .map(|r| r.to_string())
+                .collect::<Vec<_>>()
+        );
+
+        // the bytes before the entry point are never reached
+        ctx.entry_points = vec![0x800A];
+        assert_eq!(
+            "00008000 .db $20, $08, $80, $4c, $03, $80, $ad, $34\n00008008 .db $d0, $01\n\
+             0000800a nop\n0000800b rts\n",
+            disas(&data, &mut ctx)
+        );
+    }
+
+    #[test]
+    fn register_widths() {
+        let mut ctx = Context {
+            org: 0x8000,
+            descent: true,
+            ..Default::default()
+        };
+        // rep #$20, jmp 8009, then a sep that is never reached
+        // 8009: lda #$1234, rts
+        let data = [
+            0xC2, 0x20, 0x4C, 0x09, 0x80, 0xE2, 0x20, 0xEA, 0xEA, 0xA9, 0x34, 0x12, 0x60,
+        ];
+        assert_eq!(
+            "00008000 rep #$20\n00008002 jmp $8009\n00008005 .db $e2, $20, $ea, $ea\n\
+             00008009 lda #$1234\n0000800c rts\n",
+            disas_arch(&a65c816::ARCH, &data, &mut ctx)
+        );
+    }
+}
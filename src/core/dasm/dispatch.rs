@@ -0,0 +1,271 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
+
+use crate::prelude::FdResult;
+
+use super::{
+    arch::{Archs, Context},
+    classify::ByteClass,
+    flow::{self, Instruction},
+    interrupts, stack,
+    symbols::{Scope, Symbol, SymbolKind},
+    Address, ValueType,
+};
+
+/// The prefix of the labels that are defined for the targets of a dispatch table
+pub const TARGET_PREFIX: &str = "case_";
+
+/// A routine that pushes an address from a table and returns to it
+/// e.g. lda high,x, pha, lda low,x, pha, rts
+/// The table entries are stored minus one because rts adds one to the pulled address
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Dispatch {
+    pub name: String,
+    pub entry: Address,
+    // the rts that jumps to the pushed address
+    pub ret: Address,
+    // the tables of the low and high bytes
+    // the high bytes directly follow the low bytes if the table is made of words
+    pub low: Address,
+    pub high: Address,
+    pub targets: Vec<Address>,
+}
+
+impl Dispatch {
+    /// The entries are words instead of split low and high byte tables
+    pub fn words(&self) -> bool {
+        self.high == self.low + 1
+    }
+
+    // the distance between the entries of a table
+    fn stride(&self) -> Address {
+        if self.words() {
+            2
+        } else {
+            1
+        }
+    }
+
+    // the size of the table (the amount of bytes)
+    fn table_len(&self) -> Address {
+        self.targets.len() as Address * self.stride()
+    }
+}
+
+impl Display for Dispatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let table = if self.words() {
+            format!("{:x} (words)", self.low)
+        } else {
+            format!("{:x}/{:x}", self.low, self.high)
+        };
+        let targets: Vec<String> = self.targets.iter().map(|t| format!("{t:x}")).collect();
+        write!(
+            f,
+            "{}: rts at {:x}, table {table}: {}",
+            self.name,
+            self.ret,
+            targets.join(", ")
+        )
+    }
+}
+
+// reads a byte of the input by its address
+fn byte_at(data: &[u8], ctx: &Context, address: Address) -> Option<u8> {
+    ctx.offset_of(address, "")
+        .and_then(|offset| data.get(offset))
+        .copied()
+}
+
+/// Reads the dispatch table of a routine that pushes two bytes and returns to them
+/// The high byte is pushed first. The table ends at the first entry that is not
+/// the start of an instruction, that overlaps the code or the other table
+pub fn detect(
+    instructions: &BTreeMap<Address, Instruction>,
+    effect: &stack::StackEffect,
+    code: &BTreeSet<Address>,
+    data: &[u8],
+    ctx: &Context,
+) -> Option<Dispatch> {
+    let (ret, _) = effect.returns.iter().find(|(_, depth)| *depth == 2)?;
+    let [high, low] = effect.tables[..] else {
+        return None;
+    };
+    if high == low {
+        return None;
+    }
+    let mut dispatch = Dispatch {
+        name: effect.name.clone(),
+        entry: effect.entry,
+        ret: *ret,
+        low,
+        high,
+        targets: vec![],
+    };
+    // split tables end where the other one starts, the index is a single byte
+    let limit = if dispatch.words() {
+        0x80
+    } else {
+        high.abs_diff(low).min(0x100)
+    };
+    for i in 0..limit {
+        let (low, high) = (low + i * dispatch.stride(), high + i * dispatch.stride());
+        if code.contains(&low) || code.contains(&high) {
+            break;
+        }
+        let (Some(lo), Some(hi)) = (byte_at(data, ctx, low), byte_at(data, ctx, high)) else {
+            break;
+        };
+        let target = u16::from_le_bytes([lo, hi]).wrapping_add(1) as Address;
+        if !instructions.contains_key(&target) {
+            break;
+        }
+        dispatch.targets.push(target);
+    }
+    (!dispatch.targets.is_empty()).then_some(dispatch)
+}
+
+/// Finds the dispatch tables of the functions that are reached from the roots
+/// and adds their targets to the returns. The targets can contain dispatches as well,
+/// so this repeats until no new table is found.
+pub fn resolve(
+    instructions: &mut BTreeMap<Address, Instruction>,
+    roots: &[(String, Address)],
+    data: &[u8],
+    ctx: &Context,
+) -> Vec<Dispatch> {
+    let mut dispatches: Vec<Dispatch> = vec![];
+    loop {
+        let functions = flow::functions(instructions, roots, ctx);
+        let code: BTreeSet<Address> = functions
+            .iter()
+            .flat_map(|f| f.blocks.iter())
+            .flat_map(|b| b.instructions.iter().copied())
+            .collect();
+        let found: Vec<Dispatch> = functions
+            .iter()
+            .map(|function| stack::effect(instructions, function))
+            .filter_map(|effect| detect(instructions, &effect, &code, data, ctx))
+            .filter(|d| !dispatches.iter().any(|other| other.ret == d.ret))
+            .collect();
+        if found.is_empty() {
+            return dispatches;
+        }
+        for dispatch in found {
+            if let Some(ret) = instructions.get_mut(&dispatch.ret) {
+                ret.targets = dispatch.targets.clone();
+            }
+            dispatches.push(dispatch);
+        }
+    }
+}
+
+/// Finds the dispatch tables of the code that is reached from the start and the vectors
+pub fn find(arch: &Archs, data: &[u8], ctx: &mut Context) -> FdResult<Vec<Dispatch>> {
+    let mut instructions = flow::instructions(arch, data, ctx)?;
+    let roots = flow::roots(&instructions, interrupts::vectors(ctx, data));
+    Ok(resolve(&mut instructions, &roots, data, ctx))
+}
+
+/// Comments the tables and defines a case_ label for every target that is not labeled yet
+/// The bytes of the tables are classified as data unless they are classified already
+/// Returns the amount of labels that were defined
+pub fn annotate(ctx: &mut Context, dispatches: &[Dispatch]) -> usize {
+    let mut count = 0;
+    for dispatch in dispatches {
+        ctx.comments.entry(dispatch.low).or_insert_with(|| {
+            format!(
+                "rts dispatch table ({}), {} bytes, the entries are the targets minus one",
+                dispatch.name,
+                dispatch.table_len()
+            )
+        });
+        let tables = if dispatch.words() {
+            vec![dispatch.low]
+        } else {
+            vec![dispatch.low, dispatch.high]
+        };
+        for table in tables {
+            if let Some(start) = ctx.offset_of(table, "") {
+                let end = start + dispatch.table_len() as usize;
+                ctx.classes.set_unknown(start, end, ByteClass::Data);
+            }
+        }
+        for target in &dispatch.targets {
+            let labeled = ctx
+                .syms
+                .get_symbols(*target as ValueType)
+                .iter()
+                .any(|s| s.kind == SymbolKind::Label);
+            if !labeled {
+                ctx.def_auto_symbol(Symbol::new(
+                    format!("{TARGET_PREFIX}{target:x}"),
+                    SymbolKind::Label,
+                    Scope::Global,
+                    *target as ValueType,
+                    1,
+                ));
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{
+        arch::{a6502, Context},
+        flow,
+    };
+
+    use super::{annotate, find};
+
+    #[test]
+    fn dispatch() {
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        // ldx #2, jsr dispatch, jmp 8005
+        let start = [0xA2, 0x02, 0x20, 0x08, 0x80, 0x4C, 0x05, 0x80];
+        // 8008: lda 8012,x, pha, lda 8011,x, pha, rts
+        let dispatch = [0xBD, 0x12, 0x80, 0x48, 0xBD, 0x11, 0x80, 0x48, 0x60];
+        // 8011: the words 8016 and 8018 minus one
+        let table = [0x15, 0x80, 0x17, 0x80, 0xFF];
+        // 8016: nop, rts, 8018: rts
+        let targets = [0xEA, 0x60, 0x60];
+        let data = [&start[..], &dispatch, &table, &targets].concat();
+
+        let dispatches = find(&a6502::ARCH, &data, &mut ctx).unwrap();
+        assert_eq!(
+            vec!["sub_8008: rts at 8010, table 8011 (words): 8016, 8018"],
+            dispatches.iter().map(|d| d.to_string()).collect::<Vec<_>>()
+        );
+
+        let dot = flow::control_flow_graph(&a6502::ARCH, &data, &mut ctx).unwrap();
+        assert!(dot.contains("    f1_8008 -> f2_8016 [style=dotted];\n"));
+        assert!(dot.contains("    f1_8008 -> f3_8018 [style=dotted];\n"));
+
+        assert_eq!(2, annotate(&mut ctx, &dispatches));
+        assert_eq!(
+            0x8018,
+            ctx.syms.get_first_by_name("case_8018").unwrap().value
+        );
+        assert_eq!(
+            "rts dispatch table (sub_8008), 4 bytes, the entries are the targets minus one",
+            ctx.comments[&0x8011]
+        );
+        assert_eq!(
+            vec!["0x11..0x15 Data"],
+            ctx.classes
+                .ranges()
+                .iter()
+                .map(|r| r.to_string())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(0, annotate(&mut ctx, &dispatches));
+    }
+}
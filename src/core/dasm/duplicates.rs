@@ -0,0 +1,207 @@
+use std::collections::{btree_map::Entry, HashMap};
+
+use super::{
+    arch::Context,
+    symbols::{Symbol, SymbolKind, NAMESPACE_SEPARATOR},
+    Address, ValueType,
+};
+
+pub const DEFAULT_MIN_LEN: usize = 16;
+
+/// Two identical byte sequences of the input
+/// Banked roms often repeat the same routine in every bank
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Duplicate {
+    pub original: usize,
+    pub copy: usize,
+    pub len: usize,
+}
+
+impl Duplicate {
+    /// Both copies as namespace.address
+    pub fn describe(&self, ctx: &Context) -> String {
+        let location = |offset: usize| match ctx.locate(offset) {
+            (address, namespace) if namespace.is_empty() => format!("{address:x}"),
+            (address, namespace) => format!("{namespace}{NAMESPACE_SEPARATOR}{address:x}"),
+        };
+        format!(
+            "{} = {} ({:#x} bytes)",
+            location(self.original),
+            location(self.copy),
+            self.len
+        )
+    }
+}
+
+/// Finds byte sequences of at least min_len bytes that appear more than once
+/// Sequences of a single repeated byte are padding and are ignored
+pub fn find(data: &[u8], min_len: usize) -> Vec<Duplicate> {
+    let min_len = min_len.max(1);
+    let mut first: HashMap<&[u8], usize> = HashMap::new();
+    let mut duplicates = vec![];
+    let mut i = 0;
+    while i + min_len <= data.len() {
+        let window = &data[i..i + min_len];
+        if window.iter().all(|b| *b == window[0]) {
+            i += 1;
+            continue;
+        }
+        match first.get(window) {
+            Some(&original) if original + min_len <= i => {
+                // the copies may not overlap
+                let mut len = min_len;
+                while i + len < data.len()
+                    && original + len < i
+                    && data[original + len] == data[i + len]
+                {
+                    len += 1;
+                }
+                duplicates.push(Duplicate {
+                    original,
+                    copy: i,
+                    len,
+                });
+                i += len;
+            }
+            Some(_) => i += 1,
+            None => {
+                first.insert(window, i);
+                i += 1;
+            }
+        }
+    }
+    duplicates
+}
+
+/// Copies the labels and comments of each copy to the other one
+/// where it does not have a label or comment yet
+/// Labels that are copied into the same namespace get the address as a suffix
+/// Returns the amount of labels and comments that were copied
+pub fn propagate(ctx: &mut Context, duplicates: &[Duplicate]) -> usize {
+    let mut copied = 0;
+    for dup in duplicates {
+        let len = dup.len as Address;
+        for (from, to) in [(dup.original, dup.copy), (dup.copy, dup.original)] {
+            let (from_address, from_namespace) = ctx.locate(from);
+            let (to_address, to_namespace) = ctx.locate(to);
+            let is_label_at = |sym: &Symbol, namespace: &str, address: Address| {
+                sym.kind == SymbolKind::Label
+                    && sym.namespace == namespace
+                    && sym.is_match(address as ValueType, None)
+            };
+
+            let labels: Vec<Symbol> = ctx
+                .syms
+                .iter()
+                .filter(|sym| {
+                    let value = sym.value as Address;
+                    sym.kind == SymbolKind::Label
+                        && sym.namespace == from_namespace
+                        && value >= from_address
+                        && value < from_address + len
+                })
+                .cloned()
+                .collect();
+            for label in labels {
+                let address = to_address + (label.value as Address - from_address);
+                if ctx
+                    .syms
+                    .iter()
+                    .any(|sym| is_label_at(sym, &to_namespace, address))
+                {
+                    continue;
+                }
+                let mut sym = label.clone();
+                if to_namespace == from_namespace {
+                    sym.name = format!("{}_{address:x}", label.name);
+                }
+                sym.value = address as ValueType;
+                sym.additional_values.clear();
+                sym.namespace = to_namespace.clone();
+                sym.auto = true;
+                ctx.syms.def_symbol(sym);
+                copied += 1;
+            }
+
+            let comments: Vec<(Address, String)> = ctx
+                .comments
+                .range(from_address..from_address + len)
+                .map(|(address, comment)| (*address, comment.clone()))
+                .collect();
+            for (address, comment) in comments {
+                let address = to_address + (address - from_address);
+                if let Entry::Vacant(entry) = ctx.comments.entry(address) {
+                    entry.insert(comment);
+                    copied += 1;
+                }
+            }
+        }
+    }
+    copied
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{
+        arch::Context,
+        region::Region,
+        symbols::{Scope, Symbol, SymbolKind},
+    };
+
+    use super::{find, propagate, Duplicate};
+
+    #[test]
+    fn duplicates() {
+        let routine = [0xA9, 0x01, 0x8D, 0x00, 0x20, 0x60];
+        let mut data = routine.to_vec();
+        data.extend([0xFF; 10]);
+        data.extend(routine);
+        data.extend([0x00; 10]);
+        assert_eq!(
+            vec![Duplicate {
+                original: 0,
+                copy: 0x10,
+                len: 6
+            }],
+            find(&data, 4)
+        );
+        assert!(find(&data, 7).is_empty());
+
+        // both copies are in their own bank at the same address
+        let mut ctx = Context::default();
+        ctx.regions.push(Region::new("bank0", "", 0, 0x10, 0xF000));
+        ctx.regions
+            .push(Region::new("bank1", "", 0x10, 0x20, 0xF000));
+        let mut sym = Symbol::new("store".into(), SymbolKind::Label, Scope::Global, 0xF002, 1);
+        sym.namespace = "bank1".into();
+        ctx.syms.def_symbol(sym);
+        ctx.comments.insert(0xF000, "write 1 to 2000".into());
+
+        let dups = find(&data, 4);
+        assert_eq!(
+            "bank0.f000 = bank1.f000 (0x6 bytes)",
+            dups[0].describe(&ctx)
+        );
+        // the label is copied to bank0, the comments share the address
+        assert_eq!(1, propagate(&mut ctx, &dups));
+        assert!(ctx
+            .syms
+            .iter()
+            .any(|s| s.name == "store" && s.namespace == "bank0" && s.auto));
+        assert_eq!(0, propagate(&mut ctx, &dups));
+
+        // in the same namespace the label is renamed
+        let mut ctx = Context::default();
+        ctx.syms.def_symbol(Symbol::new(
+            "init".into(),
+            SymbolKind::Label,
+            Scope::Global,
+            0,
+            1,
+        ));
+        ctx.comments.insert(1, "value".into());
+        assert_eq!(2, propagate(&mut ctx, &dups));
+        assert_eq!("init_10", ctx.syms.get_symbols(0x10)[0].name);
+        assert_eq!("value", ctx.comments[&0x11]);
+    }
+}
@@ -0,0 +1,25 @@
+use std::sync::{Arc, Mutex};
+
+use super::{region::Region, symbols::Symbol};
+
+/// Events that are emitted while disassembling or analyzing
+#[derive(Clone, Debug)]
+pub enum Event {
+    // a symbol is about to be defined automatically
+    SymbolDefined(Symbol),
+    // a co-processor region was entered
+    RegionEntered(Region),
+    Warning(String),
+}
+
+/// The response of an event hook
+/// Only symbol events can be vetoed or renamed
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum EventResponse {
+    #[default]
+    Accept,
+    Veto,
+    Rename(String),
+}
+
+pub type EventHook = Arc<Mutex<dyn FnMut(&Event) -> EventResponse + Send>>;
@@ -0,0 +1,185 @@
+use crate::{
+    core::config::auto_radix_value,
+    prelude::{Error, FdResult},
+};
+
+use super::{symbols::SymbolList, ValueType};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Value(ValueType),
+    Symbol(String),
+    Op(&'static str),
+    Open,
+    Close,
+}
+
+// longer operators first
+const OPS: [&str; 10] = ["<<", ">>", "+", "-", "*", "/", "%", "&", "|", "^"];
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.'
+}
+
+fn tokenize(expr: &str) -> FdResult<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut rest = expr.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if let Some(op) = OPS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        } else if c == '~' {
+            tokens.push(Token::Op("~"));
+            rest = &rest[1..];
+        } else if c == '(' || c == ')' {
+            tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            rest = &rest[1..];
+        } else if c == '$' || is_symbol_char(c) {
+            let start = if c == '$' { 1 } else { 0 };
+            let len = rest[start..]
+                .find(|c| !is_symbol_char(c))
+                .unwrap_or(rest.len() - start)
+                + start;
+            let word = &rest[..len];
+            tokens.push(if let Some(hex) = word.strip_prefix('$') {
+                Token::Value(ValueType::from_str_radix(hex, 16)?)
+            } else if c.is_ascii_digit() {
+                Token::Value(auto_radix_value(word)?)
+            } else {
+                Token::Symbol(word.to_owned())
+            });
+            rest = &rest[len..];
+        } else {
+            return Err(Error::InvalidExpression(expr.to_owned()));
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+// binary operators from the lowest to the highest precedence
+const PRECEDENCE: [&[&str]; 6] = [
+    &["|"],
+    &["^"],
+    &["&"],
+    &["<<", ">>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+struct Parser<'a> {
+    expr: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+    syms: &'a SymbolList,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self) -> Error {
+        Error::InvalidExpression(self.expr.to_owned())
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn binary(&mut self, level: usize) -> FdResult<ValueType> {
+        if level == PRECEDENCE.len() {
+            return self.unary();
+        }
+        let mut lhs = self.binary(level + 1)?;
+        while let Some(Token::Op(op)) = self.tokens.get(self.pos) {
+            let op = *op;
+            if !PRECEDENCE[level].contains(&op) {
+                break;
+            }
+            self.pos += 1;
+            let rhs = self.binary(level + 1)?;
+            lhs = match op {
+                "|" => lhs | rhs,
+                "^" => lhs ^ rhs,
+                "&" => lhs & rhs,
+                "<<" => lhs.wrapping_shl(rhs as u32),
+                ">>" => lhs.wrapping_shr(rhs as u32),
+                "+" => lhs.wrapping_add(rhs),
+                "-" => lhs.wrapping_sub(rhs),
+                "*" => lhs.wrapping_mul(rhs),
+                "/" => lhs.checked_div(rhs).ok_or_else(|| self.error())?,
+                _ => lhs.checked_rem(rhs).ok_or_else(|| self.error())?,
+            };
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> FdResult<ValueType> {
+        match self.next() {
+            Some(Token::Op("-")) => Ok(self.unary()?.wrapping_neg()),
+            Some(Token::Op("~")) => Ok(!self.unary()?),
+            Some(Token::Value(value)) => Ok(value),
+            Some(Token::Symbol(name)) => self
+                .syms
+                .get_first_by_name(&name)
+                .map(|sym| sym.value)
+                .ok_or(Error::LabelNotFound(name)),
+            Some(Token::Open) => {
+                let value = self.binary(0)?;
+                match self.next() {
+                    Some(Token::Close) => Ok(value),
+                    _ => Err(self.error()),
+                }
+            }
+            _ => Err(self.error()),
+        }
+    }
+}
+
+/// Evaluates an integer expression
+/// Values may be decimal or use the 0x, 0b, 0o and $ prefixes
+/// and symbols are referenced by their (qualified) name
+pub fn eval(expr: &str, syms: &SymbolList) -> FdResult<ValueType> {
+    let mut parser = Parser {
+        expr,
+        tokens: tokenize(expr)?,
+        pos: 0,
+        syms,
+    };
+    let value = parser.binary(0)?;
+    if parser.pos != parser.tokens.len() {
+        return Err(parser.error());
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::symbols::{Scope, Symbol, SymbolKind, SymbolList};
+
+    use super::eval;
+
+    #[test]
+    fn expressions() {
+        let mut syms = SymbolList::default();
+        syms.def_symbol(Symbol::new(
+            "label".into(),
+            SymbolKind::Label,
+            Scope::Global,
+            0x8000,
+            1,
+        ));
+
+        assert_eq!(0x8020, eval("label+0x20", &syms).unwrap());
+        assert_eq!(0x0F, eval("0b1010|0x0f", &syms).unwrap());
+        assert_eq!(14, eval("2 + 3 * 4", &syms).unwrap());
+        assert_eq!(20, eval("(2 + 3) * 4", &syms).unwrap());
+        assert_eq!(0x80, eval("label >> 8", &syms).unwrap());
+        assert_eq!(-1, eval("-$01", &syms).unwrap());
+        assert_eq!(0xFF, eval("~0xFF00 & 0o377", &syms).unwrap());
+
+        assert!(eval("missing + 1", &syms).is_err());
+        assert!(eval("1 / 0", &syms).is_err());
+        assert!(eval("(1 + 2", &syms).is_err());
+        assert!(eval("1 2", &syms).is_err());
+    }
+}
@@ -0,0 +1,455 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::prelude::FdResult;
+
+use super::{
+    arch::{Archs, CallbackKind, Context, Node, NodeKind},
+    dispatch, interrupts,
+    symbols::{ReferenceKind, SymbolKind},
+    Address, ValueType,
+};
+
+const CONDITIONAL_BRANCHES: [&str; 14] = [
+    "bcc", "bcs", "beq", "bmi", "bne", "bpl", "bvc", "bvs", "bhi", "bls", "bge", "blt", "bgt",
+    "ble",
+];
+
+/// An instruction of a silent output pass
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Instruction {
+    pub address: Address,
+    pub name: String,
+    // the value of the first operand, the target of branches
+    pub operand: Option<Address>,
+    // the operand is a pointer to the target, e.g. jmp ($fffc)
+    pub indirect: bool,
+    // the name and the operands as they are output
+    pub text: String,
+    // the known targets of a computed jump, e.g. the entries of an rts dispatch table
+    pub targets: Vec<Address>,
+}
+
+/// Where the execution continues after an instruction
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Flow {
+    // the next instruction
+    Next,
+    // the target or the next instruction
+    Branch(Address),
+    Jump(Address),
+    // the target and then the next instruction
+    Call(Address),
+    Return,
+    InterruptReturn,
+    // the target is not known or the instruction is data
+    Stop,
+}
+
+impl Instruction {
+    /// The flow of the 6502 family and related cpus, based on the matcher names
+    pub fn flow(&self) -> Flow {
+        let name = self.name.to_lowercase();
+        let target = self.operand.filter(|_| !self.indirect);
+        match (name.as_str(), target) {
+            ("rts" | "rtl", _) => Flow::Return,
+            ("rti" | "reti", _) => Flow::InterruptReturn,
+            ("jmp" | "jml" | "bra" | "brl", Some(target)) => Flow::Jump(target),
+            ("jsr" | "jsl" | "bsr", Some(target)) => Flow::Call(target),
+            ("jmp" | "jml" | "bra" | "brl" | "brk" | "stp", _) => Flow::Stop,
+            (name, Some(target))
+                if CONDITIONAL_BRANCHES.contains(&name)
+                    || name.starts_with("bbr")
+                    || name.starts_with("bbs") =>
+            {
+                Flow::Branch(target)
+            }
+            (name, _) if name.starts_with('.') => Flow::Stop,
+            _ => Flow::Next,
+        }
+    }
+}
+
+/// How an instruction references its operand, based on the matcher names like the flow
+pub fn reference_kind(name: &str) -> ReferenceKind {
+    let instruction = Instruction {
+        name: name.trim().to_owned(),
+        operand: Some(0),
+        ..Default::default()
+    };
+    match instruction.flow() {
+        Flow::Call(_) => ReferenceKind::Call,
+        Flow::Branch(_) | Flow::Jump(_) => ReferenceKind::Branch,
+        _ => ReferenceKind::Data,
+    }
+}
+
+// builds the instructions from the nodes of an output pass
+#[derive(Default)]
+pub(crate) struct Collector {
+    pub(crate) instructions: Vec<Instruction>,
+    // the text of an instruction ends with its line
+    line_open: bool,
+}
+
+impl Collector {
+    pub(crate) fn push(&mut self, node: &Node, kind: &CallbackKind, address: Address) {
+        if matches!(kind, CallbackKind::MatcherName) {
+            self.instructions.push(Instruction {
+                address,
+                name: node.string.trim().to_owned(),
+                ..Default::default()
+            });
+            self.line_open = true;
+        }
+        let Some(instruction) = self.instructions.last_mut().filter(|_| self.line_open) else {
+            return;
+        };
+        match kind {
+            CallbackKind::Val | CallbackKind::Symbol => {
+                if let NodeKind::Value(value) = node.kind {
+                    instruction.operand = instruction.operand.or(Some(value as Address));
+                }
+            }
+            CallbackKind::Static if node.string.contains(['(', '[']) => {
+                instruction.indirect |= instruction.operand.is_none();
+            }
+            _ => {}
+        }
+        if matches!(
+            kind,
+            CallbackKind::MatcherName
+                | CallbackKind::Val
+                | CallbackKind::Symbol
+                | CallbackKind::Static
+        ) {
+            let mut lines = node.string.split('\n');
+            instruction.text.push_str(lines.next().unwrap_or_default());
+            self.line_open = lines.next().is_none();
+        }
+    }
+}
+
+/// Collects the instructions of the output by their address
+/// This restarts the ctx and runs a silent output pass over the whole data,
+/// so the analyses that build on it cost one disassembly each
+pub fn instructions(
+    arch: &Archs,
+    data: &[u8],
+    ctx: &mut Context,
+) -> FdResult<BTreeMap<Address, Instruction>> {
+    let mut collector = Collector::default();
+    ctx.restart();
+    arch.disas_ctx(
+        |node, kind, _raw, _arch, ctx| {
+            collector.push(node, &kind, ctx.address());
+            Ok(())
+        },
+        data,
+        ctx,
+    )?;
+    Ok(collector
+        .instructions
+        .into_iter()
+        .map(|mut instruction| {
+            instruction.text = instruction.text.trim().to_owned();
+            (instruction.address, instruction)
+        })
+        .collect())
+}
+
+/// The instructions that can follow the instruction at the address
+/// Calls continue at their target if they are followed, and at the next instruction otherwise
+pub fn successors(
+    instructions: &BTreeMap<Address, Instruction>,
+    address: Address,
+    follow_calls: bool,
+) -> Vec<Address> {
+    let Some(instruction) = instructions.get(&address) else {
+        return vec![];
+    };
+    let next = instructions
+        .range(address + 1..)
+        .next()
+        .map(|(address, _)| *address);
+    match instruction.flow() {
+        Flow::Next => next.into_iter().collect(),
+        Flow::Call(target) if follow_calls => std::iter::once(target).chain(next).collect(),
+        Flow::Call(_) => next.into_iter().collect(),
+        Flow::Branch(target) => std::iter::once(target).chain(next).collect(),
+        Flow::Jump(target) => vec![target],
+        Flow::Return | Flow::InterruptReturn | Flow::Stop => vec![],
+    }
+}
+
+fn walk(
+    instructions: &BTreeMap<Address, Instruction>,
+    root: Address,
+    follow_calls: bool,
+) -> BTreeSet<Address> {
+    let mut reached = BTreeSet::new();
+    let mut pending = vec![root];
+    while let Some(address) = pending.pop() {
+        if instructions.contains_key(&address) && reached.insert(address) {
+            pending.extend(successors(instructions, address, follow_calls));
+        }
+    }
+    reached
+}
+
+/// The addresses of the instructions that can be executed from the root
+/// Calls are followed, the walk stops at returns and unknown targets
+pub fn reachable(
+    instructions: &BTreeMap<Address, Instruction>,
+    root: Address,
+) -> BTreeSet<Address> {
+    walk(instructions, root, true)
+}
+
+/// Instructions that are executed in order, only the first one is a target
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Block {
+    pub start: Address,
+    pub instructions: Vec<Address>,
+    // the starts of the blocks that may be executed next
+    pub successors: Vec<Address>,
+}
+
+/// The blocks that are reached from an entry point without following calls
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Function {
+    pub name: String,
+    pub entry: Address,
+    pub blocks: Vec<Block>,
+    // the entry points of the called functions and of the dispatch targets
+    pub calls: Vec<Address>,
+}
+
+impl Function {
+    fn new(instructions: &BTreeMap<Address, Instruction>, name: String, entry: Address) -> Self {
+        let body = walk(instructions, entry, false);
+        let mut leaders = BTreeSet::from([entry]);
+        let mut calls = vec![];
+        for address in &body {
+            match instructions[address].flow() {
+                Flow::Branch(_) | Flow::Jump(_) => {
+                    leaders.extend(successors(instructions, *address, false))
+                }
+                Flow::Call(target) if !calls.contains(&target) => calls.push(target),
+                _ => {}
+            }
+            for target in &instructions[address].targets {
+                if !calls.contains(target) {
+                    calls.push(*target);
+                }
+            }
+        }
+
+        let mut blocks: Vec<Block> = vec![];
+        for address in &body {
+            let follows = blocks
+                .last()
+                .and_then(|b| b.instructions.last())
+                .filter(|last| matches!(instructions[last].flow(), Flow::Next | Flow::Call(_)))
+                .is_some_and(|last| successors(instructions, *last, false) == [*address]);
+            match blocks.last_mut() {
+                Some(block) if follows && !leaders.contains(address) => {
+                    block.instructions.push(*address)
+                }
+                _ => blocks.push(Block {
+                    start: *address,
+                    instructions: vec![*address],
+                    successors: vec![],
+                }),
+            }
+        }
+        for block in blocks.iter_mut() {
+            if let Some(last) = block.instructions.last() {
+                block.successors = successors(instructions, *last, false);
+            }
+        }
+        Self {
+            name,
+            entry,
+            blocks,
+            calls,
+        }
+    }
+}
+
+/// Splits the code that is reached from the roots into functions
+/// The called functions are named by their label or sub_ and the address
+pub fn functions(
+    instructions: &BTreeMap<Address, Instruction>,
+    roots: &[(String, Address)],
+    ctx: &Context,
+) -> Vec<Function> {
+    let label = |address: Address| {
+        ctx.syms
+            .get_symbols(address as ValueType)
+            .iter()
+            .find(|s| s.kind == SymbolKind::Label)
+            .map(|s| s.name.clone())
+    };
+    let mut functions: Vec<Function> = vec![];
+    let mut pending: Vec<(String, Address)> = roots.iter().rev().cloned().collect();
+    while let Some((name, entry)) = pending.pop() {
+        if !instructions.contains_key(&entry) || functions.iter().any(|f| f.entry == entry) {
+            continue;
+        }
+        let function = Function::new(instructions, label(entry).unwrap_or(name), entry);
+        for call in function.calls.iter().rev() {
+            pending.push((format!("sub_{call:x}"), *call));
+        }
+        functions.push(function);
+    }
+    functions
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Writes the functions as a Graphviz digraph with a cluster per function
+/// Calls are dashed edges to the entry of the called function
+/// and dispatch targets are dotted edges
+pub fn to_dot(
+    functions: &[Function],
+    instructions: &BTreeMap<Address, Instruction>,
+    handlers: &[Address],
+) -> String {
+    let id = |function: usize, block: Address| format!("f{function}_{block:x}");
+    let mut result = "digraph cfg {\n    node [shape=box fontname=\"monospace\"];\n".to_owned();
+    let mut edges = String::new();
+    for (i, function) in functions.iter().enumerate() {
+        let kind = if handlers.contains(&function.entry) {
+            " (interrupt handler)"
+        } else {
+            ""
+        };
+        result.push_str(&format!(
+            "    subgraph cluster_{i} {{\n        label=\"{}{kind}\";\n",
+            escape(&function.name)
+        ));
+        for block in &function.blocks {
+            let lines: String = block
+                .instructions
+                .iter()
+                .map(|at| format!("{at:04x} {}\\l", escape(&instructions[at].text)))
+                .collect();
+            result.push_str(&format!(
+                "        {} [label=\"{lines}\"];\n",
+                id(i, block.start)
+            ));
+            for successor in &block.successors {
+                edges.push_str(&format!(
+                    "    {} -> {};\n",
+                    id(i, block.start),
+                    id(i, *successor)
+                ));
+            }
+            for at in &block.instructions {
+                let instruction = &instructions[at];
+                let call = match instruction.flow() {
+                    Flow::Call(target) => Some((target, "dashed")),
+                    _ => None,
+                };
+                let targets = instruction.targets.iter().map(|t| (*t, "dotted"));
+                for (target, style) in call.into_iter().chain(targets) {
+                    if let Some(callee) = functions.iter().position(|f| f.entry == target) {
+                        edges.push_str(&format!(
+                            "    {} -> {} [style={style}];\n",
+                            id(i, block.start),
+                            id(callee, target)
+                        ));
+                    }
+                }
+            }
+        }
+        result.push_str("    }\n");
+    }
+    result.push_str(&edges);
+    result.push_str("}\n");
+    result
+}
+
+/// The first instruction of the window followed by the vectors
+pub fn roots(
+    instructions: &BTreeMap<Address, Instruction>,
+    vectors: Vec<(String, Address)>,
+) -> Vec<(String, Address)> {
+    instructions
+        .keys()
+        .next()
+        .map(|start| ("start".to_owned(), *start))
+        .into_iter()
+        .chain(vectors)
+        .collect()
+}
+
+/// The control flow graph of the code that is reached from the start of the window
+/// and from the interrupt vectors as a Graphviz digraph
+/// The targets of rts dispatch tables are followed as well
+pub fn control_flow_graph(arch: &Archs, data: &[u8], ctx: &mut Context) -> FdResult<String> {
+    let mut instructions = instructions(arch, data, ctx)?;
+    let vectors = interrupts::vectors(ctx, data);
+    let handlers: Vec<Address> = interrupts::handlers(&instructions, &vectors)
+        .iter()
+        .map(|h| h.address)
+        .collect();
+    let roots = roots(&instructions, vectors);
+    dispatch::resolve(&mut instructions, &roots, data, ctx);
+    let functions = functions(&instructions, &roots, ctx);
+    Ok(to_dot(&functions, &instructions, &handlers))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{
+        arch::{a6502, Context},
+        Address,
+    };
+
+    use super::{functions, instructions, to_dot, Block, Flow};
+
+    #[test]
+    fn control_flow() {
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        let data = [
+            // ldx #0, loop: inx, bne loop
+            0xA2, 0x00, 0xE8, 0xD0, 0xFD, // jsr 800b, jmp 8008
+            0x20, 0x0B, 0x80, 0x4C, 0x08, 0x80, // 800b: rts
+            0x60,
+        ];
+        let instructions = instructions(&a6502::ARCH, &data, &mut ctx).unwrap();
+        assert_eq!(Flow::Branch(0x8002), instructions[&0x8003].flow());
+        assert_eq!(Flow::Call(0x800B), instructions[&0x8005].flow());
+        assert_eq!("jsr $800b", instructions[&0x8005].text);
+
+        let functions = functions(&instructions, &[("start".into(), 0x8000)], &ctx);
+        assert_eq!(2, functions.len());
+        assert_eq!("sub_800b", functions[1].name);
+        let block = |start, instructions: &[Address], successors: &[Address]| Block {
+            start,
+            instructions: instructions.to_vec(),
+            successors: successors.to_vec(),
+        };
+        assert_eq!(
+            vec![
+                block(0x8000, &[0x8000], &[0x8002]),
+                block(0x8002, &[0x8002, 0x8003], &[0x8002, 0x8005]),
+                block(0x8005, &[0x8005], &[0x8008]),
+                block(0x8008, &[0x8008], &[0x8008]),
+            ],
+            functions[0].blocks
+        );
+
+        let dot = to_dot(&functions, &instructions, &[]);
+        assert!(dot.contains("f0_8002 [label=\"8002 inx\\l8003 bne $fd\\l\"];\n"));
+        assert!(dot.contains("label=\"sub_800b\";"));
+        assert!(dot.contains("    f0_8002 -> f0_8005;\n"));
+        assert!(dot.contains("    f0_8005 -> f1_800b [style=dashed];\n"));
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::prelude::FdResult;
+
+use super::{
+    arch::{Archs, Context},
+    flow::{self, Flow, Instruction},
+    symbols::{Scope, Symbol, SymbolKind},
+    Address, ValueType,
+};
+
+/// The prefix of the labels that are defined for handlers
+pub const HANDLER_PREFIX: &str = "isr_";
+
+// the interrupt and reset vectors of the 6502 family at the end of the address space
+const VECTORS: [(&str, Address); 3] = [("nmi", 0xFFFA), ("reset", 0xFFFC), ("irq", 0xFFFE)];
+
+// the native mode vectors of the 65816 followed by its emulation mode vectors
+// the names are the ones of the snes loader
+const NATIVE_VECTORS: [(&str, Address); 10] = [
+    ("cop", 0xFFE4),
+    ("brk", 0xFFE6),
+    ("abort", 0xFFE8),
+    ("nmi", 0xFFEA),
+    ("irq", 0xFFEE),
+    ("emu_cop", 0xFFF4),
+    ("emu_abort", 0xFFF8),
+    ("emu_nmi", 0xFFFA),
+    ("reset", 0xFFFC),
+    ("emu_irq", 0xFFFE),
+];
+
+// the labels the loaders define for interrupt vectors (e.g. ines and snes)
+const VECTOR_LABELS: [&str; 10] = [
+    "nmi",
+    "reset",
+    "irq",
+    "brk",
+    "cop",
+    "abort",
+    "emu_nmi",
+    "emu_irq",
+    "emu_cop",
+    "emu_abort",
+];
+
+/// A routine that is reached from an interrupt vector and ends with an rti
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Handler {
+    pub vector: String,
+    pub address: Address,
+}
+
+/// The entry points of the interrupt and reset vectors
+/// The vectors at fffa-ffff (and ffe4-ffff for the 65816) are read if the context enables them
+/// and they are part of the input, the labels of vectors that loaders define are entry points as well
+pub fn vectors(ctx: &Context, data: &[u8]) -> Vec<(String, Address)> {
+    let mut vectors: Vec<(String, Address)> = vec![];
+    let table: &[(&str, Address)] = if ctx.native_vectors {
+        &NATIVE_VECTORS
+    } else if ctx.cpu_vectors {
+        &VECTORS
+    } else {
+        &[]
+    };
+    for (name, vector) in table.iter().copied() {
+        let Some(offset) = ctx.offset_of(vector, "") else {
+            continue;
+        };
+        if let Some(bytes) = data.get(offset..offset + 2) {
+            vectors.push((
+                name.into(),
+                u16::from_le_bytes([bytes[0], bytes[1]]) as Address,
+            ));
+        }
+    }
+    for sym in ctx.syms.iter() {
+        if sym.kind == SymbolKind::Label && VECTOR_LABELS.contains(&sym.name.as_str()) {
+            vectors.push((sym.name.clone(), sym.value as Address));
+        }
+    }
+    // the first name of an entry point is kept
+    let mut seen = BTreeSet::new();
+    vectors.retain(|(_, address)| seen.insert(*address));
+    vectors
+}
+
+/// Defines a label with the name of the vector for every entry point of the vectors
+/// that is not labeled yet. Returns the amount of labels that were defined
+pub fn label_vectors(ctx: &mut Context, data: &[u8]) -> usize {
+    let mut count = 0;
+    for (name, address) in vectors(ctx, data) {
+        if !is_labeled(ctx, address) {
+            ctx.def_auto_symbol(Symbol::new(
+                name,
+                SymbolKind::Label,
+                Scope::Global,
+                address as ValueType,
+                1,
+            ));
+            count += 1;
+        }
+    }
+    count
+}
+
+fn is_labeled(ctx: &Context, address: Address) -> bool {
+    ctx.syms
+        .get_symbols(address as ValueType)
+        .iter()
+        .any(|s| s.kind == SymbolKind::Label)
+}
+
+/// The vectors whose reachable code ends with an rti
+pub fn handlers(
+    instructions: &BTreeMap<Address, Instruction>,
+    vectors: &[(String, Address)],
+) -> Vec<Handler> {
+    vectors
+        .iter()
+        .filter(|(_, address)| {
+            flow::reachable(instructions, *address)
+                .iter()
+                .any(|at| instructions[at].flow() == Flow::InterruptReturn)
+        })
+        .map(|(vector, address)| Handler {
+            vector: vector.clone(),
+            address: *address,
+        })
+        .collect()
+}
+
+/// Finds the interrupt handlers that the vectors of the input point to
+pub fn find(arch: &Archs, data: &[u8], ctx: &mut Context) -> FdResult<Vec<Handler>> {
+    let instructions = flow::instructions(arch, data, ctx)?;
+    Ok(handlers(&instructions, &vectors(ctx, data)))
+}
+
+/// Adds a comment header to each handler and defines an isr_ label
+/// for the handlers that do not have a label yet.
+/// Returns the amount of labels that were defined
+pub fn annotate(ctx: &mut Context, handlers: &[Handler]) -> usize {
+    let mut count = 0;
+    for handler in handlers {
+        ctx.comments
+            .entry(handler.address)
+            .or_insert_with(|| format!("interrupt handler ({})", handler.vector));
+        if !is_labeled(ctx, handler.address) {
+            ctx.def_auto_symbol(Symbol::new(
+                format!("{HANDLER_PREFIX}{}", handler.vector),
+                SymbolKind::Label,
+                Scope::Global,
+                handler.address as ValueType,
+                1,
+            ));
+            count += 1;
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{
+        arch::{a6502, Context},
+        symbols::{Scope, Symbol, SymbolKind},
+        Address,
+    };
+
+    use super::{annotate, find, label_vectors, vectors, Handler};
+
+    #[test]
+    fn handlers() {
+        let mut ctx = Context {
+            org: 0xFFF0,
+            cpu_vectors: true,
+            ..Default::default()
+        };
+        // a loader label that is not a handler
+        ctx.def_symbol(Symbol::new(
+            "brk".into(),
+            SymbolKind::Label,
+            Scope::Global,
+            0xFFF5,
+            1,
+        ));
+        let data = [
+            // fff0: inc $10, bne fff5, rti
+            0xE6, 0x10, 0xD0, 0x01, 0x40, // fff5: rts
+            0x60, // fff6: pha, pla, rti, nop
+            0x48, 0x68, 0x40, 0xEA, // fffa: nmi fff0, reset fff0, irq fff6
+            0xF0, 0xFF, 0xF0, 0xFF, 0xF6, 0xFF,
+        ];
+        let handlers = find(&a6502::ARCH, &data, &mut ctx).unwrap();
+        assert_eq!(
+            vec![
+                Handler {
+                    vector: "nmi".into(),
+                    address: 0xFFF0
+                },
+                Handler {
+                    vector: "irq".into(),
+                    address: 0xFFF6
+                }
+            ],
+            handlers
+        );
+
+        assert_eq!(2, annotate(&mut ctx, &handlers));
+        assert_eq!("interrupt handler (irq)", ctx.comments[&0xFFF6]);
+        assert_eq!(0xFFF0, ctx.syms.get_first_by_name("isr_nmi").unwrap().value);
+        assert_eq!(0, annotate(&mut ctx, &handlers));
+    }
+
+    #[test]
+    fn vector_labels() {
+        let mut ctx = Context {
+            org: 0xFFF0,
+            cpu_vectors: true,
+            ..Default::default()
+        };
+        // fff0: rti, fff1: jmp fff1
+        let mut data = vec![0x40, 0x4C, 0xF1, 0xFF];
+        data.resize(0x0A, 0xEA);
+        // fffa: nmi fff0, reset fff1, irq fff0
+        data.extend([0xF0, 0xFF, 0xF1, 0xFF, 0xF0, 0xFF]);
+        // archs outside of the 6502 family have no vectors there
+        let other = Context {
+            org: 0xFFF0,
+            ..Default::default()
+        };
+        assert!(vectors(&other, &data).is_empty());
+        assert_eq!(
+            vec![("nmi".to_owned(), 0xFFF0), ("reset".into(), 0xFFF1)],
+            vectors(&ctx, &data)
+        );
+        assert_eq!(2, label_vectors(&mut ctx, &data));
+        assert_eq!(0xFFF1, ctx.syms.get_first_by_name("reset").unwrap().value);
+        assert_eq!(0, label_vectors(&mut ctx, &data));
+
+        // the 65816 reads the native mode vectors as well
+        let mut ctx = Context {
+            org: 0xFFE0,
+            native_vectors: true,
+            ..Default::default()
+        };
+        let mut data = vec![0xEA; 0x20];
+        data[0x0A..0x0C].copy_from_slice(&[0xE0, 0xFF]);
+        data[0x1C..0x1E].copy_from_slice(&[0xE1, 0xFF]);
+        let names: Vec<(String, Address)> = vectors(&ctx, &data)
+            .into_iter()
+            .filter(|(_, address)| *address != 0xEAEA)
+            .collect();
+        assert_eq!(
+            vec![("nmi".to_owned(), 0xFFE0), ("reset".into(), 0xFFE1)],
+            names
+        );
+        label_vectors(&mut ctx, &data);
+        assert_eq!(0xFFE0, ctx.syms.get_first_by_name("nmi").unwrap().value);
+    }
+}
@@ -0,0 +1,233 @@
+use log::info;
+
+use crate::{
+    core::dasm::{
+        arch::Context,
+        overlay::Overlay,
+        region::Region,
+        symbols::{Scope, Symbol, SymbolKind},
+        Address, ValueType,
+    },
+    prelude::{Error, FdResult},
+};
+
+pub const BANK_ORG: Address = 0xF000;
+// the 2K banks of 3F like schemes that are always mapped to the upper half
+pub const FIXED_BANK_ORG: Address = 0xF800;
+
+// the 6507 vectors counted from the end of the address space
+const VECTORS: [(&str, usize); 2] = [("reset", 4), ("irq", 2)];
+
+/// The bankswitching schemes of the most common 2600 cartridges
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BankScheme {
+    // 2 banks of 4K, switched by accessing fff8-fff9
+    F8,
+    // 4 banks of 4K, switched by accessing fff6-fff9
+    F6,
+    // 8 banks of 4K, switched by accessing fff4-fffb
+    F4,
+    // 2K banks at f000-f7ff, switched by writing to 3f
+    // the last bank is always at f800-ffff
+    Tigervision3F,
+}
+
+impl BankScheme {
+    pub fn bank_size(&self) -> usize {
+        match self {
+            BankScheme::Tigervision3F => 0x800,
+            _ => 0x1000,
+        }
+    }
+
+    // the required size of the rom, 3F roms only need to be a multiple of the bank size
+    fn rom_size(&self) -> Option<usize> {
+        match self {
+            BankScheme::F8 => Some(0x2000),
+            BankScheme::F6 => Some(0x4000),
+            BankScheme::F4 => Some(0x8000),
+            BankScheme::Tigervision3F => None,
+        }
+    }
+
+    // the first address that switches banks
+    fn hotspot(&self) -> Address {
+        match self {
+            BankScheme::F8 => 0xFFF8,
+            BankScheme::F6 => 0xFFF6,
+            BankScheme::F4 => 0xFFF4,
+            BankScheme::Tigervision3F => 0x3F,
+        }
+    }
+
+    fn bank_org(&self, bank: usize, banks: usize) -> Address {
+        match self {
+            BankScheme::Tigervision3F if bank + 1 == banks => FIXED_BANK_ORG,
+            _ => BANK_ORG,
+        }
+    }
+}
+
+// the flag that holds the bank that is mapped to f000
+pub const BANK_FLAG: &str = "bank";
+
+pub fn bank_name(bank: usize) -> String {
+    format!("bank{bank}")
+}
+
+/// Splits the rom into a region per bank
+/// Every bank is disassembled at its own org and uses its own label namespace
+pub fn load(data: &[u8], ctx: &mut Context, scheme: BankScheme) -> FdResult<()> {
+    let size = scheme.bank_size();
+    let valid = match scheme.rom_size() {
+        Some(rom_size) => data.len() == rom_size,
+        None => !data.is_empty() && data.len().is_multiple_of(size),
+    };
+    if !valid {
+        return Err(Error::InvalidHeader(format!(
+            "Atari 2600 {scheme:?} ({:#x} bytes)",
+            data.len()
+        )));
+    }
+
+    let banks = data.len() / size;
+    info!(
+        "Atari 2600 {scheme:?}: {banks} banks of {size:#x} bytes, hotspot {:x}",
+        scheme.hotspot()
+    );
+    // the switchable banks share their window, references into it use the active bank
+    ctx.overlays.push(Overlay::new(
+        BANK_ORG,
+        BANK_ORG + size as Address,
+        BANK_FLAG,
+    ));
+    for bank in 0..banks {
+        let name = bank_name(bank);
+        let org = scheme.bank_org(bank, banks);
+        ctx.regions.push(Region::new(
+            &name,
+            &ctx.arch_key,
+            bank * size,
+            (bank + 1) * size,
+            org,
+        ));
+        // all banks share their addresses, the bank's label tells them apart
+        let mut label = Symbol::new(
+            name.clone(),
+            SymbolKind::Label,
+            Scope::Global,
+            org as ValueType,
+            1,
+        );
+        label.namespace = name.clone();
+        ctx.def_symbol(label);
+
+        // only banks that reach the end of the address space contain vectors
+        if org + size as Address != 0x10000 {
+            continue;
+        }
+        let end = (bank + 1) * size;
+        for (vector, from_end) in VECTORS {
+            let pointer = &data[end - from_end..end - from_end + 2];
+            // carts may use any mirror of the rom area
+            let address =
+                (u16::from_le_bytes([pointer[0], pointer[1]]) as Address & 0xFFF) | BANK_ORG;
+            let mut sym = Symbol::new(
+                vector.into(),
+                SymbolKind::Label,
+                Scope::Global,
+                address as ValueType,
+                1,
+            );
+            sym.namespace = name.clone();
+            ctx.def_symbol(sym);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{
+        arch::{a6502, Context},
+        loader::LoaderKind,
+    };
+
+    #[test]
+    fn f8() {
+        let mut rom = vec![0xEA; 0x2000];
+        rom[0x0FFC..0x1000].copy_from_slice(&[0x00, 0xF0, 0x01, 0xF0]);
+        rom[0x1FFC..0x2000].copy_from_slice(&[0x00, 0x10, 0x01, 0x10]);
+        let mut ctx = Context::default();
+        let data = LoaderKind::AtariF8.load(rom, &mut ctx).unwrap();
+        assert_eq!(2, ctx.regions.len());
+        assert_eq!(0xF000, ctx.regions[1].org);
+        assert_eq!(0xF000..0x10000, ctx.overlays[0].start..ctx.overlays[0].end);
+
+        let resets = ctx
+            .syms
+            .get_symbols(0xF000)
+            .iter()
+            .filter(|s| s.name == "reset")
+            .map(|s| s.namespace.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["bank0".to_string(), "bank1".to_string()], resets);
+
+        let mut result = String::new();
+        a6502::ARCH
+            .disas_ctx(
+                |n, _kind, _raw, _arch, _ctx| {
+                    result.push_str(&n.string);
+                    Ok(())
+                },
+                &data,
+                &mut ctx,
+            )
+            .unwrap();
+        assert!(result.starts_with("bank0:\nreset:\n0000f000 nop\nirq:\n0000f001 nop\n"));
+        assert!(result.contains("bank1:\nreset:\n0000f000 nop\nirq:\n0000f001 nop\n"));
+
+        assert!(LoaderKind::AtariF6
+            .load(vec![0; 0x2000], &mut Context::default())
+            .is_err());
+    }
+
+    #[test]
+    fn tigervision() {
+        let mut ctx = Context::default();
+        LoaderKind::Atari3F.load(vec![0; 0x2000], &mut ctx).unwrap();
+        assert_eq!(4, ctx.regions.len());
+        assert_eq!(0xF000, ctx.regions[2].org);
+        assert_eq!(0xF800, ctx.regions[3].org);
+        // the vectors are only in the fixed bank
+        let vectors = ctx
+            .syms
+            .get_symbols(0xF000)
+            .iter()
+            .filter(|s| s.name == "reset" || s.name == "irq")
+            .map(|s| s.namespace.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["bank3".to_string(), "bank3".to_string()], vectors);
+        assert_eq!(
+            "bank3",
+            ctx.syms.get_first_by_name("bank3").unwrap().namespace
+        );
+    }
+
+    #[test]
+    fn banks() {
+        let mut ctx = Context::default();
+        LoaderKind::AtariF6.load(vec![0; 0x4000], &mut ctx).unwrap();
+        assert_eq!(4, ctx.banks().len());
+        assert_eq!(0x2000, ctx.bank("2").unwrap().start);
+        assert_eq!(0x3000, ctx.bank("bank3").unwrap().start);
+        assert_eq!(0x1010, ctx.bank_offset("1", 0x10).unwrap());
+        assert!(ctx.bank_offset("1", 0x1000).is_err());
+        assert!(ctx.bank("4").is_err());
+
+        ctx.view_bank("2").unwrap();
+        let window = ctx.window(&[0; 0x4000]);
+        assert_eq!(0x2000..0x3000, window.start..window.end);
+        assert_eq!(Some(&"bank2".to_string()), ctx.active_bank(0xF000));
+    }
+}
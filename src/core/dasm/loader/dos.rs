@@ -0,0 +1,196 @@
+use log::info;
+
+use crate::{
+    core::dasm::{
+        arch::Context,
+        symbols::{Scope, Symbol, SymbolKind},
+        Address, ValueType,
+    },
+    prelude::{Error, FdResult},
+};
+
+pub const MZ_HEADER_SIZE: usize = 0x1C;
+pub const PARAGRAPH_SIZE: usize = 16;
+const PAGE_SIZE: usize = 512;
+// the segment the image is loaded at, addresses are linear (segment * 16 + offset)
+pub const LOAD_SEGMENT: u16 = 0x1000;
+// .com files are loaded directly after the program segment prefix
+pub const COM_ORG: Address = 0x100;
+
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct MzHeader {
+    // the size of the load image including the header
+    pub image_size: usize,
+    pub header_size: usize,
+    pub min_alloc: u16,
+    pub max_alloc: u16,
+    pub ss: u16,
+    pub sp: u16,
+    pub ip: u16,
+    pub cs: u16,
+    // segment:offset pairs relative to the start of the load image
+    pub relocations: Vec<(u16, u16)>,
+}
+
+impl MzHeader {
+    pub fn parse(data: &[u8]) -> FdResult<Self> {
+        if data.len() < MZ_HEADER_SIZE || (&data[..2] != b"MZ" && &data[..2] != b"ZM") {
+            return Err(Error::InvalidHeader("MZ".into()));
+        }
+        let word = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+
+        let last_page = word(0x02) as usize;
+        let pages = word(0x04) as usize;
+        let image_size = match last_page {
+            0 => pages * PAGE_SIZE,
+            last => (pages.saturating_sub(1)) * PAGE_SIZE + last,
+        };
+        let header_size = word(0x08) as usize * PARAGRAPH_SIZE;
+        if header_size < MZ_HEADER_SIZE || header_size > image_size.min(data.len()) {
+            return Err(Error::InvalidHeader("MZ".into()));
+        }
+
+        let table = word(0x18) as usize;
+        let relocations = (0..word(0x06) as usize)
+            .map(|i| {
+                let at = table + i * 4;
+                match data.get(at..at + 4) {
+                    Some(entry) => Ok((
+                        u16::from_le_bytes([entry[2], entry[3]]),
+                        u16::from_le_bytes([entry[0], entry[1]]),
+                    )),
+                    None => Err(Error::InvalidHeader("MZ relocation table".into())),
+                }
+            })
+            .collect::<FdResult<Vec<_>>>()?;
+
+        Ok(Self {
+            image_size,
+            header_size,
+            min_alloc: word(0x0A),
+            max_alloc: word(0x0C),
+            ss: word(0x0E),
+            sp: word(0x10),
+            ip: word(0x14),
+            cs: word(0x16),
+            relocations,
+        })
+    }
+
+    pub fn entry(&self, load_segment: u16) -> Address {
+        linear(load_segment.wrapping_add(self.cs), self.ip)
+    }
+
+    pub fn comment(&self, load_segment: u16) -> String {
+        format!(
+            "MZ header: {} bytes, {} relocations, cs:ip {:04x}:{:04x}, ss:sp {:04x}:{:04x}, loaded at {load_segment:04x}",
+            self.image_size - self.header_size,
+            self.relocations.len(),
+            self.cs,
+            self.ip,
+            self.ss,
+            self.sp
+        )
+    }
+}
+
+fn linear(segment: u16, offset: u16) -> Address {
+    (segment as Address) * PARAGRAPH_SIZE as Address + offset as Address
+}
+
+/// Skips the header of a DOS executable and applies the relocations
+/// for the load segment
+pub fn load_mz(data: &[u8], ctx: &mut Context) -> FdResult<Vec<u8>> {
+    let header = MzHeader::parse(data)?;
+    info!("{}", header.comment(LOAD_SEGMENT));
+
+    // the segment references in the image are relative to the load segment
+    let mut data = data.to_vec();
+    for (segment, offset) in header.relocations.iter() {
+        let at = header.header_size + linear(*segment, *offset) as usize;
+        match data.get_mut(at..at + 2) {
+            Some(word) if at + 2 <= header.image_size => {
+                let value = u16::from_le_bytes([word[0], word[1]]).wrapping_add(LOAD_SEGMENT);
+                word.copy_from_slice(&value.to_le_bytes());
+            }
+            _ => ctx.warn(&format!(
+                "MZ relocation {segment:04x}:{offset:04x} is outside of the load image"
+            )),
+        }
+    }
+
+    let base = linear(LOAD_SEGMENT, 0);
+    ctx.set_start(Some(header.header_size));
+    ctx.set_len(Some(header.image_size.min(data.len()) - header.header_size));
+    // the header is not part of the address space
+    ctx.set_org(base.wrapping_sub(header.header_size as Address));
+    ctx.comments.insert(base, header.comment(LOAD_SEGMENT));
+    ctx.def_symbol(Symbol::new(
+        "entry".into(),
+        SymbolKind::Label,
+        Scope::Global,
+        header.entry(LOAD_SEGMENT) as ValueType,
+        1,
+    ));
+    Ok(data)
+}
+
+/// .com files do not have a header and always start at 100
+pub fn load_com(_data: &[u8], ctx: &mut Context) -> FdResult<()> {
+    ctx.set_org(COM_ORG);
+    ctx.def_symbol(Symbol::new(
+        "entry".into(),
+        SymbolKind::Label,
+        Scope::Global,
+        COM_ORG as ValueType,
+        1,
+    ));
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{arch::Context, loader::LoaderKind};
+
+    use super::MzHeader;
+
+    // a header of 2 paragraphs, 4 bytes of code and one relocation at 0000:0002
+    fn executable() -> Vec<u8> {
+        let mut data = vec![0; 0x24];
+        data[..2].copy_from_slice(b"MZ");
+        data[0x02] = 0x24;
+        data[0x04] = 1;
+        data[0x06] = 1;
+        data[0x08] = 2;
+        data[0x14] = 0x02;
+        data[0x16] = 0x01;
+        data[0x18] = 0x1C;
+        data[0x1C] = 0x02;
+        data[0x20..0x24].copy_from_slice(&[0x90, 0x90, 0x34, 0x12]);
+        data
+    }
+
+    #[test]
+    fn mz() {
+        let header = MzHeader::parse(&executable()).unwrap();
+        assert_eq!(0x20, header.header_size);
+        assert_eq!(vec![(0, 2)], header.relocations);
+        assert!(MzHeader::parse(b"MZ").is_err());
+
+        let mut ctx = Context::default();
+        let data = LoaderKind::Mz.load(executable(), &mut ctx).unwrap();
+        assert_eq!(&[0x34, 0x22], &data[0x22..0x24]);
+        assert_eq!(0x20, ctx.start_read);
+        assert_eq!(Some(4), ctx.len_read);
+        assert_eq!(0x10000, ctx.address());
+        assert_eq!(0x10012, ctx.syms.get_first_by_name("entry").unwrap().value);
+    }
+
+    #[test]
+    fn com() {
+        let mut ctx = Context::default();
+        LoaderKind::Com.load(vec![0xC3], &mut ctx).unwrap();
+        assert_eq!(0x100, ctx.address());
+        assert_eq!(0x100, ctx.syms.get_first_by_name("entry").unwrap().value);
+    }
+}
@@ -0,0 +1,409 @@
+use log::info;
+
+use crate::{
+    core::dasm::{
+        arch::Context,
+        region::Region,
+        symbols::{Scope, Symbol, SymbolKind},
+        Address, ValueType,
+    },
+    prelude::{Error, FdResult},
+};
+
+const MAGIC: &[u8] = b"\x7FELF";
+const CLASS_64: u8 = 2;
+const DATA_BIG_ENDIAN: u8 = 2;
+
+const PT_LOAD: u32 = 1;
+const PF_X: u32 = 1;
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_DYNSYM: u32 = 11;
+const SHF_EXECINSTR: u64 = 4;
+const SHN_UNDEF: u16 = 0;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+
+// reads values using the elf's class and byte order
+struct Reader<'a> {
+    data: &'a [u8],
+    is_64: bool,
+    big_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&self, at: usize, len: usize) -> FdResult<&'a [u8]> {
+        at.checked_add(len)
+            .and_then(|end| self.data.get(at..end))
+            .ok_or_else(|| Error::InvalidHeader("ELF".into()))
+    }
+
+    // the offset of an entry of a table, it has to start inside of the file
+    // so that the offsets of its fields do not overflow
+    fn entry(&self, offset: usize, index: usize, size: usize) -> FdResult<usize> {
+        index
+            .checked_mul(size)
+            .and_then(|n| n.checked_add(offset))
+            .filter(|at| *at <= self.data.len())
+            .ok_or_else(|| Error::InvalidHeader("ELF".into()))
+    }
+
+    fn uint(&self, at: usize, len: usize) -> FdResult<u64> {
+        let bytes = self.bytes(at, len)?;
+        let fold = |v: u64, b: &u8| v << 8 | *b as u64;
+        Ok(if self.big_endian {
+            bytes.iter().fold(0, fold)
+        } else {
+            bytes.iter().rev().fold(0, fold)
+        })
+    }
+
+    fn u16(&self, at: usize) -> FdResult<u16> {
+        Ok(self.uint(at, 2)? as u16)
+    }
+
+    fn u32(&self, at: usize) -> FdResult<u32> {
+        Ok(self.uint(at, 4)? as u32)
+    }
+
+    // addresses, offsets and sizes depend on the class
+    fn word(&self, at: usize) -> FdResult<u64> {
+        self.uint(at, if self.is_64 { 8 } else { 4 })
+    }
+
+    fn string(&self, at: usize) -> FdResult<String> {
+        let bytes = self
+            .data
+            .get(at..)
+            .ok_or_else(|| Error::InvalidHeader("ELF".into()))?;
+        let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+        Ok(String::from_utf8_lossy(&bytes[..end]).into_owned())
+    }
+}
+
+/// A piece of executable code in the file
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct ElfSegment {
+    pub name: String,
+    pub offset: usize,
+    pub size: usize,
+    pub address: Address,
+}
+
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+struct SectionHeader {
+    name: u32,
+    kind: u32,
+    flags: u64,
+    address: u64,
+    offset: u64,
+    size: u64,
+    link: u32,
+    entry_size: u64,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct Elf {
+    pub is_64: bool,
+    pub big_endian: bool,
+    pub machine: u16,
+    pub entry: Address,
+    // executable load segments or executable sections
+    // if the file has no program headers
+    pub segments: Vec<ElfSegment>,
+    pub symbols: Vec<Symbol>,
+}
+
+impl Elf {
+    pub fn parse(data: &[u8]) -> FdResult<Self> {
+        if data.len() < 0x34 || &data[..MAGIC.len()] != MAGIC {
+            return Err(Error::InvalidHeader("ELF".into()));
+        }
+        let r = Reader {
+            data,
+            is_64: data[4] == CLASS_64,
+            big_endian: data[5] == DATA_BIG_ENDIAN,
+        };
+        // the header fields after the entry point are shifted in 64 bit files
+        let w = if r.is_64 { 8 } else { 4 };
+        let mut elf = Self {
+            is_64: r.is_64,
+            big_endian: r.big_endian,
+            machine: r.u16(0x12)?,
+            entry: r.word(0x18)?,
+            ..Default::default()
+        };
+        let ph_offset = r.word(0x18 + w)? as usize;
+        let sh_offset = r.word(0x18 + w * 2)? as usize;
+        let at = 0x18 + w * 3 + 4;
+        let (ph_size, ph_num) = (r.u16(at + 2)? as usize, r.u16(at + 4)? as usize);
+        let (sh_size, sh_num) = (r.u16(at + 6)? as usize, r.u16(at + 8)? as usize);
+        let sh_strings = r.u16(at + 10)? as usize;
+
+        for i in 0..ph_num {
+            let at = r.entry(ph_offset, i, ph_size)?;
+            let kind = r.u32(at)?;
+            let flags = r.u32(if r.is_64 { at + 4 } else { at + 24 })?;
+            if kind != PT_LOAD || flags & PF_X == 0 {
+                continue;
+            }
+            let field = |n: usize| r.word(at + if r.is_64 { 8 } else { 4 } + n * w);
+            elf.segments.push(ElfSegment {
+                name: format!("segment{i}"),
+                offset: field(0)? as usize,
+                address: field(1)?,
+                size: field(3)? as usize,
+            });
+        }
+
+        let sections = (0..sh_num)
+            .map(|i| Self::section_header(&r, r.entry(sh_offset, i, sh_size)?))
+            .collect::<FdResult<Vec<_>>>()?;
+        let section_name = |section: &SectionHeader| -> FdResult<String> {
+            match sections.get(sh_strings).filter(|_| sh_strings != 0) {
+                Some(strings) => {
+                    r.string(r.entry(strings.offset as usize, section.name as usize, 1)?)
+                }
+                None => Ok("".into()),
+            }
+        };
+
+        // relocatable objects do not have program headers
+        if ph_num == 0 {
+            for section in sections.iter() {
+                if section.kind == SHT_PROGBITS && section.flags & SHF_EXECINSTR != 0 {
+                    elf.segments.push(ElfSegment {
+                        name: section_name(section)?,
+                        offset: section.offset as usize,
+                        size: section.size as usize,
+                        address: section.address,
+                    });
+                }
+            }
+        }
+
+        for section in sections.iter() {
+            if section.kind != SHT_SYMTAB && section.kind != SHT_DYNSYM {
+                continue;
+            }
+            let Some(strings) = sections.get(section.link as usize) else {
+                continue;
+            };
+            let count = section.size.checked_div(section.entry_size).unwrap_or(0);
+            for i in 0..count as usize {
+                let at = r.entry(section.offset as usize, i, section.entry_size as usize)?;
+                let (info, shndx, value) = if r.is_64 {
+                    (r.bytes(at + 4, 1)?[0], r.u16(at + 6)?, r.word(at + 8)?)
+                } else {
+                    (r.bytes(at + 12, 1)?[0], r.u16(at + 14)?, r.word(at + 4)?)
+                };
+                let kind = info & 0xF;
+                if shndx == SHN_UNDEF || (kind != STT_FUNC && kind != STT_OBJECT) {
+                    continue;
+                }
+                let name = r.string(r.entry(strings.offset as usize, r.u32(at)? as usize, 1)?)?;
+                if name.is_empty() {
+                    continue;
+                }
+                // symbols only label their first byte
+                elf.symbols.push(Symbol::new(
+                    name,
+                    SymbolKind::Label,
+                    Scope::Global,
+                    value as ValueType,
+                    1,
+                ));
+            }
+        }
+        Ok(elf)
+    }
+
+    fn section_header(r: &Reader, at: usize) -> FdResult<SectionHeader> {
+        let w = if r.is_64 { 8 } else { 4 };
+        Ok(SectionHeader {
+            name: r.u32(at)?,
+            kind: r.u32(at + 4)?,
+            flags: r.word(at + 8)?,
+            address: r.word(at + 8 + w)?,
+            offset: r.word(at + 8 + w * 2)?,
+            size: r.word(at + 8 + w * 3)?,
+            link: r.u32(at + 8 + w * 4)?,
+            entry_size: r.word(at + 16 + w * 5)?,
+        })
+    }
+}
+
+/// Only the executable segments are disassembled, each at its own address
+/// The segments are concatenated and a region is added for every segment
+pub fn load(data: &[u8], ctx: &mut Context) -> FdResult<Vec<u8>> {
+    let elf = Elf::parse(data)?;
+    info!(
+        "ELF machine {:#x}, entry {:x}, {} executable segments",
+        elf.machine,
+        elf.entry,
+        elf.segments.len()
+    );
+
+    let mut code = vec![];
+    for segment in elf.segments.iter() {
+        let bytes = segment
+            .offset
+            .checked_add(segment.size)
+            .and_then(|end| data.get(segment.offset..end))
+            .ok_or_else(|| Error::InvalidHeader("ELF".into()))?;
+        // the segments use the main processor's namespace
+        ctx.regions.push(Region::new(
+            "",
+            &ctx.arch_key,
+            code.len(),
+            code.len() + bytes.len(),
+            segment.address,
+        ));
+        ctx.comments.insert(
+            segment.address,
+            format!("ELF {}: {} bytes", segment.name, segment.size),
+        );
+        code.extend_from_slice(bytes);
+    }
+
+    let has_entry = elf
+        .symbols
+        .iter()
+        .any(|sym| sym.value == elf.entry as ValueType);
+    for sym in elf.symbols {
+        ctx.def_symbol(sym);
+    }
+    if !has_entry && elf.entry != 0 {
+        ctx.def_symbol(Symbol::new(
+            "entry".into(),
+            SymbolKind::Label,
+            Scope::Global,
+            elf.entry as ValueType,
+            1,
+        ));
+    }
+    Ok(code)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{
+        arch::{a6502, Context},
+        loader::LoaderKind,
+    };
+
+    use super::Elf;
+
+    fn put(data: &mut [u8], at: usize, value: u32, len: usize) {
+        data[at..at + len].copy_from_slice(&value.to_le_bytes()[..len]);
+    }
+
+    // a 32 bit little endian executable with one load segment at 1000
+    // followed by a symbol table with a single function
+    fn executable() -> Vec<u8> {
+        let mut data = vec![0; 0xF8];
+        data[..7].copy_from_slice(b"\x7FELF\x01\x01\x01");
+        put(&mut data, 0x12, 0x02, 2);
+        put(&mut data, 0x18, 0x1000, 4);
+        put(&mut data, 0x1C, 0x34, 4);
+        put(&mut data, 0x20, 0x80, 4);
+        put(&mut data, 0x2A, 0x20, 2);
+        put(&mut data, 0x2C, 1, 2);
+        put(&mut data, 0x2E, 0x28, 2);
+        put(&mut data, 0x30, 3, 2);
+
+        // program header
+        put(&mut data, 0x34, 1, 4);
+        put(&mut data, 0x38, 0x54, 4);
+        put(&mut data, 0x3C, 0x1000, 4);
+        put(&mut data, 0x44, 3, 4);
+        put(&mut data, 0x4C, 5, 4);
+        data[0x54..0x57].copy_from_slice(&[0xEA, 0xEA, 0x60]);
+
+        // the second symbol is a function at 1002
+        put(&mut data, 0x68, 1, 4);
+        put(&mut data, 0x6C, 0x1002, 4);
+        data[0x74] = 0x12;
+        put(&mut data, 0x76, 1, 2);
+        data[0x78..0x7F].copy_from_slice(b"\0done\0\0");
+
+        // section headers: null, symtab and strtab
+        put(&mut data, 0xA8 + 4, 2, 4);
+        put(&mut data, 0xA8 + 16, 0x58, 4);
+        put(&mut data, 0xA8 + 20, 0x20, 4);
+        put(&mut data, 0xA8 + 24, 2, 4);
+        put(&mut data, 0xA8 + 36, 0x10, 4);
+        put(&mut data, 0xD0 + 4, 3, 4);
+        put(&mut data, 0xD0 + 16, 0x78, 4);
+        put(&mut data, 0xD0 + 20, 7, 4);
+        data
+    }
+
+    #[test]
+    fn parse() {
+        let elf = Elf::parse(&executable()).unwrap();
+        assert!(!elf.is_64);
+        assert_eq!(0x1000, elf.entry);
+        assert_eq!(1, elf.segments.len());
+        assert_eq!(0x54, elf.segments[0].offset);
+        assert_eq!(1, elf.symbols.len());
+        assert_eq!("done", elf.symbols[0].name);
+        assert!(Elf::parse(&[0; 0x40]).is_err());
+    }
+
+    #[test]
+    fn overflow() {
+        // a 64 bit header with a program header at 40
+        let mut data = vec![0; 0x78];
+        data[..7].copy_from_slice(b"\x7FELF\x02\x01\x01");
+        let put64 = |data: &mut [u8], at: usize, value: u64| {
+            data[at..at + 8].copy_from_slice(&value.to_le_bytes());
+        };
+        put64(&mut data, 0x20, 0x40);
+        put(&mut data, 0x36, 0x38, 2);
+        put(&mut data, 0x38, 1, 2);
+        put(&mut data, 0x40, 1, 4);
+        put(&mut data, 0x44, 1, 4);
+        put64(&mut data, 0x48, 1);
+        assert!(Elf::parse(&data).is_ok());
+
+        // the segment ends past the end of the address space
+        put64(&mut data, 0x60, u64::MAX);
+        assert!(LoaderKind::Elf
+            .load(data.clone(), &mut Context::default())
+            .is_err());
+        // and so do the program and the section headers
+        put64(&mut data, 0x20, u64::MAX);
+        assert!(Elf::parse(&data).is_err());
+        put64(&mut data, 0x20, 0x40);
+        put(&mut data, 0x38, 0, 2);
+        put64(&mut data, 0x28, u64::MAX - 1);
+        put(&mut data, 0x3A, 0x40, 2);
+        put(&mut data, 0x3C, 2, 2);
+        assert!(Elf::parse(&data).is_err());
+    }
+
+    #[test]
+    fn segments() {
+        let mut ctx = Context::default();
+        let data = LoaderKind::Elf.load(executable(), &mut ctx).unwrap();
+        assert_eq!(vec![0xEA, 0xEA, 0x60], data);
+        assert_eq!(0x1000, ctx.syms.get_first_by_name("entry").unwrap().value);
+
+        let mut result = String::new();
+        a6502::ARCH
+            .disas_ctx(
+                |n, _kind, _raw, _arch, _ctx| {
+                    result.push_str(&n.string);
+                    Ok(())
+                },
+                &data,
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(
+            "; ELF segment0: 3 bytes\nentry:\n00001000 nop\n00001001 nop\ndone:\n00001002 rts\n",
+            result
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use log::info;
+
+use crate::{
+    core::dasm::{
+        arch::Context,
+        memory_map::MemoryMap,
+        symbols::{Scope, Symbol, SymbolKind},
+        Address, ValueType,
+    },
+    prelude::{Error, FdResult},
+};
+
+pub const HEADER_START: usize = 0x100;
+pub const HEADER_END: usize = 0x150;
+const TITLE: std::ops::Range<usize> = 0x134..0x144;
+const CARTRIDGE_TYPE: usize = 0x147;
+const ROM_SIZE: usize = 0x148;
+const RAM_SIZE: usize = 0x149;
+const HEADER_CHECKSUM: usize = 0x14D;
+
+// restart and interrupt vectors in bank 0
+const VECTORS: [(&str, usize); 14] = [
+    ("rst_00", 0x00),
+    ("rst_08", 0x08),
+    ("rst_10", 0x10),
+    ("rst_18", 0x18),
+    ("rst_20", 0x20),
+    ("rst_28", 0x28),
+    ("rst_30", 0x30),
+    ("rst_38", 0x38),
+    ("vblank", 0x40),
+    ("lcd_stat", 0x48),
+    ("timer", 0x50),
+    ("serial", 0x58),
+    ("joypad", 0x60),
+    ("entry", HEADER_START),
+];
+
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mbc {
+    #[default]
+    None,
+    Mbc1,
+    Mbc2,
+    Mmm01,
+    Mbc3,
+    Mbc5,
+    Mbc6,
+    Mbc7,
+    Other(u8),
+}
+
+impl Mbc {
+    pub fn from_cartridge_type(kind: u8) -> Self {
+        match kind {
+            0x00 | 0x08 | 0x09 => Mbc::None,
+            0x01..=0x03 => Mbc::Mbc1,
+            0x05 | 0x06 => Mbc::Mbc2,
+            0x0B..=0x0D => Mbc::Mmm01,
+            0x0F..=0x13 => Mbc::Mbc3,
+            0x19..=0x1E => Mbc::Mbc5,
+            0x20 => Mbc::Mbc6,
+            0x22 => Mbc::Mbc7,
+            _ => Mbc::Other(kind),
+        }
+    }
+}
+
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct GameBoyHeader {
+    pub title: String,
+    pub mbc: Mbc,
+    pub cartridge_type: u8,
+    pub rom_size: usize,
+    pub ram_size: usize,
+    pub header_checksum: u8,
+    // the checksum computed over the title to 0x14c
+    pub computed_checksum: u8,
+}
+
+impl GameBoyHeader {
+    pub fn parse(data: &[u8]) -> FdResult<Self> {
+        if data.len() < HEADER_END {
+            return Err(Error::InvalidHeader("Game Boy".into()));
+        }
+
+        let title = data[TITLE]
+            .iter()
+            .take_while(|b| **b != 0)
+            .map(|b| *b as char)
+            .collect::<String>();
+        Ok(Self {
+            title: title.trim_end().to_owned(),
+            mbc: Mbc::from_cartridge_type(data[CARTRIDGE_TYPE]),
+            cartridge_type: data[CARTRIDGE_TYPE],
+            rom_size: (0x8000usize)
+                .checked_shl(data[ROM_SIZE] as u32)
+                .unwrap_or(0),
+            ram_size: match data[RAM_SIZE] {
+                0x02 => 0x2000,
+                0x03 => 0x8000,
+                0x04 => 0x20000,
+                0x05 => 0x10000,
+                _ => 0,
+            },
+            header_checksum: data[HEADER_CHECKSUM],
+            computed_checksum: data[TITLE.start..HEADER_CHECKSUM]
+                .iter()
+                .fold(0u8, |x, b| x.wrapping_sub(*b).wrapping_sub(1)),
+        })
+    }
+
+    pub fn comment(&self) -> String {
+        format!(
+            "Game Boy header: {}, {:?} ({:02x}), {}K rom, {}K ram",
+            self.title,
+            self.mbc,
+            self.cartridge_type,
+            self.rom_size / 1024,
+            self.ram_size / 1024
+        )
+    }
+}
+
+/// Defines the vectors and maps the rom banks
+/// Without a memory bank controller the rom is mapped linearly
+pub fn load(data: &[u8], ctx: &mut Context) -> FdResult<()> {
+    let header = GameBoyHeader::parse(data)?;
+    info!("{}", header.comment());
+    if header.header_checksum != header.computed_checksum {
+        ctx.warn(&format!(
+            "Game Boy header checksum mismatch: computed {:02x}, stored {:02x}",
+            header.computed_checksum, header.header_checksum
+        ));
+    }
+
+    ctx.memory_map = match header.mbc {
+        Mbc::None => MemoryMap::Linear,
+        _ => MemoryMap::GameBoy,
+    };
+    ctx.comments
+        .insert(HEADER_START as Address, header.comment());
+
+    for (name, value) in VECTORS {
+        ctx.def_symbol(Symbol::new(
+            name.into(),
+            SymbolKind::Label,
+            Scope::Global,
+            value as ValueType,
+            1,
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{arch::Context, loader::LoaderKind, memory_map::MemoryMap};
+
+    use super::{GameBoyHeader, Mbc};
+
+    fn rom(cartridge_type: u8, banks: usize) -> Vec<u8> {
+        let mut data = vec![0; banks * 0x4000];
+        data[0x134..0x139].copy_from_slice(b"TETRA");
+        data[0x147] = cartridge_type;
+        data[0x148] = 1;
+        data[0x149] = 2;
+        data[0x14D] = data[0x134..0x14D]
+            .iter()
+            .fold(0u8, |x, b| x.wrapping_sub(*b).wrapping_sub(1));
+        data
+    }
+
+    #[test]
+    fn header() {
+        let header = GameBoyHeader::parse(&rom(0x03, 4)).unwrap();
+        assert_eq!("TETRA", header.title);
+        assert_eq!(Mbc::Mbc1, header.mbc);
+        assert_eq!(0x10000, header.rom_size);
+        assert_eq!(header.header_checksum, header.computed_checksum);
+        assert_eq!(
+            "Game Boy header: TETRA, Mbc1 (03), 64K rom, 8K ram",
+            header.comment()
+        );
+        assert!(GameBoyHeader::parse(&[0; 0x100]).is_err());
+    }
+
+    #[test]
+    fn banks() {
+        let mut ctx = Context::default();
+        LoaderKind::GameBoy.load(rom(0x01, 4), &mut ctx).unwrap();
+        assert_eq!(MemoryMap::GameBoy, ctx.memory_map);
+        assert_eq!(0x100, ctx.syms.get_first_by_name("entry").unwrap().value);
+        assert_eq!(0x40, ctx.syms.get_first_by_name("vblank").unwrap().value);
+
+        ctx.offset = 0x3FFF;
+        assert_eq!(0x3FFF, ctx.address());
+        ctx.offset = 0x4000;
+        assert_eq!(0x14000, ctx.address());
+        ctx.offset = 0xC123;
+        assert_eq!(0x34123, ctx.address());
+        assert_eq!(Some(0xC123), MemoryMap::GameBoy.to_offset(0x34123));
+
+        let mut ctx = Context::default();
+        LoaderKind::GameBoy.load(rom(0x00, 2), &mut ctx).unwrap();
+        assert_eq!(MemoryMap::Linear, ctx.memory_map);
+    }
+}
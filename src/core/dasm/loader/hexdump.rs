@@ -0,0 +1,167 @@
+use crate::{
+    core::dasm::{arch::Context, Address},
+    prelude::{Error, FdResult},
+};
+
+use super::{
+    hex_bytes, ihex,
+    image::{Image, MAX_FLAT_LEN},
+    srec,
+};
+
+fn parse_bytes(hex: &str, line_nr: usize) -> FdResult<Vec<u8>> {
+    let hex: String = hex.split_whitespace().collect();
+    hex_bytes(&hex).map_err(|_| Error::InvalidHeader(format!("hex dump line {line_nr}")))
+}
+
+/// Parses xxd (00000000: 4c00 8060  L..`), hexdump -C (00000000  4c 00 80 60  |L..`|)
+/// and plain hex dumps (xxd -p) into an image
+/// Lines with an offset are written at the offset, plain lines continue after the previous line.
+/// A line of `*` repeats the previous line until the next offset like hexdump does,
+/// at most MAX_FLAT_LEN bytes
+pub fn parse(data: &[u8]) -> FdResult<Image> {
+    let text = String::from_utf8_lossy(data);
+    let mut image = Image::default();
+    let mut position: Address = 0;
+    let mut has_offsets = false;
+    let mut previous: Vec<u8> = vec![];
+    let mut repeat = false;
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.trim() == "*" {
+            repeat = true;
+            continue;
+        }
+        let err = || Error::InvalidHeader(format!("hex dump line {}", i + 1));
+
+        let first = line.split_whitespace().next().unwrap_or_default();
+        let (offset, hex) = if let Some(offset) = first.strip_suffix(':') {
+            // the ascii column of xxd starts after two spaces
+            let rest = line.trim_start()[first.len()..].trim_start_matches(' ');
+            let hex = rest.split_once("  ").map_or(rest, |(hex, _)| hex);
+            (Some(offset), hex)
+        } else if has_offsets || line.contains('|') || line.split_whitespace().count() > 1 {
+            let line = line.split_once('|').map_or(line, |(hex, _)| hex).trim();
+            let (offset, hex) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            (Some(offset), hex)
+        } else {
+            (None, line)
+        };
+
+        if let Some(offset) = offset {
+            let offset = Address::from_str_radix(offset, 16).map_err(|_| err())?;
+            has_offsets = true;
+            if repeat && !previous.is_empty() {
+                if offset.saturating_sub(position) > MAX_FLAT_LEN as Address {
+                    return Err(err());
+                }
+                while position < offset {
+                    image.write(position, &previous);
+                    position += previous.len() as Address;
+                }
+            }
+            position = offset;
+        }
+        repeat = false;
+
+        let bytes = parse_bytes(hex, i + 1)?;
+        image.write(position, &bytes);
+        position = position.wrapping_add(bytes.len() as Address);
+        if !bytes.is_empty() {
+            previous = bytes;
+        }
+    }
+    Ok(image)
+}
+
+/// Converts a hex dump back to binary
+/// A dump without holes that starts at offset 0 is converted as is.
+/// Sparse dumps only keep the bytes of the dump and add a segment at the org
+/// plus the offset of every block, so the holes are never allocated
+pub fn load(data: &[u8], ctx: &mut Context) -> FdResult<Vec<u8>> {
+    let image = parse(data)?;
+    let blocks = image.blocks();
+    if blocks.len() <= 1 && blocks.first().is_none_or(|(offset, _)| *offset == 0) {
+        return image.flat();
+    }
+    let (first, org) = (ctx.segments.len(), ctx.org);
+    let data = image.load("dump", ctx);
+    for segment in ctx.segments[first..].iter_mut() {
+        segment.org = segment.org.wrapping_add(org);
+    }
+    Ok(data)
+}
+
+/// Converts a hex dump, Intel HEX or S-record file to binary
+/// Intel HEX and S-record files only contain the records' bytes and
+/// add a segment at the load address of every block to the context
+pub fn unhex(data: &[u8], ctx: &mut Context) -> FdResult<Vec<u8>> {
+    let text = String::from_utf8_lossy(data);
+    match text.trim_start().as_bytes() {
+        [b':', ..] => ihex::load(data, ctx),
+        [b'S', b'0'..=b'9', ..] => srec::load(data, ctx),
+        _ => load(data, ctx),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{arch::Context, Address};
+
+    use super::{load, unhex};
+
+    #[test]
+    fn formats() {
+        let xxd = "00000000: 4c00 8060 4142  L..`AB\n00000006: 00ff                ..\n";
+        assert_eq!(
+            vec![0x4C, 0x00, 0x80, 0x60, 0x41, 0x42, 0x00, 0xFF],
+            load(xxd.as_bytes(), &mut Context::default()).unwrap()
+        );
+
+        // the repeated line is filled in up to the next offset
+        let hexdump =
+            "00000000  01 02 03 04 05 06 07 08  09 0a 0b 0c 0d 0e 0f 10  |................|\n\
+                       *\n\
+                       00000020  ff                                                |.|\n\
+                       00000021\n";
+        let data = load(hexdump.as_bytes(), &mut Context::default()).unwrap();
+        assert_eq!(0x21, data.len());
+        assert_eq!(data[..0x10], data[0x10..0x20]);
+        assert_eq!(0xFF, data[0x20]);
+
+        assert_eq!(
+            vec![0xEA, 0x60, 0x01],
+            load(b"ea60\n01\n", &mut Context::default()).unwrap()
+        );
+        assert!(load(b"0000: zz", &mut Context::default()).is_err());
+        assert!(load("0000: 0\u{e9}".as_bytes(), &mut Context::default()).is_err());
+        // the repeated line and the image are limited
+        assert!(load(b"00000000: ea\n*\nffffffff: ea\n", &mut Context::default()).is_err());
+
+        // sparse dumps only keep their bytes, the blocks are segments at their offset
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        let data = load(b"00000002: ea60\nffffffff: 60\n", &mut ctx).unwrap();
+        assert_eq!(vec![0xEA, 0x60, 0x60], data);
+        let segments: Vec<(usize, usize, Address)> = ctx
+            .segments
+            .iter()
+            .map(|s| (s.start, s.end, s.org))
+            .collect();
+        assert_eq!(vec![(0, 2, 0x8002), (2, 3, 0x1_0000_7fff)], segments);
+    }
+
+    #[test]
+    fn records() {
+        let mut ctx = Context::default();
+        let data = unhex(b":02800000EA6034\n:00000001FF\n", &mut ctx).unwrap();
+        assert_eq!(vec![0xEA, 0x60], data);
+        assert_eq!(0x8000, ctx.segments[0].org);
+    }
+}
@@ -0,0 +1,211 @@
+use log::info;
+
+use crate::{
+    core::dasm::{
+        arch::Context,
+        symbols::{Scope, Symbol, SymbolKind},
+        Address, ValueType,
+    },
+    prelude::{Error, FdResult},
+};
+
+use super::{hex_bytes, image::Image};
+
+const DATA: u8 = 0x00;
+const END_OF_FILE: u8 = 0x01;
+const EXTENDED_SEGMENT_ADDRESS: u8 = 0x02;
+const START_SEGMENT_ADDRESS: u8 = 0x03;
+const EXTENDED_LINEAR_ADDRESS: u8 = 0x04;
+const START_LINEAR_ADDRESS: u8 = 0x05;
+
+/// A decoded line of a hex file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    pub kind: u8,
+    pub address: u16,
+    pub data: Vec<u8>,
+}
+
+impl Record {
+    /// Parses a record (:LLAAAATT...CC) and verifies its checksum
+    pub fn parse(line: &str, line_nr: usize) -> FdResult<Self> {
+        let err = || Error::InvalidHeader(format!("Intel HEX line {line_nr}"));
+        let hex = line.strip_prefix(':').ok_or_else(err)?;
+        let bytes = hex_bytes(hex).map_err(|_| err())?;
+        if bytes.len() < 5 {
+            return Err(err());
+        }
+
+        let len = bytes[0] as usize;
+        if bytes.len() != len + 5 || bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0 {
+            return Err(err());
+        }
+        Ok(Self {
+            kind: bytes[3],
+            address: u16::from_be_bytes([bytes[1], bytes[2]]),
+            data: bytes[4..4 + len].to_vec(),
+        })
+    }
+
+    fn value(&self) -> Address {
+        self.data
+            .iter()
+            .fold(0, |value, b| (value << 8) | *b as Address)
+    }
+}
+
+/// Decodes the records into an image and disassembles every contiguous
+/// block as a segment at its address
+pub fn load(data: &[u8], ctx: &mut Context) -> FdResult<Vec<u8>> {
+    let text = String::from_utf8_lossy(data);
+    let mut image = Image::default();
+    let mut base: Address = 0;
+    let mut entry = None;
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let record = Record::parse(line, i + 1)?;
+        match record.kind {
+            DATA => {
+                let address = base + record.address as Address;
+                let overwritten = image.write(address, &record.data);
+                if overwritten > 0 {
+                    ctx.warn(&format!(
+                        "Intel HEX line {}: {overwritten} bytes at {address:x} are written twice",
+                        i + 1
+                    ));
+                }
+            }
+            END_OF_FILE => break,
+            EXTENDED_SEGMENT_ADDRESS => base = record.value() << 4,
+            EXTENDED_LINEAR_ADDRESS => base = record.value() << 16,
+            // cs:ip
+            START_SEGMENT_ADDRESS => {
+                let value = record.value();
+                entry = Some(((value >> 16) << 4) + (value & 0xFFFF));
+            }
+            START_LINEAR_ADDRESS => entry = Some(record.value()),
+            _ => {
+                return Err(Error::InvalidHeader(format!(
+                    "Intel HEX line {}: record type {:02x}",
+                    i + 1,
+                    record.kind
+                )))
+            }
+        }
+    }
+    if image.is_empty() {
+        return Err(Error::InvalidHeader("Intel HEX without data".into()));
+    }
+
+    let code = image.load("hex", ctx);
+    info!(
+        "Intel HEX: {} bytes in {} segments",
+        code.len(),
+        ctx.segments.len()
+    );
+    if let Some(entry) = entry {
+        ctx.def_symbol(Symbol::new(
+            "entry".into(),
+            SymbolKind::Label,
+            Scope::Global,
+            entry as ValueType,
+            1,
+        ));
+    }
+    Ok(code)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::dasm::{
+            arch::{a6502, Context},
+            loader::{self, LoaderKind},
+        },
+        prelude::InputFile,
+    };
+
+    use super::Record;
+
+    #[test]
+    fn records() {
+        assert_eq!(
+            Record {
+                kind: 0,
+                address: 0x8000,
+                data: vec![0xEA, 0x60],
+            },
+            Record::parse(":02800000EA6034", 1).unwrap()
+        );
+        // bad checksum
+        assert!(Record::parse(":02800000EA6035", 1).is_err());
+        assert!(Record::parse("02800000EA6014", 1).is_err());
+        // a character that is not a hex digit and not ascii either
+        assert!(Record::parse(":0000000\u{e9}001", 1).is_err());
+    }
+
+    #[test]
+    fn load() {
+        let hex = ":02800000EA6034\n:01801000EA85\n:040000050000800077\n:00000001FF\n";
+        let mut ctx = Context::default();
+        let data = LoaderKind::IntelHex
+            .load(hex.as_bytes().to_vec(), &mut ctx)
+            .unwrap();
+        assert_eq!(vec![0xEA, 0x60, 0xEA], data);
+        assert_eq!(2, ctx.segments.len());
+        assert_eq!(0x8010, ctx.segments[1].org);
+
+        let mut result = String::new();
+        a6502::ARCH
+            .disas_ctx(
+                |n, _kind, _raw, _arch, _ctx| {
+                    result.push_str(&n.string);
+                    Ok(())
+                },
+                &data,
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(
+            "; segment hex0: 0x0..0x2 at 8000\nentry:\n00008000 nop\n00008001 rts\n\
+             ; segment hex1: 0x2..0x3 at 8010\n00008010 nop\n",
+            result
+        );
+    }
+
+    #[test]
+    fn append() {
+        let path = std::env::temp_dir().join("litedasm-append.hex");
+        std::fs::write(&path, ":02800000EA6034\n:01801000EA85\n:00000001FF\n").unwrap();
+        assert_eq!(Some(LoaderKind::IntelHex), LoaderKind::from_path(&path));
+
+        // the records are decoded after the main input instead of being appended as text
+        let mut ctx = Context::default();
+        let mut data = vec![0x60];
+        let file = InputFile {
+            path: path.clone(),
+            org: 0x1000,
+            name: Some("patch".into()),
+        };
+        loader::append_files(&mut data, "main", &[file], &mut ctx).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(vec![0x60, 0xEA, 0x60, 0xEA], data);
+        let segments = ctx
+            .segments
+            .iter()
+            .map(|s| (s.name.as_str(), s.start, s.end, s.org))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                ("main", 0, 1, 0),
+                ("patch0", 1, 3, 0x9000),
+                ("patch1", 3, 4, 0x9010)
+            ],
+            segments
+        );
+    }
+}
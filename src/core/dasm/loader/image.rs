@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+
+use log::info;
+
+use crate::{
+    core::dasm::{
+        arch::{Context, StaticOp},
+        segment::Segment,
+        Address,
+    },
+    prelude::{Error, FdResult},
+};
+
+/// The largest flat image that is allocated, 64M
+pub const MAX_FLAT_LEN: usize = 0x400_0000;
+
+/// A sparse memory image that is built from the records of a hex file
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    bytes: BTreeMap<Address, u8>,
+}
+
+impl Image {
+    /// Writes the data at the address and returns the number of bytes
+    /// that were already written by an earlier record
+    pub fn write(&mut self, address: Address, data: &[u8]) -> usize {
+        data.iter()
+            .enumerate()
+            .filter(|(i, b)| {
+                self.bytes
+                    .insert(address.wrapping_add(*i as Address), **b)
+                    .is_some()
+            })
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// The contiguous blocks of the image ordered by their address
+    pub fn blocks(&self) -> Vec<(Address, Vec<u8>)> {
+        let mut blocks: Vec<(Address, Vec<u8>)> = vec![];
+        for (address, b) in self.bytes.iter() {
+            match blocks.last_mut() {
+                Some((start, data)) if *start + data.len() as Address == *address => data.push(*b),
+                _ => blocks.push((*address, vec![*b])),
+            }
+        }
+        blocks
+    }
+
+    /// The bytes from address 0 to the end of the image, gaps are filled with zeros
+    /// Images that end past MAX_FLAT_LEN are an error instead of being allocated
+    pub fn flat(&self) -> FdResult<Vec<u8>> {
+        let len = match self.bytes.last_key_value() {
+            Some((address, _)) => (*address as usize)
+                .checked_add(1)
+                .filter(|len| *len <= MAX_FLAT_LEN)
+                .ok_or_else(|| {
+                    Error::InvalidHeader(format!(
+                        "the image ends at {address:x}, past the limit of {MAX_FLAT_LEN:#x} bytes"
+                    ))
+                })?,
+            None => 0,
+        };
+        let mut data = vec![0; len];
+        for (address, b) in self.bytes.iter() {
+            data[*address as usize] = *b;
+        }
+        Ok(data)
+    }
+
+    /// Concatenates the blocks and adds a segment at the block's address for each of them
+    /// The gaps between blocks are emitted as org directives if enabled
+    pub fn load(&self, name: &str, ctx: &mut Context) -> Vec<u8> {
+        let mut data = vec![];
+        let mut prev_end = None;
+        for (i, (address, block)) in self.blocks().into_iter().enumerate() {
+            if let Some(prev_end) = prev_end {
+                info!(
+                    "{name}: gap of {:#x} bytes before {address:x}",
+                    address - prev_end
+                );
+            }
+            prev_end = Some(address + block.len() as Address);
+            ctx.segments.push(Segment::new(
+                &format!("{name}{i}"),
+                data.len(),
+                data.len() + block.len(),
+                address,
+                "",
+            ));
+            if ctx.output.org_directive {
+                ctx.static_ops_pre
+                    .lock()
+                    .unwrap()
+                    .push(StaticOp::Address(address, vec![StaticOp::Org]));
+            }
+            data.extend(block);
+        }
+        data
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{
+        arch::{a6502, Context},
+        Address,
+    };
+
+    use super::Image;
+
+    #[test]
+    fn blocks() {
+        let mut image = Image::default();
+        assert_eq!(0, image.write(0x10, &[1, 2]));
+        assert_eq!(0, image.write(0x12, &[3]));
+        assert_eq!(0, image.write(0x20, &[4, 5]));
+        assert_eq!(1, image.write(0x21, &[6]));
+        assert_eq!(
+            vec![(0x10, vec![1, 2, 3]), (0x20, vec![4, 6])],
+            image.blocks()
+        );
+        assert_eq!(0x22, image.flat().unwrap().len());
+
+        // the image is not allocated up to the last address
+        image.write(Address::MAX, &[0xFF]);
+        assert!(image.flat().is_err());
+    }
+
+    #[test]
+    fn org_directive() {
+        let mut image = Image::default();
+        image.write(0x8000, &[0xEA]);
+        image.write(0x9000, &[0x60]);
+        let disas = |clean: bool| {
+            let mut ctx = Context::default();
+            ctx.output.org_directive = true;
+            ctx.output.clean = clean;
+            let data = image.load("image", &mut ctx);
+            let mut result = String::new();
+            a6502::ARCH
+                .disas_ctx(
+                    |n, _kind, _raw, _arch, _ctx| {
+                        result.push_str(&n.string);
+                        Ok(())
+                    },
+                    &data,
+                    &mut ctx,
+                )
+                .unwrap();
+            result
+        };
+        assert_eq!(
+            "; segment image0: 0x0..0x1 at 8000\n.org $8000\n00008000 nop\n\
+             ; segment image1: 0x1..0x2 at 9000\n.org $9000\n00009000 rts\n",
+            disas(false)
+        );
+        // clean output emits the org itself
+        let clean = disas(true);
+        assert_eq!(1, clean.matches(".org $8000").count());
+        assert_eq!(1, clean.matches(".org $9000").count());
+    }
+}
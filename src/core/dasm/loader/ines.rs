@@ -0,0 +1,204 @@
+use log::info;
+
+use crate::{
+    core::dasm::{
+        arch::Context,
+        symbols::{Scope, Symbol, SymbolKind},
+        Address, ValueType,
+    },
+    prelude::{Error, FdResult},
+};
+
+pub const HEADER_SIZE: usize = 16;
+pub const TRAINER_SIZE: usize = 512;
+pub const PRG_BANK_SIZE: usize = 0x4000;
+pub const CHR_BANK_SIZE: usize = 0x2000;
+const MAGIC: &[u8] = b"NES\x1A";
+
+// the cpu vectors are located at the end of the last prg bank
+const VECTORS: [&str; 3] = ["nmi", "reset", "irq"];
+
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct InesHeader {
+    pub nes2: bool,
+    pub mapper: u16,
+    pub prg_size: usize,
+    pub chr_size: usize,
+    pub trainer: bool,
+    pub battery: bool,
+    pub vertical_mirroring: bool,
+}
+
+// NES 2.0 sizes may use an exponent-multiplier notation
+// if the upper nibble is 0xF
+fn rom_size(lsb: u8, msb: u8, bank_size: usize) -> usize {
+    if msb == 0xF {
+        let exponent = (lsb >> 2) as u32;
+        let multiplier = (lsb & 0b11) as usize * 2 + 1;
+        2usize.saturating_pow(exponent).saturating_mul(multiplier)
+    } else {
+        ((msb as usize) << 8 | lsb as usize) * bank_size
+    }
+}
+
+impl InesHeader {
+    pub fn parse(data: &[u8]) -> FdResult<Self> {
+        if data.len() < HEADER_SIZE || &data[..MAGIC.len()] != MAGIC {
+            return Err(Error::InvalidHeader("iNES".into()));
+        }
+
+        let flags6 = data[6];
+        let flags7 = data[7];
+        let nes2 = flags7 & 0x0C == 0x08;
+        let (prg_msb, chr_msb, mapper_msb) = if nes2 {
+            (data[9] & 0x0F, data[9] >> 4, data[8] & 0x0F)
+        } else {
+            (0, 0, 0)
+        };
+
+        Ok(Self {
+            nes2,
+            mapper: (mapper_msb as u16) << 8 | (flags7 & 0xF0) as u16 | (flags6 >> 4) as u16,
+            prg_size: rom_size(data[4], prg_msb, PRG_BANK_SIZE),
+            chr_size: rom_size(data[5], chr_msb, CHR_BANK_SIZE),
+            trainer: flags6 & 0x04 != 0,
+            battery: flags6 & 0x02 != 0,
+            vertical_mirroring: flags6 & 0x01 != 0,
+        })
+    }
+
+    /// File offset of the first prg byte
+    pub fn prg_offset(&self) -> usize {
+        HEADER_SIZE + if self.trainer { TRAINER_SIZE } else { 0 }
+    }
+
+    /// Bus address of the first prg byte
+    /// A single 16K bank is mirrored to 0xC000, everything else starts at 0x8000
+    pub fn prg_address(&self) -> Address {
+        if self.prg_size <= PRG_BANK_SIZE {
+            0xC000
+        } else {
+            0x8000
+        }
+    }
+
+    pub fn comment(&self) -> String {
+        format!(
+            "{} header: mapper {}, {}K prg, {}K chr, {} mirroring{}{}",
+            if self.nes2 { "NES 2.0" } else { "iNES" },
+            self.mapper,
+            self.prg_size / 1024,
+            self.chr_size / 1024,
+            if self.vertical_mirroring {
+                "vertical"
+            } else {
+                "horizontal"
+            },
+            if self.battery { ", battery" } else { "" },
+            if self.trainer { ", trainer" } else { "" },
+        )
+    }
+}
+
+/// Skips the header and trainer, only reads the prg rom
+/// and defines symbols for the vectors
+pub fn load(data: &[u8], ctx: &mut Context) -> FdResult<()> {
+    let header = InesHeader::parse(data)?;
+    let prg_offset = header.prg_offset();
+    // the prg rom has to contain the vectors
+    let prg_end = prg_offset
+        .checked_add(header.prg_size)
+        .filter(|_| header.prg_size >= VECTORS.len() * 2)
+        .ok_or_else(|| Error::InvalidHeader("iNES".into()))?;
+    if data.len() < prg_end {
+        return Err(Error::InvalidHeader("iNES".into()));
+    }
+    info!("{}", header.comment());
+
+    let address = header.prg_address();
+    ctx.set_start(Some(prg_offset));
+    ctx.set_len(Some(header.prg_size));
+    // the start offset is part of the address
+    ctx.set_org(address.wrapping_sub(prg_offset as Address));
+    ctx.comments.insert(address, header.comment());
+
+    // the vectors are always in the last 6 bytes of the prg rom
+    let vectors = prg_end - VECTORS.len() * 2;
+    for (i, name) in VECTORS.iter().enumerate() {
+        let at = vectors + i * 2;
+        let value = u16::from_le_bytes([data[at], data[at + 1]]);
+        ctx.def_symbol(Symbol::new(
+            name.to_string(),
+            SymbolKind::Label,
+            Scope::Global,
+            value as ValueType,
+            1,
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{arch::Context, loader::LoaderKind};
+
+    use super::InesHeader;
+
+    fn rom(flags6: u8, prg_banks: u8) -> Vec<u8> {
+        let mut data = vec![b'N', b'E', b'S', 0x1A, prg_banks, 1, flags6, 0x10];
+        data.resize(16, 0);
+        if flags6 & 0x04 != 0 {
+            data.resize(16 + 512, 0xEE);
+        }
+        let prg_start = data.len();
+        data.resize(prg_start + prg_banks as usize * 0x4000, 0xEA);
+        let len = data.len();
+        data[len - 6..].copy_from_slice(&[0x00, 0xC1, 0x00, 0xC0, 0x10, 0xC0]);
+        data
+    }
+
+    #[test]
+    fn header() {
+        let header = InesHeader::parse(&rom(0x13, 2)).unwrap();
+        assert_eq!(
+            InesHeader {
+                nes2: false,
+                mapper: 0x11,
+                prg_size: 0x8000,
+                chr_size: 0x2000,
+                trainer: false,
+                battery: true,
+                vertical_mirroring: true,
+            },
+            header
+        );
+        assert_eq!(0x8000, header.prg_address());
+        assert!(InesHeader::parse(&[0; 16]).is_err());
+    }
+
+    #[test]
+    fn load() {
+        let mut ctx = Context::default();
+        let data = LoaderKind::Ines.load(rom(0x04, 1), &mut ctx).unwrap();
+        assert_eq!(16 + 512, ctx.start_read);
+        assert_eq!(Some(0x4000), ctx.len_read);
+        assert_eq!(0xC000, ctx.address());
+        assert_eq!(0xC000, ctx.syms.get_first_by_name("reset").unwrap().value);
+        assert_eq!(0xC100, ctx.syms.get_first_by_name("nmi").unwrap().value);
+        assert_eq!(0xC010, ctx.syms.get_first_by_name("irq").unwrap().value);
+        assert_eq!(
+            "iNES header: mapper 16, 16K prg, 8K chr, horizontal mirroring, trainer",
+            ctx.comments.get(&0xC000).unwrap()
+        );
+        assert_eq!(16 + 512 + 0x4000, data.len());
+
+        // no prg rom, and a NES 2.0 size that does not fit the address space
+        let mut ctx = Context::default();
+        assert!(LoaderKind::Ines.load(rom(0x00, 0), &mut ctx).is_err());
+        let mut data = rom(0x00, 1);
+        data[7] = 0x08;
+        data[4] = 0xFF;
+        data[9] = 0x0F;
+        assert!(LoaderKind::Ines.load(data, &mut ctx).is_err());
+    }
+}
@@ -0,0 +1,218 @@
+use std::fmt::Display;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::dasm::{
+        arch::{Context, Endianess},
+        symbols::{Scope, Symbol, SymbolKind},
+        Address, DataType, ValueType,
+    },
+    prelude::{Error, FdResult},
+};
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MetadataLocation {
+    // blocks at the start are stacked from the start of the file
+    #[default]
+    Start,
+    // blocks at the end are stacked from the end of the file
+    End,
+}
+
+impl Display for MetadataLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MetadataLocation::Start => write!(f, "start"),
+            MetadataLocation::End => write!(f, "end"),
+        }
+    }
+}
+
+/// A value inside of a metadata block
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct MetadataField {
+    pub name: String,
+    // offset from the start of the block
+    pub offset: usize,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub data_type: DataType,
+    // define a constant named block_field instead of a comment
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub symbol: bool,
+}
+
+/// A header, trainer or footer that is stripped from the input
+/// instead of being disassembled
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct Metadata {
+    pub name: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub location: MetadataLocation,
+    pub size: usize,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub endianess: Endianess,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub fields: Vec<MetadataField>,
+}
+
+impl Metadata {
+    pub fn new(name: &str, location: MetadataLocation, size: usize) -> Self {
+        Self {
+            name: name.into(),
+            location,
+            size,
+            ..Default::default()
+        }
+    }
+
+    fn read(&self, block: &[u8], field: &MetadataField) -> FdResult<ValueType> {
+        let data = block
+            .get(field.offset..field.offset + field.data_type.data_len())
+            .ok_or_else(|| Error::MetadataOutOfRange(format!("{}.{}", self.name, field.name)))?;
+        let data = self.endianess.pad(data, std::mem::size_of::<ValueType>());
+        self.endianess
+            .transform(&data)
+            .ok_or_else(|| Error::MetadataOutOfRange(format!("{}.{}", self.name, field.name)))
+    }
+
+    /// Defines the symbol fields and returns the comment lines
+    fn parse(&self, block: &[u8], ctx: &mut Context) -> FdResult<Vec<String>> {
+        let mut lines = vec![format!(
+            "{}: {} bytes at the {}",
+            self.name, self.size, self.location
+        )];
+        for field in self.fields.iter() {
+            let value = self.read(block, field)?;
+            if field.symbol {
+                ctx.def_symbol(Symbol::new(
+                    format!("{}_{}", self.name, field.name),
+                    SymbolKind::Const,
+                    Scope::Global,
+                    value,
+                    1,
+                ));
+            } else {
+                lines.push(format!("{}.{} = {value:#x}", self.name, field.name));
+            }
+        }
+        Ok(lines)
+    }
+}
+
+/// Strips all metadata blocks of the context from the input
+/// The first address after the blocks at the start is the org
+pub fn apply(data: &[u8], ctx: &mut Context) -> FdResult<()> {
+    if ctx.metadata.is_empty() {
+        return Ok(());
+    }
+    let mut start = 0;
+    let mut end = data.len();
+    let mut lines = vec![];
+    for block in ctx.metadata.clone() {
+        let at = match block.location {
+            MetadataLocation::Start => {
+                start += block.size;
+                start - block.size
+            }
+            MetadataLocation::End => {
+                end = end.saturating_sub(block.size);
+                end
+            }
+        };
+        if start > end || at + block.size > data.len() {
+            return Err(Error::MetadataOutOfRange(block.name));
+        }
+        lines.extend(block.parse(&data[at..at + block.size], ctx)?);
+    }
+
+    if start > ctx.start_read {
+        // the stripped bytes are not part of the address space
+        ctx.org = ctx.org.wrapping_sub((start - ctx.start_read) as Address);
+        ctx.set_start(Some(start));
+    }
+    let len = end.saturating_sub(ctx.start_read);
+    ctx.set_len(Some(ctx.len_read.map_or(len, |l| l.min(len))));
+
+    if !lines.is_empty() {
+        let address = ctx.address();
+        let comment = ctx.comments.entry(address).or_default();
+        for line in lines {
+            if !comment.is_empty() {
+                comment.push('\n');
+            }
+            comment.push_str(&line);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{
+        arch::{a6502, Context},
+        loader::LoaderKind,
+        DataType,
+    };
+
+    use super::{Metadata, MetadataField, MetadataLocation};
+
+    #[test]
+    fn strip() {
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        let mut header = Metadata::new("copier", MetadataLocation::Start, 2);
+        header.fields.push(MetadataField {
+            name: "size".into(),
+            offset: 0,
+            data_type: DataType::U16,
+            symbol: true,
+        });
+        let mut footer = Metadata::new("footer", MetadataLocation::End, 1);
+        footer.fields.push(MetadataField {
+            name: "version".into(),
+            offset: 0,
+            data_type: DataType::U8,
+            symbol: false,
+        });
+        ctx.metadata.extend([header, footer]);
+
+        let data = LoaderKind::Raw
+            .load(vec![0x34, 0x12, 0xEA, 0x60, 0x02], &mut ctx)
+            .unwrap();
+        assert_eq!(
+            0x1234,
+            ctx.syms.get_first_by_name("copier_size").unwrap().value
+        );
+
+        let mut result = String::new();
+        a6502::ARCH
+            .disas_ctx(
+                |n, _kind, _raw, _arch, _ctx| {
+                    result.push_str(&n.string);
+                    Ok(())
+                },
+                &data,
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(
+            "; copier: 2 bytes at the start\n; footer: 1 bytes at the end\n; footer.version = 0x2\n00008000 nop\n00008001 rts\n",
+            result
+        );
+    }
+
+    #[test]
+    fn out_of_range() {
+        let mut ctx = Context::default();
+        ctx.metadata
+            .push(Metadata::new("trainer", MetadataLocation::Start, 512));
+        assert!(LoaderKind::Raw.load(vec![0; 16], &mut ctx).is_err());
+    }
+}
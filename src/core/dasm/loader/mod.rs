@@ -0,0 +1,191 @@
+pub mod atari2600;
+pub mod dos;
+pub mod elf;
+pub mod gameboy;
+pub mod hexdump;
+pub mod ihex;
+pub mod image;
+pub mod ines;
+pub mod metadata;
+pub mod nsf;
+pub mod prg;
+pub mod sid;
+pub mod snes;
+pub mod spc;
+pub mod srec;
+
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use std::path::Path;
+
+use crate::prelude::{Error, FdResult, InputFile};
+
+use self::atari2600::BankScheme;
+
+use super::{arch::Context, segment::Segment, Address};
+
+/// Loaders understand the container format of the input.
+/// They set up the context (start, length, org, symbols and comments)
+/// before disassembling and may convert the input data
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LoaderKind {
+    // the input is disassembled as is
+    #[default]
+    Raw,
+    // iNES and NES 2.0 roms
+    Ines,
+    // SNES roms with an optional copier header
+    Snes,
+    // C64 programs with a load address
+    Prg,
+    // Game Boy roms with a memory bank controller
+    GameBoy,
+    // the executable segments of ELF objects and executables
+    Elf,
+    // DOS MZ executables
+    Mz,
+    // flat DOS .com programs at 100
+    Com,
+    // Atari 2600 bankswitching schemes, every bank is disassembled at f000
+    AtariF8,
+    AtariF6,
+    AtariF4,
+    #[cfg_attr(feature = "cli", value(name = "atari-3f"))]
+    Atari3F,
+    // Intel HEX records, every contiguous block is a segment
+    IntelHex,
+    // Motorola S19, S28 and S37 records
+    Srec,
+    // C64 PSID and RSID tunes, disassembled from the init routine
+    Sid,
+    // NES sound files, banked tunes are split into their 4K banks
+    Nsf,
+    // SPC700 save states, the ram is disassembled from the saved pc
+    Spc,
+    // xxd, hexdump -C and plain hex dumps
+    Hexdump,
+}
+
+impl LoaderKind {
+    /// The loader of record formats that are recognized by their extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "hex" | "ihex" | "ihx" => Some(LoaderKind::IntelHex),
+            "srec" | "s19" | "s28" | "s37" | "mot" => Some(LoaderKind::Srec),
+            _ => None,
+        }
+    }
+
+    pub fn load(&self, mut data: Vec<u8>, ctx: &mut Context) -> FdResult<Vec<u8>> {
+        match self {
+            LoaderKind::Raw => {}
+            LoaderKind::Ines => ines::load(&data, ctx)?,
+            LoaderKind::Snes => snes::load(&data, ctx)?,
+            LoaderKind::Prg => prg::load(&data, ctx)?,
+            LoaderKind::GameBoy => gameboy::load(&data, ctx)?,
+            // only the executable segments are kept
+            LoaderKind::Elf => data = elf::load(&data, ctx)?,
+            // the relocations are applied to the image
+            LoaderKind::Mz => data = dos::load_mz(&data, ctx)?,
+            LoaderKind::Com => dos::load_com(&data, ctx)?,
+            LoaderKind::AtariF8 => atari2600::load(&data, ctx, BankScheme::F8)?,
+            LoaderKind::AtariF6 => atari2600::load(&data, ctx, BankScheme::F6)?,
+            LoaderKind::AtariF4 => atari2600::load(&data, ctx, BankScheme::F4)?,
+            LoaderKind::Atari3F => atari2600::load(&data, ctx, BankScheme::Tigervision3F)?,
+            // the records are decoded into a binary image
+            LoaderKind::IntelHex => data = ihex::load(&data, ctx)?,
+            LoaderKind::Srec => data = srec::load(&data, ctx)?,
+            LoaderKind::Sid => sid::load(&data, ctx)?,
+            LoaderKind::Nsf => data = nsf::load(&data, ctx)?,
+            // the ram and dsp registers are extracted from the state
+            LoaderKind::Spc => data = spc::load(&data, ctx)?,
+            LoaderKind::Hexdump => data = hexdump::load(&data, ctx)?,
+        }
+        // declared headers and footers work with any loader
+        metadata::apply(&data, ctx)?;
+        Ok(data)
+    }
+}
+
+/// Decodes pairs of hex digits into bytes
+/// The text is read as bytes, anything that is not a hex digit is an error
+pub fn hex_bytes(hex: &str) -> FdResult<Vec<u8>> {
+    let err = || Error::InvalidHeader(format!("hex digits {hex:?}"));
+    let nibble = |c: u8| (c as char).to_digit(16).map(|d| d as u8);
+    let hex = hex.as_bytes();
+    if !hex.len().is_multiple_of(2) {
+        return Err(err());
+    }
+    hex.chunks(2)
+        .map(|pair| match (nibble(pair[0]), nibble(pair[1])) {
+            (Some(high), Some(low)) => Ok(high << 4 | low),
+            _ => Err(err()),
+        })
+        .collect()
+}
+
+/// Appends an input to the data as a segment at its own org
+/// All segments share the main namespace, references between the inputs
+/// resolve to the labels of the other inputs
+pub fn append_input(data: &mut Vec<u8>, input: &[u8], name: &str, org: Address, ctx: &mut Context) {
+    let start = data.len();
+    data.extend_from_slice(input);
+    ctx.segments
+        .push(Segment::new(name, start, data.len(), org, ""));
+}
+
+/// Appends a file as a segment at its org
+/// Intel HEX and S-record files are decoded and every block becomes a segment
+/// at its record address (moved by the org), so gaps are not filled
+pub fn append_file(data: &mut Vec<u8>, file: &InputFile, ctx: &mut Context) -> FdResult<()> {
+    let input = file.read()?;
+    let first = ctx.segments.len();
+    let start = data.len();
+    let records = match LoaderKind::from_path(&file.path) {
+        Some(LoaderKind::IntelHex) => ihex::load(&input, ctx)?,
+        Some(LoaderKind::Srec) => srec::load(&input, ctx)?,
+        _ => {
+            append_input(data, &input, &file.name(), file.org, ctx);
+            return Ok(());
+        }
+    };
+    let name = file.name();
+    for (i, segment) in ctx.segments[first..].iter_mut().enumerate() {
+        segment.name = format!("{name}{i}");
+        segment.start += start;
+        segment.end += start;
+        segment.org += file.org;
+    }
+    data.extend(records);
+    Ok(())
+}
+
+/// Appends the files after the loaded main input
+/// Unless the loader declared segments the window of the main input
+/// becomes the first segment
+pub fn append_files(
+    data: &mut Vec<u8>,
+    main: &str,
+    files: &[InputFile],
+    ctx: &mut Context,
+) -> FdResult<()> {
+    if files.is_empty() {
+        return Ok(());
+    }
+    if ctx.segments.is_empty() {
+        let window = ctx.window(data);
+        let org = ctx.address_of(window.start as Address);
+        ctx.segments
+            .push(Segment::new(main, window.start, window.end, org, ""));
+    }
+    for file in files {
+        append_file(data, file, ctx)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,234 @@
+use log::info;
+
+use crate::{
+    core::dasm::{
+        arch::Context,
+        region::Region,
+        symbols::{Scope, Symbol, SymbolKind},
+        Address, ValueType,
+    },
+    prelude::{Error, FdResult},
+};
+
+use super::atari2600::bank_name;
+
+pub const NSF_HEADER_SIZE: usize = 0x80;
+pub const BANK_SIZE: usize = 0x1000;
+// the 8 bank slots at 8000-ffff
+pub const BANK_ORG: Address = 0x8000;
+const TEXT_LEN: usize = 32;
+
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct NsfHeader {
+    pub version: u8,
+    pub songs: u8,
+    pub start_song: u8,
+    pub load: Address,
+    pub init: Address,
+    pub play: Address,
+    pub name: String,
+    pub artist: String,
+    pub copyright: String,
+    // the bank that is mapped to each slot, all zero if the tune is not banked
+    pub banks: [u8; 8],
+    // the expansion audio chips
+    pub chips: u8,
+}
+
+impl NsfHeader {
+    pub fn parse(data: &[u8]) -> FdResult<Self> {
+        if data.len() < NSF_HEADER_SIZE || &data[..5] != b"NESM\x1A" {
+            return Err(Error::InvalidHeader("NSF".into()));
+        }
+        let word = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]) as Address;
+        let text = |at: usize| {
+            let bytes = &data[at..at + TEXT_LEN];
+            let end = bytes.iter().position(|b| *b == 0).unwrap_or(TEXT_LEN);
+            String::from_utf8_lossy(&bytes[..end]).into_owned()
+        };
+        let mut banks = [0; 8];
+        banks.copy_from_slice(&data[0x70..0x78]);
+
+        Ok(Self {
+            version: data[0x05],
+            songs: data[0x06],
+            start_song: data[0x07],
+            load: word(0x08),
+            init: word(0x0A),
+            play: word(0x0C),
+            name: text(0x0E),
+            artist: text(0x2E),
+            copyright: text(0x4E),
+            banks,
+            chips: data[0x7B],
+        })
+    }
+
+    pub fn is_banked(&self) -> bool {
+        self.banks.iter().any(|b| *b != 0)
+    }
+
+    pub fn comment(&self) -> String {
+        format!(
+            "NSF v{}: '{}' by '{}' ({}), {} songs starting at {}, loaded at {:04x}, chips {:02x}",
+            self.version,
+            self.name,
+            self.artist,
+            self.copyright,
+            self.songs,
+            self.start_song,
+            self.load,
+            self.chips
+        )
+    }
+}
+
+fn label(name: &str, address: Address, namespace: &str) -> Symbol {
+    let mut sym = Symbol::new(
+        name.into(),
+        SymbolKind::Label,
+        Scope::Global,
+        address as ValueType,
+        1,
+    );
+    sym.namespace = namespace.into();
+    sym
+}
+
+/// Skips the header of an NSF tune
+/// Banked tunes are split into a region per 4K bank that is disassembled
+/// at the slot the bank is mapped to when the tune starts
+pub fn load(data: &[u8], ctx: &mut Context) -> FdResult<Vec<u8>> {
+    let header = NsfHeader::parse(data)?;
+    info!("{}", header.comment());
+    let entries = [
+        ("load", header.load),
+        ("init", header.init),
+        ("play", header.play),
+    ];
+
+    if !header.is_banked() {
+        let end = header.load + (data.len() - NSF_HEADER_SIZE) as Address;
+        let start = header.init.min(header.play);
+        if start < header.load || start >= end {
+            return Err(Error::InvalidHeader(format!(
+                "NSF init {:04x} is outside of the data",
+                header.init
+            )));
+        }
+        ctx.set_org(header.load.wrapping_sub(NSF_HEADER_SIZE as Address));
+        // the init and play routines usually follow the driver's data
+        ctx.set_start(Some(NSF_HEADER_SIZE + (start - header.load) as usize));
+        ctx.comments.insert(start, header.comment());
+        for (name, address) in entries {
+            ctx.def_symbol(label(name, address, ""));
+        }
+        return Ok(data.to_vec());
+    }
+
+    // the data starts at the load address' offset into the first bank
+    let padding = (header.load & (BANK_SIZE as Address - 1)) as usize;
+    let mut banked = vec![0; padding];
+    banked.extend_from_slice(&data[NSF_HEADER_SIZE..]);
+    banked.resize(banked.len().div_ceil(BANK_SIZE) * BANK_SIZE, 0);
+
+    let banks = banked.len() / BANK_SIZE;
+    info!("NSF: {banks} banks, initial slots {:?}", header.banks);
+    for bank in 0..banks {
+        let slot = header.banks.iter().position(|b| *b as usize == bank);
+        let org = BANK_ORG + (slot.unwrap_or(0) * BANK_SIZE) as Address;
+        let name = bank_name(bank);
+        ctx.regions.push(Region::new(
+            &name,
+            &ctx.arch_key,
+            bank * BANK_SIZE,
+            (bank + 1) * BANK_SIZE,
+            org,
+        ));
+        ctx.def_symbol(label(&name, org, &name));
+    }
+    ctx.comments.insert(BANK_ORG, header.comment());
+    // the entry points are in the banks that are mapped when the tune starts
+    for (name, address) in entries {
+        if address < BANK_ORG {
+            continue;
+        }
+        let slot = ((address - BANK_ORG) as usize) / BANK_SIZE;
+        ctx.def_symbol(label(
+            name,
+            address,
+            &bank_name(header.banks[slot] as usize),
+        ));
+    }
+    Ok(banked)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{
+        arch::{a6502, Context},
+        loader::LoaderKind,
+    };
+
+    fn nsf(load: u16, init: u16, play: u16, banks: [u8; 8]) -> Vec<u8> {
+        let mut data = vec![0; 0x80];
+        data[..5].copy_from_slice(b"NESM\x1A");
+        data[0x05] = 1;
+        data[0x06] = 2;
+        data[0x07] = 1;
+        data[0x08..0x0A].copy_from_slice(&load.to_le_bytes());
+        data[0x0A..0x0C].copy_from_slice(&init.to_le_bytes());
+        data[0x0C..0x0E].copy_from_slice(&play.to_le_bytes());
+        data[0x0E..0x12].copy_from_slice(b"Tune");
+        data[0x70..0x78].copy_from_slice(&banks);
+        data
+    }
+
+    #[test]
+    fn load() {
+        let mut data = nsf(0x8000, 0x8001, 0x8002, [0; 8]);
+        data.extend([0x00, 0x60, 0x60]);
+        let mut ctx = Context::default();
+        let data = LoaderKind::Nsf.load(data, &mut ctx).unwrap();
+
+        let mut result = String::new();
+        a6502::ARCH
+            .disas_ctx(
+                |n, _kind, _raw, _arch, _ctx| {
+                    result.push_str(&n.string);
+                    Ok(())
+                },
+                &data,
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(
+            "; NSF v1: 'Tune' by '' (), 2 songs starting at 1, loaded at 8000, chips 00\n\
+             init:\n00008001 rts\nplay:\n00008002 rts\n",
+            result
+        );
+        assert_eq!(0x8000, ctx.syms.get_first_by_name("load").unwrap().value);
+    }
+
+    #[test]
+    fn banked() {
+        // bank 1 is mapped to the last slot
+        let mut data = nsf(0x8100, 0xF000, 0x8100, [0, 2, 3, 4, 5, 6, 7, 1]);
+        data.extend(vec![0xEA; 0x1F00]);
+        let mut ctx = Context::default();
+        let data = LoaderKind::Nsf.load(data, &mut ctx).unwrap();
+        assert_eq!(0x2000, data.len());
+        assert_eq!(0xEA, data[0x100]);
+        assert_eq!(2, ctx.regions.len());
+        assert_eq!(0x8000, ctx.regions[0].org);
+        assert_eq!(0xF000, ctx.regions[1].org);
+        assert_eq!(
+            "bank1",
+            ctx.syms.get_first_by_name("init").unwrap().namespace
+        );
+        assert_eq!(
+            "bank0",
+            ctx.syms.get_first_by_name("play").unwrap().namespace
+        );
+    }
+}
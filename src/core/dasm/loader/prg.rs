@@ -0,0 +1,65 @@
+use log::info;
+
+use crate::{
+    core::dasm::{
+        arch::{Context, StaticOp},
+        Address,
+    },
+    prelude::{Error, FdResult},
+};
+
+pub const LOAD_ADDRESS_SIZE: usize = 2;
+
+/// Reads the load address of a C64 PRG file, uses it as the org
+/// and skips it
+pub fn load(data: &[u8], ctx: &mut Context) -> FdResult<()> {
+    let [lo, hi, ..] = data[..] else {
+        return Err(Error::InvalidHeader("PRG".into()));
+    };
+    let address = u16::from_le_bytes([lo, hi]) as Address;
+    info!("PRG load address {address:04x}");
+
+    ctx.set_start(Some(LOAD_ADDRESS_SIZE));
+    // the load address is not part of the program
+    ctx.set_org(address.wrapping_sub(LOAD_ADDRESS_SIZE as Address));
+
+    if ctx.output.org_directive {
+        ctx.static_ops_pre.lock().unwrap().push(StaticOp::Address(
+            address,
+            vec![StaticOp::StringLn(format!("* = ${address:04x}"))],
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{
+        arch::{a6502, Context},
+        loader::LoaderKind,
+    };
+
+    #[test]
+    fn load() {
+        let mut ctx = Context::default();
+        ctx.output.org_directive = true;
+        let data = LoaderKind::Prg
+            .load(vec![0x01, 0x08, 0xEA, 0x60], &mut ctx)
+            .unwrap();
+
+        let mut result = String::new();
+        a6502::ARCH
+            .disas_ctx(
+                |n, _kind, _raw, _arch, _ctx| {
+                    result.push_str(&n.string);
+                    Ok(())
+                },
+                &data,
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!("* = $0801\n00000801 nop\n00000802 rts\n", result);
+
+        assert!(LoaderKind::Prg.load(vec![0x01], &mut ctx).is_err());
+    }
+}
@@ -0,0 +1,189 @@
+use log::info;
+
+use crate::{
+    core::dasm::{
+        arch::Context,
+        symbols::{Scope, Symbol, SymbolKind},
+        Address, ValueType,
+    },
+    prelude::{Error, FdResult},
+};
+
+const MIN_HEADER_SIZE: usize = 0x76;
+const TEXT_LEN: usize = 32;
+
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct SidHeader {
+    // RSID tunes require a real C64 environment
+    pub rsid: bool,
+    pub version: u16,
+    // the offset of the c64 data in the file
+    pub data_offset: usize,
+    pub load: Address,
+    pub init: Address,
+    // 0 if init installs an interrupt handler
+    pub play: Address,
+    pub songs: u16,
+    pub start_song: u16,
+    pub name: String,
+    pub author: String,
+    pub released: String,
+}
+
+impl SidHeader {
+    pub fn parse(data: &[u8]) -> FdResult<Self> {
+        let rsid = match data.get(..4) {
+            Some(b"PSID") => false,
+            Some(b"RSID") => true,
+            _ => return Err(Error::InvalidHeader("SID".into())),
+        };
+        if data.len() < MIN_HEADER_SIZE {
+            return Err(Error::InvalidHeader("SID".into()));
+        }
+        // the header is big endian, unlike the c64
+        let word = |at: usize| u16::from_be_bytes([data[at], data[at + 1]]);
+        let text = |at: usize| {
+            let bytes = &data[at..at + TEXT_LEN];
+            let end = bytes.iter().position(|b| *b == 0).unwrap_or(TEXT_LEN);
+            String::from_utf8_lossy(&bytes[..end]).into_owned()
+        };
+
+        let data_offset = word(0x06) as usize;
+        let mut load = word(0x08) as Address;
+        let mut data_start = data_offset;
+        // the first two bytes of the data are the load address
+        if load == 0 {
+            let address = data
+                .get(data_offset..data_offset + 2)
+                .ok_or_else(|| Error::InvalidHeader("SID load address".into()))?;
+            load = u16::from_le_bytes([address[0], address[1]]) as Address;
+            data_start += 2;
+        }
+        if data_start > data.len() {
+            return Err(Error::InvalidHeader("SID data offset".into()));
+        }
+        let init = match word(0x0A) as Address {
+            0 => load,
+            init => init,
+        };
+
+        Ok(Self {
+            rsid,
+            version: word(0x04),
+            data_offset: data_start,
+            load,
+            init,
+            play: word(0x0C) as Address,
+            songs: word(0x0E),
+            start_song: word(0x10),
+            name: text(0x16),
+            author: text(0x36),
+            released: text(0x56),
+        })
+    }
+
+    pub fn comment(&self) -> String {
+        format!(
+            "{} v{}: '{}' by '{}' ({}), {} songs starting at {}, loaded at {:04x}",
+            if self.rsid { "RSID" } else { "PSID" },
+            self.version,
+            self.name,
+            self.author,
+            self.released,
+            self.songs,
+            self.start_song,
+            self.load
+        )
+    }
+}
+
+/// Skips the header of a SID tune and starts at the init routine
+pub fn load(data: &[u8], ctx: &mut Context) -> FdResult<()> {
+    let header = SidHeader::parse(data)?;
+    info!("{}", header.comment());
+
+    let end = header.load + (data.len() - header.data_offset) as Address;
+    if header.init < header.load || header.init >= end {
+        return Err(Error::InvalidHeader(format!(
+            "SID init {:04x} is outside of the data",
+            header.init
+        )));
+    }
+
+    // the header is not part of the address space
+    ctx.set_org(header.load.wrapping_sub(header.data_offset as Address));
+    ctx.set_start(Some(
+        header.data_offset + (header.init - header.load) as usize,
+    ));
+    ctx.comments.insert(header.init, header.comment());
+    ctx.def_symbol(Symbol::new(
+        "init".into(),
+        SymbolKind::Label,
+        Scope::Global,
+        header.init as ValueType,
+        1,
+    ));
+    if header.play != 0 {
+        ctx.def_symbol(Symbol::new(
+            "play".into(),
+            SymbolKind::Label,
+            Scope::Global,
+            header.play as ValueType,
+            1,
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{
+        arch::{a6502, Context},
+        loader::LoaderKind,
+    };
+
+    fn sid(load: u16, init: u16, play: u16) -> Vec<u8> {
+        let mut data = vec![0; 0x7C];
+        data[..4].copy_from_slice(b"PSID");
+        data[0x04..0x06].copy_from_slice(&2u16.to_be_bytes());
+        data[0x06..0x08].copy_from_slice(&0x7Cu16.to_be_bytes());
+        data[0x08..0x0A].copy_from_slice(&load.to_be_bytes());
+        data[0x0A..0x0C].copy_from_slice(&init.to_be_bytes());
+        data[0x0C..0x0E].copy_from_slice(&play.to_be_bytes());
+        data[0x0E..0x10].copy_from_slice(&1u16.to_be_bytes());
+        data[0x10..0x12].copy_from_slice(&1u16.to_be_bytes());
+        data[0x16..0x1A].copy_from_slice(b"Tune");
+        data
+    }
+
+    #[test]
+    fn load() {
+        // the load address is in the data, play at 1000 and init at 1001
+        let mut data = sid(0, 0x1001, 0x1000);
+        data.extend([0x00, 0x10, 0x60, 0xEA, 0x60]);
+        let mut ctx = Context::default();
+        let data = LoaderKind::Sid.load(data, &mut ctx).unwrap();
+
+        let mut result = String::new();
+        a6502::ARCH
+            .disas_ctx(
+                |n, _kind, _raw, _arch, _ctx| {
+                    result.push_str(&n.string);
+                    Ok(())
+                },
+                &data,
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(
+            "; PSID v2: 'Tune' by '' (), 1 songs starting at 1, loaded at 1000\n\
+             init:\n00001001 nop\n00001002 rts\n",
+            result
+        );
+        assert_eq!(0x1000, ctx.syms.get_first_by_name("play").unwrap().value);
+
+        assert!(LoaderKind::Sid
+            .load(sid(0x1000, 0x2000, 0), &mut Context::default())
+            .is_err());
+    }
+}
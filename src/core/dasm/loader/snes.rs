@@ -0,0 +1,247 @@
+use log::info;
+
+use crate::{
+    core::dasm::{
+        arch::Context,
+        memory_map::MemoryMap,
+        symbols::{Scope, Symbol, SymbolKind},
+        Address, ValueType,
+    },
+    prelude::{Error, FdResult},
+};
+
+// some copiers prepend a 512 byte header to the rom
+pub const COPIER_HEADER_SIZE: usize = 512;
+pub const HEADER_SIZE: usize = 0x40;
+const TITLE_SIZE: usize = 21;
+
+// possible header locations in the rom
+const CANDIDATES: [(MemoryMap, usize); 3] = [
+    (MemoryMap::LoRom, 0x7FC0),
+    (MemoryMap::HiRom, 0xFFC0),
+    (MemoryMap::ExHiRom, 0x40FFC0),
+];
+
+// native mode vectors followed by emulation mode vectors
+const VECTORS: [(&str, usize); 10] = [
+    ("cop", 0x24),
+    ("brk", 0x26),
+    ("abort", 0x28),
+    ("nmi", 0x2A),
+    ("irq", 0x2E),
+    ("emu_cop", 0x34),
+    ("emu_abort", 0x38),
+    ("emu_nmi", 0x3A),
+    ("reset", 0x3C),
+    ("emu_irq", 0x3E),
+];
+
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct SnesHeader {
+    pub title: String,
+    pub memory_map: MemoryMap,
+    // the rom offset of the header
+    pub location: usize,
+    pub fast_rom: bool,
+    pub rom_size: usize,
+    pub ram_size: usize,
+    pub checksum: u16,
+    pub complement: u16,
+    pub vectors: Vec<(&'static str, u16)>,
+}
+
+fn word(data: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([data[at], data[at + 1]])
+}
+
+impl SnesHeader {
+    fn parse_at(rom: &[u8], memory_map: MemoryMap, location: usize) -> Option<Self> {
+        let header = rom.get(location..location + HEADER_SIZE)?;
+        Some(Self {
+            title: String::from_utf8_lossy(&header[..TITLE_SIZE])
+                .trim_end()
+                .to_owned(),
+            memory_map,
+            location,
+            fast_rom: header[0x15] & 0x10 != 0,
+            rom_size: 1usize.checked_shl(header[0x17] as u32).unwrap_or(0),
+            ram_size: match header[0x18] {
+                0 => 0,
+                n => 1usize.checked_shl(n as u32).unwrap_or(0),
+            },
+            complement: word(header, 0x1C),
+            checksum: word(header, 0x1E),
+            vectors: VECTORS
+                .iter()
+                .map(|(name, at)| (*name, word(header, *at)))
+                .collect(),
+        })
+    }
+
+    pub fn reset(&self) -> u16 {
+        self.vectors
+            .iter()
+            .find(|(name, _)| *name == "reset")
+            .map(|(_, value)| *value)
+            .unwrap_or(0)
+    }
+
+    // how likely it is that this is the actual header
+    fn score(&self, map_mode: u8) -> usize {
+        let mut score = 0;
+        if self.checksum ^ self.complement == 0xFFFF {
+            score += 4;
+        }
+        let expected_mode = match self.memory_map {
+            MemoryMap::LoRom => 0x0,
+            MemoryMap::HiRom => 0x1,
+            _ => 0x5,
+        };
+        if map_mode & 0xE0 == 0x20 && map_mode & 0x0F == expected_mode {
+            score += 2;
+        }
+        if self.reset() >= 0x8000 {
+            score += 2;
+        }
+        if self.title.chars().all(|c| c.is_ascii_graphic() || c == ' ') {
+            score += 1;
+        }
+        score
+    }
+
+    /// Finds the most likely header in a rom without a copier header
+    pub fn detect(rom: &[u8]) -> FdResult<Self> {
+        CANDIDATES
+            .iter()
+            .filter_map(|(map, location)| {
+                let header = Self::parse_at(rom, *map, *location)?;
+                Some((header.score(rom[location + 0x15]), header))
+            })
+            .filter(|(score, _)| *score > 0)
+            // the first candidate wins a tie
+            .rev()
+            .max_by_key(|(score, _)| *score)
+            .map(|(_, header)| header)
+            .ok_or_else(|| Error::InvalidHeader("SNES".into()))
+    }
+
+    pub fn comment(&self) -> String {
+        format!(
+            "SNES header: {}, {}{}, {}K rom, {}K ram, checksum {:04x}",
+            self.title,
+            match self.memory_map {
+                MemoryMap::LoRom => "LoROM",
+                MemoryMap::HiRom => "HiROM",
+                MemoryMap::ExHiRom => "ExHiROM",
+                _ => "linear",
+            },
+            if self.fast_rom { ", FastROM" } else { "" },
+            self.rom_size,
+            self.ram_size,
+            self.checksum
+        )
+    }
+}
+
+/// The size of the copier header that may be in front of the rom
+pub fn copier_header_size(data: &[u8]) -> usize {
+    if data.len() % 1024 == COPIER_HEADER_SIZE {
+        COPIER_HEADER_SIZE
+    } else {
+        0
+    }
+}
+
+/// Skips a copier header, selects the memory map from the internal header
+/// and defines symbols for the vectors
+pub fn load(data: &[u8], ctx: &mut Context) -> FdResult<()> {
+    let copier = copier_header_size(data);
+    let header = SnesHeader::detect(&data[copier..])?;
+    info!("{}", header.comment());
+
+    ctx.memory_map = header.memory_map;
+    ctx.set_start(Some(copier));
+    // the copier header is not part of the rom
+    ctx.set_org((0 as Address).wrapping_sub(copier as Address));
+    ctx.comments.insert(
+        header.memory_map.to_address(header.location as Address),
+        header.comment(),
+    );
+
+    for (name, value) in header.vectors.iter() {
+        // vectors below 0x8000 do not point into the rom
+        if *value < 0x8000 {
+            continue;
+        }
+        ctx.def_symbol(Symbol::new(
+            name.to_string(),
+            SymbolKind::Label,
+            Scope::Global,
+            header.memory_map.bank0_address(*value) as ValueType,
+            1,
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{arch::Context, loader::LoaderKind, memory_map::MemoryMap};
+
+    use super::SnesHeader;
+
+    fn rom(len: usize, location: usize, map_mode: u8) -> Vec<u8> {
+        let mut data = vec![0xEA; len];
+        let header = &mut data[location..location + 0x40];
+        header.fill(0);
+        header[..21].copy_from_slice(b"TEST GAME            ");
+        header[0x15] = map_mode;
+        header[0x17] = 6;
+        header[0x18] = 3;
+        header[0x1C..0x20].copy_from_slice(&[0x34, 0x12, 0xCB, 0xED]);
+        header[0x3C..0x3E].copy_from_slice(&[0x00, 0x80]);
+        header[0x2A..0x2C].copy_from_slice(&[0x10, 0x80]);
+        data
+    }
+
+    #[test]
+    fn detect() {
+        let header = SnesHeader::detect(&rom(0x10000, 0x7FC0, 0x20)).unwrap();
+        assert_eq!(MemoryMap::LoRom, header.memory_map);
+        assert_eq!("TEST GAME", header.title);
+        assert_eq!(
+            "SNES header: TEST GAME, LoROM, 64K rom, 8K ram, checksum edcb",
+            header.comment()
+        );
+
+        let header = SnesHeader::detect(&rom(0x10000, 0xFFC0, 0x31)).unwrap();
+        assert_eq!(MemoryMap::HiRom, header.memory_map);
+        assert!(header.fast_rom);
+    }
+
+    #[test]
+    fn load() {
+        // with copier header
+        let mut data = vec![0; 512];
+        data.extend(rom(0x10000, 0x7FC0, 0x20));
+        let mut ctx = Context::default();
+        LoaderKind::Snes.load(data, &mut ctx).unwrap();
+        assert_eq!(512, ctx.start_read);
+        assert_eq!(0x8000, ctx.address());
+        ctx.offset = 0x8000;
+        assert_eq!(0x18000, ctx.address());
+        assert_eq!(0x8000, ctx.syms.get_first_by_name("reset").unwrap().value);
+        assert_eq!(0x8010, ctx.syms.get_first_by_name("nmi").unwrap().value);
+        assert!(ctx.syms.get_first_by_name("irq").is_none());
+        assert!(ctx.comments.contains_key(&0xFFC0));
+
+        let mut ctx = Context::default();
+        LoaderKind::Snes
+            .load(rom(0x10000, 0xFFC0, 0x21), &mut ctx)
+            .unwrap();
+        assert_eq!(0xC00000, ctx.address());
+        assert_eq!(0xC08000, ctx.syms.get_first_by_name("reset").unwrap().value);
+        assert_eq!(Some(0x8000), MemoryMap::HiRom.to_offset(0xC08000));
+        assert_eq!(Some(0x8000), MemoryMap::LoRom.to_offset(0x18000));
+    }
+}
@@ -0,0 +1,193 @@
+use log::info;
+
+use crate::{
+    core::dasm::{
+        arch::{spc700::DATA, Context},
+        region::Region,
+        symbols::{Scope, Symbol, SymbolKind},
+        Address, ValueType,
+    },
+    prelude::{Error, FdResult},
+};
+
+const MAGIC: &[u8] = b"SNES-SPC700 Sound File Data";
+// the header contains id666 tags if this byte is 26
+const ID666: u8 = 26;
+const RAM_OFFSET: usize = 0x100;
+pub const RAM_SIZE: usize = 0x10000;
+const DSP_OFFSET: usize = RAM_OFFSET + RAM_SIZE;
+pub const DSP_SIZE: usize = 0x80;
+pub const DSP_REGION: &str = "dsp";
+const TEXT_LEN: usize = 32;
+
+// the registers that every voice has at voice * 0x10
+const VOICE_REGISTERS: [&str; 10] = [
+    "voll", "volr", "pitchl", "pitchh", "srcn", "adsr1", "adsr2", "gain", "envx", "outx",
+];
+
+const GLOBAL_REGISTERS: [(&str, Address); 15] = [
+    ("mvoll", 0x0C),
+    ("mvolr", 0x1C),
+    ("evoll", 0x2C),
+    ("evolr", 0x3C),
+    ("kon", 0x4C),
+    ("koff", 0x5C),
+    ("flg", 0x6C),
+    ("endx", 0x7C),
+    ("efb", 0x0D),
+    ("pmon", 0x2D),
+    ("non", 0x3D),
+    ("eon", 0x4D),
+    ("dir", 0x5D),
+    ("esa", 0x6D),
+    ("edl", 0x7D),
+];
+
+/// The cpu state at the time the file was saved
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct SpcHeader {
+    pub pc: Address,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub psw: u8,
+    pub sp: u8,
+    pub song: String,
+    pub game: String,
+}
+
+impl SpcHeader {
+    pub fn parse(data: &[u8]) -> FdResult<Self> {
+        if !data.starts_with(MAGIC) || data.len() < DSP_OFFSET + DSP_SIZE {
+            return Err(Error::InvalidHeader("SPC".into()));
+        }
+        let text = |at: usize| {
+            let bytes = &data[at..at + TEXT_LEN];
+            let end = bytes.iter().position(|b| *b == 0).unwrap_or(TEXT_LEN);
+            String::from_utf8_lossy(&bytes[..end]).trim().to_owned()
+        };
+        let (song, game) = if data[0x23] == ID666 {
+            (text(0x2E), text(0x4E))
+        } else {
+            Default::default()
+        };
+
+        Ok(Self {
+            pc: u16::from_le_bytes([data[0x25], data[0x26]]) as Address,
+            a: data[0x27],
+            x: data[0x28],
+            y: data[0x29],
+            psw: data[0x2A],
+            sp: data[0x2B],
+            song,
+            game,
+        })
+    }
+
+    pub fn comment(&self) -> String {
+        format!(
+            "SPC: '{}' from '{}', pc={:04x} a={:02x} x={:02x} y={:02x} psw={:02x} sp=01{:02x}",
+            self.song, self.game, self.pc, self.a, self.x, self.y, self.psw, self.sp
+        )
+    }
+}
+
+fn dsp_label(ctx: &mut Context, name: String, address: Address) {
+    let mut sym = Symbol::new(
+        name,
+        SymbolKind::Label,
+        Scope::Global,
+        address as ValueType,
+        1,
+    );
+    sym.namespace = DSP_REGION.into();
+    ctx.def_symbol(sym);
+}
+
+/// Returns the 64K ram followed by the dsp registers
+/// The ram is disassembled from the saved pc and the dsp registers are output
+/// as data in their own region
+pub fn load(data: &[u8], ctx: &mut Context) -> FdResult<Vec<u8>> {
+    let header = SpcHeader::parse(data)?;
+    info!("{}", header.comment());
+
+    ctx.set_org(0);
+    ctx.set_start(Some(header.pc as usize));
+    ctx.comments.insert(header.pc, header.comment());
+    ctx.def_symbol(Symbol::new(
+        "entry".into(),
+        SymbolKind::Label,
+        Scope::Global,
+        header.pc as ValueType,
+        1,
+    ));
+
+    ctx.regions.push(Region::new(
+        DSP_REGION,
+        DATA,
+        RAM_SIZE,
+        RAM_SIZE + DSP_SIZE,
+        0,
+    ));
+    for voice in 0..8 {
+        for (i, name) in VOICE_REGISTERS.iter().enumerate() {
+            dsp_label(ctx, format!("v{voice}{name}"), voice * 0x10 + i as Address);
+        }
+        // the echo filter coefficients
+        dsp_label(ctx, format!("c{voice}"), voice * 0x10 + 0x0F);
+    }
+    for (name, address) in GLOBAL_REGISTERS {
+        dsp_label(ctx, name.into(), address);
+    }
+
+    Ok(data[RAM_OFFSET..DSP_OFFSET + DSP_SIZE].to_vec())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{
+        arch::{spc700, Context},
+        loader::LoaderKind,
+    };
+
+    use super::{DSP_OFFSET, DSP_SIZE, MAGIC};
+
+    #[test]
+    fn load() {
+        let mut spc = vec![0; DSP_OFFSET + DSP_SIZE];
+        spc[..MAGIC.len()].copy_from_slice(MAGIC);
+        spc[0x23] = 26;
+        spc[0x25..0x27].copy_from_slice(&0xFFF8u16.to_le_bytes());
+        spc[0x27] = 0x12;
+        spc[0x2B] = 0xEF;
+        spc[0x2E..0x32].copy_from_slice(b"Song");
+        // mov a, #$34 and ret at fff8
+        spc[0x100 + 0xFFF8..0x100 + 0xFFFB].copy_from_slice(&[0xE8, 0x34, 0x6F]);
+        spc[DSP_OFFSET + 0x4C] = 0x01;
+
+        let mut ctx = Context::default();
+        let data = LoaderKind::Spc.load(spc, &mut ctx).unwrap();
+        assert_eq!(0x10000 + DSP_SIZE, data.len());
+
+        let mut result = String::new();
+        spc700::ARCH
+            .disas_ctx(
+                |n, _kind, _raw, _arch, _ctx| {
+                    result.push_str(&n.string);
+                    Ok(())
+                },
+                &data,
+                &mut ctx,
+            )
+            .unwrap();
+        assert!(result.starts_with(
+            "; SPC: 'Song' from '', pc=fff8 a=12 x=00 y=00 psw=00 sp=01ef\n\
+             entry:\n0000fff8 mov a, #$34\n0000fffa ret\n"
+        ));
+        assert!(result.contains("kon:\n0000004c .db $01\n"));
+
+        assert!(LoaderKind::Spc
+            .load(vec![0; DSP_OFFSET], &mut Context::default())
+            .is_err());
+    }
+}
@@ -0,0 +1,152 @@
+use log::info;
+
+use crate::{
+    core::dasm::{
+        arch::Context,
+        symbols::{Scope, Symbol, SymbolKind},
+        Address, ValueType,
+    },
+    prelude::{Error, FdResult},
+};
+
+use super::{hex_bytes, image::Image};
+
+/// A decoded line of an S-record file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    pub kind: u8,
+    pub address: Address,
+    pub data: Vec<u8>,
+}
+
+// the size of the address field per record type
+fn address_len(kind: u8) -> Option<usize> {
+    match kind {
+        0 | 1 | 5 | 9 => Some(2),
+        2 | 6 | 8 => Some(3),
+        3 | 7 => Some(4),
+        _ => None,
+    }
+}
+
+impl Record {
+    /// Parses a record (STCCAAAA...DDSS) and verifies its checksum
+    pub fn parse(line: &str, line_nr: usize) -> FdResult<Self> {
+        let err = || Error::InvalidHeader(format!("S-record line {line_nr}"));
+        let hex = line.strip_prefix('S').ok_or_else(err)?;
+        let kind = hex
+            .get(..1)
+            .and_then(|k| k.parse::<u8>().ok())
+            .ok_or_else(err)?;
+        let address_len = address_len(kind).ok_or_else(err)?;
+        let bytes = hex_bytes(&hex[1..]).map_err(|_| err())?;
+
+        // the count covers the address, the data and the checksum
+        let count = *bytes.first().ok_or_else(err)? as usize;
+        if bytes.len() != count + 1
+            || count < address_len + 1
+            || bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0xFF
+        {
+            return Err(err());
+        }
+        Ok(Self {
+            kind,
+            address: bytes[1..1 + address_len]
+                .iter()
+                .fold(0, |value, b| (value << 8) | *b as Address),
+            data: bytes[1 + address_len..count].to_vec(),
+        })
+    }
+}
+
+/// Decodes S19, S28 and S37 records into an image and disassembles every
+/// contiguous block as a segment at its address
+pub fn load(data: &[u8], ctx: &mut Context) -> FdResult<Vec<u8>> {
+    let text = String::from_utf8_lossy(data);
+    let mut image = Image::default();
+    let mut entry = None;
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let record = Record::parse(line, i + 1)?;
+        match record.kind {
+            0 => info!(
+                "S-record header '{}'",
+                String::from_utf8_lossy(&record.data).trim_end_matches('\0')
+            ),
+            1..=3 => {
+                let overwritten = image.write(record.address, &record.data);
+                if overwritten > 0 {
+                    ctx.warn(&format!(
+                        "S-record line {}: {overwritten} bytes at {:x} are written twice",
+                        i + 1,
+                        record.address
+                    ));
+                }
+            }
+            // the record count is not needed
+            5 | 6 => {}
+            _ => entry = Some(record.address),
+        }
+    }
+    if image.is_empty() {
+        return Err(Error::InvalidHeader("S-record without data".into()));
+    }
+
+    let code = image.load("srec", ctx);
+    info!(
+        "S-record: {} bytes in {} segments",
+        code.len(),
+        ctx.segments.len()
+    );
+    if let Some(entry) = entry {
+        ctx.def_symbol(Symbol::new(
+            "entry".into(),
+            SymbolKind::Label,
+            Scope::Global,
+            entry as ValueType,
+            1,
+        ));
+    }
+    Ok(code)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{arch::Context, loader::LoaderKind};
+
+    use super::Record;
+
+    #[test]
+    fn records() {
+        assert_eq!(
+            Record {
+                kind: 2,
+                address: 0x018000,
+                data: vec![0x4E, 0x75],
+            },
+            Record::parse("S2060180004E75B5", 1).unwrap()
+        );
+        // bad checksum
+        assert!(Record::parse("S2060180004E75B6", 1).is_err());
+        assert!(Record::parse("S4060180004E75B5", 1).is_err());
+        assert!(Record::parse("S10\u{e9}0", 1).is_err());
+    }
+
+    #[test]
+    fn load() {
+        let srec = "S00600004844521B\nS1058000EA6030\nS1048010EA81\nS90380007C\n";
+        let mut ctx = Context::default();
+        let data = LoaderKind::Srec
+            .load(srec.as_bytes().to_vec(), &mut ctx)
+            .unwrap();
+        assert_eq!(vec![0xEA, 0x60, 0xEA], data);
+        assert_eq!(2, ctx.segments.len());
+        assert_eq!("srec1", ctx.segments[1].name);
+        assert_eq!(0x8010, ctx.segments[1].org);
+        assert_eq!(0x8000, ctx.syms.get_first_by_name("entry").unwrap().value);
+    }
+}
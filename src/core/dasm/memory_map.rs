@@ -0,0 +1,83 @@
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::Address;
+
+/// Maps offsets into the rom to addresses on the cpu's bus
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MemoryMap {
+    // the address is the offset
+    #[default]
+    Linear,
+    // 32K banks at bank:8000-ffff
+    LoRom,
+    // 64K banks at c0:0000-ff:ffff
+    HiRom,
+    // the first 4M at c0:0000-ff:ffff, the rest at 40:0000-7d:ffff
+    ExHiRom,
+    // bank 0 at 0000-3fff, the switchable 16K banks at bank:4000-7fff
+    GameBoy,
+}
+
+const LO_ROM_BANK_SIZE: Address = 0x8000;
+const HI_ROM_BASE: Address = 0xC00000;
+const EX_HI_ROM_SIZE: Address = 0x400000;
+const GB_BANK_SIZE: Address = 0x4000;
+
+impl MemoryMap {
+    pub fn to_address(&self, offset: Address) -> Address {
+        match self {
+            MemoryMap::Linear => offset,
+            MemoryMap::LoRom => {
+                (offset / LO_ROM_BANK_SIZE) << 16 | LO_ROM_BANK_SIZE | (offset % LO_ROM_BANK_SIZE)
+            }
+            MemoryMap::HiRom => HI_ROM_BASE | offset,
+            MemoryMap::ExHiRom => {
+                if offset < EX_HI_ROM_SIZE {
+                    HI_ROM_BASE | offset
+                } else {
+                    offset
+                }
+            }
+            MemoryMap::GameBoy => match offset / GB_BANK_SIZE {
+                0 => offset,
+                bank => bank << 16 | GB_BANK_SIZE | (offset % GB_BANK_SIZE),
+            },
+        }
+    }
+
+    /// The rom offset of an address
+    /// Returns None if the address is not mapped to the rom
+    pub fn to_offset(&self, address: Address) -> Option<Address> {
+        let bank = address >> 16;
+        let low = address & 0xFFFF;
+        match self {
+            MemoryMap::Linear => Some(address),
+            MemoryMap::LoRom if low >= LO_ROM_BANK_SIZE => {
+                Some((bank & 0x7F) * LO_ROM_BANK_SIZE + low - LO_ROM_BANK_SIZE)
+            }
+            MemoryMap::LoRom => None,
+            MemoryMap::HiRom => Some(address & 0x3FFFFF),
+            MemoryMap::ExHiRom if bank >= 0xC0 => Some(address - HI_ROM_BASE),
+            MemoryMap::ExHiRom if (0x40..0x7E).contains(&bank) => Some(address),
+            MemoryMap::ExHiRom => Some(address & 0x3FFFFF),
+            MemoryMap::GameBoy if bank == 0 && low < GB_BANK_SIZE => Some(low),
+            MemoryMap::GameBoy if (GB_BANK_SIZE..GB_BANK_SIZE * 2).contains(&low) => {
+                Some(bank.max(1) * GB_BANK_SIZE + low - GB_BANK_SIZE)
+            }
+            MemoryMap::GameBoy => None,
+        }
+    }
+
+    /// The address of a 16 bit pointer in bank 0
+    pub fn bank0_address(&self, pointer: u16) -> Address {
+        match self {
+            MemoryMap::HiRom | MemoryMap::ExHiRom => HI_ROM_BASE | pointer as Address,
+            _ => pointer as Address,
+        }
+    }
+}
@@ -8,8 +8,34 @@ use self::arch::{Arch, Node, NodeKind};
 use lazy_static::lazy_static;
 
 pub mod arch;
+pub mod check;
+pub mod checksum;
+pub mod classify;
+pub mod descent;
+pub mod dispatch;
+pub mod duplicates;
+pub mod event;
+pub mod expr;
+pub mod flow;
+pub mod interrupts;
+pub mod loader;
+pub mod memory_map;
+pub mod output;
+pub mod overlay;
+pub mod packs;
 pub mod patch;
+pub mod pointers;
+pub mod regex;
+pub mod region;
+pub mod report;
+pub mod segment;
+pub mod similarity;
+pub mod split;
+pub mod stack;
+pub mod stats;
+pub mod strings;
 pub mod symbols;
+pub mod symfile;
 
 pub type Address = u64;
 
@@ -56,6 +82,16 @@ impl DataType {
             DataType::None => 0,
         }
     }
+
+    /// Sign extends a value of this type to the full value type
+    pub fn sign_extend(&self, value: ValueType) -> ValueType {
+        match self {
+            DataType::I8 => value as i8 as ValueType,
+            DataType::I16 => value as i16 as ValueType,
+            DataType::I32 => value as i32 as ValueType,
+            _ => value,
+        }
+    }
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -149,11 +185,21 @@ pub fn try_to_node(v: ValueType, fmt: ValueTypeFmt, arch: &Arch) -> FdResult<Nod
 #[cfg(test)]
 mod test {
     use super::{
-        arch::{a6502, a65c02, a65c816, Context},
-        symbols::{Symbol, SymbolKind},
+        arch::{
+            a6502, a65c02, a65c816, a6800, a6803, a8051, chip8, dialect::Dialect, msp430, pdp11,
+            sh2, spc700, superfx, CallbackKind, Context, Endianess, EndianessRange, StaticOp,
+        },
+        loader,
+        memory_map::MemoryMap,
+        output::{LineKind, OutputProfile, SourceMap, Substitution},
+        overlay::Overlay,
+        region::Region,
+        segment::{Gap, Segment},
+        symbols::{split_qualified, Scope, Symbol, SymbolKind},
         Address,
     };
     use crate::core::dasm::arch::Archs;
+    use std::collections::BTreeMap;
 
     fn test_arch_result(arch: &Archs, data: &[u8], expected: &str, end_addr: Address) {
         let mut result = "".to_string();
@@ -193,13 +239,160 @@ mod test {
         assert_eq!(end_addr, ctx.address())
     }
 
+    #[test]
+    fn terminator() {
+        let mut ctx = Context::default();
+        ctx.set_terminator(Some(vec![0xFF, 0x00]));
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &[0xEA, 0x60, 0xFF, 0x00, 0xEA, 0xEA],
+            "00000000 nop\n00000001 rts\n00000002 .db $ff\n00000003 brk\n",
+            4,
+        );
+    }
+
+    #[test]
+    fn reassemble() {
+        // the bytes outside of the window are output as data
+        let mut ctx = Context {
+            output: OutputProfile {
+                reassemble: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        ctx.set_start(Some(2));
+        ctx.set_len(Some(2));
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &[0x4E, 0x45, 0xEA, 0x60, 0xEA, 0xFF],
+            "00000000 .db $4e, $45\n00000002 nop\n00000003 rts\n00000004 .db $ea, $ff\n",
+            6,
+        );
+    }
+
+    #[test]
+    fn bank_crossing() {
+        // the operand of the lda is in the next lorom bank
+        let mut ctx = Context {
+            org: 0x7FFE,
+            memory_map: MemoryMap::LoRom,
+            output: OutputProfile {
+                crossing_comments: true,
+                size_report: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &[0xAD, 0x00, 0x20, 0xEA],
+            "0000fffe lda $2000\n; fffe: lda crosses a bank boundary into 18000\n00018001 nop\n",
+            0x18002,
+        );
+        assert_eq!(1, ctx.size_report.crossings.len());
+
+        // a linear map has no banks
+        let mut ctx = Context {
+            org: 0xFFFE,
+            output: OutputProfile {
+                crossing_comments: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &[0xAD, 0x00, 0x20, 0xEA],
+            "0000fffe lda $2000\n00010001 nop\n",
+            0x10002,
+        );
+
+        // but the end of an overlay window is a boundary
+        let mut ctx = Context {
+            org: 0xBFFE,
+            overlays: vec![Overlay::new(0x8000, 0xC000, "bank")],
+            output: OutputProfile {
+                crossing_comments: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &[0xAD, 0x00, 0x20, 0xEA],
+            "0000bffe lda $2000\n; bffe: lda crosses a bank boundary into c000\n0000c001 nop\n",
+            0xC002,
+        );
+    }
+
+    #[test]
+    fn segments() {
+        // the bytes between the segments are not disassembled
+        let mut ctx = Context::default();
+        ctx.segments.extend([
+            Segment::new("code", 0, 2, 0x8000, ""),
+            Segment::new("data", 3, 5, 0xC000, ""),
+        ]);
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &[0xEA, 0x60, 0xFF, 0xEA, 0xEA],
+            "; segment code: 0x0..0x2 at 8000\n00008000 nop\n00008001 rts\n\
+            ; segment data: 0x3..0x5 at c000\n0000c000 nop\n0000c001 nop\n",
+            4,
+        );
+
+        // the gap is output as data in reassemble mode
+        ctx.restart();
+        ctx.output.reassemble = true;
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &[0xEA, 0x60, 0xFF, 0xEA, 0xEA],
+            "; segment code: 0x0..0x2 at 8000\n00008000 nop\n00008001 rts\n\
+            00000002 .db $ff\n\
+            ; segment data: 0x3..0x5 at c000\n0000c000 nop\n0000c001 nop\n",
+            5,
+        );
+    }
+
+    #[test]
+    fn multiple_inputs() {
+        // the jump into the second input resolves to its label
+        let mut ctx = Context::default();
+        let mut data = vec![];
+        loader::append_input(&mut data, &[0x4C, 0x00, 0x80], "main", 0x1000, &mut ctx);
+        loader::append_input(&mut data, &[0x60], "bank1", 0x8000, &mut ctx);
+        ctx.def_symbol(Symbol::new(
+            "sub".into(),
+            SymbolKind::Label,
+            super::symbols::Scope::Global,
+            0x8000,
+            1,
+        ));
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &data,
+            "; segment main: 0x0..0x3 at 1000\n00001000 jmp sub\n\
+            ; segment bank1: 0x3..0x4 at 8000\nsub:\n00008000 rts\n",
+            4,
+        );
+    }
+
     #[test]
     fn a6502() {
         // byte and immediate
         test_arch_result(
             &a6502::ARCH,
             &[0xFF, 0xab, 0x69, 0x02, 0x01],
-            "00000000 .db $ff\n00000001 .db $ab\n00000002 adc #$02\n00000004 .db $01\n",
+            "00000000 .db $ff, $ab\n00000002 adc #$02\n00000004 .db $01\n",
             0x5,
         );
 
@@ -273,6 +466,37 @@ mod test {
             "00000000 jmp ($1234, x)\n",
             3,
         );
+
+        // bit instructions
+        test_arch_result(
+            &a65c02::ARCH,
+            &[0x37, 0x12, 0xF7, 0x34],
+            "00000000 rmb3 $12\n00000002 smb7 $34\n",
+            4,
+        );
+
+        // zero page and relative with label
+        {
+            let mut ctx = Context {
+                org: 0x8000,
+                ..Default::default()
+            };
+            ctx.def_symbol(Symbol::new(
+                "loop".into(),
+                SymbolKind::Label,
+                super::symbols::Scope::Global,
+                0x8000,
+                1,
+            ));
+
+            test_arch_result_ctx(
+                &a65c02::ARCH,
+                &mut ctx,
+                &[0x9F, 0x12, 0xFD, 0x0F, 0x12, 0x00],
+                "loop:\n00008000 bbs1 $12, loop\n00008003 bbr0 $12, $00\n",
+                0x8006,
+            );
+        }
     }
 
     #[test]
@@ -311,6 +535,56 @@ mod test {
         // [dp]
         test_arch_result(&a65c816::ARCH, &[0x07, 0x12], "00000000 ora [$12]\n", 2);
 
+        // switching to emulation mode forces 8-bit registers
+        {
+            let mut ctx = Context::default();
+            ctx.def_flag("m", "");
+            ctx.def_flag("x", "");
+
+            test_arch_result_ctx(
+                &a65c816::ARCH,
+                &mut ctx,
+                &[0x38, 0xFB, 0xA9, 0x12, 0xA2, 0x34, 0x18, 0xFB],
+                "00000000 sec\n00000001 xce\n00000002 lda #$12\n00000004 ldx #$34\n\
+                00000006 clc\n00000007 xce\n",
+                8,
+            );
+            assert_eq!(None, ctx.get_flag("e"));
+            assert_eq!(Some(&"".to_string()), ctx.get_flag("c"));
+        }
+
+        // rep and sep change the register widths, plp restores the widths of php
+        {
+            let mut ctx = Context::default();
+
+            test_arch_result_ctx(
+                &a65c816::ARCH,
+                &mut ctx,
+                &[
+                    0xC2, 0x30, 0xA9, 0x34, 0x12, 0xA2, 0x34, 0x12, 0x08, 0xE2, 0x20, 0xA9, 0x12,
+                    0x28, 0xA9, 0x34, 0x12,
+                ],
+                "00000000 rep #$30\n00000002 lda #$1234\n00000005 ldx #$1234\n\
+                00000008 php\n00000009 sep #$20\n0000000b lda #$12\n0000000d plp\n\
+                0000000e lda #$1234\n",
+                17,
+            );
+        }
+
+        // rep does not widen the registers in emulation mode
+        {
+            let mut ctx = Context::default();
+            ctx.def_flag("e", "");
+
+            test_arch_result_ctx(
+                &a65c816::ARCH,
+                &mut ctx,
+                &[0xC2, 0x30, 0xA9, 0x12],
+                "00000000 rep #$30\n00000002 lda #$12\n",
+                4,
+            );
+        }
+
         // long + test data read size override
         test_arch_result(
             &a65c816::ARCH,
@@ -445,6 +719,1142 @@ mod test {
         );
     }
 
+    #[test]
+    fn superfx() {
+        // register
+        test_arch_result(&superfx::ARCH, &[0x15], "00000000 to r5\n", 1);
+
+        // alt prefixes select the variant of the next instruction only
+        test_arch_result(
+            &superfx::ARCH,
+            &[0x53, 0x3D, 0x53, 0x3E, 0x53, 0x3F, 0x53, 0x53],
+            "00000000 add r3\n00000001 alt1\n00000002 adc r3\n00000003 alt2\n00000004 add #3\n00000005 alt3\n00000006 adc #3\n00000007 add r3\n",
+            8,
+        );
+
+        // alt without a matching variant falls back to the base instruction
+        test_arch_result(
+            &superfx::ARCH,
+            &[0x3E, 0x4C],
+            "00000000 alt2\n00000001 plot\n",
+            2,
+        );
+
+        // register indirect
+        test_arch_result(&superfx::ARCH, &[0x3B], "00000000 stw (r11)\n", 1);
+
+        // immediate byte and word
+        test_arch_result(&superfx::ARCH, &[0xA1, 0x12], "00000000 ibt r1, #$12\n", 2);
+        test_arch_result(
+            &superfx::ARCH,
+            &[0xF2, 0x34, 0x12],
+            "00000000 iwt r2, #$1234\n",
+            3,
+        );
+
+        // short ram address is stored divided by 2
+        test_arch_result(
+            &superfx::ARCH,
+            &[0x3D, 0xA4, 0x12],
+            "00000000 alt1\n00000001 lms r4, ($0024)\n",
+            3,
+        );
+        test_arch_result(
+            &superfx::ARCH,
+            &[0x3E, 0xF4, 0x34, 0x12],
+            "00000000 alt2\n00000001 sm ($1234), r4\n",
+            4,
+        );
+
+        // relative
+        test_arch_result(&superfx::ARCH, &[0x05, 0x11], "00000000 bra $11\n", 2);
+    }
+
+    #[test]
+    fn a8051() {
+        // register and immediate
+        test_arch_result(&a8051::ARCH, &[0x7A, 0x12], "00000000 mov r2, #0x12\n", 2);
+
+        // register indirect
+        test_arch_result(&a8051::ARCH, &[0x27], "00000000 add a, @r1\n", 1);
+
+        // direct to direct has the source encoded first
+        test_arch_result(
+            &a8051::ARCH,
+            &[0x85, 0x30, 0x40],
+            "00000000 mov 0x40, 0x30\n",
+            3,
+        );
+
+        // dptr
+        test_arch_result(
+            &a8051::ARCH,
+            &[0x90, 0x12, 0x34, 0x93],
+            "00000000 mov dptr, #0x1234\n00000003 movc a, @a+dptr\n",
+            4,
+        );
+
+        // bit addressable ram and sfr
+        test_arch_result(&a8051::ARCH, &[0xD2, 0x0B], "00000000 setb 0x21.3\n", 2);
+        test_arch_result(&a8051::ARCH, &[0xC2, 0xE7], "00000000 clr 0xe0.7\n", 2);
+        test_arch_result(&a8051::ARCH, &[0xA0, 0x01], "00000000 orl c, /0x20.1\n", 2);
+
+        // 11 bit address within the current 2k page
+        {
+            let mut ctx = Context {
+                org: 0x0800,
+                ..Default::default()
+            };
+            ctx.def_symbol(Symbol::new(
+                "test".into(),
+                SymbolKind::Label,
+                super::symbols::Scope::Global,
+                0x0B34,
+                1,
+            ));
+
+            test_arch_result_ctx(
+                &a8051::ARCH,
+                &mut ctx,
+                &[0x71, 0x34],
+                "00000800 acall test\n",
+                0x0802,
+            );
+        }
+
+        // relative with label
+        {
+            let mut ctx = Context {
+                org: 0x8000,
+                ..Default::default()
+            };
+            ctx.def_symbol(Symbol::new(
+                "test".into(),
+                SymbolKind::Label,
+                super::symbols::Scope::Global,
+                0x8000,
+                1,
+            ));
+
+            test_arch_result_ctx(
+                &a8051::ARCH,
+                &mut ctx,
+                &[0x20, 0x00, (-3_i8) as u8],
+                "test:\n00008000 jb 0x20.0, test\n",
+                0x8003,
+            );
+        }
+    }
+
+    #[test]
+    fn spc700() {
+        test_arch_result(&spc700::ARCH, &[0xE8, 0x12], "00000000 mov a, #$12\n", 2);
+
+        // direct page to direct page has the source encoded first
+        test_arch_result(
+            &spc700::ARCH,
+            &[0xFA, 0x10, 0x20],
+            "00000000 mov $20, $10\n",
+            3,
+        );
+
+        // indexed and indirect
+        test_arch_result(
+            &spc700::ARCH,
+            &[0xD5, 0x00, 0x02, 0xF7, 0x10],
+            "00000000 mov $0200+x, a\n00000003 mov a, [$10]+y\n",
+            5,
+        );
+
+        // bit operands
+        test_arch_result(&spc700::ARCH, &[0xE2, 0x10], "00000000 set1 $10.7\n", 2);
+        test_arch_result(
+            &spc700::ARCH,
+            &[0x2A, 0x34, 0x52],
+            "00000000 or1 c, /$1234.2\n",
+            3,
+        );
+
+        // tables and the upper page
+        test_arch_result(
+            &spc700::ARCH,
+            &[0x31, 0x4F, 0x20],
+            "00000000 tcall 3\n00000001 pcall $ff20\n",
+            3,
+        );
+
+        // relative with label after the direct page operand
+        {
+            let mut ctx = Context {
+                org: 0x0400,
+                ..Default::default()
+            };
+            ctx.def_symbol(Symbol::new(
+                "loop".into(),
+                SymbolKind::Label,
+                super::symbols::Scope::Global,
+                0x0400,
+                1,
+            ));
+
+            test_arch_result_ctx(
+                &spc700::ARCH,
+                &mut ctx,
+                &[0x6E, 0x10, (-3_i8) as u8],
+                "loop:\n00000400 dbnz $10, loop\n",
+                0x0403,
+            );
+        }
+    }
+
+    #[test]
+    fn a6800() {
+        let mut ctx = Context {
+            org: 0xE000,
+            ..Default::default()
+        };
+        ctx.def_symbol(Symbol::new(
+            "store".into(),
+            SymbolKind::Label,
+            super::symbols::Scope::Global,
+            0xE002,
+            1,
+        ));
+
+        test_arch_result_ctx(
+            &a6800::ARCH,
+            &mut ctx,
+            &[
+                0x86, 0x10, 0x97, 0x80, 0xA7, 0x05, 0xB7, 0x12, 0x34, 0xCE, 0x00, 0x20, 0x4F, 0x26,
+                0xF3, 0x39,
+            ],
+            "0000e000 ldaa #$10\nstore:\n0000e002 staa $80\n0000e004 staa $05, x\n\
+            0000e006 staa $1234\n0000e009 ldx #$0020\n0000e00c clra\n0000e00d bne store\n\
+            0000e00f rts\n",
+            0xE010,
+        );
+
+        // 6803 only instructions
+        test_arch_result(&a6800::ARCH, &[0x3D], "00000000 .db $3d\n", 1);
+        test_arch_result(
+            &a6803::ARCH,
+            &[0xDC, 0x80, 0x3D, 0x9D, 0x40],
+            "00000000 ldd $80\n00000002 mul\n00000003 jsr $40\n",
+            5,
+        );
+    }
+
+    #[test]
+    fn msp430() {
+        // register to register
+        test_arch_result(&msp430::ARCH, &[0x06, 0x45], "00000000 mov r5, r6\n", 2);
+
+        // byte mode with immediate and absolute
+        test_arch_result(
+            &msp430::ARCH,
+            &[0xF2, 0x40, 0x12, 0x00, 0x00, 0x02],
+            "00000000 mov.b #0x0012, &0x0200\n",
+            6,
+        );
+
+        // the source extension word comes first
+        test_arch_result(
+            &msp430::ARCH,
+            &[0x96, 0x45, 0x02, 0x00, 0x04, 0x00],
+            "00000000 mov 0x0002(r5), 0x0004(r6)\n",
+            6,
+        );
+
+        // constant generator
+        test_arch_result(&msp430::ARCH, &[0x14, 0x53], "00000000 add #1, r4\n", 2);
+
+        // single operand and emulated instructions
+        test_arch_result(
+            &msp430::ARCH,
+            &[0xB0, 0x12, 0x34, 0x12, 0x75, 0x12, 0x3A, 0x41, 0x30, 0x41],
+            "00000000 call #0x1234\n00000004 push.b @r5+\n00000006 pop r10\n00000008 ret\n",
+            10,
+        );
+
+        // jumps and symbolic mode are relative to the next word
+        {
+            let mut ctx = Context {
+                org: 0xC000,
+                ..Default::default()
+            };
+            ctx.def_symbol(Symbol::new(
+                "loop".into(),
+                SymbolKind::Label,
+                super::symbols::Scope::Global,
+                0xC000,
+                1,
+            ));
+            ctx.def_symbol(Symbol::new(
+                "data".into(),
+                SymbolKind::Label,
+                super::symbols::Scope::Global,
+                0xC100,
+                1,
+            ));
+
+            test_arch_result_ctx(
+                &msp430::ARCH,
+                &mut ctx,
+                &[0x15, 0x40, 0xFE, 0x00, 0xFD, 0x3F],
+                "loop:\n0000c000 mov data, r5\n0000c004 jmp loop\n",
+                0xC006,
+            );
+        }
+    }
+
+    #[test]
+    fn chip8() {
+        let mut ctx = Context {
+            org: 0x200,
+            ..Default::default()
+        };
+        ctx.def_symbol(Symbol::new(
+            "start".into(),
+            SymbolKind::Label,
+            super::symbols::Scope::Global,
+            0x200,
+            1,
+        ));
+
+        test_arch_result_ctx(
+            &chip8::ARCH,
+            &mut ctx,
+            &[
+                0x00, 0xE0, 0x6A, 0x12, 0x8A, 0xB4, 0xA2, 0x34, 0xDA, 0xB5, 0xFA, 0x07, 0x12, 0x00,
+            ],
+            "start:\n00000200 cls\n00000202 ld vA, 0x12\n00000204 add vA, vB\n\
+            00000206 ld i, 0x234\n00000208 drw vA, vB, 5\n0000020a ld vA, dt\n\
+            0000020c jp start\n",
+            0x20E,
+        );
+    }
+
+    #[test]
+    fn pdp11() {
+        let mut ctx = Context {
+            org: 0o1000,
+            ..Default::default()
+        };
+        ctx.def_symbol(Symbol::new(
+            "loop".into(),
+            SymbolKind::Label,
+            super::symbols::Scope::Global,
+            0o1006,
+            1,
+        ));
+        ctx.def_symbol(Symbol::new(
+            "counter".into(),
+            SymbolKind::Label,
+            super::symbols::Scope::Global,
+            0o1032,
+            1,
+        ));
+
+        test_arch_result_ctx(
+            &pdp11::ARCH,
+            &mut ctx,
+            &[
+                0xC0, 0x15, 0x08, 0x00, 0x11, 0x0A, 0xC0, 0x0A, 0xFE, 0x02, 0xDF, 0x09, 0x00,
+                0x04, 0x72, 0x10, 0x06, 0x00, 0x87, 0x00, 0xB7, 0x0A, 0x02, 0x00, 0x00, 0x00,
+                0x00, 0xF0,
+            ],
+            "00000200 mov #000010, r0\n00000204 clr (r1)+\nloop:\n00000206 dec r0\n\
+            00000208 bne loop\n0000020a jsr r7, @#002000\n0000020e mov r1, 000006(r2)\n\
+            00000212 rts r7\n00000214 inc counter\n00000218 halt\ncounter:\n0000021a .word 170000\n",
+            0x21C,
+        );
+    }
+
+    #[test]
+    fn sh2() {
+        let mut ctx = Context {
+            org: 0x06000000,
+            ..Default::default()
+        };
+        ctx.def_symbol(Symbol::new(
+            "loop".into(),
+            SymbolKind::Label,
+            super::symbols::Scope::Global,
+            0x06000004,
+            1,
+        ));
+
+        // pc relative loads show the constant from the literal pool
+        test_arch_result_ctx(
+            &sh2::ARCH,
+            &mut ctx,
+            &[
+                0xD1, 0x02, 0x92, 0x05, 0xE0, 0xFF, 0x8B, 0xFD, 0x00, 0x0B, 0x00, 0x09, 0x06, 0x00,
+                0x12, 0x34, 0x80, 0x00,
+            ],
+            "06000000 mov.l @(0x08, pc), r1 ; 0x06001234\n\
+            06000002 mov.w @(0x0a, pc), r2 ; 0x8000\nloop:\n06000004 mov #-1, r0\n\
+            06000006 bf loop\n06000008 rts\n0600000a nop\n0600000c .word 0x0600\n\
+            0600000e mov.l r3, @(0x10, r2)\n06000010 .word 0x8000\n",
+            0x06000012,
+        );
+    }
+
+    #[test]
+    fn endianess_override() {
+        let mut ctx = Context {
+            org: 0x06000000,
+            endianess: vec![EndianessRange::new(
+                0x0600000C,
+                0x06000012,
+                Endianess::Little,
+            )],
+            ..Default::default()
+        };
+        ctx.def_symbol(Symbol::new(
+            "loop".into(),
+            SymbolKind::Label,
+            super::symbols::Scope::Global,
+            0x06000004,
+            1,
+        ));
+
+        // the literals and data words in the range are little endian, the instructions are not
+        test_arch_result_ctx(
+            &sh2::ARCH,
+            &mut ctx,
+            &[
+                0xD1, 0x02, 0x92, 0x05, 0xE0, 0xFF, 0x8B, 0xFD, 0x00, 0x0B, 0x00, 0x09, 0x06, 0x00,
+                0x12, 0x34, 0x80, 0x00,
+            ],
+            "06000000 mov.l @(0x08, pc), r1 ; 0x34120006\n\
+            06000002 mov.w @(0x0a, pc), r2 ; 0x0080\nloop:\n06000004 mov #-1, r0\n\
+            06000006 bf loop\n06000008 rts\n0600000a nop\n0600000c .word 0x0006\n\
+            0600000e mov.l r3, @(0x10, r2)\n06000010 .word 0x0080\n",
+            0x06000012,
+        );
+    }
+
+    #[test]
+    fn line_order() {
+        let ctx = || {
+            let mut ctx = Context {
+                org: 0x8000,
+                ..Default::default()
+            };
+            for name in ["b_label", "a_label"] {
+                ctx.def_symbol(Symbol::new(
+                    name.into(),
+                    SymbolKind::Label,
+                    super::symbols::Scope::Global,
+                    0x8000,
+                    1,
+                ));
+            }
+            ctx.comments.insert(0x8000, "comment".into());
+            ctx.static_ops_pre.lock().unwrap().push(StaticOp::Address(
+                0x8000,
+                vec![StaticOp::StringLn("* = $8000".into())],
+            ));
+            ctx
+        };
+
+        // the labels are sorted by name
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx(),
+            &[0xEA],
+            "* = $8000\n; comment\na_label:\nb_label:\n00008000 nop\n",
+            0x8001,
+        );
+
+        let mut ctx = ctx();
+        ctx.output.line_order = vec![LineKind::Labels, LineKind::Statics];
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &[0xEA],
+            "a_label:\nb_label:\n* = $8000\n; comment\n00008000 nop\n",
+            0x8001,
+        );
+    }
+
+    #[test]
+    fn substitutions() {
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        ctx.comments.insert(0x8002, "$ff".into());
+        ctx.output.substitutions = vec![
+            Substitution {
+                pattern: r"^[0-9a-f]+ +".into(),
+                replace: "  ".into(),
+            },
+            Substitution {
+                pattern: r"\$([0-9a-f]+)".into(),
+                replace: "0x$1".into(),
+            },
+            // the pads of the mnemonics
+            Substitution {
+                pattern: " +$".into(),
+                replace: "".into(),
+            },
+        ];
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &[0xA9, 0x10, 0xEA],
+            "  lda #0x10\n; 0xff\n  nop\n",
+            0x8003,
+        );
+
+        ctx.restart();
+        ctx.output.substitutions[0].pattern = "(".into();
+        assert!(a6502::ARCH
+            .disas_ctx(|_n, _kind, _raw, _arch, _ctx| Ok(()), &[0xEA], &mut ctx)
+            .is_err());
+    }
+
+    #[test]
+    fn json() {
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        ctx.output.json = true;
+        ctx.comments.insert(0x8002, "back to \"reset\"".into());
+        ctx.def_symbol(Symbol::new(
+            "reset".into(),
+            SymbolKind::Label,
+            Scope::Global,
+            0x8000,
+            1,
+        ));
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &[0xA9, 0x10, 0x4C, 0x00, 0x80],
+            concat!(
+                r##"{"address":32768,"bytes":[169,16],"mnemonic":"lda","operand":"#$10","value":16,"##,
+                r#""symbol":null,"matcher":"lda","labels":["reset"],"comments":[],"statics":[]}"#,
+                "\n",
+                r#"{"address":32770,"bytes":[76,0,128],"mnemonic":"jmp","operand":"reset","value":32768,"#,
+                r#""symbol":"reset","matcher":"jmp","labels":[],"comments":["back to \"reset\""],"#,
+                r#""statics":[]}"#,
+                "\n"
+            ),
+            0x8005,
+        );
+    }
+
+    #[test]
+    fn clean() {
+        let mut ctx = Context::default();
+        ctx.output.clean = true;
+        ctx.allow_raw = true;
+        ctx.segments = vec![
+            Segment::new("main", 0, 7, 0x8000, ""),
+            Segment::new("bank", 7, 8, 0xC000, ""),
+        ];
+        for (name, value) in [("reset", 0x8000), ("CHROUT", 0xFFD2), ("MAIN", 0xC000)] {
+            ctx.def_symbol(Symbol::new(
+                name.into(),
+                SymbolKind::Label,
+                Scope::Global,
+                value,
+                1,
+            ));
+        }
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &[0xEA, 0x4C, 0x00, 0x80, 0x4C, 0xD2, 0xFF, 0x60],
+            "CHROUT = $ffd2\n\n\
+             ; segment main: 0x0..0x7 at 8000\n.org $8000\nreset:\n nop\n jmp reset\n jmp CHROUT\n\
+             ; segment bank: 0x7..0x8 at c000\n.org $c000\nMAIN:\n rts\n",
+            // the clean output is byte-exact and ends at the end of the input
+            8,
+        );
+    }
+
+    #[test]
+    fn markdown() {
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        ctx.output.markdown = true;
+        // the comments above a label belong to its section
+        ctx.comments
+            .insert(0x8001, "count up\nuntil x wraps".into());
+        ctx.comments.insert(0x8004, "call".into());
+        for (name, value) in [("loop", 0x8001), ("done", 0x8007)] {
+            ctx.def_symbol(Symbol::new(
+                name.into(),
+                SymbolKind::Label,
+                Scope::Global,
+                value,
+                1,
+            ));
+        }
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &[0xEA, 0xE8, 0xD0, 0xFD, 0x20, 0x07, 0x80, 0x60],
+            "```asm\n00008000 nop\n```\n\n## <a id=\"loop\"></a>loop\n\ncount up\nuntil x wraps\n\n\
+             ```asm\n00008001 inx\n00008002 bne loop\n```\n\ncall\n\n```asm\n00008004 jsr done\n\
+             ```\n\nReferences: [loop](#loop), [done](#done)\n\n\
+             ## <a id=\"done\"></a>done\n\n```asm\n00008007 rts\n```\n",
+            0x8008,
+        );
+    }
+
+    #[test]
+    fn cycles() {
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        ctx.output.cycles = true;
+        ctx.def_symbol(Symbol::new(
+            "done".into(),
+            SymbolKind::Label,
+            Scope::Global,
+            0x8003,
+            1,
+        ));
+        // the total starts again at the label, data has no cycles
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &[0xA9, 0x10, 0xEA, 0x8D, 0x00, 0x20, 0x60, 0x02],
+            "00008000 lda #$10 ; 2 cycles (2)\n00008002 nop ; 2 cycles (4)\ndone:\n\
+             00008003 sta $2000 ; 4 cycles (4)\n00008006 rts ; 6 cycles (10)\n00008007 .db $02\n",
+            0x8008,
+        );
+    }
+
+    #[test]
+    fn source_map() {
+        let mut ctx = Context {
+            org: 0x8000,
+            source_map: Some(SourceMap::default()),
+            ..Default::default()
+        };
+        ctx.set_start(Some(1));
+        for (name, kind, value) in [
+            ("loop", SymbolKind::Label, 0x8001),
+            ("io", SymbolKind::Const, 0x2000),
+        ] {
+            ctx.def_symbol(Symbol::new(name.into(), kind, Scope::Global, value, 1));
+        }
+        let data = [0x02, 0xEA, 0x8D, 0x00, 0x20, 0x4C, 0x01, 0x80];
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &data,
+            "loop:\n00008001 nop\n00008002 sta io\n00008005 jmp loop\n",
+            0x8008,
+        );
+        // the label line belongs to the address it labels
+        assert_eq!(
+            "1 8001 0x1\n2 8001 0x1\n3 8002 0x2\n4 8005 0x5\n",
+            ctx.source_map
+                .replace(SourceMap::default())
+                .unwrap()
+                .to_string()
+        );
+
+        // the equates of the clean output are not mapped, the byte before the window
+        // is output as data
+        ctx.output.clean = true;
+        ctx.restart();
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &data,
+            "io = $2000\n\n.org $8000\n .db $02\nloop:\n nop\n sta io\n jmp loop\n",
+            0x8008,
+        );
+        assert_eq!(
+            "3 8000 0x0\n4 8000 0x0\n5 8001 0x1\n6 8001 0x1\n7 8002 0x2\n8 8005 0x5\n",
+            ctx.source_map.unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn hexdump() {
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        ctx.output.hexdump = true;
+        let mut data = vec![0xA9, 0x10];
+        data.extend([0xEA; 13]);
+        // the jmp starts in the first row and ends in the second
+        data.extend([0x4C, 0x00, 0x80, 0x60]);
+        let mut result = String::new();
+        a6502::ARCH
+            .disas_ctx(
+                |n, _kind, _raw, _arch, _ctx| {
+                    result.push_str(&n.string);
+                    Ok(())
+                },
+                &data,
+                &mut ctx,
+            )
+            .unwrap();
+        let lines: Vec<&str> = result.lines().collect();
+        assert_eq!(
+            "00000000  a9 10 ea ea ea ea ea ea  ea ea ea ea ea ea ea 4c  | 00008000 lda #$10",
+            lines[0]
+        );
+        assert_eq!(format!("{:60}| 00008002 nop", ""), lines[1]);
+        assert_eq!(
+            format!("00000010  00 80 60{:42}| 00008012 rts", ""),
+            lines[15]
+        );
+    }
+
+    #[test]
+    fn comment_column() {
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        ctx.output.comment_column = Some(20);
+        ctx.comments.insert(0x8000, "init".into());
+        ctx.comments.insert(0x8002, "two\nlines".into());
+        let mut result = String::new();
+        a6502::ARCH
+            .disas_ctx(
+                |n, kind, _raw, _arch, ctx| {
+                    if let CallbackKind::Pad(chars) = kind {
+                        let pad = chars.saturating_sub(ctx.tr_ctx.line_len);
+                        result.extend(std::iter::repeat_n(' ', pad));
+                    }
+                    result.push_str(&n.string);
+                    Ok(())
+                },
+                &[0xEA, 0xE8, 0x60],
+                &mut ctx,
+            )
+            .unwrap();
+        // multi line comments stay on their own lines
+        assert_eq!(
+            "00008000 nop        ; init\n00008001 inx\n; two\n; lines\n00008002 rts\n",
+            result
+        );
+    }
+
+    #[test]
+    fn filter_tag() {
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        ctx.output.filter_tag = Some("audio".into());
+        for (name, value, tag) in [
+            ("init", 0x8000, "audio"),
+            ("gfx", 0x8001, "gfx"),
+            ("play", 0x8003, "audio"),
+        ] {
+            let mut sym = Symbol::new(name.into(), SymbolKind::Label, Scope::Global, value, 1);
+            sym.tags.insert(tag.into());
+            ctx.def_symbol(sym);
+        }
+        // the comments belong to the section of their label
+        ctx.comments.insert(0x8000, "sets up the sound".into());
+        ctx.comments.insert(0x8001, "hidden".into());
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &[0xEA, 0xE8, 0xE8, 0x60],
+            "; sets up the sound\ninit:\n00008000 nop\nplay:\n00008003 rts\n",
+            0x8004,
+        );
+    }
+
+    #[test]
+    fn uppercase() {
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        ctx.output.uppercase_mnemonics = true;
+        ctx.output.uppercase_hex = true;
+        // the directives keep their case
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &[0xA9, 0x1F, 0xAD, 0xEF, 0xBE, 0x02],
+            "00008000 LDA #$1F\n00008002 LDA $BEEF\n00008005 .db $02\n",
+            0x8006,
+        );
+        // the prefix of the value keeps its case
+        let mut ctx = Context::default();
+        ctx.output.uppercase_hex = true;
+        test_arch_result_ctx(
+            &a8051::ARCH,
+            &mut ctx,
+            &[0x79, 0xAB],
+            "00000000 mov r1, #0xAB\n",
+            2,
+        );
+    }
+
+    #[test]
+    fn dialect() {
+        let mut arch = a6502::ARCH.to_owned();
+        Dialect::Tass64.apply(&mut arch);
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        ctx.output.clean = true;
+        ctx.def_symbol(Symbol::new(
+            "loop".into(),
+            SymbolKind::Label,
+            Scope::Range(0x8000, 0x8010),
+            0x8000,
+            1,
+        ));
+        test_arch_result_ctx(
+            &arch,
+            &mut ctx,
+            &[0xA9, 0x10, 0xD0, 0xFC, 0x02, 0x03],
+            "* = $8000\n_loop:\n lda #$10\n bne _loop\n .byte $02, $03\n",
+            0x8006,
+        );
+    }
+
+    #[test]
+    fn zp_vars() {
+        let mut ctx = Context {
+            org: 0x8000,
+            zp_vars: true,
+            analyze: true,
+            ..Default::default()
+        };
+        // named symbols take precedence
+        ctx.def_symbol(Symbol::new(
+            "PTR".into(),
+            SymbolKind::Label,
+            Scope::Global,
+            0x20,
+            2,
+        ));
+        let data = [0xA5, 0x10, 0x91, 0x20, 0xA5, 0x21, 0xB6, 0x10, 0xA9, 0x30];
+        a6502::ARCH
+            .disas_ctx(|_n, _kind, _raw, _arch, _ctx| Ok(()), &data, &mut ctx)
+            .unwrap();
+        assert!(ctx.syms.get_first_by_name("var_21").is_none());
+        // immediate values are not addresses
+        assert!(ctx.syms.get_first_by_name("var_30").is_none());
+
+        ctx.restart();
+        ctx.analyze = false;
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &data,
+            "00008000 lda var_10\n00008002 sta (PTR), y\n00008004 lda PTR+1\n\
+             00008006 ldx var_10, y\n00008008 lda #$30\n",
+            0x800A,
+        );
+    }
+
+    #[test]
+    fn xrefs() {
+        let mut ctx = Context {
+            org: 0x8000,
+            analyze: true,
+            ..Default::default()
+        };
+        ctx.output.xrefs = true;
+        ctx.def_symbol(Symbol::new(
+            "reset".into(),
+            SymbolKind::Label,
+            Scope::Global,
+            0x8000,
+            1,
+        ));
+        let data = [0xEA, 0xD0, 0xFD, 0x4C, 0x00, 0x80, 0x20, 0x00, 0x80];
+        a6502::ARCH
+            .disas_ctx(|_n, _kind, _raw, _arch, _ctx| Ok(()), &data, &mut ctx)
+            .unwrap();
+
+        ctx.restart();
+        ctx.analyze = false;
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &data,
+            "reset:\n; xrefs: $8001, $8003, $8006\n00008000 nop\n00008001 bne reset\n\
+             00008003 jmp reset\n00008006 jsr reset\n",
+            0x8009,
+        );
+    }
+
+    #[test]
+    fn symbol_doc() {
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        ctx.output.line_width = 24;
+        let mut sym = Symbol::new(
+            "reset".into(),
+            SymbolKind::Label,
+            super::symbols::Scope::Global,
+            0x8000,
+            1,
+        );
+        sym.doc = Some("Clears the ram and jumps to the main loop\nNever returns".into());
+        ctx.def_symbol(sym);
+
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &[0xEA],
+            "; Clears the ram and\n; jumps to the main loop\n; Never returns\nreset:\n00008000 nop\n",
+            0x8001,
+        );
+    }
+
+    #[test]
+    fn gaps() {
+        let mut ctx = Context {
+            org: 0x8000,
+            gaps: vec![Gap::new(1, 3), Gap::new(2, 4)],
+            ..Default::default()
+        };
+        // the gaps are merged and the bytes after them keep their address
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &[0xEA, 0x00, 0x00, 0x00, 0x60],
+            "00008000 nop\n; undefined 8001..=8003 (0x3 bytes)\n00008004 rts\n",
+            0x8005,
+        );
+    }
+
+    #[test]
+    fn bank_overlay() {
+        let mut ctx = Context {
+            org: 0x1000,
+            overlays: vec![Overlay::new(0xF000, 0xF004, "bank")],
+            ..Default::default()
+        };
+        for (name, bank) in [("start", "bank0"), ("other", "bank1")] {
+            let mut sym = Symbol::new(
+                name.into(),
+                SymbolKind::Label,
+                super::symbols::Scope::Global,
+                0xF000,
+                1,
+            );
+            sym.namespace = bank.into();
+            ctx.def_symbol(sym);
+        }
+        ctx.def_flag("bank", "bank0");
+        // the second jump happens after switching to the other bank
+        ctx.static_ops_pre.lock().unwrap().push(StaticOp::Address(
+            0x1003,
+            vec![StaticOp::SetFlag("bank".into(), "bank1".into())],
+        ));
+
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &[0x4C, 0x00, 0xF0, 0x4C, 0x00, 0xF0],
+            "00001000 jmp bank0.start\n00001003 jmp bank1.other\n",
+            0x1006,
+        );
+    }
+
+    #[test]
+    fn coprocessor_region() {
+        let mut arch = a6502::ARCH.to_owned();
+        arch.add_coprocessor("chip8", &chip8::ARCH);
+
+        let mut ctx = Context {
+            org: 0x8000,
+            regions: vec![Region::new("chip8", "chip8", 1, 5, 0x200)],
+            ..Default::default()
+        };
+        ctx.def_symbol(Symbol::new(
+            "reset".into(),
+            SymbolKind::Label,
+            super::symbols::Scope::Global,
+            0x8000,
+            1,
+        ));
+        // labels of the main processor are not visible inside the region
+        ctx.def_symbol(Symbol::new(
+            "main_200".into(),
+            SymbolKind::Label,
+            super::symbols::Scope::Global,
+            0x200,
+            1,
+        ));
+        let mut start = Symbol::new(
+            "start".into(),
+            SymbolKind::Label,
+            super::symbols::Scope::Global,
+            0x200,
+            1,
+        );
+        start.namespace = "chip8".into();
+        ctx.def_symbol(start.clone());
+        start.name = "chip8_8005".into();
+        start.value = 0x8005;
+        ctx.def_symbol(start);
+
+        test_arch_result_ctx(
+            &arch,
+            &mut ctx,
+            &[0xEA, 0x00, 0xE0, 0x12, 0x00, 0xEA],
+            "reset:\n00008000 nop\nstart:\n00000200 cls\n00000202 jp start\n00008005 nop\n",
+            0x8006,
+        );
+    }
+
+    #[test]
+    fn namespace_lookup() {
+        let mut arch = a6502::ARCH.to_owned();
+        arch.add_coprocessor("chip8", &chip8::ARCH);
+
+        let mut ctx = Context {
+            org: 0x8000,
+            regions: vec![Region::new("chip8", "chip8", 3, 5, 0x200)],
+            namespace_lookup: BTreeMap::from([
+                ("".into(), vec!["chip8".into()]),
+                ("chip8".into(), vec!["".into()]),
+            ]),
+            ..Default::default()
+        };
+        ctx.def_symbol(Symbol::new(
+            "reset".into(),
+            SymbolKind::Label,
+            super::symbols::Scope::Global,
+            0x8000,
+            1,
+        ));
+        let mut start = Symbol::new(
+            "start".into(),
+            SymbolKind::Label,
+            super::symbols::Scope::Global,
+            0x200,
+            1,
+        );
+        start.namespace = "chip8".into();
+        ctx.def_symbol(start);
+        ctx.def_symbol(Symbol::new(
+            "boot".into(),
+            SymbolKind::Const,
+            super::symbols::Scope::Global,
+            0,
+            1,
+        ));
+        assert_eq!(
+            0x200,
+            ctx.syms.get_first_by_name("chip8.start").unwrap().value
+        );
+        // only known namespaces qualify a name
+        assert!(ctx.syms.get_first_by_name("x.start").is_none());
+        assert!(ctx.syms.get_first_by_name(".start").is_none());
+        assert_eq!(
+            ("chip8", "a.b"),
+            split_qualified("chip8.a.b", |n| ctx.is_namespace(n))
+        );
+        assert_eq!(
+            ("", ".loop"),
+            split_qualified(".loop", |n| ctx.is_namespace(n))
+        );
+        assert_eq!(
+            ("", "a.b.c"),
+            split_qualified("a.b.c", |n| ctx.is_namespace(n))
+        );
+
+        // symbols of other namespaces are qualified
+        test_arch_result_ctx(
+            &arch,
+            &mut ctx,
+            &[0xAD, 0x00, 0x02, 0x10, 0x00],
+            "reset:\n00008000 lda chip8.start\nstart:\n00000200 jp boot\n",
+            0x8005,
+        );
+    }
+
+    #[test]
+    fn data_per_line() {
+        // one value per line
+        {
+            let mut ctx = Context {
+                output: OutputProfile {
+                    data_per_line: 1,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            test_arch_result_ctx(
+                &a6502::ARCH,
+                &mut ctx,
+                &[0xFF, 0xab, 0x69, 0x02, 0x01],
+                "00000000 .db $ff\n00000001 .db $ab\n00000002 adc #$02\n00000004 .db $01\n",
+                0x5,
+            );
+        }
+
+        // runs are split after 8 values by default
+        test_arch_result(
+            &a6502::ARCH,
+            &[0xFF; 10],
+            "00000000 .db $ff, $ff, $ff, $ff, $ff, $ff, $ff, $ff\n00000008 .db $ff, $ff\n",
+            10,
+        );
+
+        // labels start a new line
+        {
+            let mut ctx = Context::default();
+            ctx.def_symbol(Symbol::new(
+                "test".into(),
+                SymbolKind::Label,
+                super::symbols::Scope::Global,
+                0x2,
+                1,
+            ));
+            test_arch_result_ctx(
+                &a6502::ARCH,
+                &mut ctx,
+                &[0xFF; 4],
+                "00000000 .db $ff, $ff\ntest:\n00000002 .db $ff, $ff\n",
+                4,
+            );
+        }
+    }
+
+    #[test]
+    fn ascii_sidebar() {
+        let mut ctx = Context {
+            output: OutputProfile {
+                ascii_sidebar: true,
+                charset: BTreeMap::from([(0xFF, '#')]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        test_arch_result_ctx(
+            &a6502::ARCH,
+            &mut ctx,
+            &[0xFF, 0x42, 0x07, 0x22],
+            "00000000 .db $ff, $42, $07, $22 ; #B.\"\n",
+            4,
+        );
+    }
+
     #[test]
     fn labels() {
         let mut ctx = Context::default();
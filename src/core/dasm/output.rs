@@ -0,0 +1,631 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
+
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::FdResult;
+
+use super::{
+    arch::{layout::Layout, CallbackKind, Node, NodeKind},
+    regex::Regex,
+    Address, ValueType,
+};
+
+/// The lines that can be output before the instruction at an address
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LineKind {
+    // the lines of static ops, e.g. org directives
+    Statics,
+    // the comments of the context and the checksums
+    Comments,
+    // the labels, user and global labels first and sorted by name
+    Labels,
+}
+
+fn default_line_order() -> Vec<LineKind> {
+    vec![LineKind::Statics, LineKind::Comments, LineKind::Labels]
+}
+
+/// A find and replace rule that is applied to every output line
+/// e.g. to adjust the syntax for a specific assembler
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Substitution {
+    // a regular expression, see regex::Regex for the supported syntax
+    pub pattern: String,
+    // the replacement, $1 inserts the first capture of the pattern
+    pub replace: String,
+}
+
+/// The output profile describes how the disassembly should be laid out
+/// independent of the architecture
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutputProfile {
+    // the maximum amount of values a data transform groups into a single line
+    #[cfg_attr(feature = "serde", serde(default = "default_data_per_line"))]
+    pub data_per_line: usize,
+    // the width that comment blocks are wrapped to
+    #[cfg_attr(feature = "serde", serde(default = "default_line_width"))]
+    pub line_width: usize,
+
+    // appends the bytes of a data line as characters in a comment
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ascii_sidebar: bool,
+    // overrides the character that is shown for a byte in the sidebar
+    // bytes that are not printable ascii and not in this map are shown as .
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub charset: BTreeMap<u8, char>,
+    // emits the org directive of a loaded file (e.g. * = $0801)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub org_directive: bool,
+    // outputs the bytes outside of the window as data and verifies
+    // that every byte of the input is output exactly once
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub reassemble: bool,
+    // counts the output bytes per section and bank and reports matchers
+    // that consumed fewer bytes than they are encoded in
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub size_report: bool,
+    // outputs a comment after instructions that cross a bank boundary
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub crossing_comments: bool,
+    // outputs the addresses that reference a label after it
+    // the references are collected by the analysis pass
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub xrefs: bool,
+    // the order of the lines before an instruction
+    // kinds that are not listed are output after the listed ones
+    #[cfg_attr(feature = "serde", serde(default = "default_line_order"))]
+    pub line_order: Vec<LineKind>,
+    // the substitutions that are applied to the finished lines in order
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub substitutions: Vec<Substitution>,
+    // outputs every instruction as a json object on its own line
+    // substitutions are not applied to the json lines
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub json: bool,
+    // output that assembles back to the input: no address and raw columns,
+    // org directives where the address changes and equates for the referenced
+    // symbols that are not labels of the output. implies reassemble
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub clean: bool,
+    // outputs the lines as fenced code blocks with a heading for every label
+    // e.g. for wiki pages. json takes precedence
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub markdown: bool,
+    // outputs a hexdump of the 16 bytes of the input that the lines start in
+    // on the left of the lines, e.g. to audit the boundaries of code and data
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hexdump: bool,
+    // outputs the cycles of the instructions that the arch has cycle counts for
+    // and the total since the last label after the code
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub cycles: bool,
+    // the order and widths of the address, code and raw columns
+    // replaces the column transforms of the arch when it is set
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub layout: Option<Layout>,
+    // outputs the single line comments of the context after the instruction,
+    // padded to this column, instead of before it
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub comment_column: Option<usize>,
+    // only outputs the sections that start at a label with the tag,
+    // a section ends at the next label
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub filter_tag: Option<String>,
+    // the same as the uppercase options of the arch
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub uppercase_mnemonics: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub uppercase_hex: bool,
+}
+
+fn default_data_per_line() -> usize {
+    8
+}
+
+fn default_line_width() -> usize {
+    80
+}
+
+impl Default for OutputProfile {
+    fn default() -> Self {
+        Self {
+            data_per_line: default_data_per_line(),
+            line_width: default_line_width(),
+            ascii_sidebar: false,
+            charset: Default::default(),
+            org_directive: false,
+            reassemble: false,
+            size_report: false,
+            crossing_comments: false,
+            xrefs: false,
+            line_order: default_line_order(),
+            substitutions: vec![],
+            json: false,
+            clean: false,
+            markdown: false,
+            hexdump: false,
+            cycles: false,
+            layout: None,
+            comment_column: None,
+            filter_tag: None,
+            uppercase_mnemonics: false,
+            uppercase_hex: false,
+        }
+    }
+}
+
+impl OutputProfile {
+    /// True if every byte of the input has to be output exactly once
+    pub fn byte_exact(&self) -> bool {
+        self.reassemble || self.clean
+    }
+
+    /// Renders the bytes using the charset
+    pub fn to_chars(&self, data: &[u8]) -> String {
+        data.iter()
+            .map(|b| match self.charset.get(b) {
+                Some(c) => *c,
+                None if b.is_ascii_graphic() || *b == b' ' => *b as char,
+                None => '.',
+            })
+            .collect()
+    }
+
+    /// The byte is a character of a string, is printable ascii or in the charset
+    /// Quotes end a string
+    pub fn is_text(&self, byte: u8) -> bool {
+        match self.charset.get(&byte) {
+            Some(c) => *c != '"',
+            None => (byte.is_ascii_graphic() || byte == b' ') && byte != b'"',
+        }
+    }
+
+    /// Splits the line order into the kinds before and after the labels
+    /// The kinds that are not listed are added in their default order
+    pub fn lines_around_labels(&self) -> (Vec<LineKind>, Vec<LineKind>) {
+        let mut order = self.line_order.clone();
+        order.dedup();
+        for kind in default_line_order() {
+            if !order.contains(&kind) {
+                order.push(kind);
+            }
+        }
+        let labels = order
+            .iter()
+            .position(|k| *k == LineKind::Labels)
+            .unwrap_or_default();
+        (order[..labels].to_vec(), order[labels + 1..].to_vec())
+    }
+
+    /// Compiles the patterns of the substitutions
+    pub fn substitutions(&self) -> FdResult<Vec<(Regex, String)>> {
+        self.substitutions
+            .iter()
+            .map(|s| Ok((Regex::new(&s.pattern)?, s.replace.clone())))
+            .collect()
+    }
+
+    /// Wraps the text to comment lines that fit the line width
+    /// Line breaks of the text are kept, words longer than a line are not split
+    pub fn comment_block(&self, text: &str, comment_pre: &str) -> String {
+        let width = self.line_width.saturating_sub(comment_pre.len()).max(1);
+        let mut result = String::new();
+        for paragraph in text.lines() {
+            let mut line = String::new();
+            for word in paragraph.split_whitespace() {
+                if !line.is_empty() && line.len() + 1 + word.len() > width {
+                    result.push_str(&format!("{comment_pre}{line}\n"));
+                    line.clear();
+                }
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(word);
+            }
+            result.push_str(&format!("{comment_pre}{line}\n"));
+        }
+        result
+    }
+}
+
+/// Applies the compiled substitutions to a line in order
+pub fn substitute(substitutions: &[(Regex, String)], line: &str) -> String {
+    substitutions
+        .iter()
+        .fold(line.to_owned(), |line, (regex, replace)| {
+            regex.replace_all(&line, replace)
+        })
+}
+
+/// The nodes that are output for a single matcher, they are written as a json object
+/// with the address, the bytes, the mnemonic, the operand text and the first operand's
+/// value and symbol
+#[derive(Default, Clone, Debug)]
+pub struct JsonLine {
+    pub address: Address,
+    pub bytes: Vec<u8>,
+    pub matcher: String,
+    // the text of the nodes without the address and the labels
+    pub text: String,
+    pub labels: Vec<String>,
+    pub value: Option<ValueType>,
+    pub symbol: Option<String>,
+}
+
+impl JsonLine {
+    pub fn push(&mut self, node: &Node, kind: &CallbackKind) {
+        match kind {
+            CallbackKind::Address | CallbackKind::Raw | CallbackKind::Pad(_) => return,
+            CallbackKind::Label => {
+                self.labels.extend(
+                    node.string
+                        .lines()
+                        .map(|l| l.trim().trim_end_matches(':').to_owned())
+                        .filter(|l| !l.is_empty()),
+                );
+                return;
+            }
+            CallbackKind::Symbol if self.symbol.is_none() => {
+                self.symbol = Some(node.string.clone());
+            }
+            _ => {}
+        }
+        if let (NodeKind::Value(value), None) = (&node.kind, self.value) {
+            if matches!(kind, CallbackKind::Val | CallbackKind::Symbol) {
+                self.value = Some(*value);
+            }
+        }
+        self.text.push_str(&node.string);
+    }
+
+    /// The json object, lines before the instruction are comments
+    /// if they start with the comment prefix and statics otherwise
+    pub fn to_json(&self, comment_pre: &str) -> String {
+        let comment_pre = comment_pre.trim();
+        let is_comment = |l: &str| !comment_pre.is_empty() && l.starts_with(comment_pre);
+        let lines: Vec<&str> = self
+            .text
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .collect();
+        let split = lines
+            .iter()
+            .rposition(|l| !is_comment(l))
+            .unwrap_or(lines.len());
+
+        let mut comments = vec![];
+        let mut statics = vec![];
+        for line in lines[..split].iter().chain(lines.iter().skip(split + 1)) {
+            match line.strip_prefix(comment_pre).filter(|_| is_comment(line)) {
+                Some(comment) => comments.push(comment.trim()),
+                None => statics.push(*line),
+            }
+        }
+        let line = lines.get(split).copied().unwrap_or_default();
+        let code = match line
+            .split_once(comment_pre)
+            .filter(|_| !comment_pre.is_empty())
+        {
+            Some((code, comment)) => {
+                comments.push(comment.trim());
+                code.trim()
+            }
+            None => line,
+        };
+        let (mnemonic, operand) = code.split_once(char::is_whitespace).unwrap_or((code, ""));
+
+        let strings = |s: &[&str]| {
+            s.iter()
+                .map(|s| json_string(s))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        let labels: Vec<&str> = self.labels.iter().map(String::as_str).collect();
+        let bytes: Vec<String> = self.bytes.iter().map(u8::to_string).collect();
+        format!(
+            "{{\"address\":{},\"bytes\":[{}],\"mnemonic\":{},\"operand\":{},\"value\":{},\
+             \"symbol\":{},\"matcher\":{},\"labels\":[{}],\"comments\":[{}],\"statics\":[{}]}}\n",
+            self.address,
+            bytes.join(","),
+            json_string(mnemonic),
+            json_string(operand.trim()),
+            self.value
+                .map(|v| v.to_string())
+                .unwrap_or_else(|| "null".into()),
+            self.symbol
+                .as_deref()
+                .map(json_string)
+                .unwrap_or_else(|| "null".into()),
+            json_string(&self.matcher),
+            strings(&labels),
+            strings(&comments),
+            strings(&statics),
+        )
+    }
+}
+
+/// Quotes and escapes a string for json
+pub fn json_string(s: &str) -> String {
+    let mut result = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+#[derive(Clone, Debug)]
+enum MarkdownEvent {
+    Code(String),
+    Prose(String),
+    Heading(Vec<String>),
+    Reference(String),
+}
+
+/// Wraps the finished lines in fenced code blocks
+/// Every label ends the current block and starts a section with the labels as its heading.
+/// The headings are anchors, the comments are paragraphs between the blocks and every section
+/// ends with links to the sections it references. The document is built by finish
+/// because the references can only be linked once all headings are known
+#[derive(Default, Clone, Debug)]
+pub struct MarkdownWriter {
+    events: Vec<MarkdownEvent>,
+    // the first event of the current address, the heading of a label is inserted here
+    // so the comments above the label belong to its section
+    address_start: usize,
+}
+
+impl MarkdownWriter {
+    /// The following lines belong to the next address
+    pub fn address(&mut self) {
+        self.address_start = self.events.len();
+    }
+
+    /// Starts a section with the names of the labels of a label node
+    pub fn label(&mut self, labels: &str) {
+        let names: Vec<String> = labels
+            .lines()
+            .map(|l| l.trim().trim_end_matches(':').to_owned())
+            .filter(|l| !l.is_empty())
+            .collect();
+        if names.is_empty() {
+            return;
+        }
+        if let Some(MarkdownEvent::Heading(heading)) = self
+            .address_start
+            .checked_sub(1)
+            .and_then(|i| self.events.get_mut(i))
+        {
+            heading.extend(names);
+            return;
+        }
+        self.events
+            .insert(self.address_start, MarkdownEvent::Heading(names));
+        self.address_start += 1;
+    }
+
+    /// A finished line, lines that start with the comment prefix are output as prose
+    pub fn line(&mut self, line: &str, comment_pre: &str) {
+        let event = match line
+            .strip_prefix(comment_pre)
+            .filter(|_| !comment_pre.is_empty())
+        {
+            Some(text) => MarkdownEvent::Prose(text.trim().to_owned()),
+            None => MarkdownEvent::Code(line.to_owned()),
+        };
+        self.events.push(event);
+    }
+
+    /// A symbol of the current section, it is linked if it is the heading of a section
+    /// The offset of name+1 is not part of the name
+    pub fn reference(&mut self, symbol: &str) {
+        let name = symbol.split('+').next().unwrap_or_default().trim();
+        self.events.push(MarkdownEvent::Reference(name.to_owned()));
+    }
+
+    /// The document with the headings, the code blocks, the prose and the links
+    pub fn finish(&mut self) -> String {
+        let events = std::mem::take(&mut self.events);
+        self.address_start = 0;
+        let anchors: BTreeSet<&String> = events
+            .iter()
+            .filter_map(|e| match e {
+                MarkdownEvent::Heading(names) => Some(names),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+
+        let mut section = MarkdownSection::default();
+        let mut result = String::new();
+        for event in &events {
+            match event {
+                MarkdownEvent::Code(line) => {
+                    if std::mem::take(&mut section.prose) {
+                        result.push('\n');
+                    }
+                    if !section.open {
+                        result.push_str("```asm\n");
+                        section.open = true;
+                    }
+                    result.push_str(line);
+                }
+                MarkdownEvent::Prose(text) => {
+                    if std::mem::take(&mut section.open) {
+                        result.push_str("```\n\n");
+                    }
+                    result.push_str(text);
+                    result.push('\n');
+                    section.prose = true;
+                }
+                MarkdownEvent::Reference(name) => {
+                    if anchors.contains(name) && !section.references.contains(name) {
+                        section.references.push(name.clone());
+                    }
+                }
+                MarkdownEvent::Heading(names) => {
+                    result.push_str(&section.close());
+                    let ids: String = names
+                        .iter()
+                        .map(|name| format!("<a id=\"{name}\"></a>"))
+                        .collect();
+                    result.push_str(&format!("## {ids}{}\n\n", names.join(", ")));
+                }
+            }
+        }
+        result.push_str(&section.close());
+        // the last section is not followed by another one
+        result.truncate(result.trim_end().len());
+        if !result.is_empty() {
+            result.push('\n');
+        }
+        result
+    }
+}
+
+// the state of the section that is being written
+#[derive(Default)]
+struct MarkdownSection {
+    // the code block is open
+    open: bool,
+    // a paragraph is open
+    prose: bool,
+    references: Vec<String>,
+}
+
+impl MarkdownSection {
+    // ends the open block or paragraph and links the references of the section
+    fn close(&mut self) -> String {
+        let mut result = String::new();
+        if std::mem::take(&mut self.open) {
+            result.push_str("```\n\n");
+        }
+        if std::mem::take(&mut self.prose) {
+            result.push('\n');
+        }
+        let references = std::mem::take(&mut self.references);
+        if !references.is_empty() {
+            let links: Vec<String> = references
+                .iter()
+                .map(|name| format!("[{name}](#{name})"))
+                .collect();
+            result.push_str(&format!("References: {}\n\n", links.join(", ")));
+        }
+        result
+    }
+}
+
+/// The bytes of a hexdump row
+pub const HEXDUMP_ROW_LEN: usize = 16;
+
+/// Prefixes the finished lines with a hexdump of the row of the input they start in
+/// The first line of a row is output next to its bytes, the following lines of the row
+/// are indented. Rows without the start of a line are not output
+#[derive(Default, Clone, Debug)]
+pub struct HexdumpWriter {
+    // the row of the last line
+    row: Option<usize>,
+}
+
+impl HexdumpWriter {
+    /// The line that starts at the file offset
+    pub fn line(&mut self, data: &[u8], position: usize, line: &str) -> String {
+        let row = position / HEXDUMP_ROW_LEN;
+        if self.row == Some(row) {
+            return format!("{:width$}| {line}", "", width = Self::width());
+        }
+        self.row = Some(row);
+        let start = row * HEXDUMP_ROW_LEN;
+        let bytes = data
+            .get(start..(start + HEXDUMP_ROW_LEN).min(data.len()))
+            .unwrap_or_default();
+        let mut result = format!("{start:08x} ");
+        for i in 0..HEXDUMP_ROW_LEN {
+            // the halves of the row are separated like the canonical hexdump
+            if i % 8 == 0 {
+                result.push(' ');
+            }
+            match bytes.get(i) {
+                Some(byte) => result.push_str(&format!("{byte:02x} ")),
+                None => result.push_str("   "),
+            }
+        }
+        format!("{result} | {line}")
+    }
+
+    // the offset, the bytes and the separators
+    fn width() -> usize {
+        9 + HEXDUMP_ROW_LEN * 3 + HEXDUMP_ROW_LEN / 8 + 1
+    }
+}
+
+/// An output line and the address and the file offset it starts at
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceMapEntry {
+    // the lines are counted from 1 like editors do
+    pub line: usize,
+    pub address: Address,
+    pub offset: usize,
+}
+
+/// Maps the lines of the text output to the addresses and the file offsets of the input
+/// Lines without a location, e.g. the equates of the clean output, are not mapped
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct SourceMap {
+    pub entries: Vec<SourceMapEntry>,
+    // the lines that were output so far
+    lines: usize,
+    // the last line is not finished and already mapped
+    open: bool,
+}
+
+impl SourceMap {
+    /// Counts the lines of the output text, every line that starts in it
+    /// is mapped to the location if there is one
+    pub fn text(&mut self, text: &str, location: Option<(Address, usize)>) {
+        for (i, part) in text.split('\n').enumerate() {
+            if i > 0 {
+                self.lines += 1;
+                self.open = false;
+            }
+            if self.open || part.is_empty() {
+                continue;
+            }
+            self.open = true;
+            if let Some((address, offset)) = location {
+                self.entries.push(SourceMapEntry {
+                    line: self.lines + 1,
+                    address,
+                    offset,
+                });
+            }
+        }
+    }
+}
+
+impl Display for SourceMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{} {:x} {:#x}", entry.line, entry.address, entry.offset)?;
+        }
+        Ok(())
+    }
+}
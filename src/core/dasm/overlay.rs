@@ -0,0 +1,31 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{Address, ValueType};
+
+/// An address range that is shared by several banks.
+/// The flag holds the name of the bank that is currently mapped to the window
+/// and values inside of the window are looked up in that bank's namespace first.
+/// Entering a region inside of the window maps the region's bank, a bank switch
+/// can also be applied by a SetFlag transform or static op
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct Overlay {
+    pub start: Address,
+    pub end: Address,
+    pub flag: String,
+}
+
+impl Overlay {
+    pub fn new(start: Address, end: Address, flag: &str) -> Self {
+        Self {
+            start,
+            end,
+            flag: flag.into(),
+        }
+    }
+
+    pub fn contains(&self, value: ValueType) -> bool {
+        value >= self.start as ValueType && value < self.end as ValueType
+    }
+}
@@ -0,0 +1,573 @@
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{
+    arch::Context,
+    symbols::{Scope, Symbol, SymbolKind},
+    Address, ValueType,
+};
+
+const NES: &[(&str, Address)] = &[
+    ("PPUCTRL", 0x2000),
+    ("PPUMASK", 0x2001),
+    ("PPUSTATUS", 0x2002),
+    ("OAMADDR", 0x2003),
+    ("OAMDATA", 0x2004),
+    ("PPUSCROLL", 0x2005),
+    ("PPUADDR", 0x2006),
+    ("PPUDATA", 0x2007),
+    ("SQ1_VOL", 0x4000),
+    ("SQ1_SWEEP", 0x4001),
+    ("SQ1_LO", 0x4002),
+    ("SQ1_HI", 0x4003),
+    ("SQ2_VOL", 0x4004),
+    ("SQ2_SWEEP", 0x4005),
+    ("SQ2_LO", 0x4006),
+    ("SQ2_HI", 0x4007),
+    ("TRI_LINEAR", 0x4008),
+    ("TRI_LO", 0x400A),
+    ("TRI_HI", 0x400B),
+    ("NOISE_VOL", 0x400C),
+    ("NOISE_LO", 0x400E),
+    ("NOISE_HI", 0x400F),
+    ("DMC_FREQ", 0x4010),
+    ("DMC_RAW", 0x4011),
+    ("DMC_START", 0x4012),
+    ("DMC_LEN", 0x4013),
+    ("OAMDMA", 0x4014),
+    ("SND_CHN", 0x4015),
+    ("JOY1", 0x4016),
+    ("JOY2", 0x4017),
+];
+
+const SNES: &[(&str, Address)] = &[
+    ("INIDISP", 0x2100),
+    ("OBSEL", 0x2101),
+    ("OAMADDL", 0x2102),
+    ("OAMADDH", 0x2103),
+    ("OAMDATA", 0x2104),
+    ("BGMODE", 0x2105),
+    ("MOSAIC", 0x2106),
+    ("BG1SC", 0x2107),
+    ("BG2SC", 0x2108),
+    ("BG3SC", 0x2109),
+    ("BG4SC", 0x210A),
+    ("BG12NBA", 0x210B),
+    ("BG34NBA", 0x210C),
+    ("BG1HOFS", 0x210D),
+    ("BG1VOFS", 0x210E),
+    ("BG2HOFS", 0x210F),
+    ("BG2VOFS", 0x2110),
+    ("BG3HOFS", 0x2111),
+    ("BG3VOFS", 0x2112),
+    ("BG4HOFS", 0x2113),
+    ("BG4VOFS", 0x2114),
+    ("VMAIN", 0x2115),
+    ("VMADDL", 0x2116),
+    ("VMADDH", 0x2117),
+    ("VMDATAL", 0x2118),
+    ("VMDATAH", 0x2119),
+    ("M7SEL", 0x211A),
+    ("M7A", 0x211B),
+    ("M7B", 0x211C),
+    ("M7C", 0x211D),
+    ("M7D", 0x211E),
+    ("M7X", 0x211F),
+    ("M7Y", 0x2120),
+    ("CGADD", 0x2121),
+    ("CGDATA", 0x2122),
+    ("W12SEL", 0x2123),
+    ("W34SEL", 0x2124),
+    ("WOBJSEL", 0x2125),
+    ("WH0", 0x2126),
+    ("WH1", 0x2127),
+    ("WH2", 0x2128),
+    ("WH3", 0x2129),
+    ("WBGLOG", 0x212A),
+    ("WOBJLOG", 0x212B),
+    ("TM", 0x212C),
+    ("TS", 0x212D),
+    ("TMW", 0x212E),
+    ("TSW", 0x212F),
+    ("CGWSEL", 0x2130),
+    ("CGADSUB", 0x2131),
+    ("COLDATA", 0x2132),
+    ("SETINI", 0x2133),
+    ("MPYL", 0x2134),
+    ("MPYM", 0x2135),
+    ("MPYH", 0x2136),
+    ("SLHV", 0x2137),
+    ("RDOAM", 0x2138),
+    ("RDVRAML", 0x2139),
+    ("RDVRAMH", 0x213A),
+    ("RDCGRAM", 0x213B),
+    ("OPHCT", 0x213C),
+    ("OPVCT", 0x213D),
+    ("STAT77", 0x213E),
+    ("STAT78", 0x213F),
+    ("APUIO0", 0x2140),
+    ("APUIO1", 0x2141),
+    ("APUIO2", 0x2142),
+    ("APUIO3", 0x2143),
+    ("WMDATA", 0x2180),
+    ("WMADDL", 0x2181),
+    ("WMADDM", 0x2182),
+    ("WMADDH", 0x2183),
+    ("JOYSER0", 0x4016),
+    ("JOYSER1", 0x4017),
+    ("NMITIMEN", 0x4200),
+    ("WRIO", 0x4201),
+    ("WRMPYA", 0x4202),
+    ("WRMPYB", 0x4203),
+    ("WRDIVL", 0x4204),
+    ("WRDIVH", 0x4205),
+    ("WRDIVB", 0x4206),
+    ("HTIMEL", 0x4207),
+    ("HTIMEH", 0x4208),
+    ("VTIMEL", 0x4209),
+    ("VTIMEH", 0x420A),
+    ("MDMAEN", 0x420B),
+    ("HDMAEN", 0x420C),
+    ("MEMSEL", 0x420D),
+    ("RDNMI", 0x4210),
+    ("TIMEUP", 0x4211),
+    ("HVBJOY", 0x4212),
+    ("RDIO", 0x4213),
+    ("RDDIVL", 0x4214),
+    ("RDDIVH", 0x4215),
+    ("RDMPYL", 0x4216),
+    ("RDMPYH", 0x4217),
+    ("JOY1L", 0x4218),
+    ("JOY1H", 0x4219),
+    ("JOY2L", 0x421A),
+    ("JOY2H", 0x421B),
+    ("JOY3L", 0x421C),
+    ("JOY3H", 0x421D),
+    ("JOY4L", 0x421E),
+    ("JOY4H", 0x421F),
+];
+
+// the registers of each of the 8 dma channels at 43x0, the channel is appended to the name
+const SNES_DMA: &[(&str, Address)] = &[
+    ("DMAP", 0x0),
+    ("BBAD", 0x1),
+    ("A1TL", 0x2),
+    ("A1TH", 0x3),
+    ("A1B", 0x4),
+    ("DASL", 0x5),
+    ("DASH", 0x6),
+    ("DASB", 0x7),
+    ("A2AL", 0x8),
+    ("A2AH", 0x9),
+    ("NTRL", 0xA),
+];
+const SNES_DMA_CHANNELS: Address = 8;
+
+// the names of Mapping the Commodore 64
+const C64: &[(&str, Address)] = &[
+    ("SP0X", 0xD000),
+    ("SP0Y", 0xD001),
+    ("SP1X", 0xD002),
+    ("SP1Y", 0xD003),
+    ("SP2X", 0xD004),
+    ("SP2Y", 0xD005),
+    ("SP3X", 0xD006),
+    ("SP3Y", 0xD007),
+    ("SP4X", 0xD008),
+    ("SP4Y", 0xD009),
+    ("SP5X", 0xD00A),
+    ("SP5Y", 0xD00B),
+    ("SP6X", 0xD00C),
+    ("SP6Y", 0xD00D),
+    ("SP7X", 0xD00E),
+    ("SP7Y", 0xD00F),
+    ("MSIGX", 0xD010),
+    ("SCROLY", 0xD011),
+    ("RASTER", 0xD012),
+    ("LPENX", 0xD013),
+    ("LPENY", 0xD014),
+    ("SPENA", 0xD015),
+    ("SCROLX", 0xD016),
+    ("YXPAND", 0xD017),
+    ("VMCSB", 0xD018),
+    ("VICIRQ", 0xD019),
+    ("IRQMSK", 0xD01A),
+    ("SPBGPR", 0xD01B),
+    ("SPMC", 0xD01C),
+    ("XXPAND", 0xD01D),
+    ("SPSPCL", 0xD01E),
+    ("SPBGCL", 0xD01F),
+    ("EXTCOL", 0xD020),
+    ("BGCOL0", 0xD021),
+    ("BGCOL1", 0xD022),
+    ("BGCOL2", 0xD023),
+    ("BGCOL3", 0xD024),
+    ("SPMC0", 0xD025),
+    ("SPMC1", 0xD026),
+    ("SP0COL", 0xD027),
+    ("SP1COL", 0xD028),
+    ("SP2COL", 0xD029),
+    ("SP3COL", 0xD02A),
+    ("SP4COL", 0xD02B),
+    ("SP5COL", 0xD02C),
+    ("SP6COL", 0xD02D),
+    ("SP7COL", 0xD02E),
+    ("FRELO1", 0xD400),
+    ("FREHI1", 0xD401),
+    ("PWLO1", 0xD402),
+    ("PWHI1", 0xD403),
+    ("VCREG1", 0xD404),
+    ("ATDCY1", 0xD405),
+    ("SUREL1", 0xD406),
+    ("FRELO2", 0xD407),
+    ("FREHI2", 0xD408),
+    ("PWLO2", 0xD409),
+    ("PWHI2", 0xD40A),
+    ("VCREG2", 0xD40B),
+    ("ATDCY2", 0xD40C),
+    ("SUREL2", 0xD40D),
+    ("FRELO3", 0xD40E),
+    ("FREHI3", 0xD40F),
+    ("PWLO3", 0xD410),
+    ("PWHI3", 0xD411),
+    ("VCREG3", 0xD412),
+    ("ATDCY3", 0xD413),
+    ("SUREL3", 0xD414),
+    ("CUTLO", 0xD415),
+    ("CUTHI", 0xD416),
+    ("RESON", 0xD417),
+    ("SIGVOL", 0xD418),
+    ("POTX", 0xD419),
+    ("POTY", 0xD41A),
+    ("RANDOM", 0xD41B),
+    ("ENV3", 0xD41C),
+    ("CIAPRA", 0xDC00),
+    ("CIAPRB", 0xDC01),
+    ("CIDDRA", 0xDC02),
+    ("CIDDRB", 0xDC03),
+    ("TIMALO", 0xDC04),
+    ("TIMAHI", 0xDC05),
+    ("TIMBLO", 0xDC06),
+    ("TIMBHI", 0xDC07),
+    ("TODTEN", 0xDC08),
+    ("TODSEC", 0xDC09),
+    ("TODMIN", 0xDC0A),
+    ("TODHRS", 0xDC0B),
+    ("CIASDR", 0xDC0C),
+    ("CIAICR", 0xDC0D),
+    ("CIACRA", 0xDC0E),
+    ("CIACRB", 0xDC0F),
+    ("CI2PRA", 0xDD00),
+    ("CI2PRB", 0xDD01),
+    ("C2DDRA", 0xDD02),
+    ("C2DDRB", 0xDD03),
+    ("TI2ALO", 0xDD04),
+    ("TI2AHI", 0xDD05),
+    ("TI2BLO", 0xDD06),
+    ("TI2BHI", 0xDD07),
+    ("TO2TEN", 0xDD08),
+    ("TO2SEC", 0xDD09),
+    ("TO2MIN", 0xDD0A),
+    ("TO2HRS", 0xDD0B),
+    ("CI2SDR", 0xDD0C),
+    ("CI2ICR", 0xDD0D),
+    ("CI2CRA", 0xDD0E),
+    ("CI2CRB", 0xDD0F),
+];
+
+// the names of hardware.inc without the r prefix
+const GAME_BOY: &[(&str, Address)] = &[
+    ("P1", 0xFF00),
+    ("SB", 0xFF01),
+    ("SC", 0xFF02),
+    ("DIV", 0xFF04),
+    ("TIMA", 0xFF05),
+    ("TMA", 0xFF06),
+    ("TAC", 0xFF07),
+    ("IF", 0xFF0F),
+    ("NR10", 0xFF10),
+    ("NR11", 0xFF11),
+    ("NR12", 0xFF12),
+    ("NR13", 0xFF13),
+    ("NR14", 0xFF14),
+    ("NR21", 0xFF16),
+    ("NR22", 0xFF17),
+    ("NR23", 0xFF18),
+    ("NR24", 0xFF19),
+    ("NR30", 0xFF1A),
+    ("NR31", 0xFF1B),
+    ("NR32", 0xFF1C),
+    ("NR33", 0xFF1D),
+    ("NR34", 0xFF1E),
+    ("NR41", 0xFF20),
+    ("NR42", 0xFF21),
+    ("NR43", 0xFF22),
+    ("NR44", 0xFF23),
+    ("NR50", 0xFF24),
+    ("NR51", 0xFF25),
+    ("NR52", 0xFF26),
+    ("LCDC", 0xFF40),
+    ("STAT", 0xFF41),
+    ("SCY", 0xFF42),
+    ("SCX", 0xFF43),
+    ("LY", 0xFF44),
+    ("LYC", 0xFF45),
+    ("DMA", 0xFF46),
+    ("BGP", 0xFF47),
+    ("OBP0", 0xFF48),
+    ("OBP1", 0xFF49),
+    ("WY", 0xFF4A),
+    ("WX", 0xFF4B),
+    ("KEY1", 0xFF4D),
+    ("VBK", 0xFF4F),
+    ("HDMA1", 0xFF51),
+    ("HDMA2", 0xFF52),
+    ("HDMA3", 0xFF53),
+    ("HDMA4", 0xFF54),
+    ("HDMA5", 0xFF55),
+    ("RP", 0xFF56),
+    ("BCPS", 0xFF68),
+    ("BCPD", 0xFF69),
+    ("OCPS", 0xFF6A),
+    ("OCPD", 0xFF6B),
+    ("SVBK", 0xFF70),
+];
+// the wave pattern ram and the interrupt enable register
+const GAME_BOY_WAVE_RAM: (&str, Address, usize) = ("WAVE_RAM", 0xFF30, 0x10);
+const GAME_BOY_IE: (&str, Address) = ("IE", 0xFFFF);
+
+// the rom ranges of the c64, the entry points only name operands inside of them
+pub const C64_BASIC_ROM: std::ops::Range<Address> = 0xA000..0xC000;
+pub const C64_KERNAL_ROM: std::ops::Range<Address> = 0xE000..0x10000;
+
+// the KERNAL jump table and the handlers that interrupt routines exit through
+const C64_KERNAL: &[(&str, Address)] = &[
+    ("IRQ_HANDLER", 0xEA31),
+    ("IRQ_EXIT", 0xEA81),
+    ("CINT", 0xFF81),
+    ("IOINIT", 0xFF84),
+    ("RAMTAS", 0xFF87),
+    ("RESTOR", 0xFF8A),
+    ("VECTOR", 0xFF8D),
+    ("SETMSG", 0xFF90),
+    ("SECOND", 0xFF93),
+    ("TKSA", 0xFF96),
+    ("MEMTOP", 0xFF99),
+    ("MEMBOT", 0xFF9C),
+    ("SCNKEY", 0xFF9F),
+    ("SETTMO", 0xFFA2),
+    ("ACPTR", 0xFFA5),
+    ("CIOUT", 0xFFA8),
+    ("UNTLK", 0xFFAB),
+    ("UNLSN", 0xFFAE),
+    ("LISTEN", 0xFFB1),
+    ("TALK", 0xFFB4),
+    ("READST", 0xFFB7),
+    ("SETLFS", 0xFFBA),
+    ("SETNAM", 0xFFBD),
+    ("OPEN", 0xFFC0),
+    ("CLOSE", 0xFFC3),
+    ("CHKIN", 0xFFC6),
+    ("CHKOUT", 0xFFC9),
+    ("CLRCHN", 0xFFCC),
+    ("CHRIN", 0xFFCF),
+    ("CHROUT", 0xFFD2),
+    ("LOAD", 0xFFD5),
+    ("SAVE", 0xFFD8),
+    ("SETTIM", 0xFFDB),
+    ("RDTIM", 0xFFDE),
+    ("STOP", 0xFFE1),
+    ("GETIN", 0xFFE4),
+    ("CLALL", 0xFFE7),
+    ("UDTIM", 0xFFEA),
+    ("SCREEN", 0xFFED),
+    ("PLOT", 0xFFF0),
+    ("IOBASE", 0xFFF3),
+];
+
+// the BASIC routines that machine language programs call
+const C64_BASIC: &[(&str, Address)] = &[
+    ("READY", 0xA474),
+    ("LINKPRG", 0xA533),
+    ("INLIN", 0xA560),
+    ("CRUNCH", 0xA57C),
+    ("FNDLIN", 0xA613),
+    ("STXPT", 0xA68E),
+    ("NEWSTT", 0xA7AE),
+    ("STROUT", 0xAB1E),
+    ("FRMNUM", 0xAD8A),
+    ("FRMEVL", 0xAD9E),
+    ("CHKCOM", 0xAEFD),
+    ("AYINT", 0xB1AA),
+    ("GIVAYF", 0xB391),
+    ("GETBYT", 0xB79E),
+    ("GETADR", 0xB7F7),
+    ("MOVFM", 0xBBA2),
+    ("MOVMF", 0xBBD4),
+    ("LINPRT", 0xBDCD),
+    ("FOUT", 0xBDDD),
+];
+
+/// The hardware registers of a system as labels
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SymbolPack {
+    // the PPU and APU registers and the joypads
+    Nes,
+    // the PPU, CPU and DMA registers
+    Snes,
+    // the VIC-II, SID and CIA registers
+    C64,
+    // the I/O registers
+    GameBoy,
+    // the entry points of the c64 KERNAL and BASIC roms
+    C64Kernal,
+}
+
+impl SymbolPack {
+    pub fn symbols(&self) -> Vec<Symbol> {
+        let register = |(name, value): (&str, Address)| (name.to_owned(), value, 1);
+        let registers: Vec<(String, Address, usize)> = match self {
+            Self::Nes => NES.iter().copied().map(register).collect(),
+            Self::Snes => SNES
+                .iter()
+                .copied()
+                .map(register)
+                .chain((0..SNES_DMA_CHANNELS).flat_map(|channel| {
+                    SNES_DMA.iter().map(move |(name, offset)| {
+                        (
+                            format!("{name}{channel}"),
+                            0x4300 + channel * 0x10 + offset,
+                            1,
+                        )
+                    })
+                }))
+                .collect(),
+            Self::C64 => C64.iter().copied().map(register).collect(),
+            Self::GameBoy => GAME_BOY
+                .iter()
+                .copied()
+                .map(register)
+                .chain([
+                    (
+                        GAME_BOY_WAVE_RAM.0.to_owned(),
+                        GAME_BOY_WAVE_RAM.1,
+                        GAME_BOY_WAVE_RAM.2,
+                    ),
+                    register(GAME_BOY_IE),
+                ])
+                .collect(),
+            Self::C64Kernal => C64_KERNAL
+                .iter()
+                .chain(C64_BASIC)
+                .copied()
+                .map(register)
+                .collect(),
+        };
+        registers
+            .into_iter()
+            .map(|(name, value, len)| {
+                Symbol::new(
+                    name,
+                    SymbolKind::Label,
+                    Scope::Global,
+                    value as ValueType,
+                    len,
+                )
+            })
+            .collect()
+    }
+
+    /// Defines the symbols of the pack, symbols that are already defined are kept
+    /// Returns the amount of symbols
+    pub fn apply(&self, ctx: &mut Context) -> usize {
+        let syms = self.symbols();
+        let count = syms.len();
+        for sym in syms {
+            ctx.def_symbol(sym);
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{
+        arch::{a6502, Context},
+        symbols::Symbol,
+        Address,
+    };
+
+    use super::{SymbolPack, C64_BASIC_ROM, C64_KERNAL_ROM};
+
+    #[test]
+    fn registers() {
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        assert_eq!(30, SymbolPack::Nes.apply(&mut ctx));
+        // applying a pack twice does not define its symbols again
+        SymbolPack::Nes.apply(&mut ctx);
+        assert_eq!(30, ctx.syms.iter().count());
+
+        let mut result = String::new();
+        a6502::ARCH
+            .disas_ctx(
+                |n, _kind, _raw, _arch, _ctx| {
+                    result.push_str(&n.string);
+                    Ok(())
+                },
+                &[0x8D, 0x16, 0x40],
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!("00008000 sta JOY1\n", result);
+
+        let snes = SymbolPack::Snes.symbols();
+        let find = |syms: &[Symbol], name: &str| {
+            syms.iter()
+                .find(|s| s.name == name)
+                .map(|s| (s.value, s.len))
+        };
+        assert_eq!(Some((0x4375, 1)), find(&snes, "DASL7"));
+        assert_eq!(
+            Some((0xD418, 1)),
+            find(&SymbolPack::C64.symbols(), "SIGVOL")
+        );
+        assert_eq!(
+            Some((0xFF30, 0x10)),
+            find(&SymbolPack::GameBoy.symbols(), "WAVE_RAM")
+        );
+    }
+
+    #[test]
+    fn kernal() {
+        let syms = SymbolPack::C64Kernal.symbols();
+        assert!(syms.iter().all(|s| {
+            let value = s.value as Address;
+            C64_BASIC_ROM.contains(&value) || C64_KERNAL_ROM.contains(&value)
+        }));
+
+        let mut ctx = Context {
+            org: 0x0801,
+            ..Default::default()
+        };
+        SymbolPack::C64Kernal.apply(&mut ctx);
+        let mut result = String::new();
+        a6502::ARCH
+            .disas_ctx(
+                |n, _kind, _raw, _arch, _ctx| {
+                    result.push_str(&n.string);
+                    Ok(())
+                },
+                &[0x4C, 0xD2, 0xFF, 0x4C, 0x1E, 0xAB],
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!("00000801 jmp CHROUT\n00000804 jmp STROUT\n", result);
+    }
+}
@@ -3,45 +3,145 @@ use std::{fs::File, io::Read, path::PathBuf};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-use crate::prelude::FdResult;
+use crate::prelude::{Error, FdResult};
+
+use super::arch::Context;
+
+/// What happens when a patch writes past the end of the data
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExtendPolicy {
+    // the patch is an error, e.g. for roms with a fixed size
+    Error,
+    // the data grows to the end of the patch, bytes before its offset are filled
+    Grow(u8),
+    // the data grows to the next power of two after the end of the patch
+    PowerOfTwo(u8),
+}
+
+impl Default for ExtendPolicy {
+    fn default() -> Self {
+        Self::Grow(0)
+    }
+}
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone)]
 pub enum Patch {
-    Data { offset: usize, data: Vec<u8> },
-    Rep { offset: usize, byte: u8, len: usize },
-    File { offset: usize, path: PathBuf },
+    Data {
+        offset: usize,
+        data: Vec<u8>,
+        // overrides the policy of the context
+        #[cfg_attr(feature = "serde", serde(default))]
+        extend: Option<ExtendPolicy>,
+    },
+    Rep {
+        offset: usize,
+        byte: u8,
+        len: usize,
+        #[cfg_attr(feature = "serde", serde(default))]
+        extend: Option<ExtendPolicy>,
+    },
+    File {
+        offset: usize,
+        path: PathBuf,
+        #[cfg_attr(feature = "serde", serde(default))]
+        extend: Option<ExtendPolicy>,
+    },
 }
 
 impl Patch {
-    pub fn apply(&self, data: &mut Vec<u8>) -> FdResult<()> {
-        let (offset, patch) = match self {
-            Self::Data { offset, data } => (*offset, data.to_vec()),
-            Self::Rep { offset, byte, len } => (*offset, vec![*byte; *len]),
-            Self::File { offset, path } => {
+    /// The offset and the bytes that are written
+    pub fn bytes(&self) -> FdResult<(usize, Vec<u8>)> {
+        Ok(match self {
+            Self::Data { offset, data, .. } => (*offset, data.to_vec()),
+            Self::Rep {
+                offset, byte, len, ..
+            } => (*offset, vec![*byte; *len]),
+            Self::File { offset, path, .. } => {
                 let mut f = File::open(path)?;
                 let mut buffer = Vec::new();
                 f.read_to_end(&mut buffer)?;
                 (*offset, buffer)
             }
-        };
-        for (i, b) in patch.iter().enumerate() {
-            let index = i + offset;
-            if data.len() <= index {
-                data.push(*b)
-            } else {
-                data[index] = *b;
+        })
+    }
+
+    pub fn extend(&self) -> Option<ExtendPolicy> {
+        match self {
+            Self::Data { extend, .. } | Self::Rep { extend, .. } | Self::File { extend, .. } => {
+                *extend
+            }
+        }
+    }
+
+    /// Applies the patch, the patch's policy is used before the default policy
+    pub fn apply(&self, data: &mut Vec<u8>, policy: ExtendPolicy) -> FdResult<()> {
+        let (offset, patch) = self.bytes()?;
+        write(data, offset, &patch, self.extend().unwrap_or(policy))
+    }
+}
+
+// the end of a patch that does not overflow the address space
+fn patch_end(offset: usize, len: usize) -> FdResult<usize> {
+    offset
+        .checked_add(len)
+        .ok_or(Error::PatchOffsetOutOfRange(offset))
+}
+
+fn write(data: &mut Vec<u8>, offset: usize, patch: &[u8], policy: ExtendPolicy) -> FdResult<()> {
+    let end = patch_end(offset, patch.len())?;
+    if end > data.len() {
+        match policy {
+            ExtendPolicy::Error => return Err(Error::PatchOffsetOutOfRange(offset)),
+            ExtendPolicy::Grow(fill) => data.resize(end, fill),
+            ExtendPolicy::PowerOfTwo(fill) => data.resize(
+                end.checked_next_power_of_two()
+                    .ok_or(Error::PatchOffsetOutOfRange(offset))?,
+                fill,
+            ),
+        }
+    }
+    data[offset..end].copy_from_slice(patch);
+    Ok(())
+}
+
+/// Applies the patches of the context in order
+/// Patches that write to the same bytes are an error unless overlaps are allowed,
+/// in which case the later patch wins and the overlap is reported as a warning
+pub fn apply_all(data: &mut Vec<u8>, ctx: &Context) -> FdResult<()> {
+    let patches = ctx
+        .patches
+        .iter()
+        .map(|patch| Ok((patch.bytes()?, patch.extend())))
+        .collect::<FdResult<Vec<_>>>()?;
+    for (i, ((offset, patch), _)) in patches.iter().enumerate() {
+        for (j, ((other, other_patch), _)) in patches.iter().enumerate().skip(i + 1) {
+            let start = (*offset).max(*other);
+            let end = patch_end(*offset, patch.len())?.min(patch_end(*other, other_patch.len())?);
+            if start >= end {
+                continue;
             }
+            let message = format!(
+                "Patches {i} and {j} overlap at {start:#x}..{end:#x}, patch {j} overwrites patch {i}"
+            );
+            if !ctx.allow_overlap {
+                return Err(Error::PatchOverlap(message));
+            }
+            ctx.warn(&message);
         }
-        Ok(())
     }
+    for ((offset, patch), extend) in patches {
+        write(data, offset, &patch, extend.unwrap_or(ctx.extend_policy))?;
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
-    use crate::core::dasm::arch::Context;
+    use crate::{core::dasm::arch::Context, prelude::Error};
 
-    use super::Patch;
+    use super::{ExtendPolicy, Patch};
 
     #[test]
     fn data() {
@@ -49,6 +149,7 @@ mod test {
         ctx.patches.push(Patch::Data {
             offset: 1,
             data: vec![0, 1, 2, 3],
+            extend: None,
         });
 
         let test_data = vec![0, 1, 2, 3];
@@ -64,6 +165,7 @@ mod test {
             offset: 1,
             byte: 5,
             len: 4,
+            extend: None,
         });
 
         let test_data = vec![0, 1, 2, 3];
@@ -71,4 +173,86 @@ mod test {
 
         assert_eq!(vec![0, 5, 5, 5, 5], res);
     }
+
+    #[test]
+    fn overlap() {
+        let mut ctx = Context::default();
+        ctx.patches.push(Patch::Data {
+            offset: 0,
+            data: vec![1, 1, 1],
+            extend: None,
+        });
+        ctx.patches.push(Patch::Rep {
+            offset: 2,
+            byte: 2,
+            len: 2,
+            extend: None,
+        });
+        assert!(ctx.patch(&[0; 4]).is_err());
+
+        ctx.allow_overlap = true;
+        assert_eq!(vec![1, 1, 2, 2], ctx.patch(&[0; 4]).unwrap());
+    }
+
+    #[test]
+    fn extend() {
+        let mut ctx = Context {
+            extend_policy: ExtendPolicy::Error,
+            ..Default::default()
+        };
+        ctx.patches.push(Patch::Data {
+            offset: 6,
+            data: vec![1],
+            extend: None,
+        });
+        assert!(ctx.patch(&[0; 4]).is_err());
+
+        ctx.extend_policy = ExtendPolicy::PowerOfTwo(0xFF);
+        assert_eq!(
+            vec![0, 0, 0, 0, 0xFF, 0xFF, 1, 0xFF],
+            ctx.patch(&[0; 4]).unwrap()
+        );
+
+        // the patch's policy overrides the context
+        ctx.patches[0] = Patch::Rep {
+            offset: 6,
+            byte: 1,
+            len: 1,
+            extend: Some(ExtendPolicy::Grow(0xEA)),
+        };
+        assert_eq!(vec![0, 0, 0, 0, 0xEA, 0xEA, 1], ctx.patch(&[0; 4]).unwrap());
+    }
+
+    #[test]
+    fn out_of_range() {
+        let mut ctx = Context::default();
+        ctx.patches.push(Patch::Data {
+            offset: usize::MAX,
+            data: vec![1],
+            extend: None,
+        });
+        assert!(matches!(
+            ctx.patch(&[0; 4]),
+            Err(Error::PatchOffsetOutOfRange(usize::MAX))
+        ));
+
+        // the overlap check does not overflow either
+        ctx.patches.push(ctx.patches[0].clone());
+        ctx.allow_overlap = true;
+        assert!(matches!(
+            ctx.patch(&[0; 4]),
+            Err(Error::PatchOffsetOutOfRange(usize::MAX))
+        ));
+
+        // there is no power of two after the end
+        ctx.patches = vec![Patch::Data {
+            offset: usize::MAX / 2 + 1,
+            data: vec![1],
+            extend: Some(ExtendPolicy::PowerOfTwo(0)),
+        }];
+        assert!(matches!(
+            ctx.patch(&[0; 4]),
+            Err(Error::PatchOffsetOutOfRange(_))
+        ));
+    }
 }
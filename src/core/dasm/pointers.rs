@@ -0,0 +1,201 @@
+use std::fmt::Display;
+
+use crate::prelude::FdResult;
+
+use super::{
+    arch::{Archs, Context},
+    classify::ByteClass,
+    descent,
+    symbols::{ReferenceKind, Scope, Symbol, SymbolKind},
+    Address, ValueType,
+};
+
+/// The least amount of pointers of a table if no minimum is given
+pub const DEFAULT_MIN_LEN: usize = 4;
+
+/// A run of pointers into the input in the bytes that are not classified yet
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PointerTable {
+    pub address: Address,
+    // the file range of the pointers
+    pub start: usize,
+    pub end: usize,
+    // the size of a pointer, 2 or 3 bytes
+    pub width: usize,
+    pub targets: Vec<Address>,
+}
+
+impl PointerTable {
+    pub fn class(&self) -> ByteClass {
+        if self.width == 3 {
+            ByteClass::LongPointers
+        } else {
+            ByteClass::Pointers
+        }
+    }
+}
+
+impl Display for PointerTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let targets: Vec<String> = self.targets.iter().map(|t| format!("{t:x}")).collect();
+        write!(
+            f,
+            "{:x} ({}-bit): {}",
+            self.address,
+            self.width * 8,
+            targets.join(", ")
+        )
+    }
+}
+
+/// Finds the runs of at least min_len values of width bytes that are not classified yet
+/// and that all point into the input. Regions and gaps are skipped
+pub fn find(
+    arch: &Archs,
+    data: &[u8],
+    ctx: &Context,
+    min_len: usize,
+    width: usize,
+) -> FdResult<Vec<PointerTable>> {
+    let endianess = arch.arch(ctx)?.endianess();
+    let ranges: Vec<(usize, usize, Address)> = if ctx.segments.is_empty() {
+        let window = ctx.window(data);
+        let org = ctx.address_of(window.start as Address);
+        vec![(window.start, window.end, org)]
+    } else {
+        ctx.segment_ranges(data)
+            .iter()
+            .map(|s| (s.start, s.end, s.org))
+            .collect()
+    };
+    let is_unclassified = |offset: usize| {
+        ctx.classes.get(offset) == ByteClass::Unknown
+            && !ctx.regions.iter().any(|r| r.contains(offset))
+            && !ctx.gaps.iter().any(|g| (g.start..g.end).contains(&offset))
+    };
+    // the file offset of the value's target if it is part of the input
+    let target_offset = |address: Address| {
+        ctx.offset_of(address, "").filter(|offset| {
+            ranges
+                .iter()
+                .any(|(start, end, _)| (start..end).contains(&offset))
+        })
+    };
+
+    let mut tables = vec![];
+    for (start, end, org) in ranges.iter().copied() {
+        let mut offset = start;
+        while offset + width <= end {
+            let address = org.wrapping_add((offset - start) as Address);
+            let endianess = ctx.endianess_at(address).unwrap_or(endianess);
+            let mut targets = vec![];
+            let mut at = offset;
+            while at + width <= end && (at..at + width).all(is_unclassified) {
+                let bytes = endianess.pad(&data[at..at + width], std::mem::size_of::<ValueType>());
+                let Some(target) = endianess.transform(&bytes).map(|v| v as Address) else {
+                    break;
+                };
+                if target_offset(target).is_none() {
+                    break;
+                }
+                targets.push(target);
+                at += width;
+            }
+            if targets.len() >= min_len.max(1) {
+                tables.push(PointerTable {
+                    address,
+                    start: offset,
+                    end: at,
+                    width,
+                    targets,
+                });
+                offset = at;
+            } else {
+                offset += 1;
+            }
+        }
+    }
+    Ok(tables)
+}
+
+/// Classifies the tables as pointers, adds their targets to the entry points and defines
+/// a label for every target that is not labeled yet.
+/// The entry points start with the vectors or the start of the output if there are none,
+/// so the descent still follows them.
+/// Returns the amount of labels that were defined
+pub fn annotate(ctx: &mut Context, data: &[u8], tables: &[PointerTable]) -> usize {
+    if ctx.entry_points.is_empty() && !tables.is_empty() {
+        ctx.entry_points = descent::entries(ctx, data);
+    }
+    let mut count = 0;
+    for table in tables {
+        ctx.classes
+            .set_unknown(table.start, table.end, table.class());
+        for target in &table.targets {
+            if !ctx.entry_points.contains(target) {
+                ctx.entry_points.push(*target);
+            }
+            let labeled = ctx
+                .syms
+                .get_symbols(*target as ValueType)
+                .iter()
+                .any(|s| s.kind == SymbolKind::Label);
+            if !labeled {
+                ctx.def_auto_symbol(Symbol::new(
+                    ctx.auto_label_name(ReferenceKind::Branch, *target as ValueType),
+                    SymbolKind::Label,
+                    Scope::Global,
+                    *target as ValueType,
+                    1,
+                ));
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::arch::{a6502, Context};
+
+    use super::{annotate, find};
+
+    #[test]
+    fn pointers() {
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        // rts, a table of pointers to 8007, 8008 and 8009, then nop, nop, rts
+        let data = [0x60, 0x07, 0x80, 0x08, 0x80, 0x09, 0x80, 0xEA, 0xEA, 0x60];
+        let tables = find(&a6502::ARCH, &data, &ctx, 3, 2).unwrap();
+        assert_eq!(
+            vec!["8001 (16-bit): 8007, 8008, 8009"],
+            tables.iter().map(|t| t.to_string()).collect::<Vec<_>>()
+        );
+        assert_eq!(3, annotate(&mut ctx, &data, &tables));
+        assert_eq!(vec![0x8000, 0x8007, 0x8008, 0x8009], ctx.entry_points);
+
+        let mut result = String::new();
+        a6502::ARCH
+            .disas_ctx(
+                |n, _kind, _raw, _arch, _ctx| {
+                    result.push_str(&n.string);
+                    Ok(())
+                },
+                &data,
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(
+            "00008000 rts\n00008001 .word loc_8007\n00008003 .word loc_8008\n\
+             00008005 .word loc_8009\nloc_8007:\n00008007 nop\nloc_8008:\n00008008 nop\n\
+             loc_8009:\n00008009 rts\n",
+            result
+        );
+
+        // the tables are not found twice
+        assert!(find(&a6502::ARCH, &data, &ctx, 3, 2).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,462 @@
+use crate::prelude::{Error, FdResult};
+
+// a piece of a pattern that matches at a position
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Item {
+    Char(char),
+    Any,
+    // inclusive character ranges, the class is negated if the flag is set
+    Class(Vec<(char, char)>, bool),
+    Start,
+    End,
+    // the alternatives of a group and the index of its capture
+    Group(Vec<Vec<Item>>, usize),
+    Repeat(Box<Item>, usize, usize),
+}
+
+// an instruction of the compiled pattern
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Vec<(char, char)>, bool),
+    Start,
+    End,
+    // continues at both targets, the first one has the higher priority
+    Split(usize, usize),
+    Jump(usize),
+    // stores the position in a capture slot, capture n uses the slots 2n and 2n+1
+    Save(usize),
+    Match,
+}
+
+// the captures as char ranges, the whole match is capture 0
+type Captures = Vec<Option<(usize, usize)>>;
+
+// the threads of a step in the order of their priority
+// an instruction only gets one thread per step
+struct Threads {
+    list: Vec<(usize, Vec<Option<usize>>)>,
+    seen: Vec<bool>,
+}
+
+impl Threads {
+    fn new(len: usize) -> Self {
+        Self {
+            list: vec![],
+            seen: vec![false; len],
+        }
+    }
+}
+
+struct Parser<'a> {
+    pattern: &'a str,
+    chars: Vec<char>,
+    pos: usize,
+    groups: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self) -> Error {
+        Error::InvalidExpression(self.pattern.to_owned())
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> FdResult<char> {
+        let c = self.peek().ok_or_else(|| self.error())?;
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn alternatives(&mut self) -> FdResult<Vec<Vec<Item>>> {
+        let mut alts = vec![self.sequence()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            alts.push(self.sequence()?);
+        }
+        Ok(alts)
+    }
+
+    fn sequence(&mut self) -> FdResult<Vec<Item>> {
+        let mut items = vec![];
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let item = self.atom()?;
+            items.push(self.repeat(item)?);
+        }
+        Ok(items)
+    }
+
+    fn repeat(&mut self, item: Item) -> FdResult<Item> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, usize::MAX),
+            Some('+') => (1, usize::MAX),
+            Some('?') => (0, 1),
+            _ => return Ok(item),
+        };
+        if matches!(item, Item::Start | Item::End) {
+            return Err(self.error());
+        }
+        self.pos += 1;
+        Ok(Item::Repeat(Box::new(item), min, max))
+    }
+
+    fn atom(&mut self) -> FdResult<Item> {
+        Ok(match self.next()? {
+            '.' => Item::Any,
+            '^' => Item::Start,
+            '$' => Item::End,
+            '(' => {
+                self.groups += 1;
+                let index = self.groups;
+                let alts = self.alternatives()?;
+                if self.next()? != ')' {
+                    return Err(self.error());
+                }
+                Item::Group(alts, index)
+            }
+            '[' => self.class()?,
+            '\\' => self.escape()?,
+            '*' | '+' | '?' => return Err(self.error()),
+            c => Item::Char(c),
+        })
+    }
+
+    fn escape(&mut self) -> FdResult<Item> {
+        Ok(match self.next()? {
+            'd' => Item::Class(vec![('0', '9')], false),
+            'D' => Item::Class(vec![('0', '9')], true),
+            'w' => Item::Class(WORD.to_vec(), false),
+            'W' => Item::Class(WORD.to_vec(), true),
+            's' => Item::Class(SPACE.to_vec(), false),
+            'S' => Item::Class(SPACE.to_vec(), true),
+            't' => Item::Char('\t'),
+            c => Item::Char(c),
+        })
+    }
+
+    fn class(&mut self) -> FdResult<Item> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+        let mut ranges = vec![];
+        loop {
+            let c = match self.next()? {
+                // ] is a literal if it is the first char
+                ']' if !ranges.is_empty() => break,
+                '\\' => match self.escape()? {
+                    Item::Class(class, false) => {
+                        ranges.extend(class);
+                        continue;
+                    }
+                    Item::Char(c) => c,
+                    _ => return Err(self.error()),
+                },
+                c => c,
+            };
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') {
+                self.pos += 1;
+                let end = self.next()?;
+                ranges.push((c, end));
+            } else {
+                ranges.push((c, c));
+            }
+        }
+        Ok(Item::Class(ranges, negated))
+    }
+}
+
+const WORD: [(char, char); 4] = [('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')];
+const SPACE: [(char, char); 2] = [(' ', ' '), ('\t', '\r')];
+
+/// A regular expression for the output substitutions
+/// Supports literals, ., ^, $, character classes ([a-z], [^,], \d, \w, \s),
+/// groups with alternatives ((a|b)) and the greedy repetitions *, + and ?
+/// Matching runs every possible path through the pattern in step over the chars of the text,
+/// so it takes time linear in the length of the text times the size of the pattern.
+/// The match is the one a backtracking matcher would find first
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Regex {
+    prog: Vec<Inst>,
+    groups: usize,
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> FdResult<Self> {
+        let mut parser = Parser {
+            pattern,
+            chars: pattern.chars().collect(),
+            pos: 0,
+            groups: 0,
+        };
+        let alts = parser.alternatives()?;
+        // only an unmatched ) ends the pattern early
+        if parser.pos != parser.chars.len() {
+            return Err(parser.error());
+        }
+        let mut prog = vec![Inst::Save(0)];
+        Self::compile_alts(&mut prog, &alts);
+        prog.push(Inst::Save(1));
+        prog.push(Inst::Match);
+        Ok(Self {
+            prog,
+            groups: parser.groups,
+        })
+    }
+
+    fn compile_alts(prog: &mut Vec<Inst>, alts: &[Vec<Item>]) {
+        // every alternative but the last one splits off the ones after it
+        let mut jumps = vec![];
+        for (i, alt) in alts.iter().enumerate() {
+            if i + 1 == alts.len() {
+                alt.iter().for_each(|item| Self::compile_item(prog, item));
+                break;
+            }
+            let split = prog.len();
+            prog.push(Inst::Split(split + 1, 0));
+            alt.iter().for_each(|item| Self::compile_item(prog, item));
+            jumps.push(prog.len());
+            prog.push(Inst::Jump(0));
+            prog[split] = Inst::Split(split + 1, prog.len());
+        }
+        let end = prog.len();
+        for jump in jumps {
+            prog[jump] = Inst::Jump(end);
+        }
+    }
+
+    fn compile_item(prog: &mut Vec<Inst>, item: &Item) {
+        match item {
+            Item::Char(c) => prog.push(Inst::Char(*c)),
+            Item::Any => prog.push(Inst::Any),
+            Item::Class(ranges, negated) => prog.push(Inst::Class(ranges.clone(), *negated)),
+            Item::Start => prog.push(Inst::Start),
+            Item::End => prog.push(Inst::End),
+            Item::Group(alts, index) => {
+                prog.push(Inst::Save(index * 2));
+                Self::compile_alts(prog, alts);
+                prog.push(Inst::Save(index * 2 + 1));
+            }
+            // the parser only creates ?, * and +
+            Item::Repeat(inner, min, max) => {
+                let start = prog.len();
+                if *min == 0 {
+                    prog.push(Inst::Split(start + 1, 0));
+                    Self::compile_item(prog, inner);
+                    if *max == usize::MAX {
+                        prog.push(Inst::Jump(start));
+                    }
+                    prog[start] = Inst::Split(start + 1, prog.len());
+                } else {
+                    Self::compile_item(prog, inner);
+                    prog.push(Inst::Split(start, prog.len() + 1));
+                }
+            }
+        }
+    }
+
+    /// Returns true if the pattern matches anywhere in the text
+    pub fn is_match(&self, text: &str) -> bool {
+        let text: Vec<char> = text.chars().collect();
+        self.find(&text, 0).is_some()
+    }
+
+    /// Replaces every match that does not overlap with a previous one
+    /// $0 to $9 insert a capture of the match and $$ inserts a $
+    pub fn replace_all(&self, text: &str, replace: &str) -> String {
+        let text: Vec<char> = text.chars().collect();
+        let mut result = String::new();
+        let mut pos = 0;
+        while let Some(caps) = self.find(&text, pos) {
+            let (start, end) = caps[0].unwrap_or((pos, pos));
+            result.extend(&text[pos..start]);
+            Self::expand(&text, &caps, replace, &mut result);
+            // empty matches must not match at the same position again
+            if end == start {
+                result.extend(text.get(end));
+                pos = end + 1;
+            } else {
+                pos = end;
+            }
+        }
+        if let Some(rest) = text.get(pos..) {
+            result.extend(rest);
+        }
+        result
+    }
+
+    fn expand(text: &[char], caps: &Captures, replace: &str, result: &mut String) {
+        let mut chars = replace.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '$' {
+                result.push(c);
+                continue;
+            }
+            match chars.peek().copied() {
+                Some('$') => {
+                    chars.next();
+                    result.push('$');
+                }
+                Some(d) if d.is_ascii_digit() => {
+                    chars.next();
+                    let index = d as usize - '0' as usize;
+                    if let Some(Some((start, end))) = caps.get(index) {
+                        result.extend(&text[*start..*end]);
+                    }
+                }
+                _ => result.push('$'),
+            }
+        }
+    }
+
+    // finds the leftmost match that starts at or after from
+    fn find(&self, text: &[char], from: usize) -> Option<Captures> {
+        if from > text.len() {
+            return None;
+        }
+        let slots = (self.groups + 1) * 2;
+        let mut current = Threads::new(self.prog.len());
+        let mut matched = None;
+        let mut pos = from;
+        loop {
+            // a match may start at every position until one is found
+            if matched.is_none() {
+                self.add(&mut current, 0, pos, text, vec![None; slots]);
+            }
+            if current.list.is_empty() {
+                break;
+            }
+            let c = text.get(pos).copied();
+            let mut next = Threads::new(self.prog.len());
+            for (pc, caps) in current.list {
+                let step = match &self.prog[pc] {
+                    // the threads with a lower priority are dropped
+                    Inst::Match => {
+                        matched = Some(caps);
+                        break;
+                    }
+                    Inst::Char(expected) => c == Some(*expected),
+                    Inst::Any => c.is_some(),
+                    Inst::Class(ranges, negated) => c.is_some_and(|c| {
+                        ranges.iter().any(|(lo, hi)| (*lo..=*hi).contains(&c)) != *negated
+                    }),
+                    _ => false,
+                };
+                if step {
+                    self.add(&mut next, pc + 1, pos + 1, text, caps);
+                }
+            }
+            if pos == text.len() {
+                break;
+            }
+            current = next;
+            pos += 1;
+        }
+        matched.map(|slots: Vec<Option<usize>>| {
+            slots.chunks(2).map(|slot| slot[0].zip(slot[1])).collect()
+        })
+    }
+
+    // adds a thread at pc and follows the instructions that do not consume a char
+    fn add(
+        &self,
+        threads: &mut Threads,
+        pc: usize,
+        pos: usize,
+        text: &[char],
+        caps: Vec<Option<usize>>,
+    ) {
+        let mut stack = vec![(pc, caps)];
+        while let Some((mut pc, mut caps)) = stack.pop() {
+            loop {
+                if std::mem::replace(&mut threads.seen[pc], true) {
+                    break;
+                }
+                match &self.prog[pc] {
+                    Inst::Jump(to) => pc = *to,
+                    Inst::Split(first, second) => {
+                        stack.push((*second, caps.clone()));
+                        pc = *first;
+                    }
+                    Inst::Save(slot) => {
+                        caps[*slot] = Some(pos);
+                        pc += 1;
+                    }
+                    Inst::Start if pos == 0 => pc += 1,
+                    Inst::End if pos == text.len() => pc += 1,
+                    Inst::Start | Inst::End => break,
+                    _ => {
+                        threads.list.push((pc, caps));
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Regex;
+
+    #[test]
+    fn replace() {
+        let hex = Regex::new(r"\$([0-9a-f]+)").unwrap();
+        assert_eq!("lda #0x10, 0xff", hex.replace_all("lda #$10, $ff", "0x$1"));
+
+        let mnemonic = Regex::new(r"^(\s*)(lda|sta) ").unwrap();
+        assert_eq!("  LDA $10", mnemonic.replace_all("  lda $10", "$1LDA "));
+        assert!(!mnemonic.is_match("jmp lda "));
+
+        let class = Regex::new("[^ ,]+,x").unwrap();
+        assert_eq!("lda X", class.replace_all("lda $10,x", "X"));
+        assert_eq!(
+            "a-b-c",
+            Regex::new("")
+                .unwrap()
+                .replace_all("abc", "-")
+                .trim_matches('-')
+        );
+        assert_eq!("$a", Regex::new("b?a").unwrap().replace_all("ba", "$$a"));
+        assert_eq!(
+            "nop ;",
+            Regex::new(r"\s*;.*$")
+                .unwrap()
+                .replace_all("nop    ; comment", " ;")
+        );
+
+        assert!(Regex::new("(a").is_err());
+        assert!(Regex::new("a)").is_err());
+        assert!(Regex::new("*a").is_err());
+        assert!(Regex::new("[a").is_err());
+    }
+
+    #[test]
+    fn long_input() {
+        // every path through the alternatives is tried at once
+        let alts = Regex::new("(a|a)*b").unwrap();
+        assert!(!alts.is_match(&"a".repeat(5000)));
+        assert_eq!(
+            "x",
+            alts.replace_all(&format!("{}b", "a".repeat(5000)), "x")
+        );
+        assert!(Regex::new("(a*)*b").unwrap().is_match("aaab"));
+
+        // repetitions do not recurse per char
+        let line = "a".repeat(200_000);
+        assert!(!Regex::new(".*x").unwrap().is_match(&line));
+        // the empty match at the end is replaced as well
+        assert_eq!("xx", Regex::new(".*").unwrap().replace_all(&line, "x"));
+        assert_eq!("b", Regex::new("(a+)").unwrap().replace_all(&line, "b"));
+        assert_eq!(
+            "[aa]b[a]",
+            Regex::new("(a+)").unwrap().replace_all("aaba", "[$1]")
+        );
+    }
+}
@@ -0,0 +1,37 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::Address;
+
+/// A region of the input that belongs to an embedded co-processor.
+/// The file offsets start..end are disassembled using the arch key
+/// at their own org, and all labels inside the region are looked up in
+/// the namespace of the region
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct Region {
+    // the namespace of the region's labels
+    pub name: String,
+    pub arch_key: String,
+    pub start: usize,
+    pub end: usize,
+    // the address of the first byte in the co-processor's address space
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub org: Address,
+}
+
+impl Region {
+    pub fn new(name: &str, arch_key: &str, start: usize, end: usize, org: Address) -> Self {
+        Self {
+            name: name.into(),
+            arch_key: arch_key.into(),
+            start,
+            end,
+            org,
+        }
+    }
+
+    pub fn contains(&self, offset: usize) -> bool {
+        offset >= self.start && offset < self.end
+    }
+}
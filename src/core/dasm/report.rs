@@ -0,0 +1,83 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use super::Address;
+
+/// A matcher whose encoding covers more bytes than it consumed
+/// The assembled output would be longer than the input
+/// and all following addresses would be shifted
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SizeMismatch {
+    pub address: Address,
+    pub matcher: String,
+    pub encoded: usize,
+    pub consumed: usize,
+}
+
+impl Display for SizeMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:x}: {} is encoded in {} bytes but consumed {}",
+            self.address, self.matcher, self.encoded, self.consumed
+        )
+    }
+}
+
+/// An instruction or data directive whose bytes are in more than one bank
+/// This usually means that the org or a region is wrong
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BankCrossing {
+    pub address: Address,
+    // the address of the last byte
+    pub last: Address,
+    pub matcher: String,
+}
+
+impl Display for BankCrossing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:x}: {} crosses a bank boundary into {:x}",
+            self.address, self.matcher, self.last
+        )
+    }
+}
+
+/// The bytes that were output per section and bank
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct SizeReport {
+    // (namespace, bank) -> bytes
+    pub sections: BTreeMap<(String, Address), usize>,
+    pub mismatches: Vec<SizeMismatch>,
+    pub crossings: Vec<BankCrossing>,
+}
+
+impl SizeReport {
+    pub fn add(&mut self, section: &str, address: Address, len: usize) {
+        *self
+            .sections
+            .entry((section.to_owned(), address >> 16))
+            .or_default() += len;
+    }
+
+    pub fn total(&self) -> usize {
+        self.sections.values().sum()
+    }
+}
+
+impl Display for SizeReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for ((section, bank), len) in self.sections.iter() {
+            let section = if section.is_empty() { "main" } else { section };
+            writeln!(f, "{section} bank {bank:02x}: {len:#x} bytes")?;
+        }
+        writeln!(f, "total: {:#x} bytes", self.total())?;
+        for mismatch in self.mismatches.iter() {
+            writeln!(f, "mismatch {mismatch}")?;
+        }
+        for crossing in self.crossings.iter() {
+            writeln!(f, "crossing {crossing}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt::Display;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::Address;
+
+/// A part of the input that is loaded at its own address.
+/// If a context declares segments only the segments are disassembled,
+/// each one after a header with its name. Unlike regions segments
+/// do not have their own label namespace
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub name: String,
+    pub start: usize,
+    pub end: usize,
+    // the load address of the first byte
+    pub org: Address,
+    // the arch that is used for the segment, the current arch if empty
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub arch_key: String,
+}
+
+impl Segment {
+    pub fn new(name: &str, start: usize, end: usize, org: Address, arch_key: &str) -> Self {
+        Self {
+            name: name.into(),
+            start,
+            end,
+            org,
+            arch_key: arch_key.into(),
+        }
+    }
+}
+
+/// A part of the input that holds no data, e.g. the space between the records of
+/// a hex dump or the unreadable parts of a partial rom dump.
+/// Gaps are skipped with a notice and keep the addresses of the bytes after them
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct Gap {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Gap {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end.saturating_sub(self.start)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Display for Segment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "segment {}: {:#x}..{:#x} at {:x}",
+            self.name, self.start, self.end, self.org
+        )
+    }
+}
@@ -0,0 +1,166 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeSet},
+    fmt::Display,
+    hash::{Hash, Hasher},
+};
+
+use crate::prelude::FdResult;
+
+use super::{
+    arch::{Archs, CallbackKind, Context},
+    symbols::NAMESPACE_SEPARATOR,
+    Address,
+};
+
+// the amount of consecutive instructions that are hashed together
+const SHINGLE_LEN: usize = 3;
+
+/// The instructions from one label to the next
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Routine {
+    pub name: String,
+    pub address: Address,
+    // the matcher names without the operands
+    pub instructions: Vec<String>,
+}
+
+impl Routine {
+    // hashes of every run of consecutive instructions
+    fn shingles(&self) -> BTreeSet<u64> {
+        self.instructions
+            .windows(SHINGLE_LEN.min(self.instructions.len()))
+            .map(|run| {
+                let mut hasher = DefaultHasher::new();
+                run.hash(&mut hasher);
+                hasher.finish()
+            })
+            .collect()
+    }
+}
+
+/// Two routines with a similar sequence of instructions
+#[derive(Clone, Debug, PartialEq)]
+pub struct Similar {
+    pub a: String,
+    pub b: String,
+    // the jaccard index of the instruction runs
+    pub score: f32,
+}
+
+impl Display for Similar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ~ {}: {:.0}%", self.a, self.b, self.score * 100.0)
+    }
+}
+
+/// Splits the output into routines at every label
+pub fn routines(arch: &Archs, data: &[u8], ctx: &mut Context) -> FdResult<Vec<Routine>> {
+    let mut routines: Vec<Routine> = vec![];
+    ctx.restart();
+    arch.disas_ctx(
+        |node, kind, _raw, _arch, ctx| {
+            match kind {
+                CallbackKind::Label if !node.string.is_empty() => {
+                    let name = node.string.lines().next().unwrap_or("");
+                    let name = name.trim_end_matches(':');
+                    routines.push(Routine {
+                        name: if ctx.namespace.is_empty() {
+                            name.to_owned()
+                        } else {
+                            format!("{}{NAMESPACE_SEPARATOR}{name}", ctx.namespace)
+                        },
+                        address: ctx.address(),
+                        instructions: vec![],
+                    });
+                }
+                CallbackKind::MatcherName => {
+                    if routines.is_empty() {
+                        routines.push(Routine {
+                            name: format!("{:x}", ctx.address()),
+                            address: ctx.address(),
+                            instructions: vec![],
+                        });
+                    }
+                    if let Some(routine) = routines.last_mut() {
+                        routine.instructions.push(node.string.clone());
+                    }
+                }
+                _ => {}
+            }
+            Ok(())
+        },
+        data,
+        ctx,
+    )?;
+    Ok(routines)
+}
+
+/// Compares every pair of routines with at least min_instructions
+/// and returns the pairs whose score is at least the threshold
+pub fn find(routines: &[Routine], threshold: f32, min_instructions: usize) -> Vec<Similar> {
+    let candidates: Vec<(&Routine, BTreeSet<u64>)> = routines
+        .iter()
+        .filter(|r| !r.instructions.is_empty() && r.instructions.len() >= min_instructions)
+        .map(|r| (r, r.shingles()))
+        .collect();
+
+    let mut similar = vec![];
+    for (i, (a, a_shingles)) in candidates.iter().enumerate() {
+        for (b, b_shingles) in candidates.iter().skip(i + 1) {
+            let shared = a_shingles.intersection(b_shingles).count();
+            let all = a_shingles.union(b_shingles).count();
+            let score = shared as f32 / all as f32;
+            if score >= threshold {
+                similar.push(Similar {
+                    a: a.name.clone(),
+                    b: b.name.clone(),
+                    score,
+                });
+            }
+        }
+    }
+    similar.sort_by(|a, b| b.score.total_cmp(&a.score));
+    similar
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{
+        arch::{a6502, Context},
+        symbols::{Scope, Symbol, SymbolKind},
+    };
+
+    use super::{find, routines};
+
+    #[test]
+    fn similar_routines() {
+        let mut ctx = Context::default();
+        for (name, address) in [("a", 0), ("b", 9), ("c", 18)] {
+            ctx.syms.def_symbol(Symbol::new(
+                name.into(),
+                SymbolKind::Label,
+                Scope::Global,
+                address,
+                1,
+            ));
+        }
+        let data = [
+            // a: lda #1, sta 2000, inx, iny, rts
+            0xA9, 0x01, 0x8D, 0x00, 0x20, 0xE8, 0xC8, 0x60, 0xEA,
+            // b: the same with other operands
+            0xA9, 0x05, 0x8D, 0x10, 0x30, 0xE8, 0xC8, 0x60, 0xEA, // c: unrelated
+            0x18, 0x38, 0x18, 0x38, 0x18, 0x38,
+        ];
+        let routines = routines(&a6502::ARCH, &data, &mut ctx).unwrap();
+        assert_eq!(3, routines.len());
+        assert_eq!(
+            vec!["lda", "sta", "inx", "iny", "rts", "nop"],
+            routines[0].instructions
+        );
+
+        let similar = find(&routines, 0.8, 4);
+        assert_eq!(1, similar.len());
+        assert_eq!("a ~ b: 100%", similar[0].to_string());
+        assert!(find(&routines, 0.8, 7).is_empty());
+    }
+}
@@ -0,0 +1,192 @@
+use crate::prelude::{Error, FdResult};
+
+use super::{
+    arch::{Arch, Context},
+    segment::Segment,
+};
+
+/// The file that includes the parts of a split output in order
+pub const MASTER_FILE: &str = "main.asm";
+
+/// A part of the input that is written to its own file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Part {
+    pub file: String,
+    // the file offsets of the part
+    pub start: usize,
+    pub end: usize,
+    selection: Selection,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Selection {
+    Bank(String),
+    Segment(Segment),
+}
+
+impl Part {
+    /// Limits the output of the context to the part
+    /// The reassembled output only contains the bytes of the part as well
+    pub fn select(&self, ctx: &mut Context) -> FdResult<()> {
+        ctx.split_range = Some((self.start, self.end));
+        match &self.selection {
+            Selection::Bank(bank) => {
+                // the segments would replace the window of the bank
+                ctx.segments.clear();
+                ctx.view_bank(bank)
+            }
+            Selection::Segment(segment) => {
+                ctx.segments = vec![segment.clone()];
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A bank_xx part per bank if the context has banks, otherwise a segment_xx part per segment
+pub fn parts(ctx: &Context, data: &[u8]) -> FdResult<Vec<Part>> {
+    let mut banks = ctx.banks();
+    let parts: Vec<Part> = if !banks.is_empty() {
+        // an overlay bank may lie past the end of the input
+        banks.retain(|b| {
+            let inside = b.start < b.end.min(data.len());
+            if !inside {
+                ctx.warn(&format!(
+                    "The bank {} at {:#x}..{:#x} is not part of the input, it is not output",
+                    b.name, b.start, b.end
+                ));
+            }
+            inside
+        });
+        let covered: usize = banks
+            .iter()
+            .map(|b| b.end.min(data.len()).saturating_sub(b.start))
+            .sum();
+        if covered < data.len() {
+            ctx.warn(&format!(
+                "The banks only cover {covered:#x} of {:#x} bytes, the rest is not output",
+                data.len()
+            ));
+        }
+        banks
+            .iter()
+            .enumerate()
+            .map(|(i, bank)| Part {
+                file: format!("bank_{i:02}.asm"),
+                start: bank.start,
+                end: bank.end.min(data.len()),
+                selection: Selection::Bank(bank.name.clone()),
+            })
+            .collect()
+    } else {
+        ctx.segment_ranges(data)
+            .into_iter()
+            .enumerate()
+            .map(|(i, segment)| Part {
+                file: format!("segment_{i:02}.asm"),
+                start: segment.start,
+                end: segment.end,
+                selection: Selection::Segment(segment),
+            })
+            .collect()
+    };
+    if parts.is_empty() {
+        return Err(Error::InvalidArgument(
+            "The output can only be split if the context has banks or segments".into(),
+        ));
+    }
+    Ok(parts)
+}
+
+/// The master file includes every part using the include directive of the arch
+pub fn master(arch: &Arch, parts: &[Part]) -> String {
+    parts
+        .iter()
+        .map(|part| format!("{}\n", arch.include(&part.file)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{
+        arch::{a6502, dialect::Dialect, Context},
+        loader::LoaderKind,
+        segment::Segment,
+    };
+
+    use super::{master, parts};
+
+    #[test]
+    fn split() {
+        let mut ctx = Context::default();
+        let mut rom = vec![0xEA; 0x2000];
+        rom[0x1000] = 0x60;
+        let data = LoaderKind::AtariF8.load(rom, &mut ctx).unwrap();
+        let banks = parts(&ctx, &data).unwrap();
+        let files: Vec<&str> = banks.iter().map(|p| p.file.as_str()).collect();
+        assert_eq!(vec!["bank_00.asm", "bank_01.asm"], files);
+
+        let mut bank = ctx.clone();
+        banks[1].select(&mut bank).unwrap();
+        let mut result = String::new();
+        a6502::ARCH
+            .disas_ctx(
+                |n, _kind, _raw, _arch, _ctx| {
+                    result.push_str(&n.string);
+                    Ok(())
+                },
+                &data,
+                &mut bank,
+            )
+            .unwrap();
+        assert!(result.starts_with("bank1:\n0000f000 rts\n"));
+
+        let mut arch = a6502::ARCH.to_owned();
+        Dialect::Asm6.apply(&mut arch);
+        assert_eq!(
+            "include \"bank_00.asm\"\ninclude \"bank_01.asm\"\n",
+            master(arch.arch(&ctx).unwrap(), &banks)
+        );
+
+        // without banks the segments are split
+        let ctx = Context {
+            segments: vec![
+                Segment::new("code", 0, 2, 0x8000, ""),
+                Segment::new("data", 2, 4, 0xC000, ""),
+            ],
+            ..Default::default()
+        };
+        let segments = parts(&ctx, &[0; 4]).unwrap();
+        assert_eq!("segment_01.asm", segments[1].file);
+        // the reassembled output only contains the bytes of the part
+        let mut segment = ctx.clone();
+        segment.output.clean = true;
+        segments[1].select(&mut segment).unwrap();
+        let mut result = String::new();
+        a6502::ARCH
+            .disas_ctx(
+                |n, _kind, _raw, _arch, _ctx| {
+                    result.push_str(&n.string);
+                    Ok(())
+                },
+                &[0xEA, 0xEA, 0x60, 0x60],
+                &mut segment,
+            )
+            .unwrap();
+        assert_eq!(
+            "; segment data: 0x2..0x4 at c000\n.org $c000\n rts\n rts\n",
+            result
+        );
+        assert!(parts(&Context::default(), &[0; 4]).is_err());
+
+        // the banks past the end of the input are left out
+        let mut ctx = Context::default();
+        LoaderKind::AtariF8
+            .load(vec![0xEA; 0x2000], &mut ctx)
+            .unwrap();
+        let banks = parts(&ctx, &[0xEA; 0x1800]).unwrap();
+        assert_eq!(2, banks.len());
+        assert_eq!(0x1800, banks[1].end);
+        assert_eq!(1, parts(&ctx, &[0xEA; 0x800]).unwrap().len());
+    }
+}
@@ -0,0 +1,198 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use crate::prelude::FdResult;
+
+use super::{
+    arch::{Archs, Context},
+    flow::{self, Flow, Function, Instruction},
+    interrupts, Address,
+};
+
+// the bytes an instruction pushes (positive) or pulls (negative)
+fn stack_change(name: &str) -> isize {
+    match name.to_lowercase().as_str() {
+        "pha" | "php" | "phx" | "phy" | "phb" | "phk" => 1,
+        "phd" | "pea" | "pei" | "per" => 2,
+        "pla" | "plp" | "plx" | "ply" | "plb" => -1,
+        "pld" => -2,
+        _ => 0,
+    }
+}
+
+/// The push and pull balance of a routine at its returns
+/// Calls are assumed to leave the stack balanced
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StackEffect {
+    pub name: String,
+    pub entry: Address,
+    // the address of every return and the bytes that were pushed but not pulled
+    pub returns: Vec<(Address, isize)>,
+    // the tables that the pushed addresses are loaded from (rts dispatch)
+    pub tables: Vec<Address>,
+}
+
+impl StackEffect {
+    /// The routine pushes its own return address or pulls the one of its caller
+    pub fn manipulates_return(&self) -> bool {
+        self.returns.iter().any(|(_, depth)| *depth != 0)
+    }
+
+    fn describe(&self) -> String {
+        if self.returns.iter().any(|(_, depth)| *depth > 0) {
+            "returns to a pushed address".into()
+        } else {
+            "pulls its return address".into()
+        }
+    }
+}
+
+impl Display for StackEffect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.manipulates_return() {
+            return write!(f, "{}: balanced", self.name);
+        }
+        let returns: Vec<String> = self
+            .returns
+            .iter()
+            .map(|(address, depth)| format!("{depth:+} at {address:x}"))
+            .collect();
+        write!(
+            f,
+            "{}: {}, {}",
+            self.name,
+            returns.join(", "),
+            self.describe()
+        )?;
+        if !self.tables.is_empty() {
+            let tables: Vec<String> = self.tables.iter().map(|t| format!("{t:x}")).collect();
+            write!(f, ", tables {}", tables.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Follows the stack depth through the function
+/// The first depth that reaches an instruction wins
+pub fn effect(instructions: &BTreeMap<Address, Instruction>, function: &Function) -> StackEffect {
+    let mut depths: BTreeMap<Address, isize> = BTreeMap::new();
+    let mut returns = vec![];
+    let mut pending = vec![(function.entry, 0)];
+    while let Some((address, depth)) = pending.pop() {
+        let Some(instruction) = instructions.get(&address) else {
+            continue;
+        };
+        if depths.contains_key(&address) {
+            continue;
+        }
+        depths.insert(address, depth);
+        if matches!(instruction.flow(), Flow::Return | Flow::InterruptReturn) {
+            returns.push((address, depth));
+        }
+        let after = depth + stack_change(&instruction.name);
+        for successor in flow::successors(instructions, address, false) {
+            pending.push((successor, after));
+        }
+    }
+    returns.sort();
+
+    // the pushed bytes are loaded from a table by an indexed load or the table follows the return
+    let mut tables = vec![];
+    let pushes_return = returns.iter().any(|(_, depth)| *depth > 0);
+    for address in depths.keys().filter(|_| pushes_return) {
+        if stack_change(&instructions[address].name) <= 0 {
+            continue;
+        }
+        let load = instructions
+            .range(..*address)
+            .next_back()
+            .map(|(_, load)| load)
+            .filter(|load| load.name.starts_with("ld") && !load.indirect)
+            .filter(|load| load.text.ends_with(", x") || load.text.ends_with(", y"));
+        if let Some(table) = load.and_then(|load| load.operand) {
+            if !tables.contains(&table) {
+                tables.push(table);
+            }
+        }
+    }
+    if pushes_return && tables.is_empty() {
+        tables.extend(
+            returns
+                .iter()
+                .filter(|(_, depth)| *depth > 0)
+                .filter_map(|(address, _)| instructions.range(address + 1..).next())
+                .map(|(address, _)| *address),
+        );
+    }
+
+    StackEffect {
+        name: function.name.clone(),
+        entry: function.entry,
+        returns,
+        tables,
+    }
+}
+
+/// The stack effects of the routines that are reached from the start and the vectors
+pub fn find(arch: &Archs, data: &[u8], ctx: &mut Context) -> FdResult<Vec<StackEffect>> {
+    let instructions = flow::instructions(arch, data, ctx)?;
+    let roots = flow::roots(&instructions, interrupts::vectors(ctx, data));
+    Ok(flow::functions(&instructions, &roots, ctx)
+        .iter()
+        .map(|function| effect(&instructions, function))
+        .collect())
+}
+
+/// Adds a comment to the routines that manipulate their return address
+/// and to their jump table candidates. Returns the amount of routines
+pub fn annotate(ctx: &mut Context, effects: &[StackEffect]) -> usize {
+    let mut count = 0;
+    for effect in effects.iter().filter(|e| e.manipulates_return()) {
+        ctx.comments
+            .entry(effect.entry)
+            .or_insert_with(|| effect.describe());
+        for table in &effect.tables {
+            ctx.comments
+                .entry(*table)
+                .or_insert_with(|| format!("jump table candidate ({})", effect.name));
+        }
+        count += 1;
+    }
+    count
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::arch::{a6502, Context};
+
+    use super::{annotate, find};
+
+    #[test]
+    fn stack_effects() {
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        // jsr dispatch, jsr pull, jmp 8006
+        let start = [0x20, 0x09, 0x80, 0x20, 0x14, 0x80, 0x4C, 0x06, 0x80];
+        // 8009: lda 8018,x, pha, lda 8017,x, pha, rts
+        let dispatch = [0xBD, 0x18, 0x80, 0x48, 0xBD, 0x17, 0x80, 0x48, 0x60];
+        // 8012: php, plp is never called
+        let unused = [0x08, 0x28];
+        // 8014: pla, pla, rts
+        let pull = [0x68, 0x68, 0x60];
+        let data = [&start[..], &dispatch, &unused, &pull].concat();
+        let effects = find(&a6502::ARCH, &data, &mut ctx).unwrap();
+        assert_eq!(
+            vec![
+                "start: balanced",
+                "sub_8009: +2 at 8011, returns to a pushed address, tables 8018, 8017",
+                "sub_8014: -2 at 8016, pulls its return address",
+            ],
+            effects.iter().map(|e| e.to_string()).collect::<Vec<_>>()
+        );
+
+        assert_eq!(2, annotate(&mut ctx, &effects));
+        assert_eq!("returns to a pushed address", ctx.comments[&0x8009]);
+        assert_eq!("jump table candidate (sub_8009)", ctx.comments[&0x8017]);
+    }
+}
@@ -0,0 +1,221 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
+
+use crate::prelude::FdResult;
+
+use super::{
+    arch::{Archs, Context},
+    dispatch, flow, interrupts,
+    symbols::SymbolKind,
+    Address,
+};
+
+/// The amount of functions that are listed by size
+pub const LARGEST_FUNCTIONS: usize = 10;
+
+// the width of the longest bar of the histogram
+const HISTOGRAM_WIDTH: usize = 40;
+
+/// How much of the input the analysis covers
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    // the instructions by their name, the most frequent first
+    pub opcodes: Vec<(String, usize)>,
+    // the bytes of the instructions that are reached from the start and the vectors
+    pub code: usize,
+    // the bytes that are output as data directives
+    pub data: usize,
+    // the bytes of the instructions that are never reached, they can be code or data
+    pub unknown: usize,
+    pub labels: usize,
+    pub consts: usize,
+    // the symbols that were defined by the analysis
+    pub auto: usize,
+    // the name, the entry and the bytes of the largest functions, the largest first
+    pub functions: Vec<(String, Address, usize)>,
+}
+
+impl Stats {
+    fn percent(&self, bytes: usize) -> usize {
+        let total = self.code + self.data + self.unknown;
+        (bytes * 100).checked_div(total).unwrap_or_default()
+    }
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (kind, bytes) in [
+            ("code", self.code),
+            ("data", self.data),
+            ("unknown", self.unknown),
+        ] {
+            writeln!(f, "{kind}: {bytes:#x} bytes ({}%)", self.percent(bytes))?;
+        }
+        writeln!(
+            f,
+            "symbols: {} labels, {} consts, {} defined by the analysis",
+            self.labels, self.consts, self.auto
+        )?;
+        if !self.functions.is_empty() {
+            writeln!(f, "largest functions:")?;
+        }
+        for (name, entry, bytes) in &self.functions {
+            writeln!(f, "  {name} at {entry:x}: {bytes:#x} bytes")?;
+        }
+        if !self.opcodes.is_empty() {
+            writeln!(f, "opcodes:")?;
+        }
+        let most = self.opcodes.first().map(|(_, n)| *n).unwrap_or_default();
+        for (name, count) in &self.opcodes {
+            let bar = (count * HISTOGRAM_WIDTH).div_ceil(most);
+            writeln!(f, "  {name:<8}{count:>6} {}", "#".repeat(bar))?;
+        }
+        Ok(())
+    }
+}
+
+/// Classifies the bytes of the output, counts the instructions and the symbols
+/// and sizes the functions that are reached from the start and the vectors
+pub fn collect(arch: &Archs, data: &[u8], ctx: &mut Context) -> FdResult<Stats> {
+    // the data directives are not instructions, a line starts at the address
+    // of its first output and ends at the address the next line continues at
+    let mut lines: Vec<(Address, Address)> = vec![];
+    let mut line = None;
+    ctx.restart();
+    arch.disas_ctx(
+        |_node, _kind, _raw, _arch, ctx| {
+            if line != Some(ctx.next_address) {
+                line = Some(ctx.next_address);
+                if let Some((start, end)) = lines.last_mut() {
+                    *end = ctx.next_address.unwrap_or(*start);
+                }
+                lines.push((ctx.address(), ctx.address()));
+            }
+            Ok(())
+        },
+        data,
+        ctx,
+    )?;
+    if let Some((start, end)) = lines.last_mut() {
+        *end = ctx.next_address.unwrap_or(*start);
+    }
+    let mut instructions = flow::instructions(arch, data, ctx)?;
+    let vectors = interrupts::vectors(ctx, data);
+    let roots = flow::roots(&instructions, vectors);
+    dispatch::resolve(&mut instructions, &roots, data, ctx);
+    let functions = flow::functions(&instructions, &roots, ctx);
+
+    let lens: BTreeMap<Address, usize> = lines
+        .into_iter()
+        .map(|(start, end)| (start, end.saturating_sub(start) as usize))
+        .collect();
+
+    let mut stats = Stats::default();
+    let code: BTreeSet<Address> = functions
+        .iter()
+        .flat_map(|f| f.blocks.iter())
+        .flat_map(|b| b.instructions.iter().copied())
+        .collect();
+    let mut opcodes: BTreeMap<&str, usize> = BTreeMap::new();
+    for (address, len) in &lens {
+        let Some(instruction) = instructions
+            .get(address)
+            .filter(|i| !i.name.starts_with('.'))
+        else {
+            stats.data += len;
+            continue;
+        };
+        *opcodes.entry(&instruction.name).or_default() += 1;
+        if code.contains(address) {
+            stats.code += len;
+        } else {
+            stats.unknown += len;
+        }
+    }
+    stats.opcodes = opcodes
+        .into_iter()
+        .map(|(name, count)| (name.to_owned(), count))
+        .collect();
+    stats
+        .opcodes
+        .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    for sym in ctx.syms.iter() {
+        match sym.kind {
+            SymbolKind::Label => stats.labels += 1,
+            SymbolKind::Const => stats.consts += 1,
+        }
+        stats.auto += sym.auto as usize;
+    }
+
+    stats.functions = functions
+        .iter()
+        .map(|function| {
+            let bytes = function
+                .blocks
+                .iter()
+                .flat_map(|b| b.instructions.iter())
+                .filter_map(|address| lens.get(address))
+                .sum();
+            (function.name.clone(), function.entry, bytes)
+        })
+        .collect();
+    stats
+        .functions
+        .sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.1.cmp(&b.1)));
+    stats.functions.truncate(LARGEST_FUNCTIONS);
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{
+        arch::{a6502, Context},
+        symbols::{Scope, Symbol, SymbolKind},
+    };
+
+    use super::collect;
+
+    #[test]
+    fn stats() {
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        ctx.def_symbol(Symbol::new(
+            "io".into(),
+            SymbolKind::Const,
+            Scope::Global,
+            0x2000,
+            1,
+        ));
+        // jsr 8006, jmp 8003, 8006: nop, rts, 8008: nop is never reached, then data
+        let data = [
+            0x20, 0x06, 0x80, 0x4C, 0x03, 0x80, 0xEA, 0x60, 0xEA, 0x02, 0x03,
+        ];
+        let stats = collect(&a6502::ARCH, &data, &mut ctx).unwrap();
+        assert_eq!((8, 2, 1), (stats.code, stats.data, stats.unknown));
+        assert_eq!(
+            vec![
+                ("start".to_owned(), 0x8000, 6),
+                ("sub_8006".into(), 0x8006, 2)
+            ],
+            stats.functions
+        );
+        assert_eq!(
+            "code: 0x8 bytes (72%)\n\
+             data: 0x2 bytes (18%)\n\
+             unknown: 0x1 bytes (9%)\n\
+             symbols: 0 labels, 1 consts, 0 defined by the analysis\n\
+             largest functions:\n  start at 8000: 0x6 bytes\n  sub_8006 at 8006: 0x2 bytes\n\
+             opcodes:\n\
+             \x20 nop          2 ########################################\n\
+             \x20 jmp          1 ####################\n\
+             \x20 jsr          1 ####################\n\
+             \x20 rts          1 ####################\n",
+            stats.to_string()
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use std::fmt::Display;
+
+use super::{
+    arch::Context,
+    classify::ByteClass,
+    symbols::{Scope, Symbol, SymbolKind},
+    Address, ValueType,
+};
+
+/// The prefix of the labels that are defined for strings
+pub const STRING_PREFIX: &str = "str_";
+
+/// The least amount of characters of a string if no minimum is given
+pub const DEFAULT_MIN_LEN: usize = 8;
+
+/// A run of characters in the bytes that are not classified yet
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Text {
+    pub address: Address,
+    // the file range of the characters
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+impl Display for Text {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:x}: \"{}\"", self.address, self.text)
+    }
+}
+
+/// Finds the runs of at least min_len characters of the output profile's charset
+/// that are not classified yet. Regions and gaps are skipped
+pub fn find(data: &[u8], ctx: &Context, min_len: usize) -> Vec<Text> {
+    let ranges: Vec<(usize, usize, Address)> = if ctx.segments.is_empty() {
+        let window = ctx.window(data);
+        let org = ctx.address_of(window.start as Address);
+        vec![(window.start, window.end, org)]
+    } else {
+        ctx.segment_ranges(data)
+            .iter()
+            .map(|s| (s.start, s.end, s.org))
+            .collect()
+    };
+    let is_char = |offset: usize| {
+        ctx.output.is_text(data[offset])
+            && ctx.classes.get(offset) == ByteClass::Unknown
+            && !ctx.regions.iter().any(|r| r.contains(offset))
+            && !ctx.gaps.iter().any(|g| (g.start..g.end).contains(&offset))
+    };
+
+    let mut texts = vec![];
+    for (start, end, org) in ranges {
+        let mut offset = start;
+        while offset < end {
+            let run = (offset..end).take_while(|at| is_char(*at)).count();
+            if run >= min_len.max(1) {
+                texts.push(Text {
+                    address: org.wrapping_add((offset - start) as Address),
+                    start: offset,
+                    end: offset + run,
+                    text: ctx.output.to_chars(&data[offset..offset + run]),
+                });
+            }
+            offset += run.max(1);
+        }
+    }
+    texts
+}
+
+/// Classifies the strings as text and defines a str_ label for every string that is not labeled yet
+/// Returns the amount of labels that were defined
+pub fn annotate(ctx: &mut Context, texts: &[Text]) -> usize {
+    let mut count = 0;
+    for text in texts {
+        ctx.classes
+            .set_unknown(text.start, text.end, ByteClass::Text);
+        let labeled = ctx
+            .syms
+            .get_symbols(text.address as ValueType)
+            .iter()
+            .any(|s| s.kind == SymbolKind::Label);
+        if !labeled {
+            ctx.def_auto_symbol(Symbol::new(
+                format!("{STRING_PREFIX}{:x}", text.address),
+                SymbolKind::Label,
+                Scope::Global,
+                text.address as ValueType,
+                1,
+            ));
+            count += 1;
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::arch::{a6502, Context};
+
+    use super::{annotate, find, DEFAULT_MIN_LEN};
+
+    #[test]
+    fn strings() {
+        let mut ctx = Context {
+            org: 0x8000,
+            ..Default::default()
+        };
+        // nop, "hello world", 0, then a run that is too short
+        let data = [&[0xEA][..], b"hello world", &[0x00], b"no", &[0x00]].concat();
+        let texts = find(&data, &ctx, DEFAULT_MIN_LEN);
+        assert_eq!(
+            vec!["8001: \"hello world\""],
+            texts.iter().map(|t| t.to_string()).collect::<Vec<_>>()
+        );
+        assert_eq!(1, annotate(&mut ctx, &texts));
+
+        let mut result = String::new();
+        a6502::ARCH
+            .disas_ctx(
+                |n, _kind, _raw, _arch, _ctx| {
+                    result.push_str(&n.string);
+                    Ok(())
+                },
+                &data,
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(
+            "00008000 nop\nstr_8001:\n00008001 .text \"hello world\"\n0000800c brk\n\
+             0000800d ror $6f\n",
+            result
+        );
+
+        // the strings are not found twice
+        assert!(find(&data, &ctx, DEFAULT_MIN_LEN).is_empty());
+    }
+}
@@ -1,3 +1,7 @@
+use std::collections::BTreeSet;
+
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
@@ -12,7 +16,7 @@ pub enum SymbolKind {
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Default, Copy, Clone, Debug)]
+#[derive(Default, Copy, Clone, PartialEq, Eq, Debug)]
 pub enum Scope {
     #[default]
     Global,
@@ -28,6 +32,44 @@ impl Scope {
     }
 }
 
+/// How the code references the value of an auto label
+/// the prefix of its name depends on it
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum ReferenceKind {
+    Call,
+    // branches and jumps
+    Branch,
+    Data,
+}
+
+impl ReferenceKind {
+    pub fn default_prefix(&self) -> &'static str {
+        match self {
+            Self::Call => "sub_",
+            Self::Branch => "loc_",
+            Self::Data => "dat_",
+        }
+    }
+}
+
+/// Separates the namespace from the name in a qualified symbol name
+pub const NAMESPACE_SEPARATOR: char = '.';
+
+/// Splits a qualified name (namespace.name) into namespace and name
+/// The name is only qualified if the part before the first separator is a known namespace,
+/// so local labels like .loop and dotted names keep their separators.
+/// Names without a namespace belong to the main processor
+pub fn split_qualified(name: &str, is_namespace: impl Fn(&str) -> bool) -> (&str, &str) {
+    match name.split_once(NAMESPACE_SEPARATOR) {
+        Some((namespace, short)) if !namespace.is_empty() && is_namespace(namespace) => {
+            (namespace, short)
+        }
+        _ => ("", name),
+    }
+}
+
 fn default_sym_len() -> usize {
     1
 }
@@ -50,6 +92,22 @@ pub struct Symbol {
     pub additional_values: Vec<ValueType>,
     #[cfg_attr(feature = "serde", serde(default = "default_sym_len"))]
     pub len: usize,
+    // the co-processor region the symbol belongs to
+    // an empty namespace belongs to the main processor
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub namespace: String,
+    // the symbol was defined by the analysis and not by the user
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub auto: bool,
+    // documentation that is output as a comment block above the label
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub doc: Option<String>,
+    // free-form tags that group symbols, e.g. audio or verified
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub tags: BTreeSet<String>,
+    // the symbol only lives for the session and is never saved with the context
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub temporary: bool,
 }
 
 impl Symbol {
@@ -61,6 +119,29 @@ impl Symbol {
             value,
             len,
             additional_values: Default::default(),
+            namespace: Default::default(),
+            auto: false,
+            doc: None,
+            tags: Default::default(),
+            temporary: false,
+        }
+    }
+
+    pub fn qualified_name(&self) -> String {
+        if self.namespace.is_empty() {
+            self.name.to_owned()
+        } else {
+            format!("{}{NAMESPACE_SEPARATOR}{}", self.namespace, self.name)
+        }
+    }
+
+    /// The name as it is referenced from the namespace
+    /// Symbols of other co-processors are qualified
+    pub fn name_in(&self, namespace: &str) -> String {
+        if self.namespace == namespace {
+            self.name.to_owned()
+        } else {
+            self.qualified_name()
         }
     }
 
@@ -90,20 +171,98 @@ impl Symbol {
         };
         value >= check_value && value < check_value + self.len as ValueType && in_scope
     }
+
+    /// Two symbols are the same if they describe the same value
+    /// in the same namespace
+    pub fn is_same(&self, other: &Symbol) -> bool {
+        self.value == other.value
+            && self.kind == other.kind
+            && self.scope == other.scope
+            && self.namespace == other.namespace
+    }
+
+    /// Does any value of the other symbol fall into the range of this symbol?
+    fn overlaps(&self, other: &Symbol) -> bool {
+        self.kind == other.kind
+            && self.namespace == other.namespace
+            && std::iter::once(&other.value)
+                .chain(other.additional_values.iter())
+                .any(|v| self.is_match(*v, None))
+    }
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Default, Clone)]
 pub struct SymbolList {
-    #[cfg_attr(feature = "serde", serde(default))]
+    #[cfg_attr(feature = "serde", serde(default, serialize_with = "persistent"))]
     map: Vec<Symbol>,
 }
 
+// the temporary symbols are not serialized
+#[cfg(feature = "serde")]
+fn persistent<S: serde::Serializer>(map: &[Symbol], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(map.iter().filter(|sym| !sym.temporary))
+}
+
 impl SymbolList {
     pub fn def_symbol(&mut self, sym: Symbol) {
         self.map.push(sym);
     }
 
+    /// True if a symbol belongs to the namespace
+    pub fn has_namespace(&self, namespace: &str) -> bool {
+        self.map.iter().any(|x| x.namespace == namespace)
+    }
+
+    /// Defines a symbol using the merge rules:
+    /// - an auto symbol is dropped if a symbol with the same value exists
+    /// - a user symbol replaces auto symbols with the same value
+    /// - a user symbol with the same name and value is only defined once
+    /// - a symbol that is not temporary replaces a temporary one with the same name and value
+    pub fn merge_symbol(&mut self, sym: Symbol) {
+        let mut same = self.map.iter().filter(|x| x.is_same(&sym));
+        if sym.auto {
+            if same.next().is_some() {
+                return;
+            }
+        } else if same.any(|x| !x.auto && x.name == sym.name && (sym.temporary || !x.temporary)) {
+            return;
+        }
+
+        if !sym.auto {
+            self.map
+                .retain(|x| !(x.is_same(&sym) && (x.auto || (x.temporary && x.name == sym.name))));
+        }
+        self.map.push(sym);
+    }
+
+    /// Removes auto symbols that are covered by a user symbol
+    /// or that duplicate an earlier auto symbol
+    /// Returns the amount of removed symbols
+    pub fn cleanup(&mut self) -> usize {
+        let before = self.map.len();
+        let mut kept: Vec<Symbol> = vec![];
+        for sym in self.map.iter() {
+            let covered = sym.auto
+                && (self.map.iter().any(|x| !x.auto && x.overlaps(sym))
+                    || kept.iter().any(|x| x.is_same(sym)));
+            if !covered {
+                kept.push(sym.clone());
+            }
+        }
+        self.map = kept;
+        before - self.map.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Symbol> {
+        self.map.iter()
+    }
+
+    /// The symbols that are not saved with the context
+    pub fn temporary(&self) -> impl Iterator<Item = &Symbol> {
+        self.map.iter().filter(|x| x.temporary)
+    }
+
     // get all symbols for a specific value
     pub fn get_symbols(&self, value: ValueType) -> Vec<Symbol> {
         self.map
@@ -113,16 +272,55 @@ impl SymbolList {
             .collect()
     }
 
+    /// The name may be qualified by a namespace
     pub fn get_first_by_name(&self, name: &str) -> Option<&Symbol> {
-        self.map.iter().find(|x| x.name == name)
+        self.position_by_name(name).map(|i| &self.map[i])
+    }
+
+    fn position_by_name(&self, name: &str) -> Option<usize> {
+        let (namespace, short) = split_qualified(name, |namespace| self.has_namespace(namespace));
+        self.map
+            .iter()
+            .position(|x| x.namespace == namespace && x.name == short)
+            .or_else(|| self.map.iter().position(|x| x.name == name))
+    }
+
+    /// Adds a tag to the first symbol of the (qualified) name
+    /// Returns false if no symbol has the name
+    pub fn tag(&mut self, name: &str, tag: &str) -> bool {
+        self.position_by_name(name)
+            .map(|i| self.map[i].tags.insert(tag.into()))
+            .is_some()
+    }
+
+    /// Removes a tag from the first symbol of the (qualified) name
+    /// Returns false if no symbol has the name
+    pub fn untag(&mut self, name: &str, tag: &str) -> bool {
+        self.position_by_name(name)
+            .map(|i| self.map[i].tags.remove(tag))
+            .is_some()
     }
 
-    pub fn get_first_symbol(&self, value: ValueType, address: Address) -> Option<&Symbol> {
-        self.map.iter().find(|x| x.is_match(value, Some(address)))
+    /// The symbols that have the tag
+    pub fn tagged<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a Symbol> {
+        self.map.iter().filter(move |x| x.tags.contains(tag))
+    }
+
+    pub fn get_first_symbol(
+        &self,
+        value: ValueType,
+        address: Address,
+        namespace: &str,
+    ) -> Option<&Symbol> {
+        self.map
+            .iter()
+            .find(|x| x.namespace == namespace && x.is_match(value, Some(address)))
     }
 
     // does any symbol in scope exist?
-    pub fn has_symbols(&self, value: ValueType, address: Address) -> bool {
-        self.map.iter().any(|x| x.is_match(value, Some(address)))
+    pub fn has_symbols(&self, value: ValueType, address: Address, namespace: &str) -> bool {
+        self.map
+            .iter()
+            .any(|x| x.namespace == namespace && x.is_match(value, Some(address)))
     }
 }
@@ -0,0 +1,167 @@
+use crate::{
+    core::dasm::{
+        expr,
+        symbols::{Scope, Symbol, SymbolKind, SymbolList},
+        Address, ValueType,
+    },
+    prelude::{Error, FdResult},
+};
+
+const HEADER: &str = "name,value,kind,scope_start,scope_end,len,namespace";
+
+// splits a line into its fields, fields in quotes may contain commas and "" for a quote
+fn fields(line: &str) -> FdResult<Vec<String>> {
+    let mut fields = vec![];
+    let mut chars = line.chars().peekable();
+    loop {
+        let mut field = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => return Err(Error::InvalidArgument(line.into())),
+                }
+            }
+        }
+        for c in chars.by_ref() {
+            if c == ',' {
+                break;
+            }
+            field.push(c);
+        }
+        fields.push(field.trim().to_owned());
+        if chars.peek().is_none() {
+            return Ok(fields);
+        }
+    }
+}
+
+// values may be decimal or use the 0x and $ prefixes
+fn value(field: &str) -> FdResult<ValueType> {
+    expr::eval(field, &SymbolList::default())
+}
+
+fn quote(field: &str) -> String {
+    if field.contains([',', '"']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.into()
+    }
+}
+
+/// Parses a table of symbols with the columns
+/// name, value, kind (label or const), scope_start, scope_end, len and namespace
+/// The header and empty columns are optional, symbols without a scope are global
+/// and values may be decimal or use the 0x and $ prefixes
+pub fn parse(data: &str) -> FdResult<Vec<Symbol>> {
+    let mut syms = vec![];
+    for line in data.lines().filter(|l| !l.trim().is_empty()) {
+        let fields = fields(line)?;
+        let field = |i: usize| fields.get(i).map(String::as_str).unwrap_or_default();
+        if field(0) == "name" {
+            continue;
+        }
+        let err = || Error::InvalidArgument(line.into());
+        if field(0).is_empty() {
+            return Err(err());
+        }
+        let kind = match field(2).to_lowercase().as_str() {
+            "" | "label" => SymbolKind::Label,
+            "const" => SymbolKind::Const,
+            _ => return Err(err()),
+        };
+        let scope = match (field(3), field(4)) {
+            ("", "") => Scope::Global,
+            ("", _) | (_, "") => return Err(err()),
+            (start, end) => Scope::Range(value(start)? as Address, value(end)? as Address),
+        };
+        let len = match field(5) {
+            "" => 1,
+            len => value(len)? as usize,
+        };
+        let mut sym = Symbol::new(field(0).into(), kind, scope, value(field(1))?, len);
+        sym.namespace = field(6).into();
+        syms.push(sym);
+    }
+    Ok(syms)
+}
+
+/// Writes every symbol as a row after the header
+pub fn write(syms: &SymbolList) -> String {
+    let mut result = format!("{HEADER}\n");
+    for sym in syms.iter() {
+        let kind = match sym.kind {
+            SymbolKind::Label => "label",
+            SymbolKind::Const => "const",
+        };
+        let scope = match sym.scope {
+            Scope::Global => ",".into(),
+            Scope::Range(start, end) => format!("{start:#x},{end:#x}"),
+        };
+        // negative constants stay readable
+        let value = if sym.value < 0 {
+            sym.value.to_string()
+        } else {
+            format!("{:#x}", sym.value)
+        };
+        result.push_str(&format!(
+            "{},{value},{kind},{scope},{},{}\n",
+            quote(&sym.name),
+            sym.len,
+            quote(&sym.namespace)
+        ));
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::symbols::{Scope, Symbol, SymbolKind, SymbolList};
+
+    use super::{parse, write};
+
+    #[test]
+    fn table() {
+        let csv = "name,value,kind,scope_start,scope_end,len\n\
+                   reset,0x8000,label,,,1\n\
+                   \"loop, inner\",0x8010,Label,0x8000,0x8100,\n\n\
+                   SIZE,16,const\n\
+                   DELTA,-2,const\n\
+                   ptr,$10,label,,,2,spc\n";
+        let syms = parse(csv).unwrap();
+        assert_eq!(5, syms.len());
+        assert_eq!("loop, inner", syms[1].name);
+        assert_eq!(Scope::Range(0x8000, 0x8100), syms[1].scope);
+        assert_eq!((SymbolKind::Const, 16), (syms[2].kind, syms[2].value));
+        assert_eq!(
+            ("spc", 2, 0x10),
+            (syms[4].namespace.as_str(), syms[4].len, syms[4].value)
+        );
+
+        let mut list = SymbolList::default();
+        for sym in syms.iter().cloned() {
+            list.def_symbol(sym);
+        }
+        let written = write(&list);
+        assert!(written.contains("\"loop, inner\",0x8010,label,0x8000,0x8100,1,\n"));
+        let fields = |syms: Vec<Symbol>| {
+            let mut fields = syms
+                .into_iter()
+                .map(|s| (s.name, s.value, s.kind, s.scope, s.len, s.namespace))
+                .collect::<Vec<_>>();
+            fields.sort_by(|a, b| a.0.cmp(&b.0));
+            fields
+        };
+        assert_eq!(fields(syms), fields(parse(&written).unwrap()));
+
+        assert!(parse("bad,0x10,enum\n").is_err());
+        assert!(parse("bad,0x10,label,0x8000\n").is_err());
+        assert!(parse("\"bad,0x10\n").is_err());
+    }
+}
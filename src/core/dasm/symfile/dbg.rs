@@ -0,0 +1,240 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    core::{
+        config::{auto_radix_address, auto_radix_value},
+        dasm::{
+            arch::Context,
+            segment::Segment,
+            symbols::{Scope, Symbol, SymbolKind},
+            Address,
+        },
+    },
+    prelude::{Error, FdResult},
+};
+
+// a line of the debug info: the kind of record and its key=value pairs
+struct Record<'a> {
+    kind: &'a str,
+    fields: BTreeMap<&'a str, &'a str>,
+}
+
+impl<'a> Record<'a> {
+    // strings are quoted and may contain commas
+    fn parse(line: &'a str) -> Option<Self> {
+        let (kind, rest) = line.split_once(char::is_whitespace)?;
+        let mut fields = BTreeMap::new();
+        let mut rest = rest.trim();
+        while !rest.is_empty() {
+            let (key, value) = rest.split_once('=')?;
+            let len = if let Some(quoted) = value.strip_prefix('"') {
+                quoted.find('"')? + 2
+            } else {
+                value.find(',').unwrap_or(value.len())
+            };
+            fields.insert(key, value[..len].trim_matches('"'));
+            rest = value[len..].trim_start_matches(',');
+        }
+        Some(Self { kind, fields })
+    }
+
+    fn get(&self, key: &str) -> FdResult<&'a str> {
+        self.fields
+            .get(key)
+            .copied()
+            .ok_or_else(|| Error::InvalidArgument(format!("ld65 {} without {key}", self.kind)))
+    }
+
+    fn id(&self) -> FdResult<usize> {
+        Ok(auto_radix_address(self.get("id")?)? as usize)
+    }
+
+    // lists such as span=1+2 only use their first entry
+    fn number(&self, key: &str) -> FdResult<Address> {
+        let value = self.get(key)?;
+        Ok(auto_radix_address(
+            value.split('+').next().unwrap_or(value),
+        )?)
+    }
+}
+
+// the fields of the records that are referenced by other records
+#[derive(Default)]
+struct DebugInfo {
+    files: BTreeMap<usize, String>,
+    // the address of every segment
+    segs: BTreeMap<usize, Address>,
+    // the segment and the offset into it
+    spans: BTreeMap<usize, (usize, Address)>,
+    // the names of the symbols that are the parents of cheap locals
+    syms: BTreeMap<usize, String>,
+}
+
+/// Defines the symbols, segments and line info of an ld65 debug file (--dbgfile)
+/// Labels and equates become labels and constants, cheap locals (@loop) are
+/// prefixed with their parent (main_loop). Segments that are written to an output
+/// file are added at their file offset and every source line adds a comment (file:line)
+/// Returns the amount of symbols, segments and lines
+pub fn import(data: &str, ctx: &mut Context) -> FdResult<usize> {
+    let records: Vec<Record> = data
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| Record::parse(l).ok_or_else(|| Error::InvalidArgument(l.into())))
+        .collect::<FdResult<_>>()?;
+
+    let mut info = DebugInfo::default();
+    for record in &records {
+        match record.kind {
+            "file" => {
+                info.files.insert(record.id()?, record.get("name")?.into());
+            }
+            "seg" => {
+                info.segs.insert(record.id()?, record.number("start")?);
+            }
+            "span" => {
+                let span = (record.number("seg")? as usize, record.number("start")?);
+                info.spans.insert(record.id()?, span);
+            }
+            "sym" => {
+                info.syms.insert(record.id()?, record.get("name")?.into());
+            }
+            _ => {}
+        }
+    }
+
+    let mut count = 0;
+    let mut onames = BTreeSet::new();
+    let mut lines = BTreeSet::new();
+    for record in &records {
+        match record.kind {
+            "sym" => {
+                let kind = match record.get("type")? {
+                    "lab" => SymbolKind::Label,
+                    "equ" => SymbolKind::Const,
+                    // imports are defined by the export of the other module
+                    _ => continue,
+                };
+                let mut name = record.get("name")?.to_owned();
+                if let Ok(parent) = record.number("parent") {
+                    let parent = info
+                        .syms
+                        .get(&(parent as usize))
+                        .cloned()
+                        .unwrap_or_default();
+                    name = format!("{parent}_{}", name.trim_start_matches('@'));
+                }
+                let size = record.number("size").unwrap_or(1).max(1);
+                ctx.def_symbol(Symbol::new(
+                    name,
+                    kind,
+                    Scope::Global,
+                    auto_radix_value(record.get("val")?)?,
+                    size as usize,
+                ));
+                count += 1;
+            }
+            "seg" => {
+                let (Ok(oname), Ok(offset)) = (record.get("oname"), record.number("ooffs")) else {
+                    continue;
+                };
+                let size = record.number("size")? as usize;
+                if size == 0 {
+                    continue;
+                }
+                onames.insert(oname);
+                let offset = offset as usize;
+                ctx.segments.push(Segment::new(
+                    record.get("name")?,
+                    offset,
+                    offset + size,
+                    record.number("start")?,
+                    "",
+                ));
+                count += 1;
+            }
+            "line" => {
+                // macro expansions point at the lines of the macro
+                if record.number("type").unwrap_or(0) == 2 {
+                    continue;
+                }
+                let Ok(span) = record.number("span") else {
+                    continue;
+                };
+                let missing =
+                    || Error::InvalidArgument(format!("ld65 line {}", record.id().unwrap_or(0)));
+                let (seg, offset) = *info.spans.get(&(span as usize)).ok_or_else(missing)?;
+                let address = info.segs.get(&seg).ok_or_else(missing)? + offset;
+                if !lines.insert(address) {
+                    continue;
+                }
+                let file = info
+                    .files
+                    .get(&(record.number("file")? as usize))
+                    .ok_or_else(missing)?;
+                let line = format!("{file}:{}", record.get("line")?);
+                ctx.comments
+                    .entry(address)
+                    .and_modify(|c| *c = format!("{c}\n{line}"))
+                    .or_insert(line);
+                count += 1;
+            }
+            _ => {}
+        }
+    }
+    if onames.len() > 1 {
+        ctx.warn(&format!(
+            "The segments are written to several files ({}), only one of them is the input",
+            onames.into_iter().collect::<Vec<_>>().join(", ")
+        ));
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{arch::Context, symbols::SymbolKind};
+
+    use super::import;
+
+    #[test]
+    fn debug_info() {
+        let dbg = "version\tmajor=2,minor=0\n\
+            file\tid=0,name=\"main, v1.s\",size=100,mtime=0x5A0A6F41,mod=0\n\
+            line\tid=0,file=0,line=12,span=1\n\
+            line\tid=1,file=0,line=4,type=2,span=2\n\
+            seg\tid=0,name=\"ZEROPAGE\",start=0x000000,size=0x0002,addrsize=zeropage,type=rw\n\
+            seg\tid=1,name=\"CODE\",start=0x008000,size=0x0010,addrsize=absolute,type=ro,oname=\"game.bin\",ooffs=16\n\
+            span\tid=1,seg=1,start=4,size=3\n\
+            span\tid=2,seg=1,start=8,size=1\n\
+            sym\tid=0,name=\"main\",addrsize=absolute,scope=0,def=1,val=0x8000,seg=1,type=lab\n\
+            sym\tid=1,name=\"@loop\",addrsize=absolute,parent=0,def=2,val=0x8004,seg=1,type=lab\n\
+            sym\tid=2,name=\"SIZE\",addrsize=zeropage,scope=0,def=3,val=0x10,type=equ\n\
+            sym\tid=3,name=\"ppu\",addrsize=absolute,scope=0,def=4,type=imp\n";
+        let mut ctx = Context::default();
+        assert_eq!(5, import(dbg, &mut ctx).unwrap());
+
+        assert_eq!(
+            0x8004,
+            ctx.syms.get_first_by_name("main_loop").unwrap().value
+        );
+        assert_eq!(
+            SymbolKind::Const,
+            ctx.syms.get_first_by_name("SIZE").unwrap().kind
+        );
+        assert!(ctx.syms.get_first_by_name("ppu").is_none());
+        assert_eq!(1, ctx.segments.len());
+        assert_eq!(
+            ("CODE", 16, 32, 0x8000),
+            (
+                ctx.segments[0].name.as_str(),
+                ctx.segments[0].start,
+                ctx.segments[0].end,
+                ctx.segments[0].org
+            )
+        );
+        assert_eq!("main, v1.s:12", ctx.comments[&0x8004]);
+        assert!(!ctx.comments.contains_key(&0x8008));
+
+        assert!(import("sym\tid=0,name=\"x\",type=lab\n", &mut Context::default()).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    core::dasm::{
+        arch::Context,
+        symbols::{Scope, Symbol, SymbolKind},
+        Address, ValueType,
+    },
+    prelude::{Error, FdResult},
+};
+
+// the namespace of the SPC700 ram of Mesen-S files
+const SPC_NAMESPACE: &str = "spc";
+
+// where the values of a memory type are located
+enum Memory {
+    // offsets into the prg rom
+    Rom,
+    // cpu addresses relative to the start of the memory
+    Cpu(Address),
+    Spc,
+}
+
+// the memory types of Mesen (P, R, ...), Mesen-S (PRG, WORK, ...) and Mesen 2 (NesPrgRom, ...)
+fn memory(kind: &str) -> Option<Memory> {
+    Some(match kind {
+        "P" | "PRG" | "NesPrgRom" | "SnesPrgRom" => Memory::Rom,
+        "R" | "NesInternalRam" => Memory::Cpu(0),
+        "W" | "S" | "NesWorkRam" | "NesSaveRam" => Memory::Cpu(0x6000),
+        "WORK" | "SnesWorkRam" => Memory::Cpu(0x7E0000),
+        "G" | "REG" | "NesMemory" | "SnesMemory" | "SnesRegister" => Memory::Cpu(0),
+        "SPCRAM" | "SpcRam" => Memory::Spc,
+        _ => return None,
+    })
+}
+
+fn parse_value(value: &str, line: &str) -> FdResult<Address> {
+    Address::from_str_radix(value, 16).map_err(|_| Error::InvalidArgument(line.into()))
+}
+
+/// Defines the labels and comments of a Mesen label file (type:address[-end]:label[:comment])
+/// Rom offsets are counted from the start of the window
+/// Returns the amount of labels and comments
+pub fn import(data: &str, ctx: &mut Context) -> FdResult<usize> {
+    let mut count = 0;
+    for line in data.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let mut parts = line.splitn(4, ':');
+        let (Some(kind), Some(range), Some(label)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(Error::InvalidArgument(line.into()));
+        };
+        let comment = parts.next().unwrap_or_default();
+        let Some(memory) = memory(kind) else {
+            ctx.warn(&format!(
+                "Skipping Mesen label of unknown memory type: {line}"
+            ));
+            continue;
+        };
+
+        let (start, end) = range.split_once('-').unwrap_or((range, range));
+        let (start, end) = (parse_value(start, line)?, parse_value(end, line)?);
+        let invalid = || Error::InvalidArgument(line.into());
+        let (address, namespace) = match memory {
+            Memory::Rom => {
+                let offset = usize::try_from(start)
+                    .ok()
+                    .and_then(|start| ctx.start_read.checked_add(start))
+                    .ok_or_else(invalid)?;
+                ctx.locate(offset)
+            }
+            Memory::Cpu(base) => (base.checked_add(start).ok_or_else(invalid)?, String::new()),
+            Memory::Spc => (start, SPC_NAMESPACE.into()),
+        };
+        let len = end
+            .saturating_sub(start)
+            .checked_add(1)
+            .and_then(|len| usize::try_from(len).ok())
+            .ok_or_else(invalid)?;
+
+        if !label.is_empty() {
+            let mut sym = Symbol::new(
+                label.into(),
+                SymbolKind::Label,
+                Scope::Global,
+                address as ValueType,
+                len,
+            );
+            sym.namespace = namespace;
+            ctx.def_symbol(sym);
+            count += 1;
+        }
+        if !comment.is_empty() {
+            ctx.comments.insert(address, comment.replace("\\n", "\n"));
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+// the memory type and value of an address in the NES layout of Mesen
+fn location(ctx: &Context, address: Address, namespace: &str) -> (&'static str, Address) {
+    if namespace == SPC_NAMESPACE {
+        return ("SPCRAM", address);
+    }
+    match ctx.offset_of(address, namespace) {
+        Some(offset) if offset >= ctx.start_read => ("P", (offset - ctx.start_read) as Address),
+        _ if address < 0x800 => ("R", address),
+        _ if (0x6000..0x8000).contains(&address) => ("W", address - 0x6000),
+        _ => ("G", address),
+    }
+}
+
+/// Writes the labels and comments in the NES layout of Mesen
+/// Labels in the rom are written as prg offsets, the spc namespace as the SPC700 ram
+pub fn export(ctx: &Context) -> String {
+    let mut comments: BTreeMap<Address, String> = ctx
+        .comments
+        .iter()
+        .map(|(address, comment)| (*address, comment.replace('\n', "\\n")))
+        .collect();
+
+    let mut result = String::new();
+    for sym in ctx.syms.iter().filter(|s| s.kind == SymbolKind::Label) {
+        let (kind, start) = location(ctx, sym.value as Address, &sym.namespace);
+        let mut line = format!("{kind}:{start:04X}");
+        if sym.len > 1 {
+            line.push_str(&format!("-{:04X}", start + sym.len as Address - 1));
+        }
+        line.push_str(&format!(":{}", sym.name));
+        if let Some(comment) = comments.remove(&(sym.value as Address)) {
+            line.push_str(&format!(":{comment}"));
+        }
+        result.push_str(&line);
+        result.push('\n');
+    }
+    for (address, comment) in comments {
+        let (kind, value) = location(ctx, address, "");
+        result.push_str(&format!("{kind}:{value:04X}::{comment}\n"));
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::arch::Context;
+
+    use super::{export, import};
+
+    #[test]
+    fn labels() {
+        // an iNES rom with a 16 byte header and 32K of prg rom
+        let mut ctx = Context::default();
+        ctx.set_start(Some(0x10));
+        ctx.set_len(Some(0x8000));
+        ctx.set_org(0x8000 - 0x10);
+
+        let mlb = "P:0000:reset:Entry point\nR:0010-0011:ptr\nG:2000:PPUCTRL\n\
+                   P:0003::loop start\\nsecond line\nSPCRAM:0200:driver\nX:0000:unknown\n";
+        assert_eq!(6, import(mlb, &mut ctx).unwrap());
+        let reset = ctx.syms.get_first_by_name("reset").unwrap();
+        assert_eq!(0x8000, reset.value);
+        assert_eq!(2, ctx.syms.get_first_by_name("ptr").unwrap().len);
+        assert_eq!(
+            "spc",
+            ctx.syms.get_first_by_name("driver").unwrap().namespace
+        );
+        assert_eq!("loop start\nsecond line", ctx.comments[&0x8003]);
+        assert!(import("P:zz:bad", &mut ctx).is_err());
+        assert!(import("P:ffffffffffffffff:far", &mut ctx).is_err());
+        assert!(import("W:fffffffffffffff0:far", &mut ctx).is_err());
+        assert!(import("R:0-ffffffffffffffff:all", &mut ctx).is_err());
+
+        assert_eq!(
+            "P:0000:reset:Entry point\nR:0010-0011:ptr\nG:2000:PPUCTRL\nSPCRAM:0200:driver\n\
+             P:0003::loop start\\nsecond line\n",
+            export(&ctx)
+        );
+    }
+}
@@ -0,0 +1,83 @@
+pub mod csv;
+pub mod dbg;
+pub mod mlb;
+pub mod nl;
+pub mod nocash;
+pub mod vice;
+
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use std::path::Path;
+
+use crate::prelude::{Error, FdResult};
+
+use super::arch::Context;
+
+/// The label files of emulators and assemblers
+/// that symbols can be exchanged with
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SymFileKind {
+    // VICE monitor labels (al C:1234 .name)
+    #[default]
+    Vice,
+    // Mesen labels and comments (P:0010:name:comment)
+    Mlb,
+    // FCEUX name lists per bank (game.nes.0.nl) or for the ram (game.nes.ram.nl)
+    Nl,
+    // ld65 debug info (--dbgfile) with symbols, segments and lines
+    Dbg,
+    // no$ and RGBDS symbol files (00:0150 name)
+    Sym,
+    // a table of symbols (name,value,kind,scope_start,scope_end,len,namespace)
+    Csv,
+}
+
+impl SymFileKind {
+    /// Defines the symbols (and comments) of the file and returns how many were read
+    /// Formats that store a file per bank need the path of the file
+    pub fn import(&self, path: Option<&Path>, data: &str, ctx: &mut Context) -> FdResult<usize> {
+        let syms = match self {
+            SymFileKind::Vice => vice::parse(data)?,
+            SymFileKind::Csv => csv::parse(data)?,
+            // the comments are defined as well
+            SymFileKind::Mlb => return mlb::import(data, ctx),
+            SymFileKind::Dbg => return dbg::import(data, ctx),
+            SymFileKind::Sym => return nocash::import(data, ctx),
+            SymFileKind::Nl => {
+                let path = path.ok_or_else(|| {
+                    Error::InvalidArgument("FCEUX name lists are read from a file".into())
+                })?;
+                return nl::import(data, nl::bank_of(path)?, ctx);
+            }
+        };
+        let count = syms.len();
+        for sym in syms {
+            ctx.def_symbol(sym);
+        }
+        Ok(count)
+    }
+
+    pub fn export(&self, ctx: &Context) -> FdResult<String> {
+        Ok(match self {
+            SymFileKind::Vice => vice::write(&ctx.syms),
+            SymFileKind::Csv => csv::write(&ctx.syms),
+            SymFileKind::Mlb => mlb::export(ctx),
+            SymFileKind::Sym => nocash::export(ctx),
+            SymFileKind::Nl => {
+                return Err(Error::InvalidArgument(
+                    "FCEUX name lists can only be imported".into(),
+                ))
+            }
+            SymFileKind::Dbg => {
+                return Err(Error::InvalidArgument(
+                    "ld65 debug info can only be imported".into(),
+                ))
+            }
+        })
+    }
+}
@@ -0,0 +1,140 @@
+use std::path::Path;
+
+use crate::{
+    core::dasm::{
+        arch::Context,
+        symbols::{Scope, Symbol, SymbolKind},
+        Address, ValueType,
+    },
+    prelude::{Error, FdResult},
+};
+
+// fceux numbers the 16K prg banks
+const BANK_SIZE: usize = 0x4000;
+const RAM: &str = "ram";
+
+/// The bank of a name list (game.nes.1.nl), None for the ram (game.nes.ram.nl)
+pub fn bank_of(path: &Path) -> FdResult<Option<usize>> {
+    let err = || {
+        Error::InvalidArgument(format!(
+            "Expected a name list named like game.nes.0.nl or game.nes.ram.nl but got {path:?}"
+        ))
+    };
+    let bank = path
+        .file_stem()
+        .map(Path::new)
+        .and_then(Path::extension)
+        .and_then(|bank| bank.to_str())
+        .ok_or_else(err)?;
+    if bank == RAM {
+        Ok(None)
+    } else {
+        usize::from_str_radix(bank, 16).map(Some).map_err(|_| err())
+    }
+}
+
+fn parse_value(value: &str, line: &str) -> FdResult<Address> {
+    Address::from_str_radix(value.trim_start_matches('$'), 16)
+        .map_err(|_| Error::InvalidArgument(line.into()))
+}
+
+/// Defines the names and comments of an FCEUX name list ($c000#name#comment)
+/// The names of a prg bank are only in scope inside of the bank,
+/// the names of the ram list are global.
+/// Arrays ($0200/10#name#) define the symbol's length and
+/// lines starting with \ continue the previous comment
+/// Returns the amount of names and comments
+pub fn import(data: &str, bank: Option<usize>, ctx: &mut Context) -> FdResult<usize> {
+    // the bank's file offset is located like any other offset
+    let bank_range = bank.map(|bank| {
+        let (start, _) = ctx.locate(ctx.start_read + bank * BANK_SIZE);
+        (start, start + BANK_SIZE as Address)
+    });
+
+    let mut count = 0;
+    let mut last_comment = None;
+    for line in data.lines().map(str::trim_end).filter(|l| !l.is_empty()) {
+        if let Some(more) = line.strip_prefix('\\') {
+            let Some(address) = last_comment else {
+                return Err(Error::InvalidArgument(line.into()));
+            };
+            let comment = ctx.comments.entry(address).or_default();
+            comment.push('\n');
+            comment.push_str(more);
+            continue;
+        }
+
+        let mut parts = line.splitn(3, '#');
+        let (Some(location), Some(name)) = (parts.next(), parts.next()) else {
+            return Err(Error::InvalidArgument(line.into()));
+        };
+        let comment = parts.next().unwrap_or_default().trim_end_matches('#');
+        let (value, size) = match location.split_once('/') {
+            Some((value, size)) => (parse_value(value, line)?, parse_value(size, line)?),
+            None => (parse_value(location, line)?, 1),
+        };
+
+        let (address, scope) = match bank_range {
+            Some((start, end)) => (
+                start + (value as usize % BANK_SIZE) as Address,
+                Scope::Range(start, end),
+            ),
+            None => (value, Scope::Global),
+        };
+        if !name.is_empty() {
+            ctx.def_symbol(Symbol::new(
+                name.into(),
+                SymbolKind::Label,
+                scope,
+                address as ValueType,
+                size.max(1) as usize,
+            ));
+            count += 1;
+        }
+        last_comment = None;
+        if !comment.is_empty() {
+            ctx.comments.insert(address, comment.into());
+            last_comment = Some(address);
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use crate::core::dasm::{arch::Context, symbols::Scope};
+
+    use super::{bank_of, import};
+
+    #[test]
+    fn name_lists() {
+        assert_eq!(Some(0x1F), bank_of(Path::new("game.nes.1f.nl")).unwrap());
+        assert_eq!(None, bank_of(Path::new("dir/game.nes.ram.nl")).unwrap());
+        assert!(bank_of(Path::new("game.nl")).is_err());
+
+        // a 32K prg rom after the iNES header
+        let mut ctx = Context::default();
+        ctx.set_start(Some(0x10));
+        ctx.set_len(Some(0x8000));
+        ctx.set_org(0x8000 - 0x10);
+
+        let nl = "$C000#reset#Entry point\n\\jumps to main\n$C010/3#table#\n$C020##loop\n";
+        assert_eq!(4, import(nl, Some(1), &mut ctx).unwrap());
+        let reset = ctx.syms.get_first_by_name("reset").unwrap();
+        assert_eq!(
+            (0xC000, Scope::Range(0xC000, 0x10000)),
+            (reset.value, reset.scope)
+        );
+        assert_eq!(3, ctx.syms.get_first_by_name("table").unwrap().len);
+        assert_eq!("Entry point\njumps to main", ctx.comments[&0xC000]);
+        assert_eq!("loop", ctx.comments[&0xC020]);
+
+        assert_eq!(1, import("$0300#buffer#\n", None, &mut ctx).unwrap());
+        let buffer = ctx.syms.get_first_by_name("buffer").unwrap();
+        assert_eq!((0x300, Scope::Global), (buffer.value, buffer.scope));
+        assert!(import("$zz#bad#", None, &mut ctx).is_err());
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{
+    core::dasm::{
+        arch::Context,
+        symbols::{Scope, Symbol, SymbolKind},
+        Address, ValueType,
+    },
+    prelude::{Error, FdResult},
+};
+
+// the switchable 16K rom banks of the Game Boy are mapped to 4000-7fff
+const BANK_SIZE: usize = 0x4000;
+const BANK_WINDOW: Address = 0x4000;
+
+fn parse_value(value: &str, line: &str) -> FdResult<Address> {
+    Address::from_str_radix(value, 16).map_err(|_| Error::InvalidArgument(line.into()))
+}
+
+/// Defines the labels of a no$ (NO$GMB, NO$SNS, NO$GBA) or RGBDS symbol file
+/// Lines are either `0150 name` or `01:4150 name`, the labels of a rom bank
+/// other than 0 are only in scope inside of the bank.
+/// Comments (;), sections ([labels]) and the data directives of no$gba (.byt:0004)
+/// are skipped. Returns the amount of labels
+pub fn import(data: &str, ctx: &mut Context) -> FdResult<usize> {
+    let mut count = 0;
+    for line in data.lines() {
+        let line = line.split(';').next().unwrap_or_default().trim();
+        if line.is_empty() || line.starts_with('[') {
+            continue;
+        }
+        let Some((location, name)) = line.split_once(char::is_whitespace) else {
+            return Err(Error::InvalidArgument(line.into()));
+        };
+        let name = name.trim();
+        if name.starts_with('.') {
+            continue;
+        }
+
+        let (address, scope) = match location.split_once(':') {
+            Some((bank, value)) => {
+                let bank = parse_value(bank, line)? as usize;
+                let value = parse_value(value, line)?;
+                if bank > 0 && (BANK_WINDOW..BANK_WINDOW * 2).contains(&value) {
+                    // the bank's file offset is located like any other offset
+                    let (start, _) = ctx.locate(ctx.start_read + bank * BANK_SIZE);
+                    (
+                        start + value - BANK_WINDOW,
+                        Scope::Range(start, start + BANK_SIZE as Address),
+                    )
+                } else {
+                    (value, Scope::Global)
+                }
+            }
+            None => (parse_value(location, line)?, Scope::Global),
+        };
+        ctx.def_symbol(Symbol::new(
+            name.into(),
+            SymbolKind::Label,
+            scope,
+            address as ValueType,
+            1,
+        ));
+        count += 1;
+    }
+    Ok(count)
+}
+
+// the rom bank and the address inside of it, labels outside of the rom are in bank 0
+fn location(ctx: &Context, sym: &Symbol) -> (usize, Address) {
+    let value = sym.value as Address;
+    let offset = match sym.scope {
+        Scope::Range(_, _) => ctx.offset_of(value, &sym.namespace),
+        // the ram and the registers start at 8000
+        _ if value < BANK_WINDOW * 2 => ctx.offset_of(value, &sym.namespace),
+        _ => None,
+    };
+    match offset.and_then(|offset| offset.checked_sub(ctx.start_read)) {
+        Some(offset) if offset >= BANK_SIZE => (
+            offset / BANK_SIZE,
+            BANK_WINDOW + (offset % BANK_SIZE) as Address,
+        ),
+        Some(offset) => (0, offset as Address),
+        None => (0, value & 0xFFFF),
+    }
+}
+
+/// Writes the labels as bank:address pairs, e.g. 01:4150 name
+pub fn export(ctx: &Context) -> String {
+    let mut result = String::new();
+    for sym in ctx.syms.iter().filter(|s| s.kind == SymbolKind::Label) {
+        let (bank, address) = location(ctx, sym);
+        result.push_str(&format!("{bank:02X}:{address:04X} {}\n", sym.name));
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::{
+        arch::Context,
+        symbols::{Scope, Symbol, SymbolKind},
+    };
+
+    use super::{export, import};
+
+    #[test]
+    fn symbols() {
+        // a 64K rom disassembled at its file offsets
+        let mut ctx = Context::default();
+        ctx.set_len(Some(0x10000));
+
+        let sym = "; no$gmb symbolic debug information\n[labels]\n00:0150 Start\n\
+                   02:4010 Bank2Routine ; comment\nC000 wBuffer\n08000100 .arm\n";
+        assert_eq!(3, import(sym, &mut ctx).unwrap());
+        let start = ctx.syms.get_first_by_name("Start").unwrap();
+        assert_eq!((0x150, Scope::Global), (start.value, start.scope));
+        let routine = ctx.syms.get_first_by_name("Bank2Routine").unwrap();
+        assert_eq!(
+            (0x8010, Scope::Range(0x8000, 0xC000)),
+            (routine.value, routine.scope)
+        );
+        assert!(ctx.syms.get_first_by_name(".arm").is_none());
+        assert!(import("zz:0150 bad\n", &mut ctx).is_err());
+
+        ctx.def_symbol(Symbol::new(
+            "Main".into(),
+            SymbolKind::Label,
+            Scope::Global,
+            0x4200,
+            1,
+        ));
+        let exported = export(&ctx);
+        assert!(exported.contains("00:0150 Start\n"));
+        assert!(exported.contains("02:4010 Bank2Routine\n"));
+        assert!(exported.contains("01:4200 Main\n"));
+        assert!(exported.contains("00:C000 wBuffer\n"));
+    }
+}
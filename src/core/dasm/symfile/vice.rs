@@ -0,0 +1,132 @@
+use crate::{
+    core::dasm::{
+        symbols::{Scope, Symbol, SymbolKind, SymbolList},
+        ValueType,
+    },
+    prelude::{Error, FdResult},
+};
+
+// the memspace of the computer, the drives are numbered by their device
+const COMPUTER: &str = "C";
+const DRIVE_PREFIX: &str = "drive";
+
+// the computer's labels use the main namespace, the drives get their own
+fn memspace_namespace(memspace: &str) -> FdResult<String> {
+    match memspace {
+        COMPUTER => Ok(String::new()),
+        "8" | "9" | "10" | "11" => Ok(format!("{DRIVE_PREFIX}{memspace}")),
+        _ => Err(Error::InvalidArgument(format!(
+            "Unknown VICE memspace '{memspace}'"
+        ))),
+    }
+}
+
+fn namespace_memspace(namespace: &str) -> Option<&str> {
+    if namespace.is_empty() {
+        return Some(COMPUTER);
+    }
+    namespace
+        .strip_prefix(DRIVE_PREFIX)
+        .filter(|device| memspace_namespace(device).is_ok())
+}
+
+/// Parses the add_label commands of a VICE monitor script
+/// Other monitor commands are ignored
+pub fn parse(data: &str) -> FdResult<Vec<Symbol>> {
+    let mut syms = vec![];
+    for line in data.lines() {
+        let mut words = line.split_whitespace();
+        if !matches!(words.next(), Some("al") | Some("add_label")) {
+            continue;
+        }
+        let (Some(address), Some(name)) = (words.next(), words.next()) else {
+            return Err(Error::InvalidArgument(line.into()));
+        };
+        let (memspace, address) = address.split_once(':').unwrap_or((COMPUTER, address));
+        let value = ValueType::from_str_radix(address.trim_start_matches('$'), 16)?;
+        let mut sym = Symbol::new(
+            name.trim_start_matches('.').into(),
+            SymbolKind::Label,
+            Scope::Global,
+            value,
+            1,
+        );
+        sym.namespace = memspace_namespace(memspace)?;
+        syms.push(sym);
+    }
+    Ok(syms)
+}
+
+/// Writes the labels as add_label commands that can be loaded with ll
+/// Labels in namespaces other than the drives are prefixed with their namespace
+pub fn write(syms: &SymbolList) -> String {
+    let mut result = String::new();
+    for sym in syms.iter().filter(|s| s.kind == SymbolKind::Label) {
+        let (memspace, name) = match namespace_memspace(&sym.namespace) {
+            Some(memspace) => (memspace, sym.name.clone()),
+            None => (COMPUTER, format!("{}_{}", sym.namespace, sym.name)),
+        };
+        result.push_str(&format!("al {memspace}:{:04x} .{name}\n", sym.value));
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::dasm::symbols::{Scope, Symbol, SymbolKind, SymbolList};
+
+    use super::{parse, write};
+
+    #[test]
+    fn labels() {
+        let syms =
+            parse("al C:080d .start\nbreak 1000\nal 8:0300 .drive_code\nadd_label $c000 end\n")
+                .unwrap();
+        assert_eq!(3, syms.len());
+        assert_eq!(
+            ("start", 0x080D, ""),
+            (
+                syms[0].name.as_str(),
+                syms[0].value,
+                syms[0].namespace.as_str()
+            )
+        );
+        assert_eq!(
+            ("drive_code", 0x300, "drive8"),
+            (
+                syms[1].name.as_str(),
+                syms[1].value,
+                syms[1].namespace.as_str()
+            )
+        );
+        assert_eq!(
+            ("end", 0xC000, ""),
+            (
+                syms[2].name.as_str(),
+                syms[2].value,
+                syms[2].namespace.as_str()
+            )
+        );
+        assert!(parse("al C:zz .bad").is_err());
+        assert!(parse("al X:1000 .bad").is_err());
+
+        let mut list = SymbolList::default();
+        for sym in syms {
+            list.def_symbol(sym);
+        }
+        let mut sym = Symbol::new("reset".into(), SymbolKind::Label, Scope::Global, 0xF000, 1);
+        sym.namespace = "bank1".into();
+        list.def_symbol(sym);
+        list.def_symbol(Symbol::new(
+            "size".into(),
+            SymbolKind::Const,
+            Scope::Global,
+            4,
+            1,
+        ));
+        assert_eq!(
+            "al C:080d .start\nal 8:0300 .drive_code\nal C:c000 .end\nal C:f000 .bank1_reset\n",
+            write(&list)
+        );
+    }
+}
@@ -17,22 +17,46 @@ pub enum Error {
     NoMatch,
     #[error("Transform was not found")]
     TransformNotFound(String),
+    #[error("Script was not found")]
+    ScriptNotFound(String),
+    #[error("Script calls itself")]
+    ScriptCycle(String),
     #[error("Unsupported format")]
     UnsupportedFormat(ValueTypeFmt),
     #[error("Arch not found")]
     ArchNotFound(String),
     #[error("Unable to patch file")]
     PatchOffsetOutOfRange(usize),
+    #[error("Patches overlap")]
+    PatchOverlap(String),
+    #[error("Checksum out of range")]
+    ChecksumOutOfRange(usize),
     #[error("Label not found")]
     LabelNotFound(String),
+    #[error("Bank not found")]
+    BankNotFound(String),
+    #[error("View not found")]
+    ViewNotFound(String),
     #[error("Unknown command")]
     UnknownCommand(String),
     #[error("Not enough arguments provided")]
     InsufficientArguments,
     #[error("Too many arguments")]
     TooManyArguments,
+    #[error("Invalid header")]
+    InvalidHeader(String),
+    #[error("Metadata out of range")]
+    MetadataOutOfRange(String),
+    #[error("Invalid expression")]
+    InvalidExpression(String),
+    #[error("Output is not byte-exact")]
+    RoundTrip(String),
     #[error("Failed reading file")]
     FileDeserError,
+    #[error("Invalid argument")]
+    InvalidArgument(String),
+    #[error("Matcher consumed too many bytes")]
+    ConsumeLimit(String),
     #[error(transparent)]
     Other(#[from] anyhow::Error),
     #[error(transparent)]
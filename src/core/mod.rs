@@ -1,3 +1,5 @@
 pub mod config;
 pub mod dasm;
 pub mod error;
+#[cfg(feature = "cli")]
+pub mod user_config;
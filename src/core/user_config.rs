@@ -0,0 +1,293 @@
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+use console::Style;
+use serde::Deserialize;
+
+use super::{config::ArchKind, dasm::arch::CallbackKind};
+
+pub const USER_CONFIG_DIR: &str = "litedasm";
+pub const USER_CONFIG_FILE: &str = "config.ron";
+pub const USER_CONFIG_TOML_FILE: &str = "config.toml";
+
+/// The styles of the output in console's dotted notation (e.g. green.bold)
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct Theme {
+    pub value: String,
+    pub raw: String,
+    pub address: String,
+    pub label: String,
+    pub symbol: String,
+    pub matcher_name: String,
+    pub text: String,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            value: "cyan".into(),
+            raw: "red".into(),
+            address: "yellow".into(),
+            label: "green".into(),
+            symbol: "cyan".into(),
+            matcher_name: "".into(),
+            text: "".into(),
+        }
+    }
+}
+
+impl Theme {
+    pub fn style(&self, kind: &CallbackKind) -> Style {
+        let style = match kind {
+            CallbackKind::Val => &self.value,
+            CallbackKind::Raw => &self.raw,
+            CallbackKind::Address => &self.address,
+            CallbackKind::Label => &self.label,
+            CallbackKind::Symbol => &self.symbol,
+            CallbackKind::MatcherName => &self.matcher_name,
+            CallbackKind::Static => &self.text,
+            _ => return Style::new(),
+        };
+        Style::from_dotted_str(style)
+    }
+}
+
+/// Per-user defaults that are loaded from the config directory
+/// Flags passed on the command line always take precedence
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct UserConfig {
+    pub verbose: Option<u8>,
+    pub no_color: Option<bool>,
+    pub theme: Theme,
+    pub arch: Option<ArchKind>,
+    // the output of disas is piped into this command if stdout is a terminal
+    pub pager: Option<String>,
+    // the interactive mode's history file
+    pub history: Option<PathBuf>,
+    // the template of the interactive mode's prompt
+    pub prompt: Option<String>,
+    // the analysis database directory
+    pub db_dir: Option<PathBuf>,
+}
+
+// a value of the toml subset the user config is written in
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum TomlValue {
+    String(String),
+    Integer(i64),
+    Bool(bool),
+}
+
+impl TomlValue {
+    // parses a basic string, a literal string, an integer or a boolean
+    // that may be followed by a comment
+    fn parse(s: &str) -> Option<Self> {
+        let (value, rest) = if let Some(s) = s.strip_prefix('"') {
+            let mut value = String::new();
+            let mut chars = s.char_indices();
+            loop {
+                match chars.next()? {
+                    (i, '"') => break (Self::String(value), &s[i + 1..]),
+                    (_, '\\') => value.push(match chars.next()?.1 {
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        c @ ('"' | '\\') => c,
+                        _ => return None,
+                    }),
+                    (_, c) => value.push(c),
+                }
+            }
+        } else if let Some(s) = s.strip_prefix('\'') {
+            let (value, rest) = s.split_once('\'')?;
+            (Self::String(value.into()), rest)
+        } else {
+            let token = s.split_once('#').map_or(s, |(token, _)| token);
+            let value = match token.trim() {
+                "true" => Self::Bool(true),
+                "false" => Self::Bool(false),
+                n => Self::Integer(n.replace('_', "").parse().ok()?),
+            };
+            (value, "")
+        };
+        let rest = rest.trim();
+        (rest.is_empty() || rest.starts_with('#')).then_some(value)
+    }
+}
+
+/// $XDG_CONFIG_HOME/litedasm or ~/.config/litedasm
+pub fn user_config_dir() -> PathBuf {
+    match std::env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir).join(USER_CONFIG_DIR),
+        _ => PathBuf::from(shellexpand::tilde("~/.config").as_ref()).join(USER_CONFIG_DIR),
+    }
+}
+
+impl UserConfig {
+    /// Loads the user config if it exists, config.ron is used if both config files exist
+    /// The logger is not initialized yet, errors are printed to stderr
+    pub fn load() -> Option<Self> {
+        let dir = user_config_dir();
+        let path = [USER_CONFIG_FILE, USER_CONFIG_TOML_FILE]
+            .iter()
+            .map(|file| dir.join(file))
+            .find(|path| path.exists())?;
+
+        let data = match std::fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(err) => {
+                eprintln!("Unable to read '{}': {err}", path.display());
+                return None;
+            }
+        };
+        let user = if path.ends_with(USER_CONFIG_TOML_FILE) {
+            Self::from_toml(&data)
+        } else {
+            ron::from_str::<Self>(&data).map_err(|err| err.to_string())
+        };
+        match user {
+            Ok(user) => Some(user),
+            Err(err) => {
+                eprintln!("Unable to parse '{}': {err}", path.display());
+                None
+            }
+        }
+    }
+
+    /// Parses the subset of toml that the config needs: `key = value` lines with strings,
+    /// integers and booleans, a `[theme]` table and `#` comments.
+    /// The arch is one of the names of `--arch`
+    pub fn from_toml(data: &str) -> Result<Self, String> {
+        let mut user = Self::default();
+        let mut table = String::new();
+        for (i, line) in data.lines().enumerate() {
+            let err = |message: &str| format!("line {}: {message}", i + 1);
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                table = name.trim().into();
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| err("expected key = value"))?;
+            let key = key.trim();
+            let value = TomlValue::parse(value.trim()).ok_or_else(|| err("invalid value"))?;
+            match (table.as_str(), key, value) {
+                ("", "verbose", TomlValue::Integer(n)) => {
+                    user.verbose = Some(u8::try_from(n).map_err(|_| err("verbose is too large"))?)
+                }
+                ("", "no_color", TomlValue::Bool(b)) => user.no_color = Some(b),
+                ("", "arch", TomlValue::String(s)) => {
+                    user.arch = Some(ArchKind::from_str(&s, true).map_err(|e| err(&e))?)
+                }
+                ("", "pager", TomlValue::String(s)) => user.pager = Some(s),
+                ("", "history", TomlValue::String(s)) => user.history = Some(s.into()),
+                ("", "prompt", TomlValue::String(s)) => user.prompt = Some(s),
+                ("", "db_dir", TomlValue::String(s)) => user.db_dir = Some(s.into()),
+                ("theme", key, TomlValue::String(s)) => {
+                    let style = match key {
+                        "value" => &mut user.theme.value,
+                        "raw" => &mut user.theme.raw,
+                        "address" => &mut user.theme.address,
+                        "label" => &mut user.theme.label,
+                        "symbol" => &mut user.theme.symbol,
+                        "matcher_name" => &mut user.theme.matcher_name,
+                        "text" => &mut user.theme.text,
+                        _ => return Err(err(&format!("unknown theme key '{key}'"))),
+                    };
+                    *style = s;
+                }
+                _ => return Err(err(&format!("unknown key or wrong type of '{key}'"))),
+            }
+        }
+        Ok(user)
+    }
+
+    pub fn history(&self) -> Option<PathBuf> {
+        self.history
+            .as_ref()
+            .map(|path| PathBuf::from(shellexpand::tilde(&path.to_string_lossy()).as_ref()))
+    }
+
+    pub fn db_dir(&self) -> Option<PathBuf> {
+        self.db_dir
+            .as_ref()
+            .map(|path| PathBuf::from(shellexpand::tilde(&path.to_string_lossy()).as_ref()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::config::{ArchKind, Config};
+
+    use super::UserConfig;
+
+    #[test]
+    fn merge() {
+        let user: UserConfig = ron::from_str(
+            r#"(verbose: Some(3), arch: Some(ArchSh2), pager: Some("less -R"), prompt: Some("{file}> "), theme: (label: "red"))"#,
+        )
+        .unwrap();
+
+        let mut cfg = Config {
+            arch: Some(ArchKind::Arch65c02),
+            ..Default::default()
+        };
+        cfg.merge(user);
+        assert_eq!(3, cfg.verbose);
+        assert!(matches!(cfg.arch(), ArchKind::Arch65c02));
+        assert_eq!(Some("less -R".into()), cfg.pager);
+        assert_eq!(Some("{file}> ".into()), cfg.prompt);
+        assert_eq!("red", cfg.theme.label);
+        assert_eq!("yellow", cfg.theme.address);
+
+        // --color overrides no_color of the user config
+        let user = UserConfig {
+            no_color: Some(true),
+            ..Default::default()
+        };
+        let mut cfg = Config::default();
+        cfg.merge(user.clone());
+        assert!(cfg.no_color);
+        let mut cfg = Config {
+            color: true,
+            ..Default::default()
+        };
+        cfg.merge(user);
+        assert!(!cfg.no_color);
+    }
+
+    #[test]
+    fn toml() {
+        let user = UserConfig::from_toml(
+            r#"
+# defaults for every run
+verbose = 2
+no_color = false
+arch = "arch65c816" # the names of --arch
+pager = 'less -R'
+prompt = "{file} \"{bank}\"> "
+
+[theme]
+label = "green.bold"
+"#,
+        )
+        .unwrap();
+        assert_eq!(Some(2), user.verbose);
+        assert_eq!(Some(false), user.no_color);
+        assert!(matches!(user.arch, Some(ArchKind::Arch65c816)));
+        assert_eq!(Some("less -R".into()), user.pager);
+        assert_eq!(Some("{file} \"{bank}\"> ".into()), user.prompt);
+        assert_eq!("green.bold", user.theme.label);
+        assert_eq!("yellow", user.theme.address);
+
+        assert!(UserConfig::from_toml("verbose = \"2\"").is_err());
+        assert!(UserConfig::from_toml("colour = true").is_err());
+        assert!(UserConfig::from_toml("pager = \"less").is_err());
+    }
+}